@@ -0,0 +1,130 @@
+use rbx_dom_weak::types::{CFrame, Matrix3, Ref, Variant, Vector3};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::query::{bounding_box, Region};
+use crate::roblox::{is_a, shift_cframes, write_atomically, CHANGE_ID_ATTRIBUTE};
+
+/// Extension of the model files that can be inserted, Roblox's XML model format
+pub const MODEL_EXTENSION: &str = "rbxmx";
+
+/// Names of the models in an asset library, the file names without .rbxmx, sorted
+pub fn list_assets(library: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(library) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == MODEL_EXTENSION))
+        .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Find the file an asset refers to: a bare name is a model in the library, anything else a path
+pub fn resolve_asset(library: Option<&Path>, asset: &str) -> PathBuf {
+    let is_name = !asset.contains(['/', '\\']) && Path::new(asset).extension().is_none();
+    match library {
+        Some(library) if is_name => library.join(format!("{}.{}", asset, MODEL_EXTENSION)),
+        _ => PathBuf::from(asset),
+    }
+}
+
+/// Read a model file, refusing binary .rbxm files with a message instead of a parse error
+pub fn read_model(path: &Path) -> Result<WeakDom, String> {
+    if path.extension().is_some_and(|ext| ext == "rbxm") {
+        return Err(format!("{} is a binary model, save it as .rbxmx from Studio instead", path.display()));
+    }
+    let contents = std::fs::read(path).map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+    let model = rbx_xml::from_reader_default(contents.as_slice()).map_err(|e| format!("Can't parse {}: {}", path.display(), e))?;
+    if model.root().children().is_empty() {
+        return Err(format!("{} holds no instances", path.display()));
+    }
+    Ok(model)
+}
+
+/// Move the instances of a model into the place under parent_id. A name renames the instance when
+/// the model holds just one, and a position moves them all so the bottom middle of their bounding
+/// box ends up there. Returns the instances inserted.
+pub fn insert_model(dom: &mut WeakDom, mut model: WeakDom, parent_id: Ref, name: Option<&str>, position: Option<[f32; 3]>) -> Vec<Ref> {
+    let roots = model.root().children().to_vec();
+    for &root_id in &roots {
+        model.transfer(root_id, dom, parent_id);
+    }
+    if let (Some(name), [root_id]) = (name, roots.as_slice()) {
+        if let Some(instance) = dom.get_by_ref_mut(*root_id) {
+            instance.name = name.to_string();
+        }
+    }
+
+    if position.is_some() {
+        // Parts saved at the origin may leave their CFrame out, which would keep them from moving
+        for &root_id in &roots {
+            fill_missing_cframes(dom, root_id);
+        }
+    }
+    let bounds = roots.iter().filter_map(|&id| bounding_box(dom, id)).reduce(Region::union);
+    if let (Some([x, y, z]), Some(bounds)) = (position, bounds) {
+        let offset = Vector3::new(
+            x - (bounds.min[0] + bounds.max[0]) / 2.0,
+            y - bounds.min[1],
+            z - (bounds.min[2] + bounds.max[2]) / 2.0,
+        );
+        for &root_id in &roots {
+            shift_cframes(dom, root_id, offset);
+        }
+    }
+    roots
+}
+
+/// Write an instance and everything under it to a model file that `insert` can read back.
+/// Change IDs are left out, since they only mean something in this place. Returns how many instances were written.
+pub fn export_model(dom: &WeakDom, instance_id: Ref, path: &Path) -> Result<usize, Box<dyn Error>> {
+    if path.extension().is_none_or(|ext| ext != MODEL_EXTENSION) {
+        return Err(format!("Models are written as .{}, which {} isn't", MODEL_EXTENSION, path.display()).into());
+    }
+    let mut model = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_id = model.root_ref();
+    let copy_id = dom.clone_into_external(instance_id, &mut model);
+    model.transfer_within(copy_id, root_id);
+
+    // Refs to instances outside the copy still hold the place's referents, which mean nothing in the file
+    let ids: Vec<Ref> = model.descendants_of(copy_id).map(|instance| instance.referent()).collect();
+    let inside: HashSet<Ref> = ids.iter().copied().collect();
+    for &id in &ids {
+        let Some(instance) = model.get_by_ref_mut(id) else {
+            continue;
+        };
+        for value in instance.properties.values_mut() {
+            match value {
+                Variant::Ref(target) if !inside.contains(target) => *target = Ref::none(),
+                Variant::Attributes(attributes) => {
+                    attributes.remove(CHANGE_ID_ATTRIBUTE);
+                }
+                _ => {}
+            }
+        }
+    }
+    write_atomically(path, |writer| {
+        rbx_xml::to_writer_default(writer, &model, &[copy_id])?;
+        Ok(())
+    })?;
+    Ok(ids.len())
+}
+
+fn fill_missing_cframes(dom: &mut WeakDom, root_id: Ref) {
+    let parts: Vec<Ref> = dom.descendants_of(root_id)
+        .filter(|instance| instance.class != "Terrain" && is_a(&instance.class, "BasePart"))
+        .map(|instance| instance.referent())
+        .collect();
+    for part_id in parts {
+        if let Some(part) = dom.get_by_ref_mut(part_id) {
+            part.properties.entry("CFrame".into())
+                .or_insert_with(|| Variant::CFrame(CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity())));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use rbx_dom_weak::WeakDom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::journal;
+use crate::patch::{self, Patch, PatchOperation};
+use crate::query;
+use crate::roblox::{self, ApplyReport, Modification};
+
+/// A modification applied to a place, with what it takes to undo it.
+/// Appended to the place's audit log and never rewritten.
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub change_id: String,
+    #[serde(default)]
+    pub session: Option<String>,
+    /// The modification as it was applied. Undos have none.
+    #[serde(default)]
+    pub modification: Option<Modification>,
+    /// ID of the change this entry undid
+    #[serde(default)]
+    pub undoes: Option<String>,
+    /// Paths of the instances the change created, as they were right after it
+    #[serde(default)]
+    pub created: Vec<String>,
+    /// Turns the place back into what it was before the change
+    pub inverse: Patch,
+}
+
+/// The audit log lives next to the place, e.g. game.rbxlx.audit.jsonl
+pub fn audit_path(place: &Path) -> PathBuf {
+    let mut name = place.file_name().unwrap_or_default().to_os_string();
+    name.push(".audit.jsonl");
+    place.with_file_name(name)
+}
+
+/// Append a change to the place's audit log, given the place before and after it
+pub fn record(
+    place: &Path,
+    change_id: &str,
+    modification: Option<&Modification>,
+    undoes: Option<&str>,
+    before: &WeakDom,
+    after: &WeakDom,
+) -> Result<(), Box<dyn Error>> {
+    let created = patch::create_patch(before, after).operations.into_iter()
+        .filter_map(|operation| match operation {
+            PatchOperation::Add { parent, instance } if parent.is_empty() => Some(roblox::escape_name(&instance.name)),
+            PatchOperation::Add { parent, instance } => Some(roblox::child_path(&parent, &instance.name)),
+            _ => None,
+        })
+        .collect();
+    append(place, change_id, modification, undoes, created, patch::create_patch(after, before))
+}
+
+/// Append a modification json_to_weakdom applied, comparing only what its report says changed.
+/// before must come from roblox::snapshot, so the instances that stayed have the same referents.
+pub fn record_applied(
+    place: &Path,
+    change_id: &str,
+    modification: &Modification,
+    before: &WeakDom,
+    after: &WeakDom,
+    report: &ApplyReport,
+) -> Result<(), Box<dyn Error>> {
+    let created = query::outermost_only(after, &report.counts.created).into_iter()
+        .map(|id| roblox::instance_path(after, id))
+        .collect();
+    append(place, change_id, Some(modification), None, created, patch::create_inverse(before, after, report))
+}
+
+fn append(
+    place: &Path,
+    change_id: &str,
+    modification: Option<&Modification>,
+    undoes: Option<&str>,
+    created: Vec<String>,
+    inverse: Patch,
+) -> Result<(), Box<dyn Error>> {
+    let entry = AuditEntry {
+        timestamp: journal::now(),
+        change_id: change_id.to_string(),
+        session: Some(journal::session_id().to_string()),
+        modification: modification.cloned(),
+        undoes: undoes.map(str::to_string),
+        created,
+        inverse,
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(audit_path(place))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Read every entry of an audit log, oldest first
+pub fn read(log: &Path) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(log)
+        .map_err(|e| format!("Error reading {}: {}", log.display(), e))?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// The entry to undo: the one with change_id, or else the latest change that hasn't been undone
+pub fn find_undoable<'a>(entries: &'a [AuditEntry], change_id: Option<&str>) -> Result<&'a AuditEntry, Box<dyn Error>> {
+    let undone: HashSet<&str> = entries.iter().filter_map(|entry| entry.undoes.as_deref()).collect();
+    match change_id {
+        Some(id) => {
+            let entry = entries.iter().rev().find(|entry| entry.change_id == id)
+                .ok_or_else(|| format!("No change {} in the audit log", id))?;
+            if undone.contains(id) {
+                return Err(format!("Change {} was already undone", id).into());
+            }
+            Ok(entry)
+        }
+        None => entries.iter().rev()
+            .find(|entry| entry.undoes.is_none() && !undone.contains(entry.change_id.as_str()))
+            .ok_or_else(|| "Nothing left to undo in the audit log".into()),
+    }
+}
@@ -0,0 +1,142 @@
+use serde_json::json;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::context::{self, ContextOptions};
+use crate::roblox::{self, ApplyOptions, Modification};
+
+/// Name of the folder the synthetic modification adds to Workspace
+const BENCH_FOLDER: &str = "RbxMcpBench";
+
+/// Timings for each stage of the DOM pipeline on one place, the median of several runs
+pub struct BenchReport {
+    pub file_size: usize,
+    pub instances: usize,
+    pub runs: usize,
+    pub parse: Duration,
+    pub serialize: Duration,
+    pub serialized_size: usize,
+    pub context: Duration,
+    pub context_size: usize,
+    pub context_tokens: usize,
+    pub applied_instances: usize,
+    pub apply: Duration,
+}
+
+/// Measure parsing, serializing and describing a place, and applying a synthetic modification
+/// that adds `applied_instances` parts to it. The place file isn't changed.
+pub fn run(place: &Path, options: &ContextOptions, applied_instances: usize, runs: usize) -> Result<BenchReport, Box<dyn Error>> {
+    let runs = runs.max(1);
+    let xml = std::fs::read_to_string(place)?;
+    let modification = synthetic_modification(applied_instances)?;
+
+    let mut dom = None;
+    let parse = median(runs, || {
+        dom = Some(roblox::parse_roblox_str(&xml)?);
+        Ok(())
+    })?;
+    let dom = dom.ok_or("place wasn't parsed")?;
+
+    let mut serialized_size = 0;
+    let serialize = median(runs, || {
+        let mut buffer = Vec::new();
+        rbx_xml::to_writer_default(&mut buffer, &dom, dom.root().children())?;
+        serialized_size = buffer.len();
+        Ok(())
+    })?;
+
+    let mut description = String::new();
+    let context = median(runs, || {
+        description = context::describe_place(&dom, options);
+        Ok(())
+    })?;
+
+    // Each run applies to its own copy, made outside the timing
+    let mut apply = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let mut copy = roblox::snapshot(&dom);
+        let root_ref = copy.root_ref();
+        let start = Instant::now();
+        roblox::json_to_weakdom(&mut copy, &modification, root_ref, &ApplyOptions::default())?;
+        apply.push(start.elapsed());
+    }
+    apply.sort();
+
+    Ok(BenchReport {
+        file_size: xml.len(),
+        instances: dom.descendants().count() - 1,
+        runs,
+        parse,
+        serialize,
+        serialized_size,
+        context,
+        context_size: description.len(),
+        context_tokens: context::estimate_tokens(&description),
+        applied_instances,
+        apply: apply[runs / 2],
+    })
+}
+
+/// Time a stage `runs` times and return the median
+fn median(runs: usize, mut stage: impl FnMut() -> Result<(), Box<dyn Error>>) -> Result<Duration, Box<dyn Error>> {
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let start = Instant::now();
+        stage()?;
+        times.push(start.elapsed());
+    }
+    times.sort();
+    Ok(times[runs / 2])
+}
+
+/// A folder of anchored parts in a grid, each with the properties a generated building usually sets
+fn synthetic_modification(count: usize) -> Result<Modification, Box<dyn Error>> {
+    let parts: Vec<_> = (0..count)
+        .map(|i| json!({
+            "class": "Part",
+            "name": format!("Part{}", i),
+            "properties": {
+                "Size": [4, 1, 4],
+                "CFrame": {"position": [(i % 100) as f32 * 4.0, 0.5, (i / 100) as f32 * 4.0], "rotation": [0, 0, 0]},
+                "Color": [0.6, 0.6, 0.6],
+                "Material": {"type": "Enum", "value": "SmoothPlastic"},
+                "Anchored": true,
+            },
+            "attributes": {"Index": i},
+        }))
+        .collect();
+    Ok(serde_json::from_value(json!({
+        "add": [{"class": "Folder", "name": BENCH_FOLDER, "properties": {}, "children": parts}],
+        "subtract": [],
+    }))?)
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} instances, {}, median of {} runs", self.instances, megabytes(self.file_size), self.runs)?;
+        writeln!(f, "parse      {:>10.1} ms", millis(self.parse))?;
+        writeln!(f, "serialize  {:>10.1} ms  {}", millis(self.serialize), megabytes(self.serialized_size))?;
+        writeln!(
+            f,
+            "context    {:>10.1} ms  {}, about {} tokens",
+            millis(self.context), megabytes(self.context_size), self.context_tokens
+        )?;
+        let rate = self.applied_instances as f64 / self.apply.as_secs_f64().max(f64::EPSILON);
+        write!(
+            f,
+            "apply      {:>10.1} ms  {} parts, {:.0} instances/s",
+            millis(self.apply), self.applied_instances, rate
+        )
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn megabytes(bytes: usize) -> String {
+    format!("{:.2} MB", bytes as f64 / 1_000_000.0)
+}
+
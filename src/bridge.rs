@@ -0,0 +1,114 @@
+use rbx_dom_weak::WeakDom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use warp::Filter;
+
+use crate::patch;
+
+/// Port the bridge listens on when --bridge is given without one
+pub const DEFAULT_PORT: u16 = 34873;
+
+/// Source of the Studio plugin that polls the bridge, to save into Studio's plugins folder
+pub const STUDIO_PLUGIN: &str = include_str!("bridge_plugin.lua");
+
+/// Updates kept for plugins that fall behind. One that misses more has to reload the place.
+const MAX_UPDATES: usize = 200;
+
+/// Changes applied to the place in an interactive session, kept for the Studio plugin to fetch
+#[derive(Default)]
+pub struct Bridge {
+    log: Mutex<UpdateLog>,
+}
+
+#[derive(Default)]
+struct UpdateLog {
+    latest: u64,
+    updates: VecDeque<BridgeUpdate>,
+}
+
+/// A change to replay in Studio: a patch from the place before it to the place after it
+#[derive(Serialize, Clone)]
+pub struct BridgeUpdate {
+    pub id: u64,
+    pub description: String,
+    pub patch: Value,
+}
+
+#[derive(Deserialize)]
+struct UpdatesQuery {
+    since: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct UpdatesResponse {
+    latest: u64,
+    /// Updates after since were dropped, so the plugin is out of sync
+    missed: bool,
+    updates: Vec<BridgeUpdate>,
+}
+
+impl Bridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the changes between two versions of the place for the plugin
+    pub fn publish(&self, description: &str, before: &WeakDom, after: &WeakDom) {
+        let patch = patch::create_patch(before, after);
+        if patch.operations.is_empty() {
+            return;
+        }
+        let patch_json = match serde_json::to_value(&patch) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Error serializing bridge update: {}", e);
+                return;
+            }
+        };
+        let mut log = self.log.lock().unwrap();
+        log.latest += 1;
+        let update = BridgeUpdate { id: log.latest, description: description.to_string(), patch: patch_json };
+        log.updates.push_back(update);
+        if log.updates.len() > MAX_UPDATES {
+            log.updates.pop_front();
+        }
+        info!("Queued for Studio: {}", patch::describe_patch(&patch));
+    }
+
+    /// Updates after since. Without since, just the latest ID, so a plugin starts from now.
+    fn updates_since(&self, since: Option<u64>) -> UpdatesResponse {
+        let log = self.log.lock().unwrap();
+        let Some(since) = since else {
+            return UpdatesResponse { latest: log.latest, missed: false, updates: Vec::new() };
+        };
+        let oldest = log.updates.front().map_or(log.latest + 1, |update| update.id);
+        UpdatesResponse {
+            latest: log.latest,
+            missed: since + 1 < oldest && since < log.latest,
+            updates: log.updates.iter().filter(|update| update.id > since).cloned().collect(),
+        }
+    }
+}
+
+/// Serve updates to the Studio plugin until the shutdown token is cancelled.
+/// GET /updates?since=N returns the updates after N, oldest first.
+pub async fn serve(addr: SocketAddr, bridge: Arc<Bridge>, shutdown: CancellationToken) {
+    let updates = warp::path("updates")
+        .and(warp::get())
+        .and(warp::query::<UpdatesQuery>())
+        .map(move |query: UpdatesQuery| warp::reply::json(&bridge.updates_since(query.since)));
+    // The plugin is served pointing at this bridge, whatever port it's on
+    let plugin_source = STUDIO_PLUGIN.replace(&format!("127.0.0.1:{}", DEFAULT_PORT), &addr.to_string());
+    let plugin = warp::path("plugin.lua")
+        .and(warp::get())
+        .map(move || warp::reply::with_header(plugin_source.clone(), "Content-Type", "text/plain"));
+
+    info!("Studio bridge listening on http://{} (plugin at /plugin.lua)", addr);
+    let (_, server) = warp::serve(updates.or(plugin)).bind_with_graceful_shutdown(addr, async move { shutdown.cancelled().await });
+    server.await;
+}
@@ -0,0 +1,252 @@
+-- rbx-mcp Studio bridge
+--
+-- Replays the changes an interactive rbx-mcp session started with --bridge applies to
+-- the place, so they show up live in Studio. Save this file in Studio's Plugins folder,
+-- open the same place, enable Studio's "Allow HTTP Requests" and click the Bridge button.
+--
+-- Changes arrive as patches in the format `diff --emit-patch` writes. Paths name instances
+-- as they were before the change, so every path is looked up before anything is changed.
+-- JSON null is dropped when decoded, so attributes cleared by a change stay set in Studio.
+
+local CollectionService = game:GetService("CollectionService")
+local ChangeHistoryService = game:GetService("ChangeHistoryService")
+local HttpService = game:GetService("HttpService")
+
+local BRIDGE_URL = "http://127.0.0.1:34873"
+local POLL_INTERVAL = 1
+
+local toolbar = plugin:CreateToolbar("rbx-mcp")
+local button = toolbar:CreateButton("Bridge", "Replay changes from an rbx-mcp session live", "")
+button.ClickableWhenViewportHidden = true
+
+local BASE64 = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+
+local function decodeBase64(encoded: string): string
+	local bits = {}
+	for i = 1, #encoded do
+		local index = string.find(BASE64, string.sub(encoded, i, i), 1, true)
+		if index then
+			local value = index - 1
+			for bit = 5, 0, -1 do
+				table.insert(bits, bit32.extract(value, bit, 1))
+			end
+		end
+	end
+	local bytes = {}
+	for i = 1, #bits - 7, 8 do
+		local byte = 0
+		for j = 0, 7 do
+			byte = byte * 2 + bits[i + j]
+		end
+		table.insert(bytes, string.char(byte))
+	end
+	return table.concat(bytes)
+end
+
+-- Find an instance by a path like "Workspace/House/Door". The empty path is the place itself.
+local function resolve(path: string): Instance?
+	local current: Instance? = game
+	for name in string.gmatch(path, "[^/]+") do
+		if current == nil then
+			return nil
+		end
+		current = current:FindFirstChild(name)
+	end
+	return current
+end
+
+-- Convert a value tagged with its type, like {"Vector3": [0, 1, 0]}. Returns nil for types this plugin doesn't know.
+local function decode(tagged: any): any
+	local kind, v = next(tagged)
+	if kind == "Bool" or kind == "String" or kind == "ContentId" or kind == "Enum" then
+		-- Enum properties accept the item's number
+		return v
+	elseif kind == "Float32" or kind == "Float64" or kind == "Int32" or kind == "Int64" then
+		return v
+	elseif kind == "BinaryString" then
+		return decodeBase64(v)
+	elseif kind == "Vector3" then
+		return Vector3.new(v[1], v[2], v[3])
+	elseif kind == "Vector2" then
+		return Vector2.new(v[1], v[2])
+	elseif kind == "Color3" then
+		return Color3.new(v[1], v[2], v[3])
+	elseif kind == "Color3uint8" then
+		return Color3.fromRGB(v[1], v[2], v[3])
+	elseif kind == "BrickColor" then
+		return BrickColor.new(v)
+	elseif kind == "UDim" then
+		return UDim.new(v[1], v[2])
+	elseif kind == "UDim2" then
+		return UDim2.new(v[1][1], v[1][2], v[2][1], v[2][2])
+	elseif kind == "NumberRange" then
+		return NumberRange.new(v[1], v[2])
+	elseif kind == "Rect" then
+		return Rect.new(v[1][1], v[1][2], v[2][1], v[2][2])
+	elseif kind == "CFrame" or (kind == "OptionalCFrame" and v ~= nil) then
+		local p, o = v.position, v.orientation
+		return CFrame.new(p[1], p[2], p[3], o[1][1], o[1][2], o[1][3], o[2][1], o[2][2], o[2][3], o[3][1], o[3][2], o[3][3])
+	end
+	return nil
+end
+
+type Pending = { instance: Instance, property: string, path: string }
+
+-- Set a property from a patch, which is {"value": tagged} or {"ref": path}.
+-- Returns false if the property couldn't be set, which is common for read-only properties.
+local function setProperty(instance: Instance, name: string, patchValue: any, pendingRefs: { Pending }): boolean
+	if patchValue.ref ~= nil then
+		table.insert(pendingRefs, { instance = instance, property = name, path = patchValue.ref })
+		return true
+	end
+	local tagged = patchValue.value
+	if tagged.Attributes ~= nil then
+		for attribute, value in tagged.Attributes do
+			pcall(instance.SetAttribute, instance, attribute, decode(value))
+		end
+		return true
+	elseif tagged.Tags ~= nil then
+		for _, tag in tagged.Tags do
+			CollectionService:AddTag(instance, tag)
+		end
+		return true
+	end
+	local value = decode(tagged)
+	if value == nil then
+		return false
+	end
+	return (pcall(function()
+		(instance :: any)[name] = value
+	end))
+end
+
+local function create(description: any, parent: Instance, pendingRefs: { Pending }): number
+	local ok, instance = pcall(Instance.new, description.class)
+	if not ok then
+		warn(`rbx-mcp: can't create a {description.class} in Studio`)
+		return 0
+	end
+	local failed = 0
+	instance.Name = description.name
+	for name, value in description.properties or {} do
+		if not setProperty(instance, name, value, pendingRefs) then
+			failed += 1
+		end
+	end
+	for _, child in description.children or {} do
+		failed += create(child, instance, pendingRefs)
+	end
+	instance.Parent = parent
+	return failed
+end
+
+local function applyPatch(patch: any)
+	local operations = patch.operations
+	local targets = {}
+	local newParents = {}
+	for i, operation in operations do
+		targets[i] = resolve(operation.path or operation.parent)
+		if operation.new_parent then
+			newParents[i] = resolve(operation.new_parent)
+		end
+	end
+
+	local pendingRefs: { Pending } = {}
+	local removals = {}
+	local failed = 0
+	for i, operation in operations do
+		local target = targets[i]
+		if target == nil then
+			warn(`rbx-mcp: {operation.path or operation.parent} isn't in Studio, skipping {operation.op}`)
+		elseif operation.op == "set" then
+			for name, value in operation.properties or {} do
+				if not setProperty(target, name, value, pendingRefs) then
+					failed += 1
+				end
+			end
+			for name, value in operation.attributes or {} do
+				pcall(target.SetAttribute, target, name, decode(value))
+			end
+		elseif operation.op == "rename" then
+			target.Name = operation.new_name
+		elseif operation.op == "move" then
+			if newParents[i] then
+				target.Parent = newParents[i]
+			else
+				warn(`rbx-mcp: {operation.new_parent} isn't in Studio, can't move {operation.path}`)
+			end
+		elseif operation.op == "add" then
+			failed += create(operation.instance, target, pendingRefs)
+		elseif operation.op == "remove" then
+			table.insert(removals, target)
+		end
+	end
+	for _, instance in removals do
+		instance:Destroy()
+	end
+	-- Refs name their targets as they are after the change
+	for _, pending in pendingRefs do
+		pcall(function()
+			(pending.instance :: any)[pending.property] = if pending.path == "" then nil else resolve(pending.path)
+		end)
+	end
+	if failed > 0 then
+		print(`rbx-mcp: {failed} properties couldn't be set in Studio`)
+	end
+end
+
+local running = false
+local generation = 0
+local latest: number? = nil
+
+local function poll()
+	local url = BRIDGE_URL .. "/updates" .. (if latest then `?since={latest}` else "")
+	local ok, response = pcall(HttpService.GetAsync, HttpService, url, true)
+	if not ok then
+		return false
+	end
+	local data = HttpService:JSONDecode(response)
+	if data.missed then
+		warn("rbx-mcp: missed some changes, reopen the place to get back in sync")
+	end
+	for _, update in data.updates do
+		local recording = ChangeHistoryService:TryBeginRecording(`rbx-mcp: {update.description}`)
+		applyPatch(update.patch)
+		if recording then
+			ChangeHistoryService:FinishRecording(recording, Enum.FinishRecordingOperation.Commit)
+		end
+		print(`rbx-mcp: {update.description}`)
+	end
+	latest = data.latest
+	return true
+end
+
+button.Click:Connect(function()
+	running = not running
+	button:SetActive(running)
+	generation += 1
+	if not running then
+		print("rbx-mcp: bridge stopped")
+		return
+	end
+
+	-- The place file already has every change made so far, so start from the latest one
+	latest = nil
+	local current = generation
+	print(`rbx-mcp: polling {BRIDGE_URL}`)
+	task.spawn(function()
+		local connected = true
+		while running and generation == current do
+			local ok = poll()
+			if ok ~= connected then
+				if ok then
+					print("rbx-mcp: connected")
+				else
+					warn(`rbx-mcp: can't reach {BRIDGE_URL}, is rbx-mcp running with --bridge?`)
+				end
+				connected = ok
+			end
+			task.wait(POLL_INTERVAL)
+		end
+	end)
+end)
@@ -0,0 +1,203 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::assets;
+use crate::property::PropertyValue;
+use crate::roblox::{find_instance_by_path, is_a, JsonInstance, Modification};
+
+/// Limits on how expensive a single modification may be before warning
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Budget {
+    pub max_parts: usize,
+    pub max_unions: usize,
+    pub max_transparent_parts: usize,
+    pub max_unanchored_parts: usize,
+    pub max_part_scripts: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            max_parts: 1000,
+            max_unions: 50,
+            max_transparent_parts: 200,
+            max_unanchored_parts: 100,
+            max_part_scripts: 20,
+        }
+    }
+}
+
+/// Rendering and physics cost estimate for the instances a modification adds
+#[derive(Default, Debug)]
+pub struct CostEstimate {
+    pub instances: usize,
+    pub parts: usize,
+    pub unions: usize,
+    /// Partly transparent parts, which are drawn with overdraw
+    pub transparent_parts: usize,
+    pub unanchored_parts: usize,
+    /// Scripts parented directly to a part
+    pub part_scripts: usize,
+}
+
+impl CostEstimate {
+    /// Describe every limit in the budget this estimate goes over
+    pub fn over_budget(&self, budget: &Budget) -> Vec<String> {
+        let checks = [
+            ("parts", self.parts, budget.max_parts),
+            ("unions", self.unions, budget.max_unions),
+            ("partly transparent parts", self.transparent_parts, budget.max_transparent_parts),
+            ("unanchored parts", self.unanchored_parts, budget.max_unanchored_parts),
+            ("scripts inside parts", self.part_scripts, budget.max_part_scripts),
+        ];
+        checks.iter()
+            .filter(|(_, count, max)| count > max)
+            .map(|(what, count, max)| format!("{} {} exceeds the budget of {}", count, what, max))
+            .collect()
+    }
+
+    /// Add the cost of a subtree copied count times. Totals that don't fit stop at usize::MAX.
+    fn add_copies(&mut self, copy: &CostEstimate, count: usize) {
+        let add = |total: &mut usize, each: usize| {
+            *total = each.checked_mul(count).and_then(|n| n.checked_add(*total)).unwrap_or(usize::MAX);
+        };
+        add(&mut self.instances, copy.instances);
+        add(&mut self.parts, copy.parts);
+        add(&mut self.unions, copy.unions);
+        add(&mut self.transparent_parts, copy.transparent_parts);
+        add(&mut self.unanchored_parts, copy.unanchored_parts);
+        add(&mut self.part_scripts, copy.part_scripts);
+    }
+}
+
+/// Estimate the cost of everything a modification creates: what it adds, each copy its clones
+/// make of their source's subtree, and everything in the model files it inserts. Clones of a
+/// source that doesn't exist and model files that can't be read cost nothing.
+pub fn estimate_cost(dom: &WeakDom, data_model_id: Ref, modification: &Modification, asset_library: Option<&Path>) -> CostEstimate {
+    let mut estimate = CostEstimate::default();
+    for instance in &modification.add {
+        add_instance_cost(&mut estimate, instance, None);
+    }
+    for op in &modification.clone {
+        if let Some(source_id) = find_instance_by_path(dom, data_model_id, &op.source_path) {
+            let mut copy = CostEstimate::default();
+            add_existing_cost(&mut copy, dom, source_id, None);
+            estimate.add_copies(&copy, op.count);
+        }
+    }
+    for op in &modification.insert {
+        if let Ok(model) = assets::read_model(&assets::resolve_asset(asset_library, &op.asset)) {
+            // The model's root is the DataModel holding it, which isn't inserted
+            let mut copy = CostEstimate::default();
+            for &child_id in model.root().children() {
+                add_existing_cost(&mut copy, &model, child_id, None);
+            }
+            estimate.add_copies(&copy, 1);
+        }
+    }
+    estimate
+}
+
+/// How many instances a modification creates, counted by estimate_cost.
+/// None when the count doesn't fit in a usize, which is more than any limit.
+pub fn count_new_instances(dom: &WeakDom, data_model_id: Ref, modification: &Modification, asset_library: Option<&Path>) -> Option<usize> {
+    Some(estimate_cost(dom, data_model_id, modification, asset_library).instances).filter(|&count| count != usize::MAX)
+}
+
+/// Add the cost of an instance already in a place or model, and everything under it
+fn add_existing_cost(estimate: &mut CostEstimate, dom: &WeakDom, instance_id: Ref, parent_class: Option<&str>) {
+    let Some(instance) = dom.get_by_ref(instance_id) else {
+        return;
+    };
+    let transparency = match instance.properties.get(&"Transparency".into()) {
+        Some(Variant::Float32(value)) => *value,
+        _ => 0.0,
+    };
+    let anchored = matches!(instance.properties.get(&"Anchored".into()), Some(Variant::Bool(true)));
+    add_cost(estimate, &instance.class, parent_class, transparency as f64, anchored);
+    for &child_id in instance.children() {
+        add_existing_cost(estimate, dom, child_id, Some(&instance.class));
+    }
+}
+
+fn add_instance_cost(estimate: &mut CostEstimate, instance: &JsonInstance, parent_class: Option<&str>) {
+    let transparency = instance.properties.get("Transparency")
+        .and_then(PropertyValue::as_f64)
+        .unwrap_or(0.0);
+    let anchored = instance.properties.get("Anchored")
+        .is_some_and(|p| *p == PropertyValue::Bool(true));
+    add_cost(estimate, &instance.class, parent_class, transparency, anchored);
+    for child in &instance.children {
+        add_instance_cost(estimate, child, Some(&instance.class));
+    }
+}
+
+/// Count one instance towards an estimate
+fn add_cost(estimate: &mut CostEstimate, class: &str, parent_class: Option<&str>, transparency: f64, anchored: bool) {
+    estimate.instances += 1;
+
+    if is_a(class, "BasePart") {
+        estimate.parts += 1;
+        if is_a(class, "PartOperation") {
+            estimate.unions += 1;
+        }
+        if transparency > 0.0 && transparency < 1.0 {
+            estimate.transparent_parts += 1;
+        }
+        if !anchored {
+            estimate.unanchored_parts += 1;
+        }
+    }
+
+    if is_a(class, "Script") && parent_class.is_some_and(|class| is_a(class, "BasePart")) {
+        estimate.part_scripts += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::CloneOperation;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn clone_of_model(count: usize) -> (WeakDom, Modification) {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let model_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Tree"));
+        dom.insert(model_id, InstanceBuilder::new("Part").with_name("Trunk"));
+        let modification = Modification {
+            clone: vec![CloneOperation {
+                source_path: "Workspace/Tree".to_string(),
+                target_parent: "Workspace".to_string(),
+                count,
+                offset: None,
+            }],
+            ..Default::default()
+        };
+        (dom, modification)
+    }
+
+    #[test]
+    fn clones_count_their_whole_subtree_per_copy() {
+        let (dom, modification) = clone_of_model(3);
+        assert_eq!(count_new_instances(&dom, dom.root_ref(), &modification, None), Some(6));
+    }
+
+    #[test]
+    fn counts_that_overflow_are_none() {
+        let (dom, modification) = clone_of_model(usize::MAX);
+        assert_eq!(count_new_instances(&dom, dom.root_ref(), &modification, None), None);
+    }
+
+    #[test]
+    fn clones_are_costed_per_copy() {
+        let (dom, modification) = clone_of_model(400);
+        let cost = estimate_cost(&dom, dom.root_ref(), &modification, None);
+        assert_eq!((cost.instances, cost.parts, cost.unanchored_parts), (800, 400, 400));
+        let budget = Budget { max_parts: 300, ..Budget::default() };
+        assert!(cost.over_budget(&budget).iter().any(|warning| warning.starts_with("400 parts")));
+    }
+}
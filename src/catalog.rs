@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::error::RbxMcpError;
+use crate::open_cloud::OpenCloudClient;
+
+/// Where models downloaded from the catalog are kept, so inserting one again doesn't download it again
+pub const ASSET_CACHE_DIR: &str = ".rbx-mcp/assets";
+
+/// Most results one search returns
+const MAX_RESULTS: usize = 30;
+
+/// Kinds of assets that can be searched for, with their Roblox asset type IDs
+const CATEGORIES: [(&str, u32); 3] = [("Model", 10), ("Decal", 13), ("Audio", 3)];
+
+/// An asset found in the catalog
+#[derive(Serialize)]
+pub struct CatalogAsset {
+    pub id: u64,
+    pub name: String,
+    pub creator: String,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<SearchResult>,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct DetailsResponse {
+    #[serde(default)]
+    data: Vec<ItemDetails>,
+}
+
+#[derive(Deserialize)]
+struct ItemDetails {
+    asset: AssetDetails,
+    #[serde(default)]
+    creator: Option<CreatorDetails>,
+}
+
+#[derive(Deserialize)]
+struct AssetDetails {
+    id: u64,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CreatorDetails {
+    #[serde(default)]
+    name: String,
+}
+
+/// Searches the Creator Store (the toolbox) for free assets, and downloads models to insert
+pub struct CatalogClient {
+    http: reqwest::Client,
+    /// Downloading assets goes through Open Cloud, searching doesn't need a key
+    open_cloud: Option<OpenCloudClient>,
+}
+
+impl CatalogClient {
+    pub fn new(open_cloud_key: Option<String>) -> Self {
+        CatalogClient { http: reqwest::Client::new(), open_cloud: open_cloud_key.map(OpenCloudClient::new) }
+    }
+
+    /// Search one category, like "Model", by keyword. Returns the assets in the order the toolbox ranks them.
+    pub async fn search(&self, category: &str, keyword: &str, limit: usize) -> Result<Vec<CatalogAsset>, RbxMcpError> {
+        let &(_, asset_type) = CATEGORIES.iter().find(|(name, _)| name.eq_ignore_ascii_case(category))
+            .ok_or_else(|| RbxMcpError::InvalidOperation(format!(
+                "Unknown category '{}', try one of {}",
+                category,
+                CATEGORIES.map(|(name, _)| name).join(", ")
+            )))?;
+        let limit = limit.clamp(1, MAX_RESULTS).to_string();
+        let found: SearchResponse = self.get_json(
+            &format!("https://apis.roblox.com/toolbox-service/v1/marketplace/{}", asset_type),
+            &[("keyword", keyword), ("limit", &limit)],
+        ).await?;
+        if found.data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Search results only have IDs, names come from the details
+        let ids: Vec<String> = found.data.iter().map(|result| result.id.to_string()).collect();
+        let details: DetailsResponse = self.get_json(
+            "https://apis.roblox.com/toolbox-service/v1/items/details",
+            &[("assetIds", &ids.join(","))],
+        ).await?;
+        Ok(details.data.into_iter()
+            .map(|item| CatalogAsset {
+                id: item.asset.id,
+                name: item.asset.name,
+                creator: item.creator.map(|creator| creator.name).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Download a model or decal into the asset cache, unless it's there already. Returns the .rbxmx file.
+    pub async fn download_model(&self, asset_id: u64) -> Result<PathBuf, RbxMcpError> {
+        let path = PathBuf::from(ASSET_CACHE_DIR).join(format!("{}.rbxmx", asset_id));
+        if path.is_file() {
+            return Ok(path);
+        }
+        let open_cloud = self.open_cloud.as_ref().ok_or_else(|| RbxMcpError::InvalidOperation(
+            "Inserting catalog assets needs an Open Cloud API key in ROBLOX_API_KEY".to_string(),
+        ))?;
+        let contents = open_cloud.download_asset(asset_id).await?;
+        // Binary models start with "<roblox!", XML ones with "<roblox " and can be read
+        if !contents.starts_with(b"<roblox ") {
+            return Err(RbxMcpError::InvalidOperation(format!(
+                "Asset {} isn't an XML model and can't be inserted. Audio is used by setting a Sound's SoundId to rbxassetid://{}",
+                asset_id, asset_id
+            )));
+        }
+        std::fs::create_dir_all(ASSET_CACHE_DIR)?;
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    async fn get_json<T: for<'de> Deserialize<'de>>(&self, url: &str, query: &[(&str, &str)]) -> Result<T, RbxMcpError> {
+        let response = self.http.get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| RbxMcpError::CatalogError(e.to_string()))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| RbxMcpError::CatalogError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(RbxMcpError::CatalogError(format!("HTTP {}. Details: {}", status, text)));
+        }
+        serde_json::from_str(&text).map_err(|e| RbxMcpError::CatalogError(format!("Unexpected response {}: {}", text, e)))
+    }
+}
+
+/// Function declarations for searching the catalog and inserting what's found, added with --catalog
+pub fn declarations() -> Vec<Value> {
+    vec![
+        json!({
+            "name": "search_catalog",
+            "description": "Search the Roblox Creator Store for free assets by keyword. Returns the ID, name and creator of each asset found. \
+                Use real IDs from here instead of making up rbxassetid:// numbers.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "category": {
+                        "type": "STRING",
+                        "description": "Model, Decal or Audio"
+                    },
+                    "keyword": {
+                        "type": "STRING",
+                        "description": "What to search for, like \"oak tree\" or \"rain ambience\""
+                    },
+                    "limit": {
+                        "type": "INTEGER",
+                        "description": "Most results to return, 10 by default and at most 30"
+                    }
+                },
+                "required": ["category", "keyword"]
+            }
+        }),
+        json!({
+            "name": "insert_asset",
+            "description": "Download a model or decal found with search_catalog and insert it into the place. \
+                Audio can't be inserted, set a Sound's SoundId to rbxassetid://ID instead. Returns the paths of the instances inserted.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "asset_id": {
+                        "type": "INTEGER",
+                        "description": "ID of the asset from search_catalog"
+                    },
+                    "target_parent": {
+                        "type": "STRING",
+                        "description": "Path to insert it into, Workspace by default. A decal goes into the part it's on"
+                    },
+                    "position": {
+                        "type": "ARRAY",
+                        "items": { "type": "NUMBER" },
+                        "description": "Where the bottom middle of a model goes, [x, y, z]"
+                    },
+                    "name": {
+                        "type": "STRING",
+                        "description": "New name for the inserted instance"
+                    }
+                },
+                "required": ["asset_id"]
+            }
+        }),
+    ]
+}
@@ -0,0 +1,141 @@
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use std::collections::HashSet;
+
+use crate::roblox::{find_instances_by_path, instance_path, is_inside_package, remove_instance};
+
+/// Classes that only exist to hold other instances, and are clutter once empty
+const CONTAINER_CLASSES: [&str; 2] = ["Folder", "Model"];
+
+/// Instances that are never cleaned up, resolved from paths in the config, which can be globs
+pub struct Protected {
+    ids: HashSet<Ref>,
+    allow_package_edits: bool,
+}
+
+impl Protected {
+    /// Protect the instances at paths. Package contents are protected too unless package edits are allowed.
+    pub fn resolve(dom: &WeakDom, paths: &[String], allow_package_edits: bool) -> Protected {
+        let ids = paths.iter()
+            .flat_map(|path| find_instances_by_path(dom, dom.root_ref(), path))
+            .collect();
+        Protected { ids, allow_package_edits }
+    }
+
+    /// Whether an instance is an empty container that may be removed. Services and anything
+    /// else directly under the DataModel never are.
+    fn removable(&self, dom: &WeakDom, instance_id: Ref) -> bool {
+        let Some(instance) = dom.get_by_ref(instance_id) else {
+            return false;
+        };
+        CONTAINER_CLASSES.contains(&instance.class.as_str())
+            && instance.children().is_empty()
+            && instance.parent() != dom.root_ref()
+            && !self.ids.contains(&instance_id)
+            && (self.allow_package_edits || !is_inside_package(dom, instance_id))
+    }
+}
+
+/// Remove every empty Folder and Model in the place, along with those that only held empty ones.
+/// Returns the paths removed.
+pub fn clean_place(dom: &mut WeakDom, protected: &Protected) -> Vec<String> {
+    // Reversed pre-order visits children before their parents, so emptied parents go too
+    let ids: Vec<Ref> = dom.descendants().map(|instance| instance.referent()).collect();
+    let mut removed = Vec::new();
+    for &id in ids.iter().rev() {
+        if protected.removable(dom, id) {
+            let path = instance_path(dom, id);
+            if remove_instance(dom, id).is_ok() {
+                removed.push(path);
+            }
+        }
+    }
+    removed
+}
+
+/// Remove the given instances that are empty containers, then their parents for as long as
+/// they're left empty too, for tidying up after removals and moves.
+/// Returns the path and former parent of each instance removed.
+pub fn remove_emptied(dom: &mut WeakDom, instance_ids: &[Ref], protected: &Protected) -> Vec<(String, Ref)> {
+    let mut removed = Vec::new();
+    for &start_id in instance_ids {
+        let mut current = start_id;
+        while protected.removable(dom, current) {
+            let path = instance_path(dom, current);
+            match remove_instance(dom, current) {
+                Ok(parent_id) => {
+                    removed.push((path, parent_id));
+                    current = parent_id;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::find_instance_by_path;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn sample_place() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let props_id = dom.insert(workspace_id, InstanceBuilder::new("Folder").with_name("Props"));
+        let old_id = dom.insert(props_id, InstanceBuilder::new("Folder").with_name("Old"));
+        dom.insert(old_id, InstanceBuilder::new("Model").with_name("Empty"));
+        let kept_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Kept"));
+        dom.insert(kept_id, InstanceBuilder::new("Part").with_name("Block"));
+        dom.insert(workspace_id, InstanceBuilder::new("Folder").with_name("Spawns"));
+        let package_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Package"));
+        dom.insert(package_id, InstanceBuilder::new("PackageLink").with_name("PackageLink"));
+        dom.insert(package_id, InstanceBuilder::new("Folder").with_name("Slots"));
+        dom.insert(dom.root_ref(), InstanceBuilder::new("Folder").with_name("ServerStorage"));
+        dom
+    }
+
+    fn exists(dom: &WeakDom, path: &str) -> bool {
+        find_instance_by_path(dom, dom.root_ref(), path).is_some()
+    }
+
+    #[test]
+    fn empty_containers_and_the_ones_holding_them_are_removed() {
+        let mut dom = sample_place();
+        let protected = Protected::resolve(&dom, &["Workspace/Spawn*".to_string()], false);
+        let removed = clean_place(&mut dom, &protected);
+        assert_eq!(removed, ["Workspace/Props/Old/Empty", "Workspace/Props/Old", "Workspace/Props"]);
+        assert!(exists(&dom, "Workspace/Kept/Block"));
+        assert!(exists(&dom, "Workspace/Spawns"));
+        assert!(exists(&dom, "Workspace/Package/Slots"));
+        assert!(exists(&dom, "ServerStorage"));
+    }
+
+    #[test]
+    fn package_contents_are_cleaned_when_allowed() {
+        let mut dom = sample_place();
+        let protected = Protected::resolve(&dom, &[], true);
+        let removed = clean_place(&mut dom, &protected);
+        assert!(removed.contains(&"Workspace/Package/Slots".to_string()));
+        assert!(removed.contains(&"Workspace/Spawns".to_string()));
+        // The package root still holds its PackageLink
+        assert!(exists(&dom, "Workspace/Package"));
+    }
+
+    #[test]
+    fn emptied_parents_are_removed_after_their_children() {
+        let mut dom = sample_place();
+        let protected = Protected::resolve(&dom, &[], false);
+        let block_id = find_instance_by_path(&dom, dom.root_ref(), "Workspace/Kept/Block").unwrap();
+        let empty_id = find_instance_by_path(&dom, dom.root_ref(), "Workspace/Props/Old/Empty").unwrap();
+        let kept_id = dom.get_by_ref(block_id).unwrap().parent();
+        dom.destroy(block_id);
+
+        let removed = remove_emptied(&mut dom, &[kept_id, empty_id], &protected);
+        let paths: Vec<&str> = removed.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["Workspace/Kept", "Workspace/Props/Old/Empty", "Workspace/Props/Old", "Workspace/Props"]);
+        // Only the instances given and their ancestors are touched
+        assert!(exists(&dom, "Workspace/Spawns"));
+    }
+}
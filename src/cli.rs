@@ -1,4 +1,4 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use std::path::PathBuf;
 
 pub fn build_cli() -> Command {
@@ -6,13 +6,14 @@ pub fn build_cli() -> Command {
         .version("0.1.0")
         .author("Author")
         .about("Roblox MCP tool")
+        .subcommand_negates_reqs(true)
         .arg(
             Arg::new("filepath")
                 .short('f')
                 .long("file")
                 .value_name("FILE")
                 .help("Input file path")
-                .required(true)
+                .required_unless_present("resume")
                 .value_parser(clap::value_parser!(PathBuf)),
         )
         .arg(
@@ -29,6 +30,768 @@ pub fn build_cli() -> Command {
                 .long("context")
                 .value_name("FILE")
                 .help("Context file path (markdown .md)")
-                .required(false),
+                .required(false)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_name("FILE")
+                .help("Config file path (defaults to rbx-mcp.json in the current directory)")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .help("Print every instance and property as it is processed (-vv for trace output)")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .help("Only print warnings and errors")
+                .conflicts_with("verbose")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .global(true)
+                .value_name("FILE")
+                .help("Also write debug logs to this file as JSON lines")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .value_name("SESSION")
+                .help("Continue an earlier session from its transcript in .rbx-mcp/sessions, by ID or path, with its prompt history and context")
+                .value_parser(clap::value_parser!(String)),
+        )
+        .arg(
+            Arg::new("image")
+                .long("image")
+                .value_name("FILE")
+                .help("Send this image with every prompt, e.g. concept art to build from. Can be given more than once")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("tools")
+                .long("tools")
+                .help("Let Gemini make changes step by step through function calls, seeing the result of each, instead of answering with one modification")
+                .conflicts_with("plan-out")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("catalog")
+                .long("catalog")
+                .help("With --tools, let Gemini search the Roblox Creator Store for free models, decals and audio and insert models by ID (inserting needs an Open Cloud key in ROBLOX_API_KEY)")
+                .requires("tools")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("retrieval")
+                .long("retrieval")
+                .help("Describe the place a few levels deep and let Gemini ask for the parts it needs before each change, for big places")
+                .conflicts_with("tools")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("streaming-feedback")
+                .long("streaming-feedback")
+                .help("Ask Gemini to fix scripts that break under StreamingEnabled before applying")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("overlap-feedback")
+                .long("overlap-feedback")
+                .help("Ask Gemini to move new parts that end up inside existing anchored parts before applying")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("bridge")
+                .long("bridge")
+                .value_name("PORT")
+                .help("Serve each change to the companion Studio plugin on 127.0.0.1, so it shows up live in Studio (default port 34873)")
+                .num_args(0..=1)
+                .default_missing_value("34873")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("no-stream")
+                .long("no-stream")
+                .help("Wait for Gemini's whole response instead of printing it as it is generated")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-context-cache")
+                .long("no-context-cache")
+                .help("Send the place with every prompt instead of uploading large places once to Gemini's context cache")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("model")
+                .long("model")
+                .global(true)
+                .value_name("MODEL")
+                .help("Gemini model to use (default gemini-2.0-flash)"),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .global(true)
+                .help("Always ask Gemini, instead of reusing the saved response when the same prompt is sent for the same place and settings")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("system-prompt")
+                .long("system-prompt")
+                .global(true)
+                .value_name("FILE")
+                .help("Use this file as the system prompt template instead of the built-in one. {place_name} and {dom_summary} are filled in")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("examples")
+                .long("examples")
+                .global(true)
+                .value_name("DIR")
+                .help("Send the NAME.txt prompt and NAME.json modification pairs in this directory to Gemini as examples")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("temperature")
+                .long("temperature")
+                .global(true)
+                .value_name("NUMBER")
+                .help("Sampling temperature from 0 to 2 (default 0.8)"),
+        )
+        .arg(
+            Arg::new("max-tokens")
+                .long("max-tokens")
+                .global(true)
+                .value_name("TOKENS")
+                .help("Most tokens Gemini may generate per response (default 8000)"),
+        )
+        .arg(
+            Arg::new("top-p")
+                .long("top-p")
+                .global(true)
+                .value_name("NUMBER")
+                .help("Nucleus sampling probability from 0 to 1 (Gemini's default if not given)"),
+        )
+        .arg(
+            Arg::new("top-k")
+                .long("top-k")
+                .global(true)
+                .value_name("K")
+                .help("Sample from only the K most likely tokens (Gemini's default if not given)"),
+        )
+        .arg(
+            Arg::new("include-packages")
+                .long("include-packages")
+                .help("Send the contents of packages to Gemini (hidden by default to save tokens)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("allow-package-edits")
+                .long("allow-package-edits")
+                .global(true)
+                .help("Allow modifications inside packages, which are lost when the package updates")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("format-scripts")
+                .long("format-scripts")
+                .global(true)
+                .help("Format the Source of added scripts with StyLua before they go into the place")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .global(true)
+                .help("Reject a modification if any property has a type that can't be converted, instead of leaving the property out")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-new-instances")
+                .long("max-new-instances")
+                .value_name("N")
+                .global(true)
+                .help("Ask before applying a change that creates more instances than this, counting clones. 0 for no limit")
+                .default_value("500")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("auto-clean")
+                .long("auto-clean")
+                .global(true)
+                .help("After each change, remove the Folders and Models its removals and moves left empty")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("snap")
+                .long("snap")
+                .value_name("STUDS")
+                .global(true)
+                .help("Round the positions and sizes of the parts each change creates to this grid, e.g. 1.0")
+                .value_parser(clap::value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("auto-anchor")
+                .long("auto-anchor")
+                .global(true)
+                .help("Anchor every part each change creates")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fill-defaults")
+                .long("fill-defaults")
+                .global(true)
+                .help("Give added instances every property they don't set, with Roblox's default value")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("plan-out")
+                .long("plan-out")
+                .value_name("FILE")
+                .help("Write each modification from Gemini to this file for review instead of applying it")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Apply a Modification JSON file to a place without calling Gemini")
+                .arg(
+                    Arg::new("modification")
+                        .value_name("MODIFICATION")
+                        .help("Modification JSON file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to modify")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the result here instead of overwriting the place")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .help("Apply it even when it creates more instances than --max-new-instances")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("build-rojo")
+                .about("Build a place from a Rojo project")
+                .arg(
+                    Arg::new("project")
+                        .value_name("PROJECT")
+                        .help("Project directory with a default.project.json, or a .project.json file")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Place file to write")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Print the instances and properties that differ between two place files")
+                .arg(
+                    Arg::new("old")
+                        .value_name("OLD")
+                        .help("Earlier version of the place")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("new")
+                        .value_name("NEW")
+                        .help("Later version of the place")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("emit-patch")
+                        .long("emit-patch")
+                        .value_name("FILE")
+                        .help("Also write the changes as a patch that `patch apply` can replay onto another copy of the place")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("export-rojo")
+                .about("Convert a place to a Rojo project with default.project.json, script files and .rbxmx models")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to convert")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory to write the project to, which must be empty or not exist yet")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge two edited copies of a place instance by instance, reporting conflicting changes")
+                .arg(
+                    Arg::new("base")
+                        .long("base")
+                        .value_name("FILE")
+                        .help("Version both copies were edited from")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("ours")
+                        .long("ours")
+                        .value_name("FILE")
+                        .help("Our edited copy, which wins conflicts")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("theirs")
+                        .long("theirs")
+                        .value_name("FILE")
+                        .help("Their edited copy")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where to write the merged place")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("patch")
+                .about("Work with patches written by diff --emit-patch")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("apply")
+                        .about("Replay a patch onto a place")
+                        .arg(
+                            Arg::new("patch")
+                                .value_name("PATCH")
+                                .help("Patch JSON file")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("place")
+                                .value_name("PLACE")
+                                .help("Place file to modify")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Write the result here instead of overwriting the place")
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("scripts")
+                .about("Edit the scripts in a place as files")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("export")
+                        .about("Write every script's Source to a .luau file mirroring its path in the place")
+                        .arg(
+                            Arg::new("place")
+                                .value_name("PLACE")
+                                .help("Place file to read scripts from")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("out")
+                                .long("out")
+                                .value_name("DIR")
+                                .help("Directory to write the script files to")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Write script files from scripts export back into a place")
+                        .arg(
+                            Arg::new("place")
+                                .value_name("PLACE")
+                                .help("Place file to modify")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("from")
+                                .long("from")
+                                .value_name("DIR")
+                                .help("Directory to read the script files from")
+                                .required(true)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Write the result here instead of overwriting the place")
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("terrain-export")
+                .about("Export the terrain in a region as a heightmap PNG and a material map PNG")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to read terrain from")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("region")
+                        .long("region")
+                        .value_names(["X1", "Y1", "Z1", "X2", "Y2", "Z2"])
+                        .help("Opposite corners of the region in studs")
+                        .required(true)
+                        .num_args(6)
+                        .allow_negative_numbers(true)
+                        .value_parser(clap::value_parser!(f32)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("PREFIX")
+                        .help("Writes PREFIX.json, PREFIX_height.png and PREFIX_material.png")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("terrain-import")
+                .about("Import an edited heightmap and material map from terrain-export back into a place")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to modify")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("prefix")
+                        .value_name("PREFIX")
+                        .help("Prefix the images were exported with")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the result here instead of overwriting the place")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("import-heightmap")
+                .about("Build terrain in a region from a grayscale heightmap PNG of any size")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to modify")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("image")
+                        .value_name("IMAGE")
+                        .help("Grayscale PNG, black for the bottom of the region and white for the top")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("region")
+                        .long("region")
+                        .value_names(["X1", "Y1", "Z1", "X2", "Y2", "Z2"])
+                        .help("Opposite corners of the region in studs, the image is stretched over it")
+                        .required(true)
+                        .num_args(6)
+                        .allow_negative_numbers(true)
+                        .value_parser(clap::value_parser!(f32)),
+                )
+                .arg(
+                    Arg::new("material")
+                        .long("material")
+                        .value_name("MATERIAL")
+                        .help("Terrain material to build with")
+                        .default_value("Grass"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the result here instead of overwriting the place")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("undo")
+                .about("Undo a modification by applying its inverse from the place's audit log")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to modify")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("from-log")
+                        .long("from-log")
+                        .value_name("AUDIT_LOG")
+                        .help("Audit log to undo from (defaults to PLACE.audit.jsonl)")
+                        .required(true)
+                        .num_args(0..=1)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("change")
+                        .long("change")
+                        .value_name("ID")
+                        .help("Change ID to undo (defaults to the latest change not undone yet)"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the result here instead of overwriting the place")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve the core operations over HTTP")
+                .arg(
+                    Arg::new("rest")
+                        .long("rest")
+                        .help("Serve a REST API with an OpenAPI spec at /openapi.json")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Port to listen on")
+                        .default_value("8080")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .value_name("ADDRESS")
+                        .help("Address to bind to")
+                        .default_value("127.0.0.1")
+                        .value_parser(clap::value_parser!(std::net::IpAddr)),
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .value_name("DIR")
+                        .help("Directory requests may read and write places in, the current directory by default")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("api-key")
+                        .short('k')
+                        .long("api-key")
+                        .value_name("KEY")
+                        .help("Gemini API key for /generate (can also be provided via GEMINI_API_KEY env variable)"),
+                ),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("Watch a place file and publish it to Open Cloud when it changes, at most every sync.interval_minutes")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to watch and publish")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("open-cloud-key")
+                        .long("open-cloud-key")
+                        .value_name("KEY")
+                        .help("Open Cloud API key with place publishing access (can also be provided via ROBLOX_API_KEY env variable)"),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manage the saved Gemini responses in .rbx-mcp/cache")
+                .subcommand_required(true)
+                .subcommand(Command::new("clear").about("Delete every saved response")),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print instance counts by class, script totals, unanchored parts, unnamed instances and the deepest nesting of a place")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to read")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Check a place for unknown classes, script syntax errors, empty Models, parts at extreme positions and duplicate services")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to check")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("fix")
+                        .long("fix")
+                        .help("Remove empty Models and merge duplicate services into the first one, then save the place")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where --fix saves the place. Overwrites the input when not given")
+                        .requires("fix")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("dedup")
+                .about("Find siblings that are copies of each other, with the same class, name, properties and children")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to check")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("remove")
+                        .long("remove")
+                        .help("Remove the copies, keeping the first of each, then save the place")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where --remove saves the place. Overwrites the input when not given")
+                        .requires("remove")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("clean")
+                .about("Remove empty Folders and Models, like those left behind by removals, except the paths in clean_protected")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to clean")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where to save the cleaned place. Overwrites the input when not given")
+                        .conflicts_with("dry-run")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Only print what would be removed")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Time parsing, serializing and describing a place, and applying a synthetic modification to it")
+                .arg(
+                    Arg::new("place")
+                        .value_name("PLACE")
+                        .help("Place file to measure, which isn't changed")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("instances")
+                        .long("instances")
+                        .value_name("N")
+                        .help("Number of parts the synthetic modification adds")
+                        .default_value("1000")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("runs")
+                        .long("runs")
+                        .value_name("N")
+                        .help("Times to run each stage, reporting the median")
+                        .default_value("5")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
         )
 }
@@ -0,0 +1,601 @@
+use clap::ArgMatches;
+use rbx_dom_weak::WeakDom;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::bench;
+use crate::clean::{self, Protected};
+use crate::budget;
+use crate::config::Config;
+use crate::context::ContextOptions;
+use crate::csg;
+use crate::dedup;
+use crate::diff;
+use crate::error::RbxMcpError;
+use crate::examples;
+use crate::gemini_api::{GeminiClient, GenerationParams};
+use crate::health;
+use crate::heightmap;
+use crate::journal;
+use crate::lock::PlaceLock;
+use crate::merge;
+use crate::open_cloud::OpenCloudClient;
+use crate::overlap;
+use crate::patch::{self, Patch};
+use crate::prompt;
+use crate::query::Region;
+use crate::report;
+use crate::response_cache::{self, ResponseCache};
+use crate::rest::{self, RestState};
+use crate::roblox::{self, ApplyOptions, Modification};
+use crate::rojo;
+use crate::scripts;
+use crate::streaming;
+use crate::sync;
+use crate::terrain;
+use crate::upload;
+use crate::vertex::VertexAuth;
+use crate::warnings::log_warnings;
+
+/// Print warnings about a modification before it is applied
+pub fn warn_before_apply(place: &WeakDom, modification: &Modification, config: &Config) {
+    // Scripts that index Workspace directly break when instances stream out
+    if streaming::streaming_enabled(place) {
+        for warning in streaming::streaming_warnings(modification) {
+            warn!("{}", warning);
+        }
+    }
+
+    // Unions can't be built or regenerated here, only in Studio
+    for warning in csg::union_warnings(place, place.root_ref(), modification) {
+        warn!("{}", warning);
+    }
+
+    // Warn about expensive content before it goes into the place
+    let cost = budget::estimate_cost(place, place.root_ref(), modification, config.asset_library.as_deref());
+    for warning in cost.over_budget(&config.budget) {
+        warn!("{}", warning);
+    }
+}
+
+/// A Gemini client with the model, sampling settings, system prompt and examples from --model, --temperature and so on.
+/// It talks to Vertex AI when the config has a `vertex` section, or else to AI Studio with the API key.
+/// None when there's neither.
+pub fn gemini_client(matches: &ArgMatches, api_key: Option<String>, config: &Config) -> Result<Option<GeminiClient>, Box<dyn Error>> {
+    let mut params = GenerationParams::default();
+    for name in GenerationParams::NAMES {
+        if let Some(value) = matches.get_one::<String>(name) {
+            params.set(name, value).map_err(|e| format!("--{}: {}", name, e))?;
+        }
+    }
+    let model = matches.get_one::<String>("model").cloned();
+    let client = match (&config.vertex, api_key) {
+        (Some(vertex), _) => {
+            let auth = VertexAuth::from_config(vertex)?;
+            info!("Using Vertex AI in project {} ({})", vertex.project, vertex.location);
+            GeminiClient::vertex(auth, model.unwrap_or_else(|| GeminiClient::FLASH_MODEL.to_string()))
+        }
+        (None, Some(api_key)) => match model {
+            Some(model) => GeminiClient::new(api_key, model),
+            None => GeminiClient::flash(api_key),
+        },
+        (None, None) => return Ok(None),
+    };
+    let mut client = client
+        .with_prices(&config.prices)
+        .with_params(params)
+        .with_safety_settings(&config.safety_settings)
+        .with_generation_config(config.generation_config.clone())
+        .with_fallbacks(&config.fallback_models);
+    if let Some(dir) = matches.get_one::<PathBuf>("examples") {
+        let examples = examples::load_examples(dir)?;
+        info!("Loaded {} few-shot examples from {}", examples.len(), dir.display());
+        client = client.with_examples(examples);
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("system-prompt") {
+        client = client.with_system_prompt(prompt::load_template(path)?);
+    }
+    if !matches.get_flag("no-cache") {
+        client = client.with_response_cache(ResponseCache::new());
+    }
+    Ok(Some(client))
+}
+
+/// Apply a Modification JSON file to a place without calling Gemini
+pub async fn apply(matches: &ArgMatches, config: &Config, options: &ApplyOptions) -> Result<(), Box<dyn Error>> {
+    let modification_path = matches.get_one::<PathBuf>("modification").ok_or("Modification file must be provided")?;
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let text = std::fs::read_to_string(modification_path)?;
+    let mut modification: Modification = serde_json::from_str(&text)
+        .map_err(|e| format!("Error parsing {}: {}", modification_path.display(), e))?;
+    let mut place = roblox::parse_roblox_file(place_path)?;
+
+    warn_before_apply(&place, &modification, config);
+
+    let root_ref = place.root_ref();
+    let change_id = journal::new_change_id();
+    let mut options = ApplyOptions { change_id: Some(change_id.clone()), ..options.clone() };
+    if matches.get_flag("yes") {
+        options.max_new_instances = None;
+    }
+    // Uploads can't be taken back, so they wait until the modification is known to apply
+    if upload::has_inline_data(&modification) {
+        roblox::check_modification(&place, &modification, root_ref, &options).map_err(with_limit_remedy)?;
+        upload::upload_modification(&mut modification, &config.upload).await?;
+    }
+    let before = roblox::snapshot(&place);
+    let report = roblox::json_to_weakdom(&mut place, &modification, root_ref, &options).map_err(with_limit_remedy)?;
+    log_warnings(&report.warnings);
+    for overlap in overlap::find_overlaps(&place, &report.counts.created) {
+        warn!("{}", overlap);
+    }
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!(
+        "Applied {} ({})",
+        modification_path.display(), journal::describe_modification(&modification)
+    ))?;
+    audit::record_applied(output_path, &change_id, &modification, &before, &place, &report)?;
+
+    info!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+/// Say how to apply a modification anyway when it's over --max-new-instances
+fn with_limit_remedy(e: RbxMcpError) -> Box<dyn Error> {
+    match e {
+        RbxMcpError::TooManyInstances { .. } => format!("{}. If that many are wanted, raise --max-new-instances or pass --yes", e).into(),
+        e => e.into(),
+    }
+}
+
+/// Build a place from a Rojo project
+pub fn build_rojo(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let project_path = matches.get_one::<PathBuf>("project").ok_or("Project must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").ok_or("Output file must be provided")?;
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let place = rojo::build_project(project_path)?;
+    roblox::write_roblox_file(output_path, &place)?;
+    info!("Wrote {} ({} instances)", output_path.display(), place.descendants().count() - 1);
+    Ok(())
+}
+
+/// Print a structural diff between two place files
+pub fn diff(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let old_path = matches.get_one::<PathBuf>("old").ok_or("Old place file must be provided")?;
+    let new_path = matches.get_one::<PathBuf>("new").ok_or("New place file must be provided")?;
+
+    let old = roblox::parse_roblox_file(old_path)?;
+    let new = roblox::parse_roblox_file(new_path)?;
+    diff::print_diff(&diff::diff_places(&old, &new));
+
+    if let Some(patch_path) = matches.get_one::<PathBuf>("emit-patch") {
+        let patch = patch::create_patch(&old, &new);
+        std::fs::write(patch_path, serde_json::to_string_pretty(&patch)?)?;
+        info!("Wrote patch to {} ({})", patch_path.display(), patch::describe_patch(&patch));
+    }
+    Ok(())
+}
+
+/// Convert a place to a Rojo project
+pub fn export_rojo(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_dir = matches.get_one::<PathBuf>("output").ok_or("Output directory must be provided")?;
+
+    let place = roblox::parse_roblox_file(place_path)?;
+    let name = place_path.file_stem().map_or("place".into(), |stem| stem.to_string_lossy());
+    let files = rojo::export_project(&place, output_dir, &name)?;
+    info!("Wrote {} files to {}", files.len(), output_dir.display());
+    Ok(())
+}
+
+/// Three-way merge of two edited copies of a place
+pub fn merge(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let base_path = matches.get_one::<PathBuf>("base").ok_or("Base place file must be provided")?;
+    let ours_path = matches.get_one::<PathBuf>("ours").ok_or("Our place file must be provided")?;
+    let theirs_path = matches.get_one::<PathBuf>("theirs").ok_or("Their place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").ok_or("Output file must be provided")?;
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let mut place = roblox::parse_roblox_file(base_path)?;
+    let ours = roblox::parse_roblox_file(ours_path)?;
+    let theirs = roblox::parse_roblox_file(theirs_path)?;
+    let merged = merge::merge_places(&place, &ours, &theirs);
+    let before = roblox::snapshot(&place);
+
+    let change_id = journal::new_change_id();
+    let report = patch::apply_patch(&mut place, &merged.patch, Some(&change_id));
+    log_warnings(&report.warnings);
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!(
+        "Merged {} and {} onto {} ({}, {} conflicts)",
+        ours_path.display(), theirs_path.display(), base_path.display(),
+        patch::describe_patch(&merged.patch), merged.conflicts.len()
+    ))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+    info!("Wrote {}", output_path.display());
+
+    if merged.conflicts.is_empty() {
+        return Ok(());
+    }
+    for conflict in &merged.conflicts {
+        warn!("Conflict: {}", conflict);
+    }
+    Err(format!("{} conflicts, kept ours for each", merged.conflicts.len()).into())
+}
+
+/// Run a patch subcommand
+pub fn patch(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("apply", sub_matches)) => patch_apply(sub_matches),
+        _ => Err("Unknown patch subcommand".into()),
+    }
+}
+
+/// Replay a patch from diff --emit-patch onto a place
+fn patch_apply(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let patch_path = matches.get_one::<PathBuf>("patch").ok_or("Patch file must be provided")?;
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let text = std::fs::read_to_string(patch_path)?;
+    let patch: Patch = serde_json::from_str(&text)
+        .map_err(|e| format!("Error parsing {}: {}", patch_path.display(), e))?;
+    let mut place = roblox::parse_roblox_file(place_path)?;
+    let before = roblox::snapshot(&place);
+
+    let change_id = journal::new_change_id();
+    let report = patch::apply_patch(&mut place, &patch, Some(&change_id));
+    log_warnings(&report.warnings);
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!(
+        "Applied patch {} ({})",
+        patch_path.display(), patch::describe_patch(&patch)
+    ))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+
+    info!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+/// Run a scripts subcommand
+pub fn scripts(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("export", sub_matches)) => scripts_export(sub_matches),
+        Some(("import", sub_matches)) => scripts_import(sub_matches),
+        _ => Err("Unknown scripts subcommand".into()),
+    }
+}
+
+/// Dump every script's Source to files so it can be edited outside rbx-mcp
+fn scripts_export(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let out_dir = matches.get_one::<PathBuf>("out").ok_or("Output directory must be provided")?;
+
+    let place = roblox::parse_roblox_file(place_path)?;
+    let files = scripts::export_scripts(&place, out_dir)?;
+    info!("Wrote {} scripts to {}", files.len(), out_dir.display());
+    Ok(())
+}
+
+/// Write edited script files back into a place
+fn scripts_import(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let from_dir = matches.get_one::<PathBuf>("from").ok_or("Script directory must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let mut place = roblox::parse_roblox_file(place_path)?;
+    let before = roblox::snapshot(&place);
+    let change_id = journal::new_change_id();
+    let report = scripts::import_scripts(&mut place, from_dir, Some(&change_id))?;
+    for path in &report.updated {
+        info!("Updated {}", path);
+    }
+    for path in &report.created {
+        info!("Created {}", path);
+    }
+    if report.updated.is_empty() && report.created.is_empty() {
+        info!("No scripts changed ({} unchanged)", report.unchanged);
+        return Ok(());
+    }
+
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!(
+        "Imported scripts from {} ({} updated, {} created)",
+        from_dir.display(), report.updated.len(), report.created.len()
+    ))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+    info!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+/// Undo a change by applying the inverse patch recorded in the audit log
+pub fn undo(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let log_path = matches.get_one::<PathBuf>("from-log").cloned().unwrap_or_else(|| audit::audit_path(place_path));
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let entries = audit::read(&log_path)?;
+    let entry = audit::find_undoable(&entries, matches.get_one::<String>("change").map(String::as_str))?;
+    let mut place = roblox::parse_roblox_file(place_path)?;
+
+    let change_id = journal::new_change_id();
+    let before = roblox::snapshot(&place);
+    let report = patch::apply_patch(&mut place, &entry.inverse, Some(&change_id));
+    log_warnings(&report.warnings);
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!(
+        "Undid {} ({})",
+        entry.change_id, patch::describe_patch(&entry.inverse)
+    ))?;
+    audit::record(output_path, &change_id, None, Some(&entry.change_id), &before, &place)?;
+
+    info!("Undid {}, wrote {}", entry.change_id, output_path.display());
+    Ok(())
+}
+
+/// Print counts and likely problems of a place
+pub fn stats(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let place = roblox::parse_roblox_file(place_path)?;
+    report::print_place_stats(&place, &report::place_stats(&place));
+    Ok(())
+}
+
+/// Check a place for problems, fixing those that can be fixed with --fix.
+/// Fails when problems are left, so scripts and CI can check a place.
+pub fn validate(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let mut place = roblox::parse_roblox_file(place_path)?;
+
+    let issues = health::check_place(&place);
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    let mut remaining = issues.len();
+    if matches.get_flag("fix") && issues.iter().any(|issue| issue.kind.fixable()) {
+        let _lock = PlaceLock::acquire(output_path)?;
+        let change_id = journal::new_change_id();
+        let before = roblox::snapshot(&place);
+        let fixed = health::fix_issues(&mut place, &issues, Some(&change_id));
+        roblox::write_roblox_file(output_path, &place)?;
+        journal::record(output_path, Some(&change_id), &format!("Fixed {} problems found by validate", fixed))?;
+        audit::record(output_path, &change_id, None, None, &before, &place)?;
+        info!("Fixed {} problems, wrote {}", fixed, output_path.display());
+        remaining -= fixed;
+    }
+
+    if remaining > 0 {
+        return Err(format!("{} problems left in {}", remaining, output_path.display()).into());
+    }
+    println!("No problems found");
+    Ok(())
+}
+
+/// Report structurally identical siblings, removing all but the first of each with --remove
+pub fn dedup(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let mut place = roblox::parse_roblox_file(place_path)?;
+
+    let groups = dedup::find_duplicates(&place);
+    if groups.is_empty() {
+        println!("No duplicates found");
+        return Ok(());
+    }
+    for group in &groups {
+        let descendants = place.descendants_of(group.original).count() - 1;
+        println!("{} has {} copies ({} descendants each):", roblox::instance_path(&place, group.original), group.duplicates.len(), descendants);
+        for &id in &group.duplicates {
+            println!("  {}", roblox::instance_path(&place, id));
+        }
+    }
+
+    if matches.get_flag("remove") {
+        let _lock = PlaceLock::acquire(output_path)?;
+        let change_id = journal::new_change_id();
+        let before = roblox::snapshot(&place);
+        let removed = dedup::remove_duplicates(&mut place, &groups);
+        roblox::write_roblox_file(output_path, &place)?;
+        journal::record(output_path, Some(&change_id), &format!("Removed {} duplicates", removed))?;
+        audit::record(output_path, &change_id, None, None, &before, &place)?;
+        info!("Removed {} duplicates, wrote {}", removed, output_path.display());
+    }
+    Ok(())
+}
+
+/// Remove every empty Folder and Model from a place, except the protected ones
+pub fn clean(matches: &ArgMatches, config: &Config, options: &ApplyOptions) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let dry_run = matches.get_flag("dry-run");
+    let _lock = (!dry_run).then(|| PlaceLock::acquire(output_path)).transpose()?;
+    let mut place = roblox::parse_roblox_file(place_path)?;
+
+    let before = roblox::snapshot(&place);
+    let protected = Protected::resolve(&place, &config.clean_protected, options.allow_package_edits);
+    let removed = clean::clean_place(&mut place, &protected);
+    for path in &removed {
+        println!("{}", path);
+    }
+    if removed.is_empty() {
+        println!("Nothing to clean");
+        return Ok(());
+    }
+    if dry_run {
+        println!("Would remove {} empty containers", removed.len());
+        return Ok(());
+    }
+
+    let change_id = journal::new_change_id();
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!("Cleaned up {} empty containers", removed.len()))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+    info!("Removed {} empty containers, wrote {}", removed.len(), output_path.display());
+    Ok(())
+}
+
+/// Export terrain in a region to heightmap images
+pub fn terrain_export(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let prefix = matches.get_one::<PathBuf>("output").ok_or("Output prefix must be provided")?;
+    let coords: Vec<f32> = matches.get_many::<f32>("region").ok_or("Region must be provided")?.copied().collect();
+    let region = Region {
+        min: [coords[0], coords[1], coords[2]],
+        max: [coords[3], coords[4], coords[5]],
+    };
+
+    let place = roblox::parse_roblox_file(place_path)?;
+    let (_, grid) = terrain::read_terrain(&place)?;
+    for path in heightmap::export_heightmap(&grid, &region, prefix)? {
+        info!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Import edited heightmap images back into a place's terrain
+pub fn terrain_import(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let prefix = matches.get_one::<PathBuf>("prefix").ok_or("Image prefix must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let mut place = roblox::parse_roblox_file(place_path)?;
+    let before = roblox::snapshot(&place);
+    let (terrain_id, mut grid) = terrain::read_terrain(&place)?;
+    let changed = heightmap::import_heightmap(&mut grid, prefix)?;
+    terrain::write_terrain(&mut place, terrain_id, &grid)?;
+
+    let change_id = journal::new_change_id();
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!("Imported terrain from {}", prefix.display()))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+    info!("Changed {} voxel columns, terrain now has {} solid voxels", changed, grid.solid_count());
+    info!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+/// Build terrain from a grayscale heightmap stretched over a region
+pub fn import_heightmap(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let image = matches.get_one::<PathBuf>("image").ok_or("Heightmap image must be provided")?;
+    let output_path = matches.get_one::<PathBuf>("output").unwrap_or(place_path);
+    let coords: Vec<f32> = matches.get_many::<f32>("region").ok_or("Region must be provided")?.copied().collect();
+    let region = Region {
+        min: [coords[0], coords[1], coords[2]],
+        max: [coords[3], coords[4], coords[5]],
+    };
+    let material_name = matches.get_one::<String>("material").ok_or("Material must be provided")?;
+    let material = terrain::material_id(material_name)
+        .filter(|&id| id != 0)
+        .ok_or_else(|| format!("Unknown terrain material '{}'", material_name))?;
+    let _lock = PlaceLock::acquire(output_path)?;
+
+    let mut place = roblox::parse_roblox_file(place_path)?;
+    let before = roblox::snapshot(&place);
+    let (terrain_id, mut grid) = terrain::read_terrain(&place)?;
+    let changed = heightmap::import_grayscale(&mut grid, image, &region, material)?;
+    terrain::write_terrain(&mut place, terrain_id, &grid)?;
+
+    let change_id = journal::new_change_id();
+    roblox::write_roblox_file(output_path, &place)?;
+    journal::record(output_path, Some(&change_id), &format!("Imported heightmap {}", image.display()))?;
+    audit::record(output_path, &change_id, None, None, &before, &place)?;
+    info!("Changed {} voxel columns, terrain now has {} solid voxels", changed, grid.solid_count());
+    info!("Wrote {}", output_path.display());
+    Ok(())
+}
+
+/// Serve the core operations over HTTP
+pub async fn serve(matches: &ArgMatches, config: &Config, options: &ApplyOptions) -> Result<(), Box<dyn Error>> {
+    if !matches.get_flag("rest") {
+        return Err("Choose a server mode. Only --rest is supported".into());
+    }
+    let host = *matches.get_one::<IpAddr>("host").ok_or("Host must be provided")?;
+    let port = *matches.get_one::<u16>("port").ok_or("Port must be provided")?;
+    let root = matches.get_one::<PathBuf>("root").cloned().unwrap_or_else(|| PathBuf::from("."));
+    let root = root.canonicalize().map_err(|e| format!("Error opening {}: {}", root.display(), e))?;
+
+    let api_key = matches
+        .get_one::<String>("api-key")
+        .cloned()
+        .or_else(|| std::env::var("GEMINI_API_KEY").ok());
+    let client = gemini_client(matches, api_key, config)?;
+    if client.is_none() {
+        warn!("No Gemini API key given, /generate will be unavailable");
+    }
+
+    // Ctrl+C stops accepting requests and cancels generations in flight
+    let shutdown = CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("Shutting down");
+                shutdown.cancel();
+            }
+        }
+    });
+
+    let state = RestState {
+        config: config.clone(),
+        apply_options: options.clone(),
+        client,
+        shutdown,
+        root,
+    };
+    rest::serve(SocketAddr::new(host, port), state).await;
+    Ok(())
+}
+
+/// Publish a place to Open Cloud whenever it changes
+pub async fn sync(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let api_key = matches
+        .get_one::<String>("open-cloud-key")
+        .cloned()
+        .or_else(|| std::env::var("ROBLOX_API_KEY").ok())
+        .ok_or("Open Cloud API key not provided. Use --open-cloud-key or set ROBLOX_API_KEY environment variable")?;
+
+    sync::run(place_path, &config.sync, &OpenCloudClient::new(api_key)).await
+}
+
+/// Manage the response cache
+pub fn cache(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    match matches.subcommand() {
+        Some(("clear", _)) => {
+            let removed = response_cache::clear(Path::new(response_cache::CACHE_DIR))?;
+            println!("Deleted {} cached responses", removed);
+            Ok(())
+        }
+        _ => Err("Unknown cache subcommand".into()),
+    }
+}
+
+/// Measure the DOM pipeline on a place
+pub fn bench(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let place_path = matches.get_one::<PathBuf>("place").ok_or("Place file must be provided")?;
+    let instances = *matches.get_one::<usize>("instances").ok_or("Instance count must be provided")?;
+    let runs = *matches.get_one::<usize>("runs").ok_or("Run count must be provided")?;
+
+    // Describe the place the way prompts do, with the configured filters and viewpoints
+    let options = ContextOptions {
+        filters: config.context_filters.clone(),
+        viewpoints: config.viewpoints.clone(),
+        ..ContextOptions::default()
+    };
+    let report = bench::run(place_path, &options, instances, runs)?;
+    println!("{}", report);
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::budget::Budget;
+use crate::context::{ContextBudget, PropertyFilter, Viewpoint};
+use crate::gemini_api::ModelPrice;
+use crate::sync::SyncConfig;
+use crate::upload::UploadConfig;
+use crate::vertex::VertexConfig;
+
+/// Config file picked up from the working directory when --config isn't given
+pub const DEFAULT_CONFIG_FILE: &str = "rbx-mcp.json";
+
+/// Project settings loaded from a JSON config file
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub budget: Budget,
+    /// How big the place description sent with each prompt may get
+    pub context_budget: ContextBudget,
+    /// Which properties to send to the model, by class name
+    pub context_filters: HashMap<String, PropertyFilter>,
+    /// Named positions included in the context so prompts can refer to them
+    pub viewpoints: Vec<Viewpoint>,
+    /// Prompt snippets expanded from @name before sending
+    pub macros: HashMap<String, String>,
+    /// Open Cloud target for the sync subcommand
+    pub sync: SyncConfig,
+    /// Token prices by model name, in US dollars per million tokens, for cost estimates.
+    /// Added to the built-in prices, replacing them for the same model.
+    pub prices: HashMap<String, ModelPrice>,
+    /// Gemini safety thresholds by harm category, like "HARM_CATEGORY_DANGEROUS_CONTENT": "BLOCK_ONLY_HIGH"
+    pub safety_settings: HashMap<String, String>,
+    /// Extra generationConfig fields sent with every request as is, like stopSequences or seed
+    pub generation_config: serde_json::Map<String, serde_json::Value>,
+    /// Properties added instances get when they don't set them, by class name (which covers
+    /// subclasses, like BasePart), in the same form as a modification's properties
+    pub default_properties: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// Models to try in order when a request to the main one fails, like "gemini-1.5-pro" or "openai:gpt-4o"
+    pub fallback_models: Vec<String>,
+    /// Send Gemini requests to Vertex AI in this Google Cloud project instead of AI Studio
+    pub vertex: Option<VertexConfig>,
+    /// Paths `clean` and --auto-clean never remove, even when empty. Globs are allowed.
+    pub clean_protected: Vec<String>,
+    /// Directory of .rbxmx models that modifications and prompts can insert by name
+    pub asset_library: Option<PathBuf>,
+    /// Who assets uploaded from inline data in modifications belong to
+    pub upload: UploadConfig,
+}
+
+impl Config {
+    /// Load config from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let config = serde_json::from_str(&text)?;
+        Ok(config)
+    }
+
+    /// Load the config file given on the command line, or rbx-mcp.json if it exists, or the defaults
+    pub fn load_or_default(path: Option<&PathBuf>) -> Result<Config, Box<dyn Error>> {
+        match path {
+            Some(path) => Config::load(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Config::load(DEFAULT_CONFIG_FILE),
+            None => Ok(Config::default()),
+        }
+    }
+}
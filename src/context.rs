@@ -0,0 +1,385 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, WeakDom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use tracing::{info, warn};
+
+use crate::property::PropertyValue;
+use crate::query;
+use crate::roblox::{instance_path, is_package_root};
+
+/// Bookkeeping properties that tell the model nothing about the place
+const NOISE_PROPERTIES: [&str; 5] = ["UniqueId", "HistoryId", "SourceAssetId", "Capabilities", "DefinesCapabilities"];
+
+/// Children described for each instance when a summary has to leave some out
+const SUMMARY_CHILDREN: usize = 20;
+/// Classes counted for each service in the overview of a summarized place
+const OVERVIEW_CLASSES: usize = 8;
+/// Top-level model names listed for each service in the overview
+const OVERVIEW_MODELS: usize = 20;
+
+/// Which properties of a class are described to the model
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PropertyFilter {
+    /// Only these properties are included. All are included when missing.
+    pub include: Option<Vec<String>>,
+    /// These properties are always left out
+    pub exclude: Vec<String>,
+}
+
+impl PropertyFilter {
+    fn allows(&self, property: &str) -> bool {
+        let included = self.include.as_ref().is_none_or(|include| include.iter().any(|p| p == property));
+        included && !self.exclude.iter().any(|p| p == property)
+    }
+}
+
+/// A named camera position the user can refer to in prompts
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Viewpoint {
+    pub name: String,
+    pub position: [f32; 3],
+    /// Direction the viewer is looking
+    pub look: [f32; 3],
+}
+
+impl Viewpoint {
+    /// Describe the viewpoint with its look direction normalized and the direction to the viewer's right
+    fn describe(&self) -> String {
+        let [x, y, z] = self.look;
+        let length = (x * x + y * y + z * z).sqrt().max(f32::EPSILON);
+        let look = [x / length, y / length, z / length];
+
+        // Right is look x up, flattened onto the ground
+        let (rx, rz) = (-look[2], look[0]);
+        let right_length = (rx * rx + rz * rz).sqrt();
+        let right = if right_length > f32::EPSILON {
+            format!("[{:.2}, 0, {:.2}]", rx / right_length, rz / right_length)
+        } else {
+            "undefined (looking straight up or down)".to_string()
+        };
+
+        format!(
+            "Viewpoint \"{}\": standing at [{}, {}, {}] looking along [{:.2}, {:.2}, {:.2}], to the right is {}",
+            self.name, self.position[0], self.position[1], self.position[2], look[0], look[1], look[2], right
+        )
+    }
+}
+
+/// What to do when the place description is bigger than the context budget
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OverBudget {
+    /// Send it anyway with a warning
+    Warn,
+    /// Don't send the prompt
+    Refuse,
+    /// Send an overview of the place with deep subtrees and long child lists summarized until it fits
+    Summarize,
+}
+
+/// How big the place description sent with each prompt may get
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ContextBudget {
+    /// Estimated tokens, at about 4 characters a token
+    pub max_tokens: usize,
+    pub over_budget: OverBudget,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        ContextBudget { max_tokens: 500_000, over_budget: OverBudget::Summarize }
+    }
+}
+
+/// Rough token count of some text, for checking it against a budget before it's sent
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Options controlling how the place is described to the model
+#[derive(Default, Clone)]
+pub struct ContextOptions {
+    /// Include the contents of packages, which are excluded by default to save tokens
+    pub include_packages: bool,
+    /// Property filters by class name. The filter for the closest superclass applies.
+    pub filters: HashMap<String, PropertyFilter>,
+    /// Named positions prompts can refer to, like "visible from spawn"
+    pub viewpoints: Vec<Viewpoint>,
+    /// Names of the models in the asset library, which a modification can insert
+    pub assets: Vec<String>,
+    /// Instances this deep are described by a summary of what's under them instead of their children.
+    /// Services are depth 0. Everything is described when None.
+    pub max_depth: Option<usize>,
+    /// Instances with more children than this describe the first ones and count the rest.
+    /// Every child is described when None.
+    pub max_children: Option<usize>,
+}
+
+impl ContextOptions {
+    /// Find the filter that applies to a class, walking up its superclasses
+    fn filter_for(&self, class: &str) -> Option<&PropertyFilter> {
+        if self.filters.is_empty() {
+            return None;
+        }
+        let database = rbx_reflection_database::get();
+        let mut current = Some(class.to_string());
+        while let Some(name) = current {
+            if let Some(filter) = self.filters.get(&name) {
+                return Some(filter);
+            }
+            current = database.classes.get(name.as_str())
+                .and_then(|descriptor| descriptor.superclass.as_ref())
+                .map(|superclass| superclass.to_string());
+        }
+        None
+    }
+}
+
+/// Describe the place as an indented tree of instances and their properties
+pub fn describe_place(dom: &WeakDom, options: &ContextOptions) -> String {
+    let mut out = String::new();
+    for viewpoint in &options.viewpoints {
+        let _ = writeln!(out, "{}", viewpoint.describe());
+    }
+    if !options.assets.is_empty() {
+        let _ = writeln!(out, "Assets that can be inserted by name: {}", options.assets.join(", "));
+    }
+    for &child_id in dom.root().children() {
+        describe_instance(dom, child_id, 0, options, &mut out);
+    }
+    out
+}
+
+/// Describe an instance and what's under it. `max_depth` counts from the instance, which is depth 0.
+pub fn describe_subtree(dom: &WeakDom, instance_id: Ref, options: &ContextOptions) -> String {
+    let mut out = String::new();
+    describe_instance(dom, instance_id, 0, options, &mut out);
+    out
+}
+
+/// Describe the place, checking its estimated size against the budget first.
+/// Errors when the budget says to refuse, or when summarizing can't get it small enough.
+pub fn describe_place_within_budget(dom: &WeakDom, options: &ContextOptions, budget: &ContextBudget) -> Result<String, String> {
+    let description = describe_place(dom, options);
+    let tokens = estimate_tokens(&description);
+    if tokens <= budget.max_tokens {
+        return Ok(description);
+    }
+    let over = format!("The place description is about {} tokens, over the context budget of {}", tokens, budget.max_tokens);
+    match budget.over_budget {
+        OverBudget::Warn => {
+            warn!("{}, the response may be cut off", over);
+            Ok(description)
+        }
+        OverBudget::Refuse => Err(format!("{}. Raise context_budget.max_tokens or narrow context_filters", over)),
+        OverBudget::Summarize => {
+            let deepest = dom.descendants().map(|instance| depth_of(dom, instance.referent())).max().unwrap_or(0);
+            let overview = describe_overview(dom);
+            // Cutting the tree off higher up loses more than leaving out some of a long list of children
+            for max_depth in (0..deepest).rev() {
+                for max_children in [None, Some(SUMMARY_CHILDREN)] {
+                    let tree = describe_place(dom, &ContextOptions { max_depth: Some(max_depth), max_children, ..options.clone() });
+                    let summarized = format!("{}\n{}\n{}", summary_hint(tokens, max_depth), overview, tree);
+                    let summarized_tokens = estimate_tokens(&summarized);
+                    if summarized_tokens <= budget.max_tokens {
+                        info!("{}, summarizing below depth {} (about {} tokens)", over, max_depth, summarized_tokens);
+                        return Ok(summarized);
+                    }
+                }
+            }
+            Err(format!("{}, even with only the services described", over))
+        }
+    }
+}
+
+/// How many ancestors below the root an instance has. Services are 0.
+fn depth_of(dom: &WeakDom, id: Ref) -> usize {
+    let mut depth: usize = 0;
+    let mut current = dom.get_by_ref(id).map(|instance| instance.parent());
+    while let Some(parent) = current.and_then(|parent| dom.get_by_ref(parent)) {
+        depth += 1;
+        current = Some(parent.parent());
+    }
+    depth.saturating_sub(1)
+}
+
+/// What a summarized description leaves out, and how to reach the instances it doesn't show
+fn summary_hint(tokens: usize, max_depth: usize) -> String {
+    format!(
+        "This place is too big to describe in full (about {} tokens), so this is an overview: instance counts for each service, \
+         the top-level models, and the tree cut off below depth {}. Lines ending in [N descendants: ...] and \"... N more children\" \
+         stand for instances that aren't described. They can still be targeted by path, and glob paths like \"Workspace/**/Door\" \
+         or class and tag entries in subtract reach them without knowing their names.",
+        tokens, max_depth
+    )
+}
+
+/// Instance counts by class for each service, and the names of the models directly in it
+pub fn describe_overview(dom: &WeakDom) -> String {
+    let mut out = String::new();
+    for &service_id in dom.root().children() {
+        let Some(service) = dom.get_by_ref(service_id) else {
+            continue;
+        };
+        let (total, classes) = count_classes(dom.descendants_of(service_id).skip(1), OVERVIEW_CLASSES);
+        if total == 0 {
+            continue;
+        }
+        let noun = if total == 1 { "instance" } else { "instances" };
+        let _ = writeln!(out, "{} ({}): {} {}, {}", service.name, service.class, total, noun, classes);
+
+        let models: Vec<&str> = service.children().iter()
+            .filter_map(|&id| dom.get_by_ref(id))
+            .filter(|child| child.class == "Model")
+            .map(|child| child.name.as_str())
+            .collect();
+        if !models.is_empty() {
+            let mut names = models[..models.len().min(OVERVIEW_MODELS)].join(", ");
+            if models.len() > OVERVIEW_MODELS {
+                let _ = write!(names, " and {} more", models.len() - OVERVIEW_MODELS);
+            }
+            let _ = writeln!(out, "  models: {}", names);
+        }
+    }
+    out
+}
+
+/// Count instances and their most common classes, like (130, "120 Part, 8 Model, 2 Script")
+fn count_classes<'a>(instances: impl Iterator<Item = &'a Instance>, limit: usize) -> (usize, String) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut total = 0;
+    for instance in instances {
+        *counts.entry(instance.class.as_str()).or_default() += 1;
+        total += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let mut classes: Vec<String> = counts.iter().take(limit).map(|(class, count)| format!("{} {}", count, class)).collect();
+    if counts.len() > limit {
+        classes.push("...".to_string());
+    }
+    (total, classes.join(", "))
+}
+
+/// Summary of everything under an instance, like "[130 descendants: 120 Part, 8 Model, 2 Script]"
+fn summarize_descendants(dom: &WeakDom, instance_id: Ref) -> String {
+    let (total, classes) = count_classes(dom.descendants_of(instance_id).skip(1), 3);
+    format!("[{} descendants: {}]", total, classes)
+}
+
+fn describe_instance(dom: &WeakDom, instance_id: Ref, depth: usize, options: &ContextOptions, out: &mut String) {
+    let Some(instance) = dom.get_by_ref(instance_id) else {
+        return;
+    };
+
+    let filter = options.filter_for(&instance.class);
+    let mut properties: Vec<(&str, &Variant)> = instance.properties.iter()
+        .map(|(name, value)| (name.as_str(), value))
+        .filter(|(name, value)| !NOISE_PROPERTIES.contains(name) && !is_empty_value(value))
+        .filter(|(name, _)| filter.is_none_or(|filter| filter.allows(name)))
+        .collect();
+    properties.sort_by_key(|(name, _)| *name);
+
+    let _ = write!(out, "{}{} ({})", "  ".repeat(depth), instance.name, instance.class);
+    for (name, value) in properties {
+        let _ = write!(out, " {}={}", name, format_variant(dom, value));
+    }
+
+    // Gemini can't see geometry, so the space each structure takes up is spelled out
+    if instance.class == "Model" && dom.get_by_ref(instance.parent()).is_some_and(|parent| parent.class == "Workspace") {
+        if let Some(bounds) = query::bounding_box(dom, instance_id) {
+            let _ = write!(out, " [bounds: min {}, max {}]", format_point(bounds.min), format_point(bounds.max));
+        }
+    }
+
+    if !options.include_packages && is_package_root(dom, instance_id) {
+        let _ = writeln!(out, " [package, {} children hidden]", instance.children().len());
+        return;
+    }
+    if options.max_depth == Some(depth) && !instance.children().is_empty() {
+        let _ = writeln!(out, " {}", summarize_descendants(dom, instance_id));
+        return;
+    }
+    out.push('\n');
+
+    let shown = instance.children().len().min(options.max_children.unwrap_or(usize::MAX));
+    for &child_id in &instance.children()[..shown] {
+        describe_instance(dom, child_id, depth + 1, options, out);
+    }
+    let hidden = &instance.children()[shown..];
+    if !hidden.is_empty() {
+        let (count, classes) = count_classes(hidden.iter().filter_map(|&id| dom.get_by_ref(id)), 3);
+        let _ = writeln!(out, "{}... {} more children: {}", "  ".repeat(depth + 1), count, classes);
+    }
+}
+
+/// A point rounded to a tenth of a stud, like [12.5, 0, -4]
+fn format_point(point: [f32; 3]) -> String {
+    let [x, y, z] = point.map(|v| (v * 10.0).round() / 10.0);
+    format!("[{}, {}, {}]", x, y, z)
+}
+
+fn is_empty_value(value: &Variant) -> bool {
+    match value {
+        Variant::Tags(tags) => tags.is_empty(),
+        Variant::Attributes(attributes) => attributes.iter().next().is_none(),
+        _ => false,
+    }
+}
+
+/// Format a property value compactly, using the same shapes the Modification schema accepts
+pub fn format_variant(dom: &WeakDom, value: &Variant) -> String {
+    match value {
+        Variant::Bool(b) => b.to_string(),
+        Variant::Int32(n) => n.to_string(),
+        Variant::Int64(n) => n.to_string(),
+        Variant::Float32(n) => n.to_string(),
+        Variant::Float64(n) => n.to_string(),
+        Variant::String(s) => format!("{:?}", s),
+        Variant::ContentId(id) => format!("{:?}", id.as_str()),
+        Variant::Enum(e) => e.to_u32().to_string(),
+        Variant::BrickColor(color) => (*color as u16).to_string(),
+        Variant::Vector3(v) => format!("[{}, {}, {}]", v.x, v.y, v.z),
+        Variant::Vector2(v) => format!("[{}, {}]", v.x, v.y),
+        Variant::Rect(r) => format!("[{}, {}, {}, {}]", r.min.x, r.min.y, r.max.x, r.max.y),
+        Variant::UDim(u) => format!("[{}, {}]", u.scale, u.offset),
+        Variant::NumberRange(r) => format!("[{}, {}]", r.min, r.max),
+        // Lists of names, like ["Top", "Front"]
+        Variant::Faces(_) | Variant::Axes(_) => serde_json::to_value(PropertyValue::from_variant(dom, value))
+            .map_or_else(|_| "?".to_string(), |json| json["value"].to_string()),
+        Variant::Font(font) => format!(
+            "{{family: {:?}, weight: {}, style: {:?}}}",
+            font.family, font.weight.as_u16(), font.style
+        ),
+        Variant::Color3(c) => format!("[{}, {}, {}]", c.r, c.g, c.b),
+        Variant::Color3uint8(c) => format!(
+            "[{:.3}, {:.3}, {:.3}]",
+            c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0
+        ),
+        Variant::Ref(referent) if referent.is_none() => "nil".to_string(),
+        Variant::Ref(referent) => instance_path(dom, *referent),
+        Variant::BinaryString(_) | Variant::SharedString(_) => "<binary>".to_string(),
+        Variant::OptionalCFrame(Some(cframe)) => format_variant(dom, &Variant::CFrame(*cframe)),
+        Variant::OptionalCFrame(None) => "nil".to_string(),
+        Variant::CFrame(cframe) => {
+            let (p, o) = (cframe.position, cframe.orientation);
+            format!(
+                "{{position: [{}, {}, {}], rotation: [{}, {}, {}, {}, {}, {}, {}, {}, {}]}}",
+                p.x, p.y, p.z,
+                o.x.x, o.x.y, o.x.z, o.y.x, o.y.y, o.y.z, o.z.x, o.z.y, o.z.z
+            )
+        }
+        Variant::Tags(tags) => format!("{:?}", tags.iter().collect::<Vec<_>>()),
+        Variant::Attributes(attributes) => {
+            let items: Vec<String> = attributes.iter()
+                .map(|(name, value)| format!("{}: {}", name, format_variant(dom, value)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,92 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+
+use crate::roblox::{instance_path, is_a, JsonInstance, Modification, PathIndex, SubtractTarget};
+
+/// Properties holding the solid modeling data of a union, which only Studio can generate
+const GEOMETRY_PROPERTIES: [&str; 6] = ["AssetId", "MeshData", "MeshData2", "ChildData", "ChildData2", "PhysicsData"];
+
+/// Check whether a class is a solid modeling operation (UnionOperation, NegateOperation, IntersectOperation)
+pub fn is_csg_class(class: &str) -> bool {
+    is_a(class, "PartOperation")
+}
+
+/// Check whether a union carries geometry data, without which it renders as nothing
+pub fn has_geometry(dom: &WeakDom, instance_id: Ref) -> bool {
+    let Some(instance) = dom.get_by_ref(instance_id) else {
+        return false;
+    };
+    GEOMETRY_PROPERTIES.iter().any(|&name| match instance.properties.get(&name.into()) {
+        Some(Variant::BinaryString(data)) => !AsRef::<[u8]>::as_ref(data).is_empty(),
+        Some(Variant::SharedString(data)) => !data.data().is_empty(),
+        Some(Variant::ContentId(id)) => !id.as_str().is_empty(),
+        _ => false,
+    })
+}
+
+/// Find every solid modeling instance in the place
+pub fn find_unions(dom: &WeakDom) -> Vec<Ref> {
+    dom.descendants()
+        .filter(|instance| is_csg_class(&instance.class))
+        .map(|instance| instance.referent())
+        .collect()
+}
+
+/// Warn about parts of a modification that create or destroy union geometry this tool can't regenerate
+pub fn union_warnings(dom: &WeakDom, data_model_id: Ref, modification: &Modification) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for instance in &modification.add {
+        collect_added_unions(instance, &mut warnings);
+    }
+
+    let mut index = PathIndex::new();
+    for target in &modification.subtract {
+        let SubtractTarget::Path(path) = target else {
+            continue;
+        };
+        let found = index.find_all(dom, data_model_id, path);
+        for descendant in found.iter().flat_map(|&id| dom.descendants_of(id)).filter(|i| is_csg_class(&i.class)) {
+            if has_geometry(dom, descendant.referent()) {
+                warnings.push(format!(
+                    "Removing '{}' deletes union geometry that can't be regenerated outside Studio",
+                    instance_path(dom, descendant.referent())
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+fn collect_added_unions(instance: &JsonInstance, warnings: &mut Vec<String>) {
+    if is_csg_class(&instance.class) {
+        warnings.push(format!(
+            "{} ({}) will have no geometry; solid modeling operations can only be built in Studio",
+            instance.name, instance.class
+        ));
+    }
+    for child in &instance.children {
+        collect_added_unions(child, warnings);
+    }
+}
+
+/// Print every union with its geometry status so they can be flattened or rebuilt in Studio
+pub fn print_union_report(dom: &WeakDom) {
+    let unions = find_unions(dom);
+    if unions.is_empty() {
+        println!("No solid modeling operations in the place");
+        return;
+    }
+
+    for &id in &unions {
+        let instance = dom.get_by_ref(id).unwrap();
+        let size = match instance.properties.get(&"Size".into()) {
+            Some(Variant::Vector3(size)) => format!(" size [{}, {}, {}]", size.x, size.y, size.z),
+            _ => String::new(),
+        };
+        let status = if has_geometry(dom, id) { "geometry ok" } else { "NO GEOMETRY" };
+        println!("{} ({}) {}{}", instance_path(dom, id), instance.class, status, size);
+    }
+    println!("{} solid modeling operations found. Their geometry can only be changed in Studio.", unions.len());
+}
@@ -0,0 +1,189 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, WeakDom};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::roblox::{is_package_root, remove_instance, CHANGE_ID_ATTRIBUTE};
+
+/// Properties every instance has its own value of, which copies don't share
+const IDENTITY_PROPERTIES: [&str; 2] = ["UniqueId", "HistoryId"];
+
+/// Siblings that are copies of the first one: same class, name, properties and children
+pub struct DuplicateGroup {
+    pub original: Ref,
+    pub duplicates: Vec<Ref>,
+}
+
+/// Find structurally identical siblings anywhere under the services. Copies are compared
+/// ignoring per-instance ids and the change that added them, and Ref properties pointing
+/// inside a copy match when they point at the same place in it. Package contents are skipped.
+pub fn find_duplicates(dom: &WeakDom) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    // Children are pushed in reverse so groups come out in tree order
+    let mut stack: Vec<Ref> = dom.root().children().iter().rev().copied().collect();
+    while let Some(parent_id) = stack.pop() {
+        let Some(parent) = dom.get_by_ref(parent_id) else {
+            continue;
+        };
+        let children: Vec<&Instance> = parent.children().iter().filter_map(|&id| dom.get_by_ref(id)).collect();
+
+        // Only siblings sharing a class and name can be copies, so most are never fingerprinted
+        let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+        for child in &children {
+            *counts.entry((child.class.as_str(), child.name.as_str())).or_default() += 1;
+        }
+        let mut originals: HashMap<(&str, &str, u64), usize> = HashMap::new();
+        let mut duplicates: HashSet<Ref> = HashSet::new();
+        for child in &children {
+            let key = (child.class.as_str(), child.name.as_str());
+            if counts[&key] < 2 {
+                continue;
+            }
+            let id = child.referent();
+            match originals.entry((key.0, key.1, fingerprint(dom, id, id))) {
+                Entry::Occupied(group) => {
+                    groups[*group.get()].duplicates.push(id);
+                    duplicates.insert(id);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(groups.len());
+                    groups.push(DuplicateGroup { original: id, duplicates: Vec::new() });
+                }
+            }
+        }
+        groups.retain(|group| !group.duplicates.is_empty());
+
+        // Duplicates would only repeat what's found in their original
+        for child in children.iter().rev() {
+            let id = child.referent();
+            if !duplicates.contains(&id) && !is_package_root(dom, id) {
+                stack.push(id);
+            }
+        }
+    }
+    groups
+}
+
+/// Remove every duplicate, keeping the originals. Returns how many were removed.
+pub fn remove_duplicates(dom: &mut WeakDom, groups: &[DuplicateGroup]) -> usize {
+    groups.iter()
+        .flat_map(|group| &group.duplicates)
+        .filter(|&&id| remove_instance(dom, id).is_ok())
+        .count()
+}
+
+/// Hash of an instance's class, name, properties and children, in order
+fn fingerprint(dom: &WeakDom, root_id: Ref, instance_id: Ref) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let Some(instance) = dom.get_by_ref(instance_id) else {
+        return hasher.finish();
+    };
+    instance.class.as_str().hash(&mut hasher);
+    instance.name.hash(&mut hasher);
+
+    let mut properties: Vec<(&str, &Variant)> = instance.properties.iter()
+        .map(|(name, value)| (name.as_str(), value))
+        .filter(|(name, _)| !IDENTITY_PROPERTIES.contains(name))
+        .collect();
+    properties.sort_by_key(|(name, _)| *name);
+    for (name, value) in properties {
+        name.hash(&mut hasher);
+        match value {
+            Variant::Ref(target) => relative_path(dom, root_id, *target).hash(&mut hasher),
+            Variant::Attributes(attributes) => {
+                for (key, value) in attributes.iter().filter(|(key, _)| key.as_str() != CHANGE_ID_ATTRIBUTE) {
+                    key.hash(&mut hasher);
+                    format!("{:?}", value).hash(&mut hasher);
+                }
+            }
+            // Variant has no Hash, its debug output stands in
+            value => format!("{:?}", value).hash(&mut hasher),
+        }
+    }
+
+    for &child_id in instance.children() {
+        fingerprint(dom, root_id, child_id).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Where a Ref points relative to the root of the copy it's in, by child indices.
+/// Err with the target itself when it points outside the copy, so only the same target matches.
+fn relative_path(dom: &WeakDom, root_id: Ref, target: Ref) -> Result<Vec<usize>, Ref> {
+    let mut indices = Vec::new();
+    let mut current = target;
+    while current != root_id {
+        let Some(instance) = dom.get_by_ref(current) else {
+            return Err(target);
+        };
+        let parent_id = instance.parent();
+        let parent = dom.get_by_ref(parent_id).ok_or(target)?;
+        indices.push(parent.children().iter().position(|&id| id == current).unwrap_or(0));
+        current = parent_id;
+    }
+    indices.reverse();
+    Ok(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::{find_instance_by_path, set_attribute};
+    use rbx_dom_weak::InstanceBuilder;
+
+    /// A tree Model whose PrimaryPart is its own Trunk
+    fn insert_tree(dom: &mut WeakDom, parent_id: Ref, change_id: &str, trunk_transparency: f32) -> Ref {
+        let tree_id = dom.insert(parent_id, InstanceBuilder::new("Model").with_name("Tree"));
+        let trunk_id = dom.insert(tree_id, InstanceBuilder::new("Part").with_name("Trunk").with_property("Transparency", trunk_transparency));
+        dom.insert(tree_id, InstanceBuilder::new("Part").with_name("Leaves"));
+        dom.get_by_ref_mut(tree_id).unwrap().properties.insert("PrimaryPart".into(), Variant::Ref(trunk_id));
+        set_attribute(dom, tree_id, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.to_string()));
+        tree_id
+    }
+
+    fn sample_place() -> (WeakDom, Vec<Ref>) {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let trees = vec![
+            insert_tree(&mut dom, workspace_id, "first", 0.0),
+            insert_tree(&mut dom, workspace_id, "second", 0.0),
+            insert_tree(&mut dom, workspace_id, "third", 0.5),
+            insert_tree(&mut dom, workspace_id, "fourth", 0.0),
+        ];
+        (dom, trees)
+    }
+
+    #[test]
+    fn copies_are_found_despite_their_own_ids_and_refs() {
+        let (dom, trees) = sample_place();
+        let groups = find_duplicates(&dom);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].original, trees[0]);
+        // The third tree's trunk differs, so it's not a copy
+        assert_eq!(groups[0].duplicates, [trees[1], trees[3]]);
+    }
+
+    #[test]
+    fn removing_duplicates_keeps_the_originals() {
+        let (mut dom, trees) = sample_place();
+        let groups = find_duplicates(&dom);
+        assert_eq!(remove_duplicates(&mut dom, &groups), 2);
+        let workspace_id = find_instance_by_path(&dom, dom.root_ref(), "Workspace").unwrap();
+        assert_eq!(dom.get_by_ref(workspace_id).unwrap().children(), [trees[0], trees[2]]);
+        assert!(find_duplicates(&dom).is_empty());
+    }
+
+    #[test]
+    fn package_contents_are_left_alone() {
+        let (mut dom, _) = sample_place();
+        let workspace_id = find_instance_by_path(&dom, dom.root_ref(), "Workspace").unwrap();
+        let package_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Forest"));
+        dom.insert(package_id, InstanceBuilder::new("PackageLink").with_name("PackageLink"));
+        insert_tree(&mut dom, package_id, "fifth", 0.0);
+        insert_tree(&mut dom, package_id, "sixth", 0.0);
+        let groups = find_duplicates(&dom);
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].duplicates.iter().all(|&id| dom.get_by_ref(id).unwrap().parent() == workspace_id));
+    }
+}
@@ -0,0 +1,66 @@
+use rbx_dom_weak::types::Variant;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::property::PropertyValue;
+use crate::roblox::{JsonInstance, Modification};
+
+/// Properties to give added instances that don't set them, by class name
+pub type DefaultProperties = HashMap<String, HashMap<String, PropertyValue>>;
+
+/// Read the default_properties from the config, typed or with the type inferred from the class
+pub fn load(config: &HashMap<String, HashMap<String, Value>>) -> Result<DefaultProperties, Box<dyn Error>> {
+    let mut defaults = DefaultProperties::new();
+    for (class, properties) in config {
+        let mut converted = HashMap::new();
+        for (name, value) in properties {
+            let property = PropertyValue::from_json(class, name, value.clone())
+                .map_err(|e| format!("default_properties {}.{}: {}", class, name, e))?;
+            converted.insert(name.clone(), property);
+        }
+        defaults.insert(class.clone(), converted);
+    }
+    Ok(defaults)
+}
+
+/// Give every instance a modification adds the properties it's missing: first those configured for
+/// its class or the nearest superclass that has them, then, with `reflection`, Roblox's defaults
+pub fn fill(modification: &mut Modification, configured: &DefaultProperties, reflection: bool) {
+    // Only Refs need a place to convert, and they're never filled in
+    let empty = WeakDom::new(InstanceBuilder::new("DataModel"));
+    for instance in &mut modification.add {
+        fill_instance(instance, configured, reflection, &empty);
+    }
+}
+
+fn fill_instance(instance: &mut JsonInstance, configured: &DefaultProperties, reflection: bool, empty: &WeakDom) {
+    let database = rbx_reflection_database::get();
+    if let Some(descriptor) = database.classes.get(instance.class.as_str()) {
+        for class in database.superclasses_iter(descriptor) {
+            for (name, value) in configured.get(class.name.as_ref()).into_iter().flatten() {
+                if !instance.properties.contains_key(name) {
+                    instance.properties.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        if reflection {
+            for class in database.superclasses_iter(descriptor) {
+                for (name, value) in &class.default_properties {
+                    if instance.properties.contains_key(name.as_ref()) || matches!(value, Variant::Ref(_)) {
+                        continue;
+                    }
+                    // Types this tool can't write would only be skipped with a warning
+                    let value = PropertyValue::from_variant(empty, value);
+                    if !matches!(value, PropertyValue::Unsupported { .. }) {
+                        instance.properties.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+    }
+    for child in &mut instance.children {
+        fill_instance(child, configured, reflection, empty);
+    }
+}
@@ -0,0 +1,394 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::property::PropertyValue;
+use crate::roblox::{attributes_to_json, instance_path};
+
+/// Longest property value printed before it is cut short
+const MAX_VALUE_LENGTH: usize = 60;
+
+/// One difference between two versions of a place
+pub enum DiffEntry {
+    Added { path: String, class: String, count: usize },
+    Removed { path: String, class: String, count: usize },
+    Renamed { old_path: String, new_path: String },
+    Moved { old_path: String, new_path: String },
+    Changed { path: String, changes: Vec<PropertyChange> },
+}
+
+/// A property or attribute that was set, cleared or given a new value.
+/// Attributes are named with a leading @.
+pub struct PropertyChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Compare two places instance by instance. Entries are sorted by path.
+pub fn diff_places(old: &WeakDom, new: &WeakDom) -> Vec<DiffEntry> {
+    let matching = match_places(old, new);
+    let mut entries = Vec::new();
+
+    for &(old_id, new_id) in &matching.renamed {
+        entries.push(DiffEntry::Renamed { old_path: instance_path(old, old_id), new_path: instance_path(new, new_id) });
+    }
+    for &(old_id, new_id) in &matching.moved {
+        entries.push(DiffEntry::Moved { old_path: instance_path(old, old_id), new_path: instance_path(new, new_id) });
+    }
+    for &(old_id, new_id) in &matching.pairs {
+        let changes = diff_properties(old, new, old_id, new_id);
+        if !changes.is_empty() {
+            entries.push(DiffEntry::Changed { path: instance_path(new, new_id), changes });
+        }
+    }
+    for &old_id in &matching.removed {
+        if let Some(instance) = old.get_by_ref(old_id) {
+            entries.push(DiffEntry::Removed {
+                path: instance_path(old, old_id),
+                class: instance.class.to_string(),
+                count: old.descendants_of(old_id).count(),
+            });
+        }
+    }
+    for &new_id in &matching.added {
+        if let Some(instance) = new.get_by_ref(new_id) {
+            entries.push(DiffEntry::Added {
+                path: instance_path(new, new_id),
+                class: instance.class.to_string(),
+                count: new.descendants_of(new_id).count(),
+            });
+        }
+    }
+
+    // Stable, so a rename or move comes before the property changes of the same instance
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    entries
+}
+
+impl DiffEntry {
+    /// Path of the instance in the newer place, or in the older one if it was removed
+    pub fn path(&self) -> &str {
+        match self {
+            DiffEntry::Added { path, .. } | DiffEntry::Removed { path, .. } | DiffEntry::Changed { path, .. } => path,
+            DiffEntry::Renamed { new_path, .. } | DiffEntry::Moved { new_path, .. } => new_path,
+        }
+    }
+}
+
+/// Which instances in one version of a place are the same instance in the other
+#[derive(Default)]
+pub(crate) struct Matching {
+    /// Every pair of old and new instances that are the same instance, renamed and moved ones included
+    pub pairs: Vec<(Ref, Ref)>,
+    pub renamed: Vec<(Ref, Ref)>,
+    pub moved: Vec<(Ref, Ref)>,
+    /// Old instances with no counterpart, without their descendants
+    pub removed: Vec<Ref>,
+    /// New instances with no counterpart, without their descendants
+    pub added: Vec<Ref>,
+}
+
+/// Match up the instances of two places.
+///
+/// Children are paired up by name and class, in order. Children left over on both sides
+/// with the same class and the same properties are treated as renamed. Instances left over
+/// anywhere in the place with the same name, class and properties are treated as moved.
+pub(crate) fn match_places(old: &WeakDom, new: &WeakDom) -> Matching {
+    let mut matching = Matching::default();
+    match_children(old, new, old.root_ref(), new.root_ref(), &mut matching);
+
+    // Pair up what was removed in one place and added in another
+    let mut moved = Vec::new();
+    matching.added.retain(|&new_id| {
+        let Some(index) = matching.removed.iter().position(|&old_id| is_move(old, new, old_id, new_id)) else {
+            return true;
+        };
+        moved.push((matching.removed.remove(index), new_id));
+        false
+    });
+    for (old_id, new_id) in moved {
+        matching.moved.push((old_id, new_id));
+        match_children(old, new, old_id, new_id, &mut matching);
+    }
+    matching
+}
+
+/// Record a matched pair and match up everything under it
+fn match_children(old: &WeakDom, new: &WeakDom, old_id: Ref, new_id: Ref, matching: &mut Matching) {
+    let (Some(old_instance), Some(new_instance)) = (old.get_by_ref(old_id), new.get_by_ref(new_id)) else {
+        return;
+    };
+    matching.pairs.push((old_id, new_id));
+
+    // Pair children with the same name and class, in the order they appear
+    let mut unmatched_old: HashMap<(&str, &str), Vec<Ref>> = HashMap::new();
+    for &child_id in old_instance.children().iter().rev() {
+        if let Some(child) = old.get_by_ref(child_id) {
+            unmatched_old.entry((child.name.as_str(), child.class.as_str())).or_default().push(child_id);
+        }
+    }
+    let mut pairs = Vec::new();
+    let mut added = Vec::new();
+    for &child_id in new_instance.children() {
+        let Some(child) = new.get_by_ref(child_id) else {
+            continue;
+        };
+        match unmatched_old.get_mut(&(child.name.as_str(), child.class.as_str())).and_then(Vec::pop) {
+            Some(old_child_id) => pairs.push((old_child_id, child_id)),
+            None => added.push(child_id),
+        }
+    }
+    let unmatched: HashSet<Ref> = unmatched_old.into_values().flatten().collect();
+    let mut removed: Vec<Ref> = old_instance.children().iter()
+        .copied()
+        .filter(|id| unmatched.contains(id))
+        .collect();
+
+    // Whatever is left on both sides with the same class and properties was renamed
+    added.retain(|&new_child_id| {
+        let Some(index) = removed.iter().position(|&old_child_id| is_rename(old, new, old_child_id, new_child_id)) else {
+            return true;
+        };
+        let old_child_id = removed.remove(index);
+        matching.renamed.push((old_child_id, new_child_id));
+        pairs.push((old_child_id, new_child_id));
+        false
+    });
+
+    matching.removed.extend(removed);
+    matching.added.extend(added);
+    for (old_child_id, new_child_id) in pairs {
+        match_children(old, new, old_child_id, new_child_id, matching);
+    }
+}
+
+/// Same name as well as the same class and properties
+fn is_move(old: &WeakDom, new: &WeakDom, old_id: Ref, new_id: Ref) -> bool {
+    match (old.get_by_ref(old_id), new.get_by_ref(new_id)) {
+        (Some(old_instance), Some(new_instance)) => {
+            old_instance.name == new_instance.name && is_rename(old, new, old_id, new_id)
+        }
+        _ => false,
+    }
+}
+
+/// Same class and the same properties, leaving out attributes, which rbx-mcp changes when it renames
+fn is_rename(old: &WeakDom, new: &WeakDom, old_id: Ref, new_id: Ref) -> bool {
+    match (old.get_by_ref(old_id), new.get_by_ref(new_id)) {
+        (Some(old_instance), Some(new_instance)) => {
+            old_instance.class == new_instance.class
+                && diff_properties(old, new, old_id, new_id).iter().all(|change| change.name.starts_with('@'))
+        }
+        _ => false,
+    }
+}
+
+fn diff_properties(old: &WeakDom, new: &WeakDom, old_id: Ref, new_id: Ref) -> Vec<PropertyChange> {
+    let (Some(old_instance), Some(new_instance)) = (old.get_by_ref(old_id), new.get_by_ref(new_id)) else {
+        return Vec::new();
+    };
+
+    let names: BTreeSet<&str> = old_instance.properties.keys()
+        .chain(new_instance.properties.keys())
+        .map(|name| name.as_str())
+        .collect();
+    let mut changes = Vec::new();
+    for name in names {
+        let old_value = old_instance.properties.get(&name.into());
+        let new_value = new_instance.properties.get(&name.into());
+        if matches!(old_value, Some(Variant::Attributes(_))) || matches!(new_value, Some(Variant::Attributes(_))) {
+            diff_attributes(old_value, new_value, &mut changes);
+            continue;
+        }
+        if values_equal(old, new, old_value, new_value) {
+            continue;
+        }
+        changes.push(PropertyChange {
+            name: name.to_string(),
+            old: old_value.map(|value| display_value(old, value)),
+            new: new_value.map(|value| display_value(new, value)),
+        });
+    }
+    changes
+}
+
+fn diff_attributes(old: Option<&Variant>, new: Option<&Variant>, changes: &mut Vec<PropertyChange>) {
+    let as_json = |value: Option<&Variant>| match value {
+        Some(Variant::Attributes(attributes)) => attributes_to_json(attributes),
+        _ => serde_json::Map::new(),
+    };
+    let (old, new) = (as_json(old), as_json(new));
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for name in names {
+        if old.get(name) != new.get(name) {
+            changes.push(PropertyChange {
+                name: format!("@{}", name),
+                old: old.get(name).map(|value| shorten(&value.to_string())),
+                new: new.get(name).map(|value| shorten(&value.to_string())),
+            });
+        }
+    }
+}
+
+/// Refs point into different places, so they are equal when their targets have the same path
+pub(crate) fn values_equal(old: &WeakDom, new: &WeakDom, old_value: Option<&Variant>, new_value: Option<&Variant>) -> bool {
+    match (old_value, new_value) {
+        (Some(Variant::Ref(a)), Some(Variant::Ref(b))) => {
+            a.is_none() == b.is_none() && (a.is_none() || instance_path(old, *a) == instance_path(new, *b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// A property value the way a modification would write it, cut short if it's long
+fn display_value(dom: &WeakDom, value: &Variant) -> String {
+    let text = match PropertyValue::from_variant(dom, value) {
+        PropertyValue::Unsupported { type_name, .. } => format!("<{}>", type_name),
+        PropertyValue::String(s) if s.contains('\n') => format!("<{} lines>", s.lines().count()),
+        other => match serde_json::to_value(&other) {
+            Ok(Value::Object(mut fields)) => fields.remove("value").map_or_else(String::new, |value| value.to_string()),
+            _ => String::new(),
+        },
+    };
+    shorten(&text)
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_VALUE_LENGTH) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Print a diff, one line per added, removed, renamed or moved instance and per changed property
+pub fn print_diff(entries: &[DiffEntry]) {
+    let (mut added, mut removed, mut renamed, mut moved, mut changed) = (0, 0, 0, 0, 0);
+    for entry in entries {
+        match entry {
+            DiffEntry::Added { path, class, count } => {
+                added += 1;
+                println!("+ {} ({}{})", path, class, descendants_note(*count));
+            }
+            DiffEntry::Removed { path, class, count } => {
+                removed += 1;
+                println!("- {} ({}{})", path, class, descendants_note(*count));
+            }
+            DiffEntry::Renamed { old_path, new_path } => {
+                renamed += 1;
+                println!("~ {} -> {} (renamed)", old_path, new_path);
+            }
+            DiffEntry::Moved { old_path, new_path } => {
+                moved += 1;
+                println!("~ {} -> {} (moved)", old_path, new_path);
+            }
+            DiffEntry::Changed { path, changes } => {
+                changed += 1;
+                println!("~ {}", path);
+                for change in changes {
+                    match (&change.old, &change.new) {
+                        (Some(old), Some(new)) => println!("    {}: {} -> {}", change.name, old, new),
+                        (None, Some(new)) => println!("    + {}: {}", change.name, new),
+                        (Some(old), None) => println!("    - {}: {}", change.name, old),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+    }
+    if entries.is_empty() {
+        println!("No differences");
+    } else {
+        println!("{} added, {} removed, {} renamed, {} moved, {} changed", added, removed, renamed, moved, changed);
+    }
+}
+
+/// ", 12 instances" for a subtree with children
+fn descendants_note(count: usize) -> String {
+    if count > 1 {
+        format!(", {} instances", count)
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::{find_instance_by_path, set_attribute, snapshot};
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn sample_place() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let house_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("House"));
+        dom.insert(house_id, InstanceBuilder::new("Part").with_name("Door").with_property("Transparency", 0.0f32));
+        dom.insert(house_id, InstanceBuilder::new("Part").with_name("Roof").with_property("Transparency", 0.25f32));
+        dom.insert(workspace_id, InstanceBuilder::new("Folder").with_name("Shed"));
+        dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Rubbish"));
+        dom
+    }
+
+    fn find(dom: &WeakDom, path: &str) -> Ref {
+        find_instance_by_path(dom, dom.root_ref(), path).unwrap()
+    }
+
+    #[test]
+    fn copies_have_no_differences() {
+        let place = sample_place();
+        assert!(diff_places(&place, &snapshot(&place)).is_empty());
+        // Nor do separately built places with different referents
+        assert!(diff_places(&place, &sample_place()).is_empty());
+    }
+
+    #[test]
+    fn each_kind_of_change_is_found() {
+        let old = sample_place();
+        let mut new = snapshot(&old);
+        let house_id = find(&new, "Workspace/House");
+        new.get_by_ref_mut(find(&new, "Workspace/House/Door")).unwrap().properties.insert("Transparency".into(), Variant::Float32(0.5));
+        set_attribute(&mut new, house_id, "Owner", Variant::String("Builder".to_string()));
+        let roof_id = find(&new, "Workspace/House/Roof");
+        new.transfer_within(roof_id, find(&new, "Workspace/Shed"));
+        new.get_by_ref_mut(find(&new, "Workspace/Shed")).unwrap().name = "Barn".to_string();
+        new.destroy(find(&new, "Workspace/Rubbish"));
+        let window_id = new.insert(house_id, InstanceBuilder::new("Part").with_name("Window"));
+        new.insert(window_id, InstanceBuilder::new("Decal").with_name("Curtain"));
+
+        let entries = diff_places(&old, &new);
+        let summary: Vec<String> = entries.iter()
+            .map(|entry| match entry {
+                DiffEntry::Added { path, class, count } => format!("added {} {} {}", path, class, count),
+                DiffEntry::Removed { path, class, count } => format!("removed {} {} {}", path, class, count),
+                DiffEntry::Renamed { old_path, new_path } => format!("renamed {} {}", old_path, new_path),
+                DiffEntry::Moved { old_path, new_path } => format!("moved {} {}", old_path, new_path),
+                DiffEntry::Changed { path, changes } => {
+                    let changes: Vec<String> = changes.iter()
+                        .map(|change| format!("{}: {:?} -> {:?}", change.name, change.old, change.new))
+                        .collect();
+                    format!("changed {} {}", path, changes.join(", "))
+                }
+            })
+            .collect();
+        assert_eq!(summary, [
+            "renamed Workspace/Shed Workspace/Barn",
+            "moved Workspace/House/Roof Workspace/Barn/Roof",
+            "changed Workspace/House @Owner: None -> Some(\"\\\"Builder\\\"\")",
+            "changed Workspace/House/Door Transparency: Some(\"0.0\") -> Some(\"0.5\")",
+            "added Workspace/House/Window Part 2",
+            "removed Workspace/Rubbish Part 1",
+        ]);
+    }
+
+    #[test]
+    fn refs_are_equal_when_their_targets_have_the_same_path() {
+        let old = sample_place();
+        let new = sample_place();
+        let (old_door, new_door) = (find(&old, "Workspace/House/Door"), find(&new, "Workspace/House/Door"));
+        let new_roof = find(&new, "Workspace/House/Roof");
+        assert!(values_equal(&old, &new, Some(&Variant::Ref(old_door)), Some(&Variant::Ref(new_door))));
+        assert!(!values_equal(&old, &new, Some(&Variant::Ref(old_door)), Some(&Variant::Ref(new_roof))));
+        assert!(!values_equal(&old, &new, Some(&Variant::Ref(old_door)), Some(&Variant::Ref(Ref::none()))));
+    }
+}
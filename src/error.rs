@@ -0,0 +1,92 @@
+use rbx_dom_weak::types::Ref;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::warnings::suggestion_hint;
+
+/// Errors from reading, modifying and writing places, and from talking to Gemini
+#[derive(Debug, Error)]
+pub enum RbxMcpError {
+    /// The place file isn't valid Roblox XML
+    #[error("Failed to parse place: {0}")]
+    ParseError(#[from] rbx_xml::DecodeError),
+
+    /// The place couldn't be serialized back to XML
+    #[error("Failed to serialize place: {0}")]
+    SerializeError(#[from] rbx_xml::EncodeError),
+
+    /// A JSON property value couldn't be converted to its Roblox type
+    #[error("Invalid value for property '{property}': {message}")]
+    PropertyConversion { property: String, message: String },
+
+    /// No instance exists at a path
+    #[error("Could not find instance at path '{path}'.{}", suggestion_hint(.suggestions))]
+    PathNotFound { path: String, suggestions: Vec<String> },
+
+    /// A referent doesn't point at an instance in the place
+    #[error("Instance with ref {0:?} not found")]
+    InstanceNotFound(Ref),
+
+    /// The modification asks for something that isn't allowed
+    #[error("{0}")]
+    InvalidOperation(String),
+
+    /// The modification creates more instances than the limit allows. Nothing was changed.
+    /// Callers add how to get past the limit, since that differs between them.
+    #[error("The modification creates {count} instances, more than the limit of {limit}. Nothing was changed")]
+    TooManyInstances { count: usize, limit: usize },
+
+    /// Instances in the modification can't be created as given. Nothing was changed.
+    #[error("Modification is invalid:\n{}", .0.join("\n"))]
+    InvalidModification(Vec<String>),
+
+    /// The Gemini request failed or returned something unusable
+    #[error("Gemini request failed: {0}")]
+    LlmError(String),
+
+    /// Gemini's safety filters blocked the prompt or the response
+    #[error("Gemini blocked the {0}. Rephrase the prompt or loosen safety_settings in the config")]
+    Blocked(String),
+
+    /// The request was cancelled before Gemini answered
+    #[error("Generation cancelled")]
+    Cancelled,
+
+    /// A Roblox Open Cloud request failed
+    #[error("Open Cloud request failed: {0}")]
+    OpenCloudError(String),
+
+    /// A Roblox catalog search failed
+    #[error("Catalog request failed: {0}")]
+    CatalogError(String),
+
+    /// The place file changed on disk since it was read, so writing it would lose those changes
+    #[error("{} changed on disk since it was read; reload it before saving", .0.display())]
+    ChangedOnDisk(PathBuf),
+
+    /// Another process holds the lock on the place
+    #[error("{} is locked by {}; if no rbx-mcp is using it, delete {}", .path.display(), .pid.map_or("another process".to_string(), |pid| format!("process {}", pid)), .lock_path.display())]
+    Locked { path: PathBuf, lock_path: PathBuf, pid: Option<u32> },
+
+    /// A REST request named a file outside the directory the server was started with
+    #[error("{} is outside {}, the directory the server may use", .path.display(), .root.display())]
+    OutsideRoot { path: PathBuf, root: PathBuf },
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+impl RbxMcpError {
+    pub fn conversion(property: &str, message: impl Into<String>) -> RbxMcpError {
+        RbxMcpError::PropertyConversion {
+            property: property.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RbxMcpError {
+    fn from(e: reqwest::Error) -> Self {
+        RbxMcpError::LlmError(e.to_string())
+    }
+}
@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::gemini_api::ConversationTurn;
+use crate::roblox::Modification;
+
+/// Read few-shot examples from a directory of pairs: `NAME.json` holds a modification and
+/// `NAME.txt` (or `NAME.md`) the prompt it answers. Sorted by name, so they're sent in a stable order.
+pub fn load_examples(dir: &Path) -> Result<Vec<ConversationTurn>, Box<dyn Error>> {
+    let mut modification_paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| format!("Error reading examples in {}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            modification_paths.push(path);
+        }
+    }
+    modification_paths.sort();
+
+    let mut examples = Vec::new();
+    for path in modification_paths {
+        let prompt_path = ["txt", "md"].iter()
+            .map(|ext| path.with_extension(ext))
+            .find(|prompt_path| prompt_path.is_file())
+            .ok_or_else(|| format!("Example {} has no prompt, put it in a .txt or .md file of the same name", path.display()))?;
+        let prompt = std::fs::read_to_string(&prompt_path)?.trim().to_string();
+        let text = std::fs::read_to_string(&path)?;
+        // Checked so a typo doesn't teach Gemini a format that fails to parse
+        let modification: Modification = serde_json::from_str(&text)
+            .map_err(|e| format!("Example {} isn't a valid modification: {}", path.display(), e))?;
+        examples.push(ConversationTurn { prompt, response: serde_json::to_string(&modification)? });
+    }
+    if examples.is_empty() {
+        return Err(format!("No examples in {}, expected NAME.json and NAME.txt pairs", dir.display()).into());
+    }
+    Ok(examples)
+}
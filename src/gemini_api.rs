@@ -1,55 +1,610 @@
+use base64::Engine;
 use reqwest;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::error::Error;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::io::Write;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::context;
+use crate::error::RbxMcpError;
+use crate::prompt::{self, TemplateVars};
+use crate::response_cache::ResponseCache;
+use crate::retrieval::ExpandRequest;
+use crate::roblox::Modification;
+use crate::tools::{self, ToolSession};
+use crate::vertex::VertexAuth;
+
+const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Place descriptions at least this big are uploaded once as cached content instead of being
+/// sent with every prompt. Gemini won't cache less than a few thousand tokens.
+const MIN_CACHED_TOKENS: usize = 4096;
+
+/// How long Gemini keeps a cached place description
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Statuses that mean the same request may work if sent again a little later
+const RETRY_STATUSES: [u16; 3] = [429, 500, 503];
+
+/// Requests are sent at most this many times before giving up
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Longest wait between attempts, whatever Gemini asks for
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Which Gemini API requests go to
+enum Backend {
+    /// Google AI Studio, with an API key
+    AiStudio { api_key: String },
+    /// Vertex AI in a Google Cloud project, signed in as a service account
+    Vertex(Box<VertexAuth>),
+}
 
 /// Structure to hold Gemini API configuration
 pub struct GeminiClient {
-    api_key: String,
+    backend: Backend,
     model: String,
+    /// Tokens used by each request this client has made, oldest first
+    usage: Mutex<Vec<TokenUsage>>,
+    /// Token prices by model
+    prices: HashMap<String, ModelPrice>,
+    /// Sampling settings sent with every request
+    params: GenerationParams,
+    /// Extra generationConfig fields from the config. The sampling settings take precedence.
+    generation_config: serde_json::Map<String, Value>,
+    /// safetySettings sent with every request, one category and threshold each
+    safety_settings: Vec<Value>,
+    /// Template of the system instruction, with {variables} filled in for each request
+    system_prompt: String,
+    /// Example prompts and responses sent before the conversation to show the expected style
+    examples: Vec<ConversationTurn>,
+    /// Models tried in order when the main one fails
+    fallbacks: Vec<FallbackModel>,
+    /// For OpenAI fallback models
+    openai_key: Option<String>,
+    /// Earlier responses to reuse for identical requests, unless --no-cache is given
+    response_cache: Option<ResponseCache>,
+    /// Print the response as it is generated
+    live_output: bool,
+    /// Whether to upload large place descriptions to Gemini's context cache.
+    /// Turned off for the rest of the run if the model doesn't support it.
+    context_caching: AtomicBool,
+    /// The place description currently in the context cache
+    cached_place: tokio::sync::Mutex<Option<CachedPlace>>,
+}
+
+/// A place description uploaded with the cachedContents API, along with the system instruction
+struct CachedPlace {
+    /// Resource name, like "cachedContents/abc123", used to refer to it in requests
+    name: String,
+    /// Hash of the description and system instruction, to notice when either changed
+    hash: u64,
+    expires: Instant,
+}
+
+/// Tokens counted by Gemini, from the usageMetadata of its responses
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    /// Includes the cached tokens
+    pub prompt_tokens: u64,
+    /// Prompt tokens read from the context cache, which cost less
+    pub cached_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Read usageMetadata from a generateContent response
+    pub fn from_response(response: &Value) -> TokenUsage {
+        let metadata = response.get("usageMetadata");
+        let count = |field: &str| metadata.and_then(|m| m.get(field)).and_then(Value::as_u64).unwrap_or(0);
+        TokenUsage {
+            prompt_tokens: count("promptTokenCount"),
+            cached_tokens: count("cachedContentTokenCount"),
+            output_tokens: count("candidatesTokenCount"),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.output_tokens
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Where requests to OpenAI fallback models go
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// A model tried when the ones before it in the chain fail
+#[derive(Debug, Clone, PartialEq)]
+pub enum FallbackModel {
+    /// Another Gemini model, through the same API as the main one
+    Gemini(String),
+    /// An OpenAI chat model, with the key in OPENAI_API_KEY
+    OpenAi(String),
+}
+
+impl FallbackModel {
+    /// Read a model from the config, like "gemini-1.5-pro" or "openai:gpt-4o".
+    /// GPT and o-series models count as OpenAI models without the prefix.
+    pub fn parse(name: &str) -> FallbackModel {
+        match name.strip_prefix("openai:") {
+            Some(model) => FallbackModel::OpenAi(model.to_string()),
+            None if name.starts_with("gpt-") || name.starts_with("o1") || name.starts_with("o3") || name.starts_with("o4") => {
+                FallbackModel::OpenAi(name.to_string())
+            }
+            None => FallbackModel::Gemini(name.strip_prefix("gemini:").unwrap_or(name).to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for FallbackModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FallbackModel::Gemini(model) => write!(f, "{}", model),
+            FallbackModel::OpenAi(model) => write!(f, "openai:{}", model),
+        }
+    }
+}
+
+/// Most requests in one function calling exchange before giving up on it
+const MAX_TOOL_STEPS: usize = 10;
+
+/// Earlier prompts sent along with a new one, most recent last. Older ones are dropped to save tokens.
+const MAX_HISTORY_TURNS: usize = 5;
+
+/// An earlier prompt and Gemini's raw response to it, sent as conversation history
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConversationTurn {
+    pub prompt: String,
+    pub response: String,
+}
+
+/// Images are sent inline, base64 encoded so a third bigger, and Gemini refuses requests over 20MB
+const MAX_IMAGE_BYTES: u64 = 15 * 1024 * 1024;
+
+/// An image sent along with a prompt, e.g. a screenshot or concept art to build from
+#[derive(Debug, Clone)]
+pub struct ImageAttachment {
+    pub path: PathBuf,
+    mime_type: &'static str,
+    /// The image file, base64 encoded
+    data: String,
+}
+
+impl ImageAttachment {
+    /// Read a PNG, JPEG, WebP, HEIC or HEIF image to attach to prompts
+    pub fn load(path: &Path) -> Result<ImageAttachment, RbxMcpError> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+        let mime_type = match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "webp" => "image/webp",
+            "heic" => "image/heic",
+            "heif" => "image/heif",
+            _ => return Err(RbxMcpError::InvalidOperation(format!(
+                "{} isn't a supported image, use PNG, JPEG, WebP, HEIC or HEIF", path.display()
+            ))),
+        };
+        let size = std::fs::metadata(path)?.len();
+        if size > MAX_IMAGE_BYTES {
+            return Err(RbxMcpError::InvalidOperation(format!(
+                "{} is {:.1}MB, images must be under {}MB", path.display(), size as f64 / 1_048_576.0, MAX_IMAGE_BYTES / 1_048_576
+            )));
+        }
+        let data = base64::engine::general_purpose::STANDARD.encode(std::fs::read(path)?);
+        Ok(ImageAttachment { path: path.to_path_buf(), mime_type, data })
+    }
+}
+
+/// A modification from Gemini along with the raw response it was parsed from
+pub struct Generation {
+    pub modification: Modification,
+    pub response: String,
+}
+
+/// Sampling settings sent with each request as its generationConfig
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationParams {
+    pub temperature: f32,
+    pub max_output_tokens: u32,
+    /// Gemini's default when None
+    pub top_p: Option<f32>,
+    /// Gemini's default when None
+    pub top_k: Option<u32>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        GenerationParams { temperature: 0.8, max_output_tokens: 8000, top_p: None, top_k: None }
+    }
+}
+
+impl GenerationParams {
+    /// Names of the settings `set` takes
+    pub const NAMES: [&'static str; 4] = ["temperature", "max-tokens", "top-p", "top-k"];
+
+    /// Change one setting by name, e.g. "temperature" to "0.3". "default" resets it.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let reset = value == "default";
+        let defaults = GenerationParams::default();
+        match name {
+            "temperature" => {
+                self.temperature = if reset { defaults.temperature } else { parse_in_range(value, 0.0, 2.0)? };
+            }
+            "max-tokens" => {
+                self.max_output_tokens = if reset { defaults.max_output_tokens } else {
+                    value.parse().ok().filter(|&tokens| tokens > 0)
+                        .ok_or_else(|| format!("max-tokens must be a whole number above 0, not {}", value))?
+                };
+            }
+            "top-p" => self.top_p = if reset { None } else { Some(parse_in_range(value, 0.0, 1.0)?) },
+            "top-k" => {
+                self.top_k = if reset { None } else {
+                    Some(value.parse().ok().filter(|&k| k > 0)
+                        .ok_or_else(|| format!("top-k must be a whole number above 0, not {}", value))?)
+                };
+            }
+            _ => return Err(format!("Unknown setting {}, expected one of {} or model", name, GenerationParams::NAMES.join(", "))),
+        }
+        Ok(())
+    }
+
+    /// The settings as the generationConfig of a request, leaving out those Gemini should default
+    fn to_json(&self) -> Value {
+        let mut config = json!({
+            "temperature": self.temperature,
+            "maxOutputTokens": self.max_output_tokens
+        });
+        if let Some(top_p) = self.top_p {
+            config["topP"] = json!(top_p);
+        }
+        if let Some(top_k) = self.top_k {
+            config["topK"] = json!(top_k);
+        }
+        config
+    }
+}
+
+impl std::fmt::Display for GenerationParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_default = |value: Option<String>| value.unwrap_or_else(|| "default".to_string());
+        write!(
+            f,
+            "temperature {}, max-tokens {}, top-p {}, top-k {}",
+            self.temperature, self.max_output_tokens,
+            or_default(self.top_p.map(|p| p.to_string())), or_default(self.top_k.map(|k| k.to_string()))
+        )
+    }
+}
+
+fn parse_in_range(value: &str, min: f32, max: f32) -> Result<f32, String> {
+    value.parse::<f32>().ok().filter(|number| (min..=max).contains(number))
+        .ok_or_else(|| format!("Expected a number from {} to {}, not {}", min, max, value))
+}
+
+/// US dollars per million prompt and output tokens
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub output: f64,
+    /// Price of prompt tokens read from the context cache. A quarter of the prompt price when missing.
+    #[serde(default)]
+    pub cached: Option<f64>,
+}
+
+/// Prices of the models the client has constructors for. The config's `prices` add to and override these.
+fn default_prices() -> HashMap<String, ModelPrice> {
+    HashMap::from([
+        ("gemini-2.0-flash".to_string(), ModelPrice { prompt: 0.10, output: 0.40, cached: Some(0.025) }),
+        ("gemini-pro".to_string(), ModelPrice { prompt: 0.50, output: 1.50, cached: None }),
+    ])
 }
 
 impl GeminiClient {
     pub fn new(api_key: String, model: String) -> Self {
-        GeminiClient { api_key, model }
+        GeminiClient::with_backend(Backend::AiStudio { api_key }, model)
     }
 
-    /// Create a default client with the gemini-pro model
-    pub fn default(api_key: String) -> Self {
+    /// Create a client that sends requests to Vertex AI instead of AI Studio
+    pub fn vertex(auth: VertexAuth, model: String) -> Self {
+        GeminiClient::with_backend(Backend::Vertex(Box::new(auth)), model)
+    }
+
+    fn with_backend(backend: Backend, model: String) -> Self {
         GeminiClient {
-            api_key,
-            model: "gemini-pro".to_string(),
+            backend,
+            model,
+            usage: Mutex::default(),
+            prices: default_prices(),
+            params: GenerationParams::default(),
+            generation_config: serde_json::Map::new(),
+            safety_settings: Vec::new(),
+            system_prompt: prompt::DEFAULT_SYSTEM_PROMPT.to_string(),
+            examples: Vec::new(),
+            fallbacks: Vec::new(),
+            openai_key: None,
+            response_cache: None,
+            live_output: false,
+            context_caching: AtomicBool::new(true),
+            cached_place: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Stream responses and print their text to stdout as it arrives, so long generations show progress
+    pub fn with_live_output(mut self, enabled: bool) -> Self {
+        self.live_output = enabled;
+        self
+    }
+
+    /// Turn uploading large place descriptions to the context cache on or off. It's on by default.
+    pub fn with_context_cache(self, enabled: bool) -> Self {
+        self.context_caching.store(enabled, Ordering::SeqCst);
+        self
+    }
+
+    /// Use these sampling settings instead of the defaults
+    pub fn with_params(mut self, params: GenerationParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Block harmful content at these thresholds by harm category instead of Gemini's defaults
+    pub fn with_safety_settings(mut self, settings: &HashMap<String, String>) -> Self {
+        let mut categories: Vec<_> = settings.iter().collect();
+        categories.sort();
+        self.safety_settings = categories.into_iter()
+            .map(|(category, threshold)| json!({ "category": category, "threshold": threshold }))
+            .collect();
+        self
+    }
+
+    /// Send these generationConfig fields with every request, for settings without their own flag
+    pub fn with_generation_config(mut self, config: serde_json::Map<String, Value>) -> Self {
+        self.generation_config = config;
+        self
+    }
+
+    /// Use this system prompt template instead of the built-in one
+    pub fn with_system_prompt(mut self, template: String) -> Self {
+        self.system_prompt = template;
+        self
+    }
+
+    /// Fall back on these models in order when a request to the main one fails,
+    /// like ["gemini-1.5-pro", "openai:gpt-4o"]. OpenAI models use OPENAI_API_KEY.
+    pub fn with_fallbacks(mut self, models: &[String]) -> Self {
+        self.fallbacks = models.iter().map(|model| FallbackModel::parse(model)).collect();
+        if self.fallbacks.iter().any(|model| matches!(model, FallbackModel::OpenAi(_))) {
+            self.openai_key = std::env::var("OPENAI_API_KEY").ok();
+            if self.openai_key.is_none() {
+                warn!("OPENAI_API_KEY isn't set, the OpenAI models in the fallback chain will fail");
+            }
         }
+        self
+    }
+
+    /// Reuse the responses saved in this cache for requests identical to earlier ones, and save new ones to it
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Send these few-shot examples ahead of every conversation
+    pub fn with_examples(mut self, examples: Vec<ConversationTurn>) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    /// Use these prices, e.g. from the config, for cost estimates on top of the built-in ones
+    pub fn with_prices(mut self, prices: &HashMap<String, ModelPrice>) -> Self {
+        self.prices.extend(prices.iter().map(|(model, price)| (model.clone(), *price)));
+        self
+    }
+
+    /// Create a default client with the gemini-pro model
+    pub fn default(api_key: String) -> Self {
+        GeminiClient::new(api_key, "gemini-pro".to_string())
     }
 
+    /// The model used unless another is chosen
+    pub const FLASH_MODEL: &'static str = "gemini-2.0-flash";
+
     /// Create a client with the flash model
     pub fn flash(api_key: String) -> Self {
-        GeminiClient {
-            api_key,
-            model: "gemini-2.0-flash".to_string(),
+        GeminiClient::new(api_key, GeminiClient::FLASH_MODEL.to_string())
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Switch models mid-session. A cached place description belongs to the old model, so it's deleted.
+    pub async fn set_model(&mut self, model: String) {
+        self.clear_context_cache().await;
+        self.model = model;
+    }
+
+    pub fn params(&self) -> &GenerationParams {
+        &self.params
+    }
+
+    pub fn params_mut(&mut self) -> &mut GenerationParams {
+        &mut self.params
+    }
+
+    /// Root of the REST API requests go to
+    fn api_root(&self) -> String {
+        match &self.backend {
+            Backend::AiStudio { .. } => API_BASE.to_string(),
+            Backend::Vertex(auth) => auth.api_root(),
+        }
+    }
+
+    /// Resource name of a model, relative to the API root
+    fn model_path(&self, model: &str) -> String {
+        match &self.backend {
+            Backend::AiStudio { .. } => format!("models/{}", model),
+            Backend::Vertex(auth) => auth.model_path(model),
         }
     }
 
-    /// Send a request to the Gemini API
+    /// URL of a method on a model, like generateContent
+    fn model_url(&self, model: &str, method: &str) -> String {
+        format!("{}/{}:{}", self.api_root(), self.model_path(model), method)
+    }
+
+    /// The header that authenticates a request: the API key, or a Vertex AI access token
+    async fn auth_header(&self) -> Result<(&'static str, String), RbxMcpError> {
+        match &self.backend {
+            Backend::AiStudio { api_key } => Ok(("x-goog-api-key", api_key.clone())),
+            Backend::Vertex(auth) => Ok(("Authorization", format!("Bearer {}", auth.access_token().await?))),
+        }
+    }
+
+    /// Tokens used by every request so far
+    pub fn usage(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for &usage in self.request_usage().iter() {
+            total += usage;
+        }
+        total
+    }
+
+    /// Tokens used by each request so far, oldest first
+    pub fn request_usage(&self) -> Vec<TokenUsage> {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Estimated price in US dollars of some usage with this client's model.
+    /// None when the model's price isn't known.
+    pub fn estimated_cost(&self, usage: TokenUsage) -> Option<f64> {
+        let price = self.prices.get(&self.model)?;
+        let cached_price = price.cached.unwrap_or(price.prompt / 4.0);
+        let uncached = usage.prompt_tokens.saturating_sub(usage.cached_tokens);
+        Some((
+            uncached as f64 * price.prompt
+                + usage.cached_tokens as f64 * cached_price
+                + usage.output_tokens as f64 * price.output
+        ) / 1_000_000.0)
+    }
+
+    /// Estimated cost of some usage, like "about $0.0012 at gemini-2.0-flash prices"
+    pub fn describe_cost(&self, usage: TokenUsage) -> String {
+        match self.estimated_cost(usage) {
+            Some(cost) => format!("about ${:.4} at {} prices", cost, self.model),
+            None => format!("no known price for {}", self.model),
+        }
+    }
+
+    /// Send a request to the Gemini API, with one of its models
     pub async fn generate_content(
         &self,
         prompt: &str,
-        place: &impl std::fmt::Debug,
-        max_tokens: u32,
-        temperature: f32,
+        place: &TemplateVars<'_>,
+        model: &str,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+    ) -> Result<Value, RbxMcpError> {
+        let system = prompt::render(&self.system_prompt, place);
+        // Templates using {dom_summary} already carry the place
+        let place_text = (!prompt::uses_dom_summary(&self.system_prompt)).then_some(place.dom_summary);
+
+        // Large places are sent once through the context cache rather than with every prompt.
+        // The cache belongs to the main model, fallback models are sent the place.
+        let cached_place = match model == self.model {
+            true => self.cached_place(&system, place_text).await,
+            false => None,
+        };
+        let prompt_text = match (place_text, &cached_place) {
+            (None, _) => prompt.to_string(),
+            (Some(_), Some(_)) => format!("{}: the place described in the cached content", prompt),
+            (Some(place), None) => format!("{}: {}", prompt, place),
+        };
+
+        let mut generation_config = self.generation_config(&self.params);
+        generation_config["response_mime_type"] = json!("application/json");
+        let mut request_body = json!({
+            "contents": self.conversation(prompt_text, context, images, history, None),
+            "generationConfig": generation_config
+        });
+        if !self.safety_settings.is_empty() {
+            request_body["safetySettings"] = json!(self.safety_settings);
+        }
+        // Requests using cached content can't set a system instruction, the cache holds it
+        match cached_place {
+            Some(name) => request_body["cachedContent"] = json!(name),
+            None => request_body["system_instruction"] = json!({ "parts": [{ "text": system }] }),
+        }
+
+        // Basic request setup for Gemini API
+        // With live output the response arrives as server-sent events, each with the next piece of text
+        let url = if self.live_output {
+            self.model_url(model, "streamGenerateContent?alt=sse")
+        } else {
+            self.model_url(model, "generateContent")
+        };
+        let (auth_name, auth_value) = self.auth_header().await?;
+        let client = reqwest::Client::new();
+        let response = send_with_retry(|| {
+            client
+                .post(&url)
+                .header(auth_name, &auth_value)
+                .header("Content-Type", "application/json")
+                .header("Accept", "application/json")
+                .json(&request_body)
+        }).await?;
+
+        if response.status().is_success() {
+            // Parse the response to JSON
+            let parsed = if self.live_output {
+                read_event_stream(response).await
+            } else {
+                response.json::<Value>().await.map_err(|e| e.to_string())
+            };
+            match parsed {
+                Ok(gemini_response) => {
+                    self.record_usage(&gemini_response);
+                    Ok(gemini_response)
+                }
+                Err(e) => Err(RbxMcpError::LlmError(format!("Failed to parse JSON response: {}", e))),
+            }
+        } else {
+            let status = response.status();
+            let error_body = response.text().await?;
+            Err(RbxMcpError::LlmError(format!("HTTP {}. Details: {}", status, error_body)))
+        }
+    }
+
+    /// The contents of a request: the examples, the last few turns of history, then the prompt
+    /// with its images and context. Examples and earlier turns only carry the prompt, the place
+    /// is described once in the current one. Instructions are added after the prompt.
+    fn conversation(
+        &self,
+        prompt_text: String,
         context: Option<String>,
-    ) -> Result<Value, Box<dyn Error>> {
-        // Create a request payload for Gemini
-        let mut request_parts = vec![
-            json!({
-                "text": format!("RESPOND ONLY WITH RAW JSON, NO MARKDOWN CODE BLOCKS, NO BACKTICKS. DO NOT INCLUDE ```json AT THE BEGINNING OR ``` AT THE END. Your response must be a pure JSON document that can be directly parsed by a JSON parser. {}: {:?}", prompt, place)
-            }),
-            json!({
-                "text": format!("IMPORTANT: DO NOT wrap your response in code blocks or any other formatting. ONLY RETURN JSON in this exact format: {}", example_prompt())
-            }),
-            json!({
-                "text": format!("RESPOND ONLY WITH ADDED INSTANCES. DO NOT PROVIDE ANYTHING ELSE. {}", documentation_prompt())
-            })
-        ];
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+        instructions: Option<&str>,
+    ) -> Vec<Value> {
+        let mut request_parts = vec![json!({ "text": prompt_text })];
+        for image in images {
+            request_parts.push(json!({ "inline_data": { "mime_type": image.mime_type, "data": image.data } }));
+        }
 
         // Add context if provided
         if let Some(ctx) = context {
@@ -57,46 +612,195 @@ impl GeminiClient {
                 "text": format!("Additional context for your consideration: {}", ctx)
             }));
         }
+        if let Some(instructions) = instructions {
+            request_parts.push(json!({ "text": instructions }));
+        }
 
-        let request_body = json!({
-            "contents": [
-                {
-                    "parts": request_parts
-                }
-            ],
-            "generationConfig": {
-                "temperature": temperature,
-                "maxOutputTokens": max_tokens,
-                "response_mime_type": "application/json"
+        let mut contents = Vec::new();
+        let recent = &history[history.len().saturating_sub(MAX_HISTORY_TURNS)..];
+        for turn in self.examples.iter().chain(recent) {
+            contents.push(json!({ "role": "user", "parts": [{ "text": turn.prompt }] }));
+            contents.push(json!({ "role": "model", "parts": [{ "text": turn.response }] }));
+        }
+        contents.push(json!({ "role": "user", "parts": request_parts }));
+        contents
+    }
+
+    /// The generationConfig of a request: the extra fields from the config, then the sampling settings
+    fn generation_config(&self, params: &GenerationParams) -> Value {
+        let mut config = Value::Object(self.generation_config.clone());
+        if let Value::Object(settings) = params.to_json() {
+            for (field, value) in settings {
+                config[field] = value;
             }
-        });
+        }
+        config
+    }
 
-        // Basic request setup for Gemini API
+    /// Add a response's token counts to the session's usage and log them
+    fn record_usage(&self, response: &Value) {
+        let usage = TokenUsage::from_response(response);
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).push(usage);
+        let total = self.usage();
+        info!(
+            "Tokens: {} prompt ({} cached) + {} output, {} this session ({})",
+            usage.prompt_tokens, usage.cached_tokens, usage.output_tokens, total.total(), self.describe_cost(total)
+        );
+    }
+
+    /// Ask for a change through function calls instead of a modification. Each call Gemini makes is
+    /// run against the session, and its result sent back so Gemini can check it before the next step.
+    /// Returns Gemini's closing text once it stops calling functions. Drop the future to cancel it.
+    pub async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+        session: &mut ToolSession<'_>,
+    ) -> Result<String, RbxMcpError> {
+        // Cached content can't be combined with tools, so the place goes in the request
+        let system = prompt::render(&self.system_prompt, place);
+        let prompt_text = match prompt::uses_dom_summary(&self.system_prompt) {
+            true => prompt.to_string(),
+            false => format!("{}: {}", prompt, place.dom_summary),
+        };
+        let mut contents = self.conversation(prompt_text, context, images, history, Some(tools::TOOLS_PROMPT));
+        let url = self.model_url(&self.model, "generateContent");
         let client = reqwest::Client::new();
-        let response = client
-            .post(format!(
-                "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-                self.model, self.api_key
-            ))
-            .header("Content-Type", "application/json")
-            .header("Accept", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
 
-        if response.status().is_success() {
-            // Clone status for the error message if needed
-            let _status = response.status();
-            
-            // Parse the response to JSON
-            match response.json::<Value>().await {
-                Ok(gemini_response) => Ok(gemini_response),
-                Err(e) => Err(format!("Failed to parse JSON response: {}", e).into())
+        for _ in 0..MAX_TOOL_STEPS {
+            // Vertex AI tokens may run out during a long exchange
+            let (auth_name, auth_value) = self.auth_header().await?;
+            let mut request_body = json!({
+                "contents": contents,
+                "system_instruction": { "parts": [{ "text": system }] },
+                "tools": [{ "function_declarations": session.declarations() }],
+                "generationConfig": self.generation_config(&self.params)
+            });
+            if !self.safety_settings.is_empty() {
+                request_body["safetySettings"] = json!(self.safety_settings);
             }
-        } else {
+            let response = send_with_retry(|| client.post(&url).header(auth_name, &auth_value).json(&request_body)).await?;
             let status = response.status();
-            let error_body = response.text().await?;
-            Err(format!("Error: HTTP {}. Details: {}", status, error_body).into())
+            if !status.is_success() {
+                return Err(RbxMcpError::LlmError(format!("HTTP {}. Details: {}", status, response.text().await?)));
+            }
+            let response: Value = response.json().await?;
+            self.record_usage(&response);
+            if let Some(reason) = blocked_reason(&response) {
+                return Err(RbxMcpError::Blocked(reason));
+            }
+
+            let content = response.pointer("/candidates/0/content").cloned()
+                .ok_or_else(|| RbxMcpError::LlmError(format!("No content in Gemini response: {}", response)))?;
+            let parts = content.get("parts").and_then(Value::as_array).cloned().unwrap_or_default();
+            let calls: Vec<&Value> = parts.iter().filter_map(|part| part.get("functionCall")).collect();
+            if calls.is_empty() {
+                let text: String = parts.iter().filter_map(|part| part.get("text").and_then(Value::as_str)).collect();
+                return Ok(text.trim().to_string());
+            }
+
+            // Gemini needs its own turn back along with the results
+            let results: Vec<Value> = calls.iter()
+                .map(|function| {
+                    let name = function.get("name").and_then(Value::as_str).unwrap_or_default();
+                    let args = function.get("args").cloned().unwrap_or_else(|| json!({}));
+                    json!({ "functionResponse": { "name": name, "response": session.call(name, &args) } })
+                })
+                .collect();
+            contents.push(content);
+            contents.push(json!({ "role": "user", "parts": results }));
+        }
+        Err(RbxMcpError::LlmError(format!("Gemini was still calling functions after {} steps", MAX_TOOL_STEPS)))
+    }
+
+    /// Name of the cached content holding this system instruction and place description, uploading
+    /// it if either changed or the cache ran out. None when they're small or caching isn't available.
+    async fn cached_place(&self, system: &str, place: Option<&str>) -> Option<String> {
+        let tokens = context::estimate_tokens(system) + place.map_or(0, context::estimate_tokens);
+        if !self.context_caching.load(Ordering::SeqCst) || tokens < MIN_CACHED_TOKENS {
+            return None;
+        }
+        let mut hasher = DefaultHasher::new();
+        system.hash(&mut hasher);
+        place.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut cached = self.cached_place.lock().await;
+        // Leave a minute for the request to arrive before it runs out
+        if let Some(current) = cached.as_ref().filter(|c| c.hash == hash && c.expires > Instant::now() + Duration::from_secs(60)) {
+            return Some(current.name.clone());
+        }
+        if let Some(stale) = cached.take() {
+            self.delete_cached_content(&stale.name).await;
+        }
+        match self.create_cached_content(system, place).await {
+            Ok(name) => {
+                info!("Uploaded the place description to Gemini's context cache ({})", name);
+                *cached = Some(CachedPlace { name: name.clone(), hash, expires: Instant::now() + CACHE_TTL });
+                Some(name)
+            }
+            Err(e) => {
+                warn!("Couldn't use the context cache, sending the place with every prompt from now on: {}", e);
+                self.context_caching.store(false, Ordering::SeqCst);
+                None
+            }
+        }
+    }
+
+    async fn create_cached_content(&self, system: &str, place: Option<&str>) -> Result<String, RbxMcpError> {
+        let mut body = json!({
+            "model": self.model_path(&self.model),
+            "system_instruction": { "parts": [{ "text": system }] },
+            "ttl": format!("{}s", CACHE_TTL.as_secs())
+        });
+        if let Some(place) = place {
+            body["contents"] = json!([{
+                "role": "user",
+                "parts": [{ "text": format!("The Roblox place, as an indented tree of instances and their properties:\n{}", place) }]
+            }]);
+        }
+        let client = reqwest::Client::new();
+        // Vertex AI keeps cached content in the project and location, next to the model
+        let url = match &self.backend {
+            Backend::AiStudio { .. } => format!("{}/cachedContents", API_BASE),
+            Backend::Vertex(auth) => format!("{}/projects/{}/locations/{}/cachedContents", auth.api_root(), auth.project, auth.location),
+        };
+        let (auth_name, auth_value) = self.auth_header().await?;
+        let response = send_with_retry(|| client.post(&url).header(auth_name, &auth_value).json(&body)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(RbxMcpError::LlmError(format!("HTTP {}. Details: {}", status, response.text().await?)));
+        }
+        let created: Value = response.json().await?;
+        created.get("name").and_then(Value::as_str).map(str::to_string)
+            .ok_or_else(|| RbxMcpError::LlmError("No name in cachedContents response".to_string()))
+    }
+
+    /// Delete cached content. It runs out on its own, so failures are only logged.
+    async fn delete_cached_content(&self, name: &str) {
+        let (auth_name, auth_value) = match self.auth_header().await {
+            Ok(header) => header,
+            Err(e) => return debug!("Couldn't delete cached content {}: {}", name, e),
+        };
+        let result = reqwest::Client::new()
+            .delete(format!("{}/{}", self.api_root(), name))
+            .header(auth_name, auth_value)
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => debug!("Deleted cached content {}", name),
+            Ok(response) => debug!("Couldn't delete cached content {}: HTTP {}", name, response.status()),
+            Err(e) => debug!("Couldn't delete cached content {}: {}", name, e),
+        }
+    }
+
+    /// Delete the cached place description, e.g. when the session ends, so it isn't stored until it runs out
+    pub async fn clear_context_cache(&self) {
+        if let Some(cached) = self.cached_place.lock().await.take() {
+            self.delete_cached_content(&cached.name).await;
         }
     }
 
@@ -112,183 +816,325 @@ impl GeminiClient {
             .and_then(|t| t.as_str())
             .map(|s| s.to_string())
     }
-}
 
+    /// Send a prompt with the place description and parse the response into a Modification.
+    /// Images are sent with the prompt for it to refer to, like "build something that looks like this".
+    /// History is the earlier prompts of the session, for prompts that refer back to them.
+    /// When the request fails or the response isn't a modification, the fallback models are tried in turn.
+    /// Cancelling the token drops the HTTP request and returns `RbxMcpError::Cancelled`.
+    pub async fn generate_modification(
+        &self,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+        cancel: &CancellationToken,
+    ) -> Result<Generation, RbxMcpError> {
+        let cache_key = self.response_cache.as_ref()
+            .map(|_| self.response_cache_key(prompt, place, context.as_deref(), images, history));
+        if let Some(generation) = cache_key.as_deref().and_then(|key| self.cached_generation(key)) {
+            info!("Reusing the cached response to this prompt, run with --no-cache to ask {} again", self.model);
+            return Ok(generation);
+        }
 
-fn example_prompt() -> String {
-
-    r#"
-    {
-        "add": [
-            {
-                "class": "Part",
-                "name": "Base",
-                "target_parent": "Workspace/House",
-                "properties": {
-                    "CFrame": {
-                        "type": "CFrame",
-                        "value": {
-                            "position": [10.0, 5.0, 0.0],
-                            "rotation": [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        let models = std::iter::once(None).chain(self.fallbacks.iter().map(Some));
+        let mut last_error = String::new();
+        for model in models {
+            if let Some(fallback) = model {
+                warn!("Falling back to {}", fallback);
+            }
+            let text = tokio::select! {
+                text = self.response_text(model, prompt, place, context.clone(), images, history) => text,
+                () = cancel.cancelled() => return Err(RbxMcpError::Cancelled),
+            };
+            // Try to parse the response as JSON directly
+            let result = text.and_then(|text| match serde_json::from_str::<Modification>(&text) {
+                Ok(modification) => Ok(Generation { modification, response: text }),
+                Err(e) => Err(RbxMcpError::LlmError(format!("Error parsing JSON: {}\nRaw response: {}", e, text))),
+            });
+            match result {
+                Err(RbxMcpError::LlmError(e)) if !self.fallbacks.is_empty() => {
+                    warn!("{} failed: {}", model.map_or(self.model.clone(), ToString::to_string), e);
+                    last_error = e;
+                }
+                Ok(generation) => {
+                    if let (Some(cache), Some(key)) = (&self.response_cache, &cache_key) {
+                        let answered_by = model.map_or(self.model.clone(), ToString::to_string);
+                        if let Err(e) = cache.put(key, &answered_by, prompt, &generation.response) {
+                            warn!("Error saving the response to the cache: {}", e);
                         }
-                    },
-                    "Size": {
-                        "type": "Vector3",
-                        "value": [10.0, 5.0, 10.0]
-                    },
-                    "BrickColor": {
-                        "type": "BrickColor",
-                        "value": 194
-                    },
-                    "Material": {
-                        "type": "Enum",
-                        "value": 1
-                    },
-                    "Color": {
-                        "type": "Color3",
-                        "value": [1.0, 1.0, 1.0]
-                    }
-                },
-                "children": [
-                    {
-                        "class": "Decal",
-                        "name": "Painting",
-                        "properties": {
-                            "Texture": {
-                                "type": "String",
-                                "value": "rbxassetid://123456"
-                            }
-                        },
-                        "children": []
                     }
-                ]
+                    return Ok(generation);
+                }
+                result => return result,
             }
-        ]
-        "subtract": [
-            "Workspace/House/Door",
-            "Workspace/Tree/Window"
-        ]
+        }
+        Err(RbxMcpError::LlmError(format!("Every model in the fallback chain failed, the last with: {}", last_error)))
+    }
+
+    /// Ask which instances of a partly described place Gemini needs to see before making a change.
+    /// Only the main model is asked, and the response isn't cached.
+    pub async fn generate_expansion(
+        &self,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+        cancel: &CancellationToken,
+    ) -> Result<Vec<String>, RbxMcpError> {
+        let text = tokio::select! {
+            text = self.response_text(None, prompt, place, context, images, history) => text?,
+            () = cancel.cancelled() => return Err(RbxMcpError::Cancelled),
+        };
+        serde_json::from_str::<ExpandRequest>(&text)
+            .map(|request| request.expand)
+            .map_err(|e| RbxMcpError::LlmError(format!("Error parsing expand request: {}\nRaw response: {}", e, text)))
+    }
+
+    /// Key of a request in the response cache. Covers everything that goes into the request:
+    /// the model and its settings, the system instruction, the place, the prompt and its conversation.
+    fn response_cache_key(
+        &self,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<&str>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+    ) -> String {
+        let turns = |turns: &[ConversationTurn]| turns.iter().map(|turn| json!([turn.prompt, turn.response])).collect::<Vec<_>>();
+        ResponseCache::key(&json!({
+            "model": self.model,
+            "generation_config": self.generation_config(&self.params),
+            "safety_settings": self.safety_settings,
+            "system": prompt::render(&self.system_prompt, place),
+            "place": place.dom_summary,
+            "prompt": prompt,
+            "context": context,
+            "images": images.iter().map(|image| &image.data).collect::<Vec<_>>(),
+            "examples": turns(&self.examples),
+            "history": turns(&history[history.len().saturating_sub(MAX_HISTORY_TURNS)..]),
+        }))
+    }
+
+    /// A response from the cache, if it's there and still parses
+    fn cached_generation(&self, key: &str) -> Option<Generation> {
+        let response = self.response_cache.as_ref()?.get(key)?;
+        let modification = serde_json::from_str::<Modification>(&response).ok()?;
+        Some(Generation { modification, response })
+    }
+
+    /// Send a prompt to the main model or a fallback and return the text of the response
+    async fn response_text(
+        &self,
+        model: Option<&FallbackModel>,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+    ) -> Result<String, RbxMcpError> {
+        let model = match model {
+            None => &self.model,
+            Some(FallbackModel::Gemini(model)) => model,
+            Some(FallbackModel::OpenAi(model)) => return self.generate_openai(model, prompt, place, context, images, history).await,
+        };
+        let response = self.generate_content(prompt, place, model, context, images, history).await?;
+
+        // Extract and process the response
+        if let Some(reason) = blocked_reason(&response) {
+            return Err(RbxMcpError::Blocked(reason));
+        }
+        let text = GeminiClient::extract_text(&response)
+            .ok_or_else(|| RbxMcpError::LlmError("No text found in Gemini response".to_string()))?;
+        // Live output already showed it
+        if self.live_output {
+            debug!("Gemini API Response:\n{}", text);
+        } else {
+            info!("Gemini API Response:\n{}", text);
+        }
+        Ok(text)
+    }
+
+    /// Send a prompt to an OpenAI chat model, asking for a JSON object like a Gemini request does
+    async fn generate_openai(
+        &self,
+        model: &str,
+        prompt: &str,
+        place: &TemplateVars<'_>,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+    ) -> Result<String, RbxMcpError> {
+        let api_key = self.openai_key.as_deref()
+            .ok_or_else(|| RbxMcpError::LlmError(format!("Set OPENAI_API_KEY to fall back to {}", model)))?;
+        let system = prompt::render(&self.system_prompt, place);
+        let prompt_text = match prompt::uses_dom_summary(&self.system_prompt) {
+            true => prompt.to_string(),
+            false => format!("{}: {}", prompt, place.dom_summary),
+        };
+
+        let mut messages = vec![json!({ "role": "system", "content": system })];
+        let recent = &history[history.len().saturating_sub(MAX_HISTORY_TURNS)..];
+        for turn in self.examples.iter().chain(recent) {
+            messages.push(json!({ "role": "user", "content": turn.prompt }));
+            messages.push(json!({ "role": "assistant", "content": turn.response }));
+        }
+        let mut content = vec![json!({ "type": "text", "text": prompt_text })];
+        for image in images {
+            let url = format!("data:{};base64,{}", image.mime_type, image.data);
+            content.push(json!({ "type": "image_url", "image_url": { "url": url } }));
+        }
+        if let Some(ctx) = context {
+            content.push(json!({ "type": "text", "text": format!("Additional context for your consideration: {}", ctx) }));
+        }
+        messages.push(json!({ "role": "user", "content": content }));
+
+        let mut request_body = json!({
+            "model": model,
+            "messages": messages,
+            "temperature": self.params.temperature,
+            "max_completion_tokens": self.params.max_output_tokens,
+            "response_format": { "type": "json_object" }
+        });
+        if let Some(top_p) = self.params.top_p {
+            request_body["top_p"] = json!(top_p);
+        }
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/chat/completions", OPENAI_API_BASE);
+        let response = send_with_retry(|| client.post(&url).bearer_auth(api_key).json(&request_body)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(RbxMcpError::LlmError(format!("HTTP {}. Details: {}", status, response.text().await?)));
+        }
+        let response: Value = response.json().await?;
+        let count = |field: &str| response.pointer(&format!("/usage/{}", field)).and_then(Value::as_u64).unwrap_or(0);
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).push(TokenUsage {
+            prompt_tokens: count("prompt_tokens"),
+            cached_tokens: count("prompt_tokens_details/cached_tokens"),
+            output_tokens: count("completion_tokens"),
+        });
+        let text = response.pointer("/choices/0/message/content").and_then(Value::as_str)
+            .ok_or_else(|| RbxMcpError::LlmError(format!("No text found in {} response", model)))?;
+        info!("{} response:\n{}", model, text);
+        Ok(text.to_string())
+    }
+}
+
+/// Finish reasons that mean a filter stopped the response
+const BLOCKED_FINISH_REASONS: [&str; 6] = ["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII", "IMAGE_SAFETY"];
+
+/// Why Gemini's filters blocked a request, like "prompt (SAFETY: HARM_CATEGORY_HARASSMENT rated HIGH)".
+/// None when nothing was blocked.
+pub fn blocked_reason(response: &Value) -> Option<String> {
+    if let Some(reason) = response.pointer("/promptFeedback/blockReason").and_then(Value::as_str) {
+        return Some(format!("prompt ({}{})", reason, flagged_categories(response.pointer("/promptFeedback/safetyRatings"))));
+    }
+    let candidate = response.pointer("/candidates/0")?;
+    let reason = candidate.get("finishReason").and_then(Value::as_str)?;
+    BLOCKED_FINISH_REASONS.contains(&reason)
+        .then(|| format!("response ({}{})", reason, flagged_categories(candidate.get("safetyRatings"))))
+}
+
+/// The categories in safetyRatings that were blocked or rated above low, like ": HARM_CATEGORY_HARASSMENT rated HIGH"
+fn flagged_categories(ratings: Option<&Value>) -> String {
+    let flagged: Vec<String> = ratings.and_then(Value::as_array).into_iter().flatten()
+        .filter(|rating| {
+            let probability = rating.get("probability").and_then(Value::as_str).unwrap_or_default();
+            rating.get("blocked").and_then(Value::as_bool).unwrap_or(false) || matches!(probability, "MEDIUM" | "HIGH")
+        })
+        .map(|rating| format!(
+            "{} rated {}",
+            rating.get("category").and_then(Value::as_str).unwrap_or("unknown category"),
+            rating.get("probability").and_then(Value::as_str).unwrap_or("unknown"),
+        ))
+        .collect();
+    if flagged.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", flagged.join(", "))
     }
-    "#.to_string()
 }
 
-fn documentation_prompt() -> String {
-    r#"
-    
-    You can target nested instances using path syntax with forward slashes:
-    - Basic services: "Workspace", "ServerScriptService", etc.
-    - Nested paths: "Workspace/Map", "Workspace/Models/House", "ReplicatedStorage/Assets/Weapons"
-    - Instance names in the path MUST MATCH EXACTLY with existing instances
-
-    YOU MUST START WITH THE HIGHEST LEVEL. i.e. "Workspace" or "ReplicatedStorage" AND INDEX TO TARGET. THIS IS REQUIRED!
-    DO NOT SKIP THIS STEP.
-    YOU MUST INDEX TO TARGET BASED ON THE PROVIDED DOM CONTEXT.
-
-    You can remove instances by providing a path to the instance you want to remove in subtract.
-    When asked to modify, or rewrite, remove the old instance when adding the new one.
-    
-    Valid target_parent examples:
-    - "Workspace" - Top-level workspace (for physical objects, parts, models)
-    - "ServerScriptService" - For server-side scripts
-    - "Workspace/Environment" - Inside a potential folder named "Environment" in Workspace
-    - "ReplicatedStorage/Weapons/Swords" - Deep nesting is supported
-    - "StarterPlayer" - For StarterPlayer
-    - "StarterPlayer/StarterPlayerScripts" - For scripts in StarterPlayerScripts
-    - "StarterPlayer/StarterCharacter" - For scripts StarterCharacter
-    - "StarterGui" - For GUI
-    - "StarterPack" - For character items.
-
-    
-    Example of correctly specifying a parent:
-    "class": "Part",
-    "name": "Door",
-    "target_parent": "Workspace/House",
-
-    Set the run context for scripts with the correct enum.
-    
-    BE VERY IN DEPTH WITH WHAT IS ADDED. ADD MORE DETAIL.
-    ADD MORE INSTANCES TO ADD MORE DETAIL.
-    DOING MANY NESTED CHILDREN IS ALSO OK, AND MAY BE NEEDED IN SOME CASES.
-
-    IF YOU ARE ASKED TO MODIFY SOMETHING, SET THE CORRECT target_parent BASED ON REQUEST.
-    EXAMPLE: If asked to add a door to an existing house model, you MUST use:
-    "target_parent": "Workspace/House"
-
-    IF YOU ARE ASKED TO MODIFY SOMETHING, SET CORRECT target_parent BASED ON REQUEST.
-    EXAMPLE: modify script in StarterPlayerScripts. YOU WILL SET StarterPlayerScripts AS THE target_parent.
-    Use target_parent for setting the parent of outer-most instances in your json response. 
-    
-    You will add a Item element. This item element will have a class, this class is the type of Instance of the item.
-    https://create.roblox.com/docs/reference/engine/classes/Instance 
-    Each class has its own properties and can also have properties infered from other classes.
-    Please correctly add the correct properties for each added item.
-
-    PROVIDE UDIM2 AS AN ARRAY OF 4 VALUES, [xScale, xOffset, yScale, yOffset].
-
-    EVERY INSTANCE MUST HAVE A NAME.
-
-    NAME IS NOT A PROPERTY
-
-    Font enum must be between 0 and 45.
-
-    Do not assign a Primary Part to a Model.
-    
-    BrickColor must be a number and not 0.
-
-    Things like doors, windows, and other objects that should be open, should be NegationOperations instead of parts.
-    Collect groups of parts together as models.
-
-    Material is an Enum type.
-    The default Plastic material has a very light texture, and the SmoothPlastic material has no texture at all.
-    Some material textures like DiamondPlate and Granite have very visible textures. 
-    Each material's texture reflects sunlight differently, especially Foil. 
-    The Glass material changes rendering behavior on moderate graphics settings. 
-    It applies a bit of reflectiveness.
-
-    Name: Plastic Value:256
-    Name: SmoothPlastic Value:272
-    Name: Neon Value:288
-    Name: Wood Value:512
-    Name: WoodPlanks Value:528
-    Name: Marble Value:784
-    Name: Basalt Value:788
-    Name: Slate Value:800
-    Name: CrackedLava Value:804
-    Name: Concrete Value:816
-    Name: Limestone Value:820
-    Name: Granite Value:832
-    Name: Pavement Value:836
-    Name: Brick Value:848
-    Name: Pebble Value:864
-    Name: Cobblestone Value:880
-    Name: Rock Value:896
-    Name: Sandstone Value:912
-    Name: CorrodedMetal Value:1040
-    Name: DiamondPlate Value:1056
-    Name: Foil Value:1072
-    Name: Metal Value:1088
-    Name: Grass Value:1280
-    Name: LeafyGrass Value:1284
-    Name: Sand Value:1296
-    Name: Fabric Value:1312
-    Name: Snow Value:1328
-    Name: Mud Value:1344
-    Name: Ground Value:1360
-    Name: Asphalt Value:1376
-    Name: Salt Value:1392
-    Name: Ice Value:1536
-    Name: Glacier Value:1552
-    Name: Glass Value:1568
-    Name: ForceField Value:1584
-    Name: Air Value:1792
-    Name: Water Value:2048
-    Name: Cardboard Value:2304
-    Name: Carpet Value:2305
-    Name: CeramicTiles Value:2306
-    Name: ClayRoofTiles Value:2307
-    Name: RoofShingles Value:2308
-    Name: Leather Value:2309
-    Name: Plaster Value:2310
-    Name: Rubber Value:2311
-    "#.to_string()
-}
\ No newline at end of file
+/// Send a request, retrying when Gemini is rate limiting (429) or briefly unavailable (500, 503).
+/// Waits as long as Retry-After or the error's retryDelay asks, or else backs off exponentially
+/// with jitter. Gives up with an error after MAX_ATTEMPTS.
+async fn send_with_retry(request: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response, RbxMcpError> {
+    let mut attempt = 1;
+    loop {
+        let response = request().send().await?;
+        let status = response.status();
+        if !RETRY_STATUSES.contains(&status.as_u16()) {
+            return Ok(response);
+        }
+        let header_delay = response.headers().get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+        if attempt == MAX_ATTEMPTS {
+            return Err(RbxMcpError::LlmError(format!(
+                "Still HTTP {} after {} attempts, try again later. Details: {}", status, attempt, body
+            )));
+        }
+        let delay = header_delay.or_else(|| retry_delay(&body)).unwrap_or_else(|| backoff(attempt)).min(MAX_RETRY_DELAY);
+        warn!("Gemini returned HTTP {}, retrying in {:.1}s (attempt {} of {})", status, delay.as_secs_f32(), attempt + 1, MAX_ATTEMPTS);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// The retryDelay Gemini puts in the details of a 429 error, like "30s"
+fn retry_delay(body: &str) -> Option<Duration> {
+    let error: Value = serde_json::from_str(body).ok()?;
+    let details = error.pointer("/error/details")?.as_array()?;
+    let delay = details.iter().find_map(|detail| detail.get("retryDelay")?.as_str())?;
+    let seconds: f64 = delay.strip_suffix('s')?.parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Exponential backoff from one second, with up to half of it again added as jitter
+fn backoff(attempt: u32) -> Duration {
+    let base = Duration::from_secs(1 << (attempt - 1).min(5));
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let jitter = RandomState::new().hash_one(nanos) % 1000;
+    base + base.mul_f64(jitter as f64 / 2000.0)
+}
+
+/// Read a streamGenerateContent response, printing each piece of text as it arrives.
+/// Returns the pieces assembled into the shape of a generateContent response.
+async fn read_event_stream(mut response: reqwest::Response) -> Result<Value, String> {
+    let mut buffer = Vec::new();
+    let mut text = String::new();
+    // Usage metadata comes with the last event
+    let mut last_event = Value::Null;
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let event: Value = serde_json::from_str(data.trim()).map_err(|e| e.to_string())?;
+            if let Some(piece) = GeminiClient::extract_text(&event) {
+                print!("{}", piece);
+                let _ = stdout.flush();
+                text.push_str(&piece);
+            }
+            last_event = event;
+        }
+    }
+    println!();
+
+    // The last candidate keeps its finishReason and safetyRatings, in case the response was blocked
+    match last_event.pointer_mut("/candidates/0") {
+        Some(candidate) => candidate["content"] = json!({ "parts": [{ "text": text }] }),
+        None if !text.is_empty() => last_event["candidates"] = json!([{ "content": { "parts": [{ "text": text }] } }]),
+        None => {}
+    }
+    Ok(last_event)
+}
@@ -0,0 +1,413 @@
+use rbx_dom_weak::types::{CFrame, Vector3};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use crate::property::{euler_degrees_to_matrix, offset_cframe, EnumValue, PropertyValue};
+use crate::roblox::JsonInstance;
+
+/// Names of the generators, as used by `gen` and the generate_ functions
+pub const GENERATORS: [&str; 4] = ["streets", "staircase", "spiral_tower", "forest"];
+
+/// Most parts one generator may build, so a typo in a count can't flood the place
+const MAX_PARTS: usize = 2000;
+
+const IDENTITY: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+/// A grid of streets around square blocks
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Streets {
+    /// Corner of the grid on the ground
+    origin: [f32; 3],
+    blocks_x: usize,
+    blocks_z: usize,
+    block_size: f32,
+    street_width: f32,
+}
+
+impl Default for Streets {
+    fn default() -> Self {
+        Streets { origin: [0.0; 3], blocks_x: 3, blocks_z: 3, block_size: 60.0, street_width: 16.0 }
+    }
+}
+
+/// Straight stairs of solid steps
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Staircase {
+    /// Foot of the stairs on the ground, at the middle of the first step's front edge
+    origin: [f32; 3],
+    steps: usize,
+    step_height: f32,
+    step_depth: f32,
+    width: f32,
+    /// Heading in degrees around the Y axis, 0 climbs toward -Z
+    direction: f32,
+}
+
+impl Default for Staircase {
+    fn default() -> Self {
+        Staircase { origin: [0.0; 3], steps: 10, step_height: 1.0, step_depth: 2.0, width: 6.0, direction: 0.0 }
+    }
+}
+
+/// Steps winding up around a central column, with a platform on top
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SpiralTower {
+    /// Middle of the column on the ground
+    center: [f32; 3],
+    height: f32,
+    radius: f32,
+    column_radius: f32,
+    step_height: f32,
+    /// Degrees turned from one step to the next
+    step_angle: f32,
+}
+
+impl Default for SpiralTower {
+    fn default() -> Self {
+        SpiralTower { center: [0.0; 3], height: 40.0, radius: 10.0, column_radius: 2.0, step_height: 1.0, step_angle: 15.0 }
+    }
+}
+
+/// Trees scattered over a disc, the same trees for the same seed
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Forest {
+    /// Middle of the disc on the ground
+    center: [f32; 3],
+    radius: f32,
+    count: usize,
+    /// Closest two trunks may be
+    min_spacing: f32,
+    seed: u64,
+}
+
+impl Default for Forest {
+    fn default() -> Self {
+        Forest { center: [0.0; 3], radius: 50.0, count: 20, min_spacing: 8.0, seed: 1 }
+    }
+}
+
+/// Build a generator's Model from its parameters, a JSON object. `name` and `target_parent` are
+/// taken by every generator, the rest are its own and default when left out.
+pub fn generate(generator: &str, params: &Value) -> Result<JsonInstance, String> {
+    let mut params = match params {
+        Value::Object(params) => params.clone(),
+        Value::Null => Map::new(),
+        _ => return Err("Generator parameters must be a JSON object".to_string()),
+    };
+    let take_string = |params: &mut Map<String, Value>, key: &str| match params.remove(key) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(format!("{} must be a string", key)),
+    };
+    let name = take_string(&mut params, "name")?;
+    let target_parent = take_string(&mut params, "target_parent")?.unwrap_or_else(|| "Workspace".to_string());
+    let params = Value::Object(params);
+
+    let (default_name, parts) = match generator {
+        "streets" => ("Streets", streets(&parse(generator, params)?)?),
+        "staircase" => ("Staircase", staircase(&parse(generator, params)?)?),
+        "spiral_tower" => ("SpiralTower", spiral_tower(&parse(generator, params)?)?),
+        "forest" => ("Forest", forest(&parse(generator, params)?)?),
+        _ => return Err(format!("There is no generator called {}, try one of {}", generator, GENERATORS.join(", "))),
+    };
+    Ok(JsonInstance {
+        class: "Model".to_string(),
+        name: name.unwrap_or_else(|| default_name.to_string()),
+        properties: HashMap::new(),
+        attributes: HashMap::new(),
+        children: parts,
+        target_parent: Some(target_parent),
+    })
+}
+
+fn parse<T: DeserializeOwned>(generator: &str, params: Value) -> Result<T, String> {
+    serde_json::from_value(params).map_err(|e| format!("Invalid {} parameters: {}", generator, e))
+}
+
+fn streets(params: &Streets) -> Result<Vec<JsonInstance>, String> {
+    let Streets { origin: [x, y, z], blocks_x, blocks_z, block_size, street_width } = *params;
+    // Each count is checked on its own first, so the total can't overflow
+    check_count("blocks", blocks_x)?;
+    check_count("blocks", blocks_z)?;
+    check_count("blocks", (blocks_x + 1) + (blocks_z + 1))?;
+    check_positive(&[("block_size", block_size), ("street_width", street_width)])?;
+
+    let spacing = block_size + street_width;
+    let length_x = blocks_x as f32 * spacing + street_width;
+    let length_z = blocks_z as f32 * spacing + street_width;
+    let mut parts = Vec::new();
+    // Streets running along Z, then those running along X
+    for i in 0..=blocks_x {
+        let position = [x + i as f32 * spacing + street_width / 2.0, y + 0.5, z + length_z / 2.0];
+        parts.push(part(&format!("StreetZ{}", i + 1), [street_width, 1.0, length_z], at(position), ASPHALT));
+    }
+    for i in 0..=blocks_z {
+        let position = [x + length_x / 2.0, y + 0.5, z + i as f32 * spacing + street_width / 2.0];
+        parts.push(part(&format!("StreetX{}", i + 1), [length_x, 1.0, street_width], at(position), ASPHALT));
+    }
+    Ok(parts)
+}
+
+fn staircase(params: &Staircase) -> Result<Vec<JsonInstance>, String> {
+    check_count("steps", params.steps)?;
+    check_positive(&[("step_height", params.step_height), ("step_depth", params.step_depth), ("width", params.width)])?;
+
+    let base = heading(params.origin, params.direction);
+    let parts = (0..params.steps)
+        .map(|i| {
+            // Each step reaches down to the ground, so nothing floats
+            let top = (i + 1) as f32 * params.step_height;
+            let offset = [0.0, top / 2.0, -(i as f32 + 0.5) * params.step_depth];
+            part(&format!("Step{}", i + 1), [params.width, top, params.step_depth], offset_cframe(base, offset, IDENTITY), STONE)
+        })
+        .collect();
+    Ok(parts)
+}
+
+fn spiral_tower(params: &SpiralTower) -> Result<Vec<JsonInstance>, String> {
+    let SpiralTower { center, height, radius, column_radius, step_height, step_angle } = *params;
+    check_positive(&[("height", height), ("column_radius", column_radius), ("step_height", step_height), ("step_angle", step_angle)])?;
+    if radius <= column_radius {
+        return Err("radius must be bigger than column_radius".to_string());
+    }
+    let steps = (height / step_height).floor() as usize;
+    check_count("steps", steps)?;
+
+    let [x, y, z] = center;
+    let mut parts = vec![
+        part("Column", [column_radius * 2.0, height, column_radius * 2.0], at([x, y + height / 2.0, z]), STONE),
+        part("Platform", [radius * 2.0, 1.0, radius * 2.0], at([x, y + height + 0.5, z]), STONE),
+    ];
+    // Steps run from the column to the outer radius, a little wider than the turn between them so they overlap
+    let length = radius - column_radius;
+    let middle = (radius + column_radius) / 2.0;
+    let width = (middle * step_angle.to_radians() * 1.2).max(1.0);
+    for i in 0..steps {
+        let base = heading(center, i as f32 * step_angle);
+        let offset = [middle, (i as f32 + 0.5) * step_height, 0.0];
+        parts.push(part(&format!("Step{}", i + 1), [length, step_height, width], offset_cframe(base, offset, IDENTITY), STONE));
+    }
+    Ok(parts)
+}
+
+fn forest(params: &Forest) -> Result<Vec<JsonInstance>, String> {
+    // Checked before multiplying, so a huge count can't overflow the part count or the attempts
+    check_count("trees", params.count)?;
+    check_count("trees", params.count * 2)?;
+    check_positive(&[("radius", params.radius)])?;
+
+    let mut random = Random::new(params.seed);
+    let [x, y, z] = params.center;
+    let mut trunks: Vec<[f32; 2]> = Vec::new();
+    // Spots too close to a tree are skipped, giving up once it's clear the disc is full
+    for _ in 0..params.count * 30 {
+        if trunks.len() == params.count {
+            break;
+        }
+        let angle = random.next() * TAU;
+        let distance = params.radius * random.next().sqrt();
+        let spot = [x + distance * angle.cos(), z + distance * angle.sin()];
+        let spacing = params.min_spacing.max(0.0);
+        if trunks.iter().all(|other| (other[0] - spot[0]).hypot(other[1] - spot[1]) >= spacing) {
+            trunks.push(spot);
+        }
+    }
+
+    let trees = trunks.iter().enumerate()
+        .map(|(i, &[tree_x, tree_z])| {
+            let trunk_height = 8.0 + random.next() * 8.0;
+            let crown = 6.0 + random.next() * 4.0;
+            let green = 0.4 + random.next() * 0.2;
+            // A cylinder's length runs along X, so the trunk is turned upright
+            let trunk_cframe = CFrame::new(
+                Vector3::new(tree_x, y + trunk_height / 2.0, tree_z),
+                euler_degrees_to_matrix(0.0, 0.0, 90.0),
+            );
+            let mut trunk = part("Trunk", [trunk_height, 1.5, 1.5], trunk_cframe, WOOD);
+            trunk.properties.insert("Shape".to_string(), PropertyValue::Enum(EnumValue::Name("Cylinder".to_string())));
+            let leaves_position = [tree_x, y + trunk_height + crown / 2.0 - 1.0, tree_z];
+            let mut leaves = part("Leaves", [crown; 3], at(leaves_position), ([0.2, green, 0.2], "Grass"));
+            leaves.properties.insert("Shape".to_string(), PropertyValue::Enum(EnumValue::Name("Ball".to_string())));
+            JsonInstance {
+                class: "Model".to_string(),
+                name: format!("Tree{}", i + 1),
+                properties: HashMap::new(),
+                attributes: HashMap::new(),
+                children: vec![trunk, leaves],
+                target_parent: None,
+            }
+        })
+        .collect();
+    Ok(trees)
+}
+
+/// Colour and material of a part
+type Look = ([f32; 3], &'static str);
+
+const ASPHALT: Look = ([0.2, 0.2, 0.22], "Asphalt");
+const STONE: Look = ([0.64, 0.64, 0.62], "Slate");
+const WOOD: Look = ([0.42, 0.28, 0.16], "Wood");
+
+/// An anchored block part
+fn part(name: &str, size: [f32; 3], cframe: CFrame, (color, material): Look) -> JsonInstance {
+    let orientation = cframe.orientation;
+    let properties = HashMap::from([
+        ("Size".to_string(), PropertyValue::Vector3(size)),
+        ("CFrame".to_string(), PropertyValue::CFrame {
+            position: [cframe.position.x, cframe.position.y, cframe.position.z],
+            rotation: [
+                orientation.x.x, orientation.x.y, orientation.x.z,
+                orientation.y.x, orientation.y.y, orientation.y.z,
+                orientation.z.x, orientation.z.y, orientation.z.z,
+            ],
+        }),
+        ("Color".to_string(), PropertyValue::Color3(color)),
+        ("Material".to_string(), PropertyValue::Enum(EnumValue::Name(material.to_string()))),
+        ("Anchored".to_string(), PropertyValue::Bool(true)),
+    ]);
+    JsonInstance {
+        class: "Part".to_string(),
+        name: name.to_string(),
+        properties,
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        target_parent: None,
+    }
+}
+
+/// An unrotated CFrame at a position
+fn at([x, y, z]: [f32; 3]) -> CFrame {
+    CFrame::new(Vector3::new(x, y, z), euler_degrees_to_matrix(0.0, 0.0, 0.0))
+}
+
+/// A CFrame at a position, turned around the Y axis by degrees
+fn heading([x, y, z]: [f32; 3], degrees: f32) -> CFrame {
+    CFrame::new(Vector3::new(x, y, z), euler_degrees_to_matrix(0.0, degrees, 0.0))
+}
+
+fn check_count(what: &str, parts: usize) -> Result<(), String> {
+    if parts > MAX_PARTS {
+        return Err(format!("Too many {}, a generator builds at most {} parts", what, MAX_PARTS));
+    }
+    Ok(())
+}
+
+fn check_positive(values: &[(&str, f32)]) -> Result<(), String> {
+    match values.iter().find(|(_, value)| *value <= 0.0 || !value.is_finite()) {
+        Some((name, value)) => Err(format!("{} must be positive, not {}", name, value)),
+        None => Ok(()),
+    }
+}
+
+/// SplitMix64, so a seed gives the same forest on every platform
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    /// A number from 0 up to 1
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Function declarations for the generators, one per generator, for Gemini's function calling
+pub fn declarations() -> Vec<Value> {
+    let point = |description: &str| json!({ "type": "ARRAY", "items": { "type": "NUMBER" }, "description": description });
+    let number = |description: &str| json!({ "type": "NUMBER", "description": description });
+    let integer = |description: &str| json!({ "type": "INTEGER", "description": description });
+    let declaration = |generator: &str, description: &str, mut properties: Value| {
+        properties["name"] = json!({ "type": "STRING", "description": "Name of the Model built" });
+        properties["target_parent"] = json!({ "type": "STRING", "description": "Path to put the Model in, Workspace by default" });
+        json!({
+            "name": format!("generate_{}", generator),
+            "description": format!("{} Every part is anchored and placed exactly. Parameters left out get defaults. Returns the path of the Model added.", description),
+            "parameters": { "type": "OBJECT", "properties": properties }
+        })
+    };
+    vec![
+        declaration("streets", "Build a grid of asphalt streets around square blocks.", json!({
+            "origin": point("Corner of the grid on the ground, [x, y, z]"),
+            "blocks_x": integer("Blocks along X, 3 by default"),
+            "blocks_z": integer("Blocks along Z, 3 by default"),
+            "block_size": number("Side of each block in studs, 60 by default"),
+            "street_width": number("Width of the streets in studs, 16 by default"),
+        })),
+        declaration("staircase", "Build straight stairs of solid steps.", json!({
+            "origin": point("Middle of the first step's front edge on the ground, [x, y, z]"),
+            "steps": integer("Number of steps, 10 by default"),
+            "step_height": number("Rise of each step in studs, 1 by default"),
+            "step_depth": number("Depth of each step in studs, 2 by default"),
+            "width": number("Width of the stairs in studs, 6 by default"),
+            "direction": number("Heading in degrees around Y, 0 climbs toward -Z"),
+        })),
+        declaration("spiral_tower", "Build steps winding up around a central column, with a platform on top.", json!({
+            "center": point("Middle of the column on the ground, [x, y, z]"),
+            "height": number("Height of the tower in studs, 40 by default"),
+            "radius": number("Outer radius in studs, 10 by default"),
+            "column_radius": number("Radius of the central column in studs, 2 by default"),
+            "step_height": number("Rise of each step in studs, 1 by default"),
+            "step_angle": number("Degrees turned from one step to the next, 15 by default"),
+        })),
+        declaration("forest", "Scatter trees over a disc on the ground. The same seed gives the same trees.", json!({
+            "center": point("Middle of the disc on the ground, [x, y, z]"),
+            "radius": number("Radius of the disc in studs, 50 by default"),
+            "count": integer("Number of trees, 20 by default. Fewer fit when the spacing doesn't allow it"),
+            "min_spacing": number("Closest two trees may be in studs, 8 by default"),
+            "seed": integer("Seed for where trees go and how they look, 1 by default"),
+        })),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_parts(instance: &JsonInstance) -> usize {
+        (instance.class == "Part") as usize + instance.children.iter().map(count_parts).sum::<usize>()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_forest() {
+        let build = |seed: u64| {
+            let forest = generate("forest", &json!({ "count": 10, "seed": seed })).unwrap();
+            serde_json::to_value(&forest).unwrap()
+        };
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+    }
+
+    #[test]
+    fn every_generator_builds_with_its_defaults() {
+        for generator in GENERATORS {
+            let model = generate(generator, &Value::Null).unwrap();
+            assert_eq!(model.class, "Model");
+            assert_eq!(model.target_parent.as_deref(), Some("Workspace"));
+            assert!(count_parts(&model) > 0, "{}", generator);
+        }
+    }
+
+    #[test]
+    fn too_many_parts_are_refused() {
+        let err = generate("streets", &json!({ "blocks_x": 1000, "blocks_z": 1000 })).err().unwrap();
+        assert!(err.contains(&MAX_PARTS.to_string()), "{}", err);
+        assert!(generate("forest", &json!({ "count": MAX_PARTS })).is_err());
+        assert!(generate("staircase", &json!({ "steps": MAX_PARTS + 1 })).is_err());
+        // A count near usize::MAX must not overflow on the way to the check
+        assert!(generate("forest", &json!({ "count": u64::MAX })).is_err());
+    }
+}
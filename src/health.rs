@@ -0,0 +1,152 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::luau;
+use crate::query::instance_position;
+use crate::roblox::{instance_path, is_a, is_service_class, move_instance, remove_instance, set_attribute, CHANGE_ID_ATTRIBUTE};
+
+/// Parts further than this from the origin on any axis lose floating point precision and jitter
+const MAX_COORDINATE: f32 = 100_000.0;
+/// Height below which Workspace destroys falling parts, when the place doesn't set its own
+const DEFAULT_DESTROY_HEIGHT: f32 = -500.0;
+
+/// Kinds of problems a place can have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueKind {
+    /// A class the reflection database doesn't know, which Studio drops when loading the place
+    UnknownClass,
+    /// A script whose Source doesn't parse as Luau
+    ScriptSyntax,
+    /// A Model with nothing in it
+    EmptyModel,
+    /// A part far from the origin, or below the height where falling parts are destroyed
+    ExtremePosition,
+    /// A second copy of a service. Studio only uses one, so whatever is in the others is lost.
+    DuplicateService,
+}
+
+impl IssueKind {
+    /// Whether `fix_issues` can repair it without deciding anything for the user
+    pub fn fixable(self) -> bool {
+        matches!(self, IssueKind::EmptyModel | IssueKind::DuplicateService)
+    }
+}
+
+/// A problem found in a place
+pub struct Issue {
+    pub id: Ref,
+    /// Path of the instance when it was found
+    pub path: String,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)?;
+        if self.kind.fixable() {
+            write!(f, " (fixable)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check every instance in the place for problems, in tree order
+pub fn check_place(dom: &WeakDom) -> Vec<Issue> {
+    let database = rbx_reflection_database::get();
+    let destroy_height = dom.root().children().iter()
+        .filter_map(|&id| dom.get_by_ref(id))
+        .find(|service| service.class == "Workspace")
+        .and_then(|workspace| match workspace.properties.get(&"FallenPartsDestroyHeight".into()) {
+            Some(Variant::Float32(height)) => Some(*height),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_DESTROY_HEIGHT);
+
+    let mut issues = Vec::new();
+    let mut issue = |id: Ref, kind: IssueKind, message: String| {
+        issues.push(Issue { id, path: instance_path(dom, id), kind, message });
+    };
+
+    let mut services: HashMap<&str, Ref> = HashMap::new();
+    for &id in dom.root().children() {
+        let Some(service) = dom.get_by_ref(id) else {
+            continue;
+        };
+        if !is_service_class(&service.class) {
+            continue;
+        }
+        if let Some(&first) = services.get(service.class.as_str()) {
+            let message = format!("another {} besides {}, its {} children are lost", service.class, instance_path(dom, first), service.children().len());
+            issue(id, IssueKind::DuplicateService, message);
+        } else {
+            services.insert(service.class.as_str(), id);
+        }
+    }
+
+    for instance in dom.descendants().skip(1) {
+        let id = instance.referent();
+        if !database.classes.contains_key(instance.class.as_str()) {
+            issue(id, IssueKind::UnknownClass, format!("unknown class '{}'", instance.class));
+            continue;
+        }
+
+        if is_a(&instance.class, "LuaSourceContainer") {
+            if let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) {
+                for error in luau::syntax_errors(source) {
+                    issue(id, IssueKind::ScriptSyntax, format!("syntax error in Source, {}", error));
+                }
+            }
+        } else if instance.class == "Model" && instance.children().is_empty() {
+            issue(id, IssueKind::EmptyModel, "Model has no children".to_string());
+        } else if instance.class != "Terrain" && is_a(&instance.class, "BasePart") {
+            let Some(position) = instance_position(dom, id) else {
+                continue;
+            };
+            if [position.x, position.y, position.z].iter().any(|coordinate| coordinate.abs() > MAX_COORDINATE) {
+                let message = format!("at [{}, {}, {}], more than {} studs from the origin", position.x, position.y, position.z, MAX_COORDINATE);
+                issue(id, IssueKind::ExtremePosition, message);
+            } else if position.y < destroy_height {
+                let message = format!("at height {}, below FallenPartsDestroyHeight ({})", position.y, destroy_height);
+                issue(id, IssueKind::ExtremePosition, message);
+            }
+        }
+    }
+    issues
+}
+
+/// Fix the issues that can be fixed: empty Models are removed, and the children of a duplicate
+/// service are moved into the first one before it's removed. Moved instances are tagged with
+/// change_id. Returns how many issues were fixed.
+pub fn fix_issues(dom: &mut WeakDom, issues: &[Issue], change_id: Option<&str>) -> usize {
+    let mut fixed = 0;
+    for issue in issues.iter().filter(|issue| issue.kind.fixable()) {
+        let Some(instance) = dom.get_by_ref(issue.id) else {
+            continue;
+        };
+        if issue.kind == IssueKind::DuplicateService {
+            let class = instance.class;
+            let children = instance.children().to_vec();
+            let first = dom.root().children().iter()
+                .copied()
+                .find(|&id| dom.get_by_ref(id).is_some_and(|service| service.class == class));
+            let Some(first) = first.filter(|&first| first != issue.id) else {
+                continue;
+            };
+            for child in children {
+                if move_instance(dom, child, first).is_ok() {
+                    if let Some(change_id) = change_id {
+                        set_attribute(dom, child, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.to_string()));
+                    }
+                }
+            }
+        }
+        // Empty Models and emptied services go
+        if remove_instance(dom, issue.id).is_ok() {
+            fixed += 1;
+        }
+    }
+    fixed
+}
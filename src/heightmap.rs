@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::query::Region;
+use crate::terrain::{stud_to_voxel, TerrainGrid, Voxel, MATERIALS, MAX_REGION_VOXELS, VOXEL_SIZE};
+
+/// Written next to the images so an import knows which voxels they cover
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HeightmapInfo {
+    pub region: Region,
+}
+
+/// Voxel bounds of a region, min inclusive and max exclusive
+struct VoxelBounds {
+    min: [i32; 3],
+    max: [i32; 3],
+}
+
+impl VoxelBounds {
+    fn of(region: &Region) -> VoxelBounds {
+        let mut min = [0; 3];
+        let mut max = [0; 3];
+        for axis in 0..3 {
+            let (lo, hi) = (region.min[axis].min(region.max[axis]), region.min[axis].max(region.max[axis]));
+            min[axis] = stud_to_voxel(lo);
+            max[axis] = ((hi / VOXEL_SIZE).ceil() as i32).max(min[axis].saturating_add(1));
+        }
+        VoxelBounds { min, max }
+    }
+
+    fn size(&self, axis: usize) -> usize {
+        (self.max[axis] - self.min[axis]) as usize
+    }
+
+    /// Refuse bounds bigger than a terrain fill may be, which would take too much memory
+    fn check_size(&self) -> Result<(), String> {
+        let count = (0..3).try_fold(1i64, |count, axis| count.checked_mul(self.max[axis] as i64 - self.min[axis] as i64));
+        match count {
+            Some(count) if count <= MAX_REGION_VOXELS => Ok(()),
+            _ => Err(format!("The region covers more than the {} voxels a heightmap may", MAX_REGION_VOXELS)),
+        }
+    }
+}
+
+/// Paths of the sidecar, heightmap and material map for an export prefix
+fn heightmap_paths(prefix: &Path) -> (PathBuf, PathBuf, PathBuf) {
+    let with_suffix = |suffix: &str| {
+        let mut name = prefix.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        prefix.with_file_name(name)
+    };
+    (with_suffix(".json"), with_suffix("_height.png"), with_suffix("_material.png"))
+}
+
+/// Export the terrain in a region as a 16-bit grayscale heightmap and an RGB material map.
+/// Each pixel is one voxel column; black in the heightmap is the bottom of the region, white the top.
+/// Returns the paths written.
+pub fn export_heightmap(grid: &TerrainGrid, region: &Region, prefix: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let bounds = VoxelBounds::of(region);
+    bounds.check_size()?;
+    let (width, depth, height) = (bounds.size(0), bounds.size(2), bounds.size(1));
+
+    let mut heights = Vec::with_capacity(width * depth * 2);
+    let mut materials = Vec::with_capacity(width * depth * 3);
+    for z in bounds.min[2]..bounds.max[2] {
+        for x in bounds.min[0]..bounds.max[0] {
+            // The surface is the top of the highest solid voxel, partly filled by its occupancy
+            let top = (bounds.min[1]..bounds.max[1]).rev()
+                .map(|y| (y, grid.get([x, y, z])))
+                .find(|(_, voxel)| !voxel.is_air());
+            let (surface, material) = match top {
+                Some((y, voxel)) => ((y - bounds.min[1]) as f32 + voxel.occupancy as f32 / 255.0, voxel.material),
+                None => (0.0, 0),
+            };
+            let value = (surface / height as f32 * 65535.0).round() as u16;
+            heights.extend_from_slice(&value.to_be_bytes());
+            let color = MATERIALS.get(material as usize).map_or([255, 0, 255], |(_, color)| *color);
+            materials.extend_from_slice(&color);
+        }
+    }
+
+    let (info_path, height_path, material_path) = heightmap_paths(prefix);
+    write_png(&height_path, width, depth, png::ColorType::Grayscale, png::BitDepth::Sixteen, &heights)?;
+    write_png(&material_path, width, depth, png::ColorType::Rgb, png::BitDepth::Eight, &materials)?;
+    std::fs::write(&info_path, serde_json::to_string_pretty(&HeightmapInfo { region: *region })?)?;
+    Ok(vec![info_path, height_path, material_path])
+}
+
+/// Import an edited heightmap and material map back into the terrain.
+/// Every voxel column in the region is rebuilt: filled with the column's material up to
+/// the height in the heightmap and cleared above it. Returns the number of columns changed.
+pub fn import_heightmap(grid: &mut TerrainGrid, prefix: &Path) -> Result<usize, Box<dyn Error>> {
+    let (info_path, height_path, material_path) = heightmap_paths(prefix);
+    let info: HeightmapInfo = serde_json::from_str(&std::fs::read_to_string(&info_path)
+        .map_err(|e| format!("Error reading {}: {}", info_path.display(), e))?)?;
+    let bounds = VoxelBounds::of(&info.region);
+    bounds.check_size()?;
+    let (width, depth, height) = (bounds.size(0), bounds.size(2), bounds.size(1));
+
+    let heights = read_png(&height_path, width, depth)?;
+    let materials = read_png(&material_path, width, depth)?;
+
+    let mut changed = 0;
+    for (i, (height_pixel, material_pixel)) in heights.iter().zip(&materials).enumerate() {
+        let x = bounds.min[0] + (i % width) as i32;
+        let z = bounds.min[2] + (i / width) as i32;
+        let surface = height_pixel[0] as f32 / 65535.0 * height as f32;
+        let material = nearest_material(material_pixel.map(|c| (c >> 8) as u8));
+        if fill_column(grid, &bounds, x, z, surface, material) {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Build terrain from a grayscale heightmap of any size, stretched over a region with bilinear
+/// sampling. Black is the bottom of the region and white the top, and every voxel column is filled
+/// with the material up to its height and cleared above it. Colour images are read by their
+/// average brightness. Returns the number of columns changed.
+pub fn import_grayscale(grid: &mut TerrainGrid, image: &Path, region: &Region, material: u8) -> Result<usize, Box<dyn Error>> {
+    let bounds = VoxelBounds::of(region);
+    bounds.check_size()?;
+    let (width, depth, height) = (bounds.size(0), bounds.size(2), bounds.size(1));
+    let Image { width: image_width, height: image_height, pixels } = read_png_any_size(image)?;
+    let brightness: Vec<f32> = pixels.iter()
+        .map(|pixel| pixel.iter().map(|&c| c as f32).sum::<f32>() / (3.0 * 65535.0))
+        .collect();
+
+    // Pixel centres line up with the centres of the columns at the region's edges
+    let sample = |u: f32, v: f32| -> f32 {
+        let x = u * (image_width - 1) as f32;
+        let y = v * (image_height - 1) as f32;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(image_width - 1), (y0 + 1).min(image_height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let at = |px: usize, py: usize| brightness[py * image_width + px];
+        let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    };
+    let fraction = |i: usize, count: usize| if count > 1 { i as f32 / (count - 1) as f32 } else { 0.5 };
+
+    let mut changed = 0;
+    for column_z in 0..depth {
+        for column_x in 0..width {
+            let surface = sample(fraction(column_x, width), fraction(column_z, depth)) * height as f32;
+            let (x, z) = (bounds.min[0] + column_x as i32, bounds.min[2] + column_z as i32);
+            if fill_column(grid, &bounds, x, z, surface, material) {
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Fill a voxel column of a region with material up to surface, in voxels above the region's
+/// bottom, partly filling the top voxel, and clear it above. Returns whether anything changed.
+fn fill_column(grid: &mut TerrainGrid, bounds: &VoxelBounds, x: i32, z: i32, surface: f32, material: u8) -> bool {
+    let mut changed = false;
+    for y in bounds.min[1]..bounds.max[1] {
+        let fill = (surface - (y - bounds.min[1]) as f32).clamp(0.0, 1.0);
+        let voxel = match (fill * 255.0).round() as u8 {
+            occupancy if occupancy > 0 && material != 0 => Voxel { material, occupancy },
+            _ => Voxel::AIR,
+        };
+        if grid.get([x, y, z]) != voxel {
+            grid.set([x, y, z], voxel);
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Find the material whose map colour is closest to a pixel
+fn nearest_material(color: [u8; 3]) -> u8 {
+    let distance = |other: &[u8; 3]| -> i32 {
+        (0..3).map(|i| (color[i] as i32 - other[i] as i32).pow(2)).sum()
+    };
+    MATERIALS.iter()
+        .enumerate()
+        .min_by_key(|(_, (_, other))| distance(other))
+        .map_or(0, |(id, _)| id as u8)
+}
+
+fn write_png(
+    path: &Path,
+    width: usize,
+    height: usize,
+    color: png::ColorType,
+    depth: png::BitDepth,
+    data: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width as u32, height as u32);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.write_header()?.write_image_data(data)?;
+    Ok(())
+}
+
+/// Read a PNG of the expected size as 16-bit RGB, whatever its colour type and bit depth
+fn read_png(path: &Path, width: usize, height: usize) -> Result<Vec<[u16; 3]>, Box<dyn Error>> {
+    let image = read_png_any_size(path)?;
+    if image.width != width || image.height != height {
+        return Err(format!(
+            "{} is {}x{} but the region is {}x{} voxels",
+            path.display(), image.width, image.height, width, height
+        ).into());
+    }
+    Ok(image.pixels)
+}
+
+/// A decoded PNG, row by row
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u16; 3]>,
+}
+
+/// Read a PNG as 16-bit RGB, whatever its colour type and bit depth
+fn read_png_any_size(path: &Path) -> Result<Image, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    if width == 0 || height == 0 {
+        return Err(format!("{} has no pixels", path.display()).into());
+    }
+
+    let samples = frame.color_type.samples();
+    let sample_bytes = if frame.bit_depth == png::BitDepth::Sixteen { 2 } else { 1 };
+    let sample = |offset: usize| -> u16 {
+        if sample_bytes == 2 {
+            u16::from_be_bytes([buffer[offset], buffer[offset + 1]])
+        } else {
+            buffer[offset] as u16 * 257
+        }
+    };
+
+    let pixels = (0..width * height)
+        .map(|i| {
+            let base = i * samples * sample_bytes;
+            match frame.color_type {
+                png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => [sample(base); 3],
+                _ => [sample(base), sample(base + sample_bytes), sample(base + 2 * sample_bytes)],
+            }
+        })
+        .collect();
+    Ok(Image { width, height, pixels })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(min: [f32; 3], max: [f32; 3]) -> Region {
+        Region { min, max }
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let mut grid = TerrainGrid::default();
+        let grass = Voxel { material: 2, occupancy: 255 };
+        grid.fill_region(&region([0.0, 0.0, 0.0], [16.0, 8.0, 16.0]), grass).unwrap();
+        let prefix = std::env::temp_dir().join(format!("rbx-mcp-heightmap-{}", std::process::id()));
+        let paths = export_heightmap(&grid, &region([0.0, 0.0, 0.0], [16.0, 32.0, 16.0]), &prefix).unwrap();
+
+        let mut imported = TerrainGrid::default();
+        let changed = import_heightmap(&mut imported, &prefix).unwrap();
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(changed, 16);
+        assert_eq!(imported.solid_count(), grid.solid_count());
+        assert_eq!(imported.get([1, 1, 1]), grass);
+        assert!(imported.get([1, 2, 1]).is_air());
+    }
+
+    #[test]
+    fn oversized_regions_are_refused() {
+        let huge = region([-1e6, 0.0, -1e6], [1e6, 100.0, 1e6]);
+        let prefix = std::env::temp_dir().join("rbx-mcp-never-written");
+        assert!(export_heightmap(&TerrainGrid::default(), &huge, &prefix).is_err());
+        assert!(VoxelBounds::of(&region([0.0; 3], [f32::MAX; 3])).check_size().is_err());
+        let image = std::env::temp_dir().join("rbx-mcp-never-read.png");
+        assert!(import_grayscale(&mut TerrainGrid::default(), &image, &huge, 2).unwrap_err().to_string().contains("voxels"));
+    }
+}
@@ -0,0 +1,405 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use tracing::debug;
+
+use crate::error::RbxMcpError;
+use crate::roblox::write_atomically;
+
+/// Saves a place that stays loaded between edits, reserializing only the parts that changed.
+///
+/// The place is split into chunks: each service's own properties, and the subtree under each
+/// child of a service. A chunk is serialized with rbx_xml once and its XML kept until an
+/// instance inside it is marked changed. Referents are rewritten to ones derived from each
+/// instance's Ref, so chunks serialized separately can be joined into one file.
+///
+/// Cached chunks are only valid for the WeakDom they came from. Call `clear` after reloading.
+#[derive(Default)]
+pub struct IncrementalWriter {
+    chunks: HashMap<Ref, Chunk>,
+    changed: HashSet<Ref>,
+}
+
+/// Serialized XML for a chunk and the shared strings it uses
+struct Chunk {
+    xml: String,
+    shared_strings: Vec<(String, String)>,
+    /// Instances outside the chunk that its Ref properties point to
+    outside_refs: Vec<Ref>,
+}
+
+/// How much of the place a save reused
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteStats {
+    pub reused: usize,
+    pub serialized: usize,
+}
+
+impl IncrementalWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark instances as changed, so the chunks holding them are serialized again.
+    /// For a removed instance, mark its former parent.
+    pub fn mark_changed(&mut self, ids: impl IntoIterator<Item = Ref>) {
+        self.changed.extend(ids);
+    }
+
+    /// Forget every cached chunk, e.g. after the place was reloaded from disk
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.changed.clear();
+    }
+
+    /// Write the place to path, atomically like `write_roblox_file`
+    pub fn write(&mut self, path: impl AsRef<Path>, dom: &WeakDom) -> Result<WriteStats, RbxMcpError> {
+        self.invalidate_changed(dom);
+
+        let root = dom.root();
+        let mut stats = WriteStats::default();
+        let mut used = HashSet::new();
+        let mut keys = Vec::new();
+        for &service_id in root.children() {
+            keys.push(service_id);
+            keys.extend(dom.get_by_ref(service_id).map_or(&[][..], |service| service.children()));
+        }
+        for &key in &keys {
+            used.insert(key);
+            if self.chunks.contains_key(&key) {
+                stats.reused += 1;
+                continue;
+            }
+            let is_service = dom.get_by_ref(key).is_some_and(|instance| instance.parent() == dom.root_ref());
+            let chunk = if is_service { serialize_service(dom, key)? } else { serialize_subtree(dom, key)? };
+            self.chunks.insert(key, chunk);
+            stats.serialized += 1;
+        }
+        self.chunks.retain(|key, _| used.contains(key));
+
+        write_atomically(path.as_ref(), |writer| {
+            writeln!(writer, "<roblox version=\"4\">")?;
+            let mut shared_strings = BTreeMap::new();
+            for &service_id in root.children() {
+                let service = &self.chunks[&service_id];
+                writer.write_all(service.xml.as_bytes())?;
+                shared_strings.extend(service.shared_strings.iter().cloned());
+                for child_id in dom.get_by_ref(service_id).map_or(&[][..], |service| service.children()) {
+                    let chunk = &self.chunks[child_id];
+                    writer.write_all(chunk.xml.as_bytes())?;
+                    shared_strings.extend(chunk.shared_strings.iter().cloned());
+                }
+                writeln!(writer, "  </Item>")?;
+            }
+            if !shared_strings.is_empty() {
+                writeln!(writer, "  <SharedStrings>")?;
+                for (md5, data) in &shared_strings {
+                    writeln!(writer, "    <SharedString md5=\"{}\">{}</SharedString>", md5, data)?;
+                }
+                writeln!(writer, "  </SharedStrings>")?;
+            }
+            writeln!(writer, "</roblox>")?;
+            Ok(())
+        })?;
+
+        debug!("Saved place, reused {} chunks and serialized {}", stats.reused, stats.serialized);
+        Ok(stats)
+    }
+
+    /// Drop the cached chunks holding changed instances, and those whose Refs point at removed instances,
+    /// since a full write would have written null for those
+    fn invalidate_changed(&mut self, dom: &WeakDom) {
+        self.chunks.retain(|_, chunk| chunk.outside_refs.iter().all(|&target| dom.get_by_ref(target).is_some()));
+        for id in std::mem::take(&mut self.changed) {
+            if id == dom.root_ref() {
+                self.chunks.clear();
+                continue;
+            }
+            if let Some(key) = chunk_key(dom, id) {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+}
+
+/// The chunk an instance is serialized in: the service itself, or the child of a service it is under.
+/// None for instances no longer in the place.
+fn chunk_key(dom: &WeakDom, id: Ref) -> Option<Ref> {
+    let mut current = id;
+    loop {
+        let parent = dom.get_by_ref(current)?.parent();
+        if parent == dom.root_ref() {
+            return Some(current);
+        }
+        if dom.get_by_ref(parent)?.parent() == dom.root_ref() {
+            return Some(current);
+        }
+        current = parent;
+    }
+}
+
+/// Referent written for an instance. Stable for as long as the place stays loaded.
+fn referent(id: Ref) -> String {
+    format!("RBX{}", id)
+}
+
+/// Serialize a child of a service with everything under it
+fn serialize_subtree(dom: &WeakDom, id: Ref) -> Result<Chunk, RbxMcpError> {
+    let mut items = Vec::new();
+    preorder(dom, id, &mut items);
+    let mut buffer = Vec::new();
+    rbx_xml::to_writer_default(&mut buffer, dom, &[id])?;
+    split_document(&buffer, &items, dom)
+}
+
+/// Serialize a service's own properties, leaving its Item open for the chunks under it
+fn serialize_service(dom: &WeakDom, id: Ref) -> Result<Chunk, RbxMcpError> {
+    let service = dom.get_by_ref(id).ok_or(RbxMcpError::InstanceNotFound(id))?;
+    let alone = WeakDom::new(
+        InstanceBuilder::new(service.class)
+            .with_name(service.name.as_str())
+            .with_properties(service.properties.iter().map(|(name, value)| (*name, value.clone()))),
+    );
+    let mut buffer = Vec::new();
+    rbx_xml::to_writer_default(&mut buffer, &alone, &[alone.root_ref()])?;
+    let mut chunk = split_document(&buffer, &[id], dom)?;
+    let end = chunk.xml.rfind("</Item>").ok_or_else(|| unexpected("service has no closing tag"))?;
+    chunk.xml.truncate(end);
+    chunk.xml.truncate(chunk.xml.trim_end_matches(' ').len());
+    Ok(chunk)
+}
+
+/// Instances in the order rbx_xml writes them
+fn preorder(dom: &WeakDom, id: Ref, out: &mut Vec<Ref>) {
+    out.push(id);
+    if let Some(instance) = dom.get_by_ref(id) {
+        for &child_id in instance.children() {
+            preorder(dom, child_id, out);
+        }
+    }
+}
+
+fn unexpected(what: &str) -> RbxMcpError {
+    RbxMcpError::InvalidOperation(format!("Unexpected rbx_xml output: {}", what))
+}
+
+/// Take the items and shared strings out of a document rbx_xml wrote, with referents rewritten.
+/// items lists the instances in the document in the order they appear.
+fn split_document(document: &[u8], items: &[Ref], dom: &WeakDom) -> Result<Chunk, RbxMcpError> {
+    let document = std::str::from_utf8(document).map_err(|_| unexpected("not UTF-8"))?;
+    let start = document.find('>').ok_or_else(|| unexpected("no root element"))? + 1;
+    let end = document.rfind("</roblox>").ok_or_else(|| unexpected("no closing root element"))?;
+    let mut body = &document[start..end];
+
+    // The shared strings block comes after every item
+    let mut shared_strings = Vec::new();
+    if let Some(block_start) = body.rfind("<SharedStrings>").filter(|&i| !body[i..].contains("</Item>")) {
+        let mut block = &body[block_start..];
+        while let Some(entry_start) = block.find("<SharedString md5=\"") {
+            block = &block[entry_start + "<SharedString md5=\"".len()..];
+            let md5_end = block.find('"').ok_or_else(|| unexpected("unterminated shared string hash"))?;
+            let data_start = block.find('>').ok_or_else(|| unexpected("unterminated shared string"))? + 1;
+            let data_end = block.find("</SharedString>").ok_or_else(|| unexpected("unterminated shared string"))?;
+            shared_strings.push((block[..md5_end].to_string(), block[data_start..data_end].to_string()));
+            block = &block[data_end..];
+        }
+        body = &body[..block_start];
+    }
+
+    let (xml, outside_refs) = rewrite_referents(body.trim_start_matches(['\r', '\n']), items, dom)?;
+    Ok(Chunk { xml, shared_strings, outside_refs })
+}
+
+/// Replace rbx_xml's per-document referents with ones that are unique across the place.
+/// Also returns the instances outside the chunk that Ref properties point to.
+fn rewrite_referents(xml: &str, items: &[Ref], dom: &WeakDom) -> Result<(String, Vec<Ref>), RbxMcpError> {
+    // First pass: which local referent belongs to which instance
+    let mut local = HashMap::new();
+    let mut cursor = 0;
+    let mut count = 0;
+    while let Some((at, markup)) = next_markup(xml, cursor) {
+        cursor = at + 1;
+        if let Markup::ItemStart { referent, .. } = markup {
+            let id = *items.get(count).ok_or_else(|| unexpected("more items than instances"))?;
+            local.insert(referent, id);
+            count += 1;
+        }
+    }
+    if count != items.len() {
+        return Err(unexpected("fewer items than instances"));
+    }
+
+    // Second pass: copy the XML, swapping referents
+    let mut out = String::with_capacity(xml.len() + items.len() * 32);
+    let mut outside_refs = Vec::new();
+    let mut open_items = Vec::new();
+    let mut copied = 0;
+    let mut cursor = 0;
+    while let Some((at, markup)) = next_markup(xml, cursor) {
+        cursor = at + 1;
+        match markup {
+            Markup::ItemStart { referent, value_range } => {
+                let id = local[referent];
+                open_items.push(id);
+                out.push_str(&xml[copied..value_range.0]);
+                out.push_str(&self::referent(id));
+                copied = value_range.1;
+            }
+            Markup::ItemEnd => {
+                open_items.pop();
+            }
+            Markup::Ref { name, value, value_range } => {
+                if value == "null" {
+                    continue;
+                }
+                // Refs to instances outside the chunk got a referent rbx_xml never wrote an item for
+                let target = local.get(value).copied().or_else(|| {
+                    let owner = dom.get_by_ref(*open_items.last()?)?;
+                    match owner.properties.get(&name.into()) {
+                        Some(Variant::Ref(target)) if dom.get_by_ref(*target).is_some() => {
+                            outside_refs.push(*target);
+                            Some(*target)
+                        }
+                        _ => None,
+                    }
+                });
+                out.push_str(&xml[copied..value_range.0]);
+                match target {
+                    Some(target) => out.push_str(&self::referent(target)),
+                    None => {
+                        debug!("Could not find the target of Ref property {}, writing null", name);
+                        out.push_str("null");
+                    }
+                }
+                copied = value_range.1;
+            }
+        }
+    }
+    out.push_str(&xml[copied..]);
+    Ok((out, outside_refs))
+}
+
+/// Markup that referent rewriting cares about
+enum Markup<'a> {
+    /// `<Item class="..." referent="N">`, with the byte range of N
+    ItemStart { referent: &'a str, value_range: (usize, usize) },
+    ItemEnd,
+    /// `<Ref name="P">N</Ref>`, with the byte range of N
+    Ref { name: &'a str, value: &'a str, value_range: (usize, usize) },
+}
+
+/// Find the next Item tag or Ref property at or after from, skipping CDATA sections
+fn next_markup(xml: &str, mut from: usize) -> Option<(usize, Markup<'_>)> {
+    loop {
+        let at = from + xml[from..].find('<')?;
+        let rest = &xml[at..];
+        if rest.starts_with("<![CDATA[") {
+            from = at + rest.find("]]>")? + 3;
+        } else if rest.starts_with("<Item ") {
+            let tag_end = rest.find('>')?;
+            let attribute = rest[..tag_end].find("referent=\"")? + "referent=\"".len();
+            let length = rest[attribute..].find('"')?;
+            let value_range = (at + attribute, at + attribute + length);
+            return Some((at, Markup::ItemStart { referent: &xml[value_range.0..value_range.1], value_range }));
+        } else if rest.starts_with("</Item>") {
+            return Some((at, Markup::ItemEnd));
+        } else if let Some(after) = rest.strip_prefix("<Ref name=\"") {
+            let name_end = after.find('"')?;
+            let value_start = at + "<Ref name=\"".len() + name_end + after[name_end..].find('>')? + 1;
+            let value_end = value_start + xml[value_start..].find("</Ref>")?;
+            let name = &after[..name_end];
+            return Some((at, Markup::Ref { name, value: &xml[value_start..value_end], value_range: (value_start, value_end) }));
+        } else {
+            from = at + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::{instance_path, parse_roblox_file, write_roblox_file};
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rbx-mcp-incremental-{}-{}.rbxlx", std::process::id(), name))
+    }
+
+    /// Every instance's path, class and properties, with Refs written as the path they point to
+    fn describe(dom: &WeakDom) -> Vec<String> {
+        let mut ids = Vec::new();
+        for &service_id in dom.root().children() {
+            preorder(dom, service_id, &mut ids);
+        }
+        ids.into_iter()
+            .map(|id| {
+                let instance = dom.get_by_ref(id).unwrap();
+                let mut properties: Vec<String> = instance.properties.iter()
+                    .map(|(name, value)| match value {
+                        Variant::Ref(target) if target.is_some() => format!("{}={}", name, instance_path(dom, *target)),
+                        value => format!("{}={:?}", name, value),
+                    })
+                    .collect();
+                properties.sort();
+                format!("{} {} {}", instance_path(dom, id), instance.class, properties.join(" "))
+            })
+            .collect()
+    }
+
+    /// Write the place both ways and check they read back the same, returning the incremental file
+    fn assert_matches_full_write(writer: &mut IncrementalWriter, dom: &WeakDom, name: &str) -> (WriteStats, String) {
+        let incremental_path = temp_path(&format!("{}-incremental", name));
+        let full_path = temp_path(&format!("{}-full", name));
+        let stats = writer.write(&incremental_path, dom).unwrap();
+        write_roblox_file(&full_path, dom).unwrap();
+        let xml = std::fs::read_to_string(&incremental_path).unwrap();
+        let incremental = describe(&parse_roblox_file(&incremental_path).unwrap());
+        let full = describe(&parse_roblox_file(&full_path).unwrap());
+        let _ = std::fs::remove_file(&incremental_path);
+        let _ = std::fs::remove_file(&full_path);
+        assert_eq!(incremental, full);
+        (stats, xml)
+    }
+
+    /// Two models in Workspace, the first with an ObjectValue pointing into the second
+    fn place_with_ref() -> (WeakDom, Ref, Ref, Ref) {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace_id = dom.insert(root_ref, InstanceBuilder::new("Workspace").with_name("Workspace"));
+        dom.insert(root_ref, InstanceBuilder::new("Lighting").with_name("Lighting"));
+        let first_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("First"));
+        let second_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Second"));
+        let target_id = dom.insert(second_id, InstanceBuilder::new("Part").with_name("Target"));
+        dom.insert(first_id, InstanceBuilder::new("ObjectValue").with_name("Pointer").with_property("Value", target_id));
+        (dom, workspace_id, second_id, target_id)
+    }
+
+    #[test]
+    fn unchanged_chunks_are_reused() {
+        let (mut dom, workspace_id, _, _) = place_with_ref();
+        let mut writer = IncrementalWriter::new();
+        let (stats, _) = assert_matches_full_write(&mut writer, &dom, "reuse");
+        assert_eq!(stats.reused, 0);
+
+        let added_id = dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Added"));
+        writer.mark_changed([added_id]);
+        let (stats, _) = assert_matches_full_write(&mut writer, &dom, "reuse");
+        assert_eq!((stats.reused, stats.serialized), (4, 1));
+    }
+
+    #[test]
+    fn refs_to_removed_instances_are_written_as_null() {
+        let (mut dom, workspace_id, second_id, target_id) = place_with_ref();
+        let mut writer = IncrementalWriter::new();
+        let (_, xml) = assert_matches_full_write(&mut writer, &dom, "removed");
+        assert_eq!(xml.matches(&referent(target_id)).count(), 2);
+
+        // Only the removed model's parent is marked, not the chunk holding the ObjectValue
+        dom.destroy(second_id);
+        writer.mark_changed([workspace_id]);
+        let (_, xml) = assert_matches_full_write(&mut writer, &dom, "removed");
+        assert!(!xml.contains(&referent(target_id)));
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// A second Ctrl+C this soon after the first ends the session even if the first cancelled a request
+const DOUBLE_PRESS: Duration = Duration::from_secs(2);
+
+/// Ctrl+C handling for an interactive session. The first press cancels the Gemini request in
+/// flight and returns to the prompt, a second press ends the session. Pressed while no request
+/// is in flight, it ends the session once the current step finishes.
+///
+/// At the prompt itself the line editor reads Ctrl+C as a key, so this only sees presses
+/// while something is running.
+pub struct Interrupts {
+    state: Arc<Mutex<State>>,
+    exit: CancellationToken,
+}
+
+#[derive(Default)]
+struct State {
+    request: Option<CancellationToken>,
+    last_press: Option<Instant>,
+}
+
+impl Interrupts {
+    /// Start listening for Ctrl+C. From then on it no longer kills the process.
+    pub fn listen() -> Interrupts {
+        let state = Arc::new(Mutex::new(State::default()));
+        let exit = CancellationToken::new();
+        tokio::spawn({
+            let state = state.clone();
+            let exit = exit.clone();
+            async move {
+                while tokio::signal::ctrl_c().await.is_ok() {
+                    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                    let double = state.last_press.is_some_and(|last| last.elapsed() < DOUBLE_PRESS);
+                    state.last_press = Some(Instant::now());
+                    match state.request.take() {
+                        Some(request) if !double => {
+                            request.cancel();
+                            println!("\nCancelling, press Ctrl+C again to exit");
+                        }
+                        request => {
+                            if let Some(request) = request {
+                                request.cancel();
+                            }
+                            exit.cancel();
+                            println!("\nExiting once the current step finishes");
+                        }
+                    }
+                }
+            }
+        });
+        Interrupts { state, exit }
+    }
+
+    /// A token the next Ctrl+C cancels, for a request about to be sent.
+    /// Call `finish_request` once it's done.
+    pub fn start_request(&self) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).request = Some(token.clone());
+        token
+    }
+
+    pub fn finish_request(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).request = None;
+    }
+
+    /// Whether Ctrl+C asked for the session to end
+    pub fn exit_requested(&self) -> bool {
+        self.exit.is_cancelled()
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::hash::BuildHasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::roblox::Modification;
+
+/// One change made to a place, appended to the place's journal
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// ID of the change, also stored on the instances it touched
+    #[serde(default)]
+    pub change_id: Option<String>,
+    /// ID of the process run that made the change
+    #[serde(default)]
+    pub session: Option<String>,
+    pub description: String,
+}
+
+/// The journal lives next to the place, e.g. game.rbxlx.journal.jsonl
+pub fn journal_path(place: &Path) -> PathBuf {
+    let mut name = place.file_name().unwrap_or_default().to_os_string();
+    name.push(".journal.jsonl");
+    place.with_file_name(name)
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// How long ago a timestamp was, like "5 minutes ago"
+pub fn format_age(timestamp: u64) -> String {
+    let seconds = now().saturating_sub(timestamp);
+    let (amount, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// A short random ID, like "3fa9c21e"
+fn short_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    format!("{:08x}", RandomState::new().hash_one(nanos) as u32)
+}
+
+/// A new ID for a modification about to be applied
+pub fn new_change_id() -> String {
+    short_id()
+}
+
+/// ID of this run of the program, shared by every change it makes
+pub fn session_id() -> &'static str {
+    static SESSION: OnceLock<String> = OnceLock::new();
+    SESSION.get_or_init(short_id)
+}
+
+/// Append a description of a change to the place's journal
+pub fn record(place: &Path, change_id: Option<&str>, description: &str) -> Result<(), Box<dyn Error>> {
+    let entry = JournalEntry {
+        timestamp: now(),
+        change_id: change_id.map(str::to_string),
+        session: Some(session_id().to_string()),
+        description: description.to_string(),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(journal_path(place))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Read every journal entry for a place, oldest first. A place with no journal has no entries.
+pub fn read(place: &Path) -> Result<Vec<JournalEntry>, Box<dyn Error>> {
+    let text = match std::fs::read_to_string(journal_path(place)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Short summary of what a modification does, like "added 3, removed 1"
+pub fn describe_modification(modification: &Modification) -> String {
+    let counts = [
+        ("added", modification.add.len()),
+        ("removed", modification.subtract.len()),
+        ("renamed", modification.rename.len()),
+        ("moved", modification.moves.len()),
+        ("cloned", modification.clone.len()),
+        ("inserted", modification.insert.len()),
+        ("set properties of", modification.set.len()),
+        ("filled terrain", modification.terrain_fill.len()),
+        ("cleared terrain", modification.terrain_clear.len()),
+    ];
+    let parts: Vec<String> = counts.iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(verb, count)| format!("{} {}", verb, count))
+        .collect();
+    if parts.is_empty() {
+        "no changes".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Find the journal entry for a change ID
+pub fn find_change(place: &Path, change_id: &str) -> Result<Option<JournalEntry>, Box<dyn Error>> {
+    Ok(read(place)?.into_iter().find(|entry| entry.change_id.as_deref() == Some(change_id)))
+}
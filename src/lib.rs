@@ -1,6 +1,62 @@
+pub mod assets;
+pub mod audit;
+pub mod bench;
+pub mod bridge;
+pub mod budget;
+pub mod catalog;
+pub mod clean;
 pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod context;
+pub mod csg;
+pub mod dedup;
+pub mod defaults;
+pub mod diff;
+pub mod error;
+pub mod examples;
 pub mod gemini_api;
+pub mod generators;
+pub mod health;
+pub mod heightmap;
+pub mod incremental;
+pub mod interrupt;
+pub mod journal;
+pub mod line_editor;
+pub mod lock;
+pub mod logging;
+pub mod luau;
+pub mod macros;
+pub mod merge;
+pub mod open_cloud;
+pub mod overlap;
+pub mod patch;
+pub mod prompt;
+pub mod property;
+pub mod query;
+pub mod repl;
+pub mod report;
+pub mod response_cache;
+pub mod rest;
+pub mod retrieval;
 pub mod roblox;
+pub mod rojo;
+pub mod scripts;
+pub mod session;
+pub mod streaming;
+pub mod sync;
+pub mod terrain;
+pub mod tidy;
+pub mod tools;
+pub mod transcript;
+pub mod tree;
+pub mod upload;
+pub mod validation;
+pub mod vertex;
+pub mod warnings;
+pub mod watcher;
 
 // Re-export common items for convenience
+pub use error::RbxMcpError;
 pub use gemini_api::GeminiClient;
+pub use property::PropertyValue;
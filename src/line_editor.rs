@@ -0,0 +1,101 @@
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Prompt shown while a multi-line prompt is being entered
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// Reads prompts with line editing, persistent history and Ctrl+R search.
+/// A line ending in a backslash continues on the next line, and `<<TAG` starts
+/// a block that runs until a line containing only TAG.
+pub struct LineEditor {
+    editor: DefaultEditor,
+    history_path: Option<PathBuf>,
+}
+
+/// History is kept in ~/.config/rbx-mcp/history
+fn history_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".config").join("rbx-mcp").join("history"))
+}
+
+impl LineEditor {
+    pub fn new() -> Result<LineEditor, Box<dyn Error>> {
+        let mut editor = DefaultEditor::new()?;
+        let history_path = history_path();
+        if let Some(path) = &history_path {
+            // No history file yet on first run
+            let _ = editor.load_history(path);
+        }
+        Ok(LineEditor { editor, history_path })
+    }
+
+    /// Read a full prompt, which may span several lines. Returns None on Ctrl+C or Ctrl+D.
+    pub fn read_prompt(&mut self, prompt: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let Some(first) = self.read_line(prompt)? else {
+            return Ok(None);
+        };
+
+        let text = if let Some(tag) = first.trim().strip_prefix("<<").map(str::trim).filter(|tag| !tag.is_empty()) {
+            let mut lines = Vec::new();
+            loop {
+                match self.read_line(CONTINUATION_PROMPT)? {
+                    Some(line) if line.trim() == tag => break,
+                    Some(line) => lines.push(line),
+                    None => return Ok(None),
+                }
+            }
+            lines.join("\n")
+        } else {
+            let mut lines = Vec::new();
+            let mut line = first;
+            while let Some(stripped) = line.strip_suffix('\\') {
+                lines.push(stripped.to_string());
+                match self.read_line(CONTINUATION_PROMPT)? {
+                    Some(next) => line = next,
+                    None => return Ok(None),
+                }
+            }
+            lines.push(line);
+            lines.join("\n")
+        };
+
+        let text = text.trim().to_string();
+        if !text.is_empty() {
+            self.remember(&text);
+        }
+        Ok(Some(text))
+    }
+
+    /// Read a one-line answer to a question, which is left out of the history
+    pub fn ask(&mut self, question: &str) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.read_line(question)?.map(|answer| answer.trim().to_lowercase()))
+    }
+
+    fn read_line(&mut self, prompt: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.editor.readline(prompt) {
+            Ok(line) => Ok(Some(line)),
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Add a prompt to the history and save it right away so it survives a crash
+    fn remember(&mut self, text: &str) {
+        if let Err(e) = self.editor.add_history_entry(text) {
+            warn!("Error adding to history: {}", e);
+        }
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        let saved = path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|()| self.editor.save_history(path).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            warn!("Error saving history to {}: {}", path.display(), e);
+        }
+    }
+}
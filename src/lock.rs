@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
+
+use crate::error::RbxMcpError;
+
+/// Hash of each place file as this process last read or wrote it
+static CHECKSUMS: LazyLock<Mutex<HashMap<PathBuf, u64>>> = LazyLock::new(Default::default);
+
+/// Remember the contents of a place file that was just read or written
+pub fn record_checksum(path: &Path, contents: &[u8]) {
+    CHECKSUMS.lock().unwrap().insert(checksum_key(path), checksum(contents));
+}
+
+/// Accept whatever is on disk now as the version this process knows, e.g. to overwrite it on purpose
+pub fn record_current(path: &Path) -> Result<(), RbxMcpError> {
+    let contents = std::fs::read(path)?;
+    record_checksum(path, &contents);
+    Ok(())
+}
+
+/// Check that a place file is still the version this process last read or wrote,
+/// so a write doesn't throw away changes something else saved in between.
+/// Files this process hasn't read, or that don't exist, pass.
+pub fn verify_checksum(path: &Path) -> Result<(), RbxMcpError> {
+    let Some(&expected) = CHECKSUMS.lock().unwrap().get(&checksum_key(path)) else {
+        return Ok(());
+    };
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if checksum(&contents) != expected {
+        return Err(RbxMcpError::ChangedOnDisk(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+fn checksum(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(contents);
+    hasher.finish()
+}
+
+/// The same key for every way of writing a path, whether or not the file exists yet
+fn checksum_key(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A lock file next to a place, held while this process may write it.
+/// Another rbx-mcp process trying to lock the same place gets an error. The lock file is
+/// removed when this is dropped.
+pub struct PlaceLock {
+    lock_path: PathBuf,
+}
+
+impl PlaceLock {
+    /// Lock a place by creating PLACE.lock holding this process's ID.
+    /// A lock left behind by a process that is no longer running is taken over where that can be checked.
+    pub fn acquire(place: &Path) -> Result<PlaceLock, RbxMcpError> {
+        let mut lock_path = place.as_os_str().to_os_string();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        for attempt in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())?;
+                    return Ok(PlaceLock { lock_path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let pid = std::fs::read_to_string(&lock_path).ok().and_then(|text| text.trim().parse::<u32>().ok());
+                    if attempt == 0 && pid.is_some_and(|pid| !process_running(pid)) {
+                        warn!("Removing stale lock {} left by process {}", lock_path.display(), pid.unwrap_or_default());
+                        std::fs::remove_file(&lock_path)?;
+                        continue;
+                    }
+                    return Err(RbxMcpError::Locked { path: place.to_path_buf(), lock_path, pid });
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(RbxMcpError::Locked { path: place.to_path_buf(), lock_path, pid: None })
+    }
+}
+
+impl Drop for PlaceLock {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.lock_path) {
+            warn!("Error removing lock {}: {}", self.lock_path.display(), e);
+        }
+    }
+}
+
+/// Whether a process is running. Only checked where /proc exists; elsewhere every process
+/// counts as running, so a stale lock has to be deleted by hand.
+fn process_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    !proc.is_dir() || proc.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_place(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rbx-mcp-lock-{}-{}.rbxlx", std::process::id(), name))
+    }
+
+    #[test]
+    fn a_place_can_only_be_locked_once_at_a_time() {
+        let place = temp_place("held");
+        let lock = PlaceLock::acquire(&place).unwrap();
+        match PlaceLock::acquire(&place) {
+            Err(RbxMcpError::Locked { pid, .. }) => assert_eq!(pid, Some(std::process::id())),
+            _ => panic!("a held lock was acquired again"),
+        }
+        let lock_path = lock.lock_path.clone();
+        drop(lock);
+        assert!(!lock_path.exists());
+        drop(PlaceLock::acquire(&place).unwrap());
+    }
+
+    #[test]
+    fn a_lock_left_by_a_finished_process_is_taken_over() {
+        let place = temp_place("stale");
+        let lock_path = PathBuf::from(format!("{}.lock", place.display()));
+        // No process gets an ID this high
+        std::fs::write(&lock_path, format!("{}\n", u32::MAX)).unwrap();
+        let result = PlaceLock::acquire(&place);
+        if Path::new("/proc").is_dir() {
+            assert!(result.is_ok());
+            assert_eq!(std::fs::read_to_string(&lock_path).unwrap().trim(), std::process::id().to_string());
+        } else {
+            assert!(matches!(result, Err(RbxMcpError::Locked { .. })));
+            std::fs::remove_file(&lock_path).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_place_changed_by_someone_else_is_not_overwritten() {
+        let place = temp_place("checksum");
+        std::fs::write(&place, "first").unwrap();
+        record_checksum(&place, b"first");
+        assert!(verify_checksum(&place).is_ok());
+
+        std::fs::write(&place, "someone else's").unwrap();
+        assert!(matches!(verify_checksum(&place), Err(RbxMcpError::ChangedOnDisk(_))));
+        record_current(&place).unwrap();
+        assert!(verify_checksum(&place).is_ok());
+
+        // A place deleted since it was read can be written again
+        std::fs::remove_file(&place).unwrap();
+        assert!(verify_checksum(&place).is_ok());
+    }
+}
@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// How much to print to the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Warnings and errors only
+    Quiet,
+    /// What each operation did
+    Normal,
+    /// Every instance and property as it is processed
+    Verbose,
+    /// Everything, including trace events
+    Trace,
+}
+
+impl Verbosity {
+    /// Pick the verbosity from the --quiet flag and the number of --verbose flags
+    pub fn from_flags(quiet: bool, verbose: u8) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    fn level(self) -> Level {
+        match self {
+            Verbosity::Quiet => Level::WARN,
+            Verbosity::Normal => Level::INFO,
+            Verbosity::Verbose => Level::DEBUG,
+            Verbosity::Trace => Level::TRACE,
+        }
+    }
+}
+
+/// Only this crate's events are logged; dependencies are limited to warnings
+fn targets(level: Level) -> Targets {
+    Targets::new()
+        .with_default(Level::WARN)
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+}
+
+/// Set up logging to stderr at the given verbosity, and to a JSON lines file at debug level or more if one is given
+pub fn init(verbosity: Verbosity, log_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let console = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_target(false)
+        .with_filter(targets(verbosity.level()));
+
+    let file = match log_file {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("Error creating log file {}: {}", path.display(), e))?;
+            let level = verbosity.level().max(Level::DEBUG);
+            Some(tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(Mutex::new(file))
+                .with_filter(targets(level)))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry().with(console).with(file).try_init()?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use full_moon::LuaVersion;
+use stylua_lib::{Config, LuaVersion as StyluaVersion, OutputVerification};
+
+use crate::property::PropertyValue;
+use crate::roblox::{child_path, is_a, JsonInstance, Modification};
+use crate::warnings::ApplyWarning;
+
+/// Parse Luau source and describe each syntax error, like "line 3: expected 'end'".
+/// Empty when the source parses.
+pub fn syntax_errors(source: &str) -> Vec<String> {
+    full_moon::parse_fallible(source, LuaVersion::luau())
+        .errors()
+        .iter()
+        .map(|error| format!("line {}: {}", error.range().0.line(), error.error_message()))
+        .collect()
+}
+
+/// Format Luau source the way StyLua does with its default settings
+pub fn format_source(source: &str) -> Result<String, String> {
+    let config = Config { syntax: StyluaVersion::Luau, ..Config::default() };
+    stylua_lib::format_code(source, config, None, OutputVerification::None).map_err(|e| e.to_string())
+}
+
+/// Format the Source of every script a modification adds.
+/// Sources that can't be formatted are left as they are, with a warning.
+pub fn format_modification(modification: &mut Modification) -> Vec<ApplyWarning> {
+    let mut warnings = Vec::new();
+    for instance in &mut modification.add {
+        let parent = instance.target_parent.clone().unwrap_or_else(|| "Workspace".to_string());
+        format_instance(instance, &parent, &mut warnings);
+    }
+    warnings
+}
+
+fn format_instance(instance: &mut JsonInstance, parent: &str, warnings: &mut Vec<ApplyWarning>) {
+    let path = child_path(parent, &instance.name);
+    if is_a(&instance.class, "LuaSourceContainer") {
+        if let Some(source) = instance.properties.get_mut("Source") {
+            let formatted = source.as_str().map(format_source);
+            match formatted {
+                Some(Ok(formatted)) => *source = PropertyValue::String(formatted),
+                Some(Err(reason)) => warnings.push(ApplyWarning::OperationFailed {
+                    operation: "format".to_string(),
+                    path: path.clone(),
+                    reason,
+                }),
+                None => {}
+            }
+        }
+    }
+    for child in &mut instance.children {
+        format_instance(child, &path, warnings);
+    }
+}
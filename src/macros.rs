@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+/// Replace every `@name` in the prompt with the text of the macro called name.
+/// Words after @ that aren't macros are left alone and returned so the caller can mention them.
+pub fn expand_macros(prompt: &str, macros: &HashMap<String, String>) -> (String, Vec<String>) {
+    let mut expanded = String::with_capacity(prompt.len());
+    let mut unknown = Vec::new();
+    let mut rest = prompt;
+
+    while let Some(at) = rest.find('@') {
+        expanded.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+        let name_length = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(after.len());
+        let name = &after[..name_length];
+
+        // Only @ at the start of a word is a macro, so emails and the like are left alone
+        let starts_word = !expanded.ends_with(|c: char| c.is_alphanumeric());
+        match macros.get(name).filter(|_| starts_word) {
+            Some(text) => expanded.push_str(text),
+            None => {
+                if starts_word && !name.is_empty() {
+                    unknown.push(name.to_string());
+                }
+                expanded.push('@');
+                expanded.push_str(name);
+            }
+        }
+        rest = &after[name_length..];
+    }
+    expanded.push_str(rest);
+
+    (expanded, unknown)
+}
@@ -1,80 +1,268 @@
 use std::env;
 use std::error::Error;
-use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use dotenv::dotenv;
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
+use roblox_mcp::assets;
+use roblox_mcp::audit;
+use roblox_mcp::bridge::{self, Bridge};
+use roblox_mcp::budget;
+use roblox_mcp::catalog::CatalogClient;
 use roblox_mcp::cli::build_cli;
-use roblox_mcp::gemini_api::GeminiClient;
-use roblox_mcp::roblox::{self, write_roblox_file, Modification};
+use roblox_mcp::commands;
+use roblox_mcp::config::Config;
+use roblox_mcp::context::{self, ContextOptions};
+use roblox_mcp::defaults;
+use roblox_mcp::gemini_api::{ConversationTurn, GeminiClient, Generation, ImageAttachment};
+use roblox_mcp::incremental::IncrementalWriter;
+use roblox_mcp::interrupt::Interrupts;
+use roblox_mcp::journal;
+use roblox_mcp::line_editor::LineEditor;
+use roblox_mcp::lock::{self, PlaceLock};
+use roblox_mcp::logging::{self, Verbosity};
+use roblox_mcp::macros::expand_macros;
+use roblox_mcp::overlap;
+use roblox_mcp::prompt::{self, TemplateVars};
+use roblox_mcp::repl::{self, CommandOutcome, ReplCommand};
+use roblox_mcp::retrieval::Retrieval;
+use roblox_mcp::roblox::{self, ApplyOptions, ChangeCounts, CHANGE_ID_ATTRIBUTE};
+use roblox_mcp::session::{self, SessionStats};
+use roblox_mcp::streaming;
+use roblox_mcp::tools::ToolSession;
+use roblox_mcp::transcript::{self, Transcript, TranscriptEntry};
+use roblox_mcp::upload;
+use roblox_mcp::validation;
+use roblox_mcp::watcher::PlaceWatcher;
+use roblox_mcp::warnings::log_warnings;
+use roblox_mcp::RbxMcpError;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() {
+    // Print errors with Display rather than the Debug output returning them from main gives
+    if let Err(e) = run().await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<(), Box<dyn Error>> {
     // Load environment variables from .env file
     dotenv().ok();
 
     // Set up CLI
     let matches = build_cli().get_matches();
 
+    // Set up logging before anything else prints
+    let verbosity = Verbosity::from_flags(matches.get_flag("quiet"), matches.get_count("verbose"));
+    logging::init(verbosity, matches.get_one::<PathBuf>("log-file").map(PathBuf::as_path))?;
+
+    // Load the config file, falling back to defaults when there isn't one
+    let config = Config::load_or_default(matches.get_one::<PathBuf>("config"))?;
+
+    let apply_options = ApplyOptions {
+        allow_package_edits: matches.get_flag("allow-package-edits"),
+        change_id: None,
+        format_scripts: matches.get_flag("format-scripts"),
+        strict: matches.get_flag("strict"),
+        fill_defaults: matches.get_flag("fill-defaults"),
+        default_properties: defaults::load(&config.default_properties)?,
+        auto_clean: matches.get_flag("auto-clean"),
+        clean_protected: config.clean_protected.clone(),
+        max_new_instances: matches.get_one::<usize>("max-new-instances").copied().filter(|&max| max > 0),
+        snap: matches.get_one::<f32>("snap").copied().filter(|&grid| grid > 0.0),
+        auto_anchor: matches.get_flag("auto-anchor"),
+        asset_library: config.asset_library.clone(),
+    };
+
+    // Subcommands run once without Gemini
+    match matches.subcommand() {
+        Some(("apply", sub_matches)) => return commands::apply(sub_matches, &config, &apply_options).await,
+        Some(("build-rojo", sub_matches)) => return commands::build_rojo(sub_matches),
+        Some(("diff", sub_matches)) => return commands::diff(sub_matches),
+        Some(("export-rojo", sub_matches)) => return commands::export_rojo(sub_matches),
+        Some(("merge", sub_matches)) => return commands::merge(sub_matches),
+        Some(("patch", sub_matches)) => return commands::patch(sub_matches),
+        Some(("scripts", sub_matches)) => return commands::scripts(sub_matches),
+        Some(("terrain-export", sub_matches)) => return commands::terrain_export(sub_matches),
+        Some(("terrain-import", sub_matches)) => return commands::terrain_import(sub_matches),
+        Some(("import-heightmap", sub_matches)) => return commands::import_heightmap(sub_matches),
+        Some(("undo", sub_matches)) => return commands::undo(sub_matches),
+        Some(("serve", sub_matches)) => return commands::serve(sub_matches, &config, &apply_options).await,
+        Some(("sync", sub_matches)) => return commands::sync(sub_matches, &config).await,
+        Some(("cache", sub_matches)) => return commands::cache(sub_matches),
+        Some(("bench", sub_matches)) => return commands::bench(sub_matches, &config),
+        Some(("stats", sub_matches)) => return commands::stats(sub_matches),
+        Some(("validate", sub_matches)) => return commands::validate(sub_matches),
+        Some(("dedup", sub_matches)) => return commands::dedup(sub_matches),
+        Some(("clean", sub_matches)) => return commands::clean(sub_matches, &config, &apply_options),
+        _ => {}
+    }
+
+    // A resumed session carries on with the earlier session's place, prompts and context
+    let resumed_transcript = matches.get_one::<String>("resume").map(|session| Transcript::open(session)).transpose()?;
+    let resumed = resumed_transcript.as_ref().map(Transcript::resume).transpose()?;
+
     // Get the filepath from the command-line arguments
     let filepath = matches.get_one::<PathBuf>("filepath")
+        .or(resumed.as_ref().map(|session| &session.place))
         .ok_or("Filepath must be provided")?;
-    println!("Input filepath: {}", filepath.display());
+    info!("Input filepath: {}", filepath.display());
 
     // Initial parse to verify the file is valid
     let _ = roblox::parse_roblox_file(filepath)?;
-    println!("Successfully parsed place file!");
+    info!("Successfully parsed place file!");
 
-    // Get the API key either from command line arguments or environment variable
+    // Keep other rbx-mcp processes from writing the place while this session may
+    let _lock = PlaceLock::acquire(filepath)?;
+
+    // Get the API key either from command line arguments or environment variable. Not needed with Vertex AI.
     let api_key = matches
         .get_one::<String>("api-key")
         .map(|s| s.to_string())
-        .or_else(|| env::var("GEMINI_API_KEY").ok())
-        .ok_or("Gemini API key not provided. Use --api-key option or set GEMINI_API_KEY environment variable")?;
+        .or_else(|| env::var("GEMINI_API_KEY").ok());
 
     // Get the context file if provided
     let context = matches
         .get_one::<PathBuf>("context")
         .and_then(|path| {
-            if path.extension().map_or(false, |ext| ext == "md") {
+            if path.extension().is_some_and(|ext| ext == "md") {
                 match std::fs::read_to_string(path) {
                     Ok(content) => {
-                        println!("Loaded context from: {}", path.display());
+                        info!("Loaded context from: {}", path.display());
                         Some(content)
                     },
                     Err(e) => {
-                        eprintln!("Error reading context file: {}", e);
+                        error!("Error reading context file: {}", e);
                         None
                     }
                 }
             } else {
-                eprintln!("Context file must have .md extension");
+                error!("Context file must have .md extension");
                 None
             }
-        });
+        })
+        .or_else(|| resumed.as_ref().and_then(|session| session.context.clone()));
+
+    let place_name = prompt::place_name(filepath);
+    let streaming_feedback = matches.get_flag("streaming-feedback");
+    let overlap_feedback = matches.get_flag("overlap-feedback");
+    let use_tools = matches.get_flag("tools");
+    let use_catalog = matches.get_flag("catalog");
+    let use_retrieval = matches.get_flag("retrieval");
+    let plan_out = matches.get_one::<PathBuf>("plan-out");
+    let context_options = ContextOptions {
+        include_packages: matches.get_flag("include-packages"),
+        filters: config.context_filters.clone(),
+        viewpoints: config.viewpoints.clone(),
+        assets: config.asset_library.as_deref().map(assets::list_assets).unwrap_or_default(),
+        max_depth: None,
+        max_children: None,
+    };
 
     // Create Gemini client
-    let client = GeminiClient::flash(api_key);
+    let mut client = commands::gemini_client(&matches, api_key, &config)?
+        .ok_or("Gemini API key not provided. Use --api-key option, set GEMINI_API_KEY environment variable or configure vertex")?
+        .with_context_cache(!matches.get_flag("no-context-cache"))
+        .with_live_output(!matches.get_flag("no-stream") && verbosity != Verbosity::Quiet);
+
+    // Images from --image go with every prompt, those from `:image` only with the next one
+    let session_images = matches.get_many::<PathBuf>("image").into_iter().flatten()
+        .map(|path| ImageAttachment::load(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut next_images = Vec::new();
+
+    // Every prompt is kept in a transcript, so the session can be continued later with --resume
+    let mut history = Vec::new();
+    if let Some(session) = &resumed {
+        info!("Resuming session with {} earlier prompts", session.history.len());
+        if session.place_hash.is_some() && session.place_hash != transcript::file_hash(filepath).ok() {
+            warn!("{} changed since the session left off, earlier prompts refer to the old version", filepath.display());
+        }
+        history = session.history.clone();
+    }
+    let transcript = match resumed_transcript {
+        Some(transcript) => Some(transcript),
+        None => Transcript::create().map_err(|e| warn!("Can't keep a transcript of this session: {}", e)).ok(),
+    };
+    record_transcript(transcript.as_ref(), TranscriptEntry::Start {
+        timestamp: journal::now(),
+        place: filepath.clone(),
+        context: context.clone(),
+        place_hash: transcript::file_hash(filepath).ok(),
+    });
     
     println!("\n===== ROBLOX MCP INTERACTIVE MODE =====");
-    println!("Enter prompts to modify your Roblox place. Press Ctrl+C to exit.");
+    println!("Enter prompts to modify your Roblox place. Press Ctrl+C to exit, or to cancel a prompt while it's generating.");
+    println!("End a line with \\ to continue it, or start a block with <<END and finish it with END.");
+    let mut editor = LineEditor::new()?;
+
+    let interrupts = Interrupts::listen();
+
+    // The place stays loaded between prompts so saves only reserialize what changed.
+    // It is parsed again whenever the file changes on disk, e.g. after a save in Studio.
+    let mut loaded: Option<(WeakDom, Option<SystemTime>)> = None;
+    let mut writer = IncrementalWriter::new();
+    let mut stats = SessionStats::new();
+    // Without a watcher the file's modification time is checked before every change instead
+    let watcher = match PlaceWatcher::new(filepath) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            warn!("Can't watch {} for changes: {}", filepath.display(), e);
+            None
+        }
+    };
+
+    // The Studio bridge lets the companion plugin replay each change in a running Studio session
+    let bridge_shutdown = CancellationToken::new();
+    let bridge = matches.get_one::<u16>("bridge").map(|&port| {
+        let bridge = Arc::new(Bridge::new());
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        tokio::spawn(bridge::serve(addr, bridge.clone(), bridge_shutdown.clone()));
+        bridge
+    });
 
     loop {
-        // Re-parse the place at the start of each loop to get fresh data
-        let mut place = match roblox::parse_roblox_file(filepath) {
-            Ok(place) => place,
-            Err(e) => {
-                eprintln!("Error parsing place file: {}", e);
-                continue;
+        if interrupts.exit_requested() {
+            break;
+        }
+        let modified = file_modified(filepath);
+        if loaded.as_ref().is_none_or(|(_, loaded_modified)| *loaded_modified != modified) {
+            match roblox::parse_roblox_file(filepath) {
+                Ok(place) => {
+                    if loaded.is_some() {
+                        info!("{} changed on disk, reloaded it", filepath.display());
+                    }
+                    loaded = Some((place, modified));
+                    writer.clear();
+                }
+                Err(e) => {
+                    error!("Error parsing place file: {}", e);
+                    continue;
+                }
             }
+        }
+        let Some((place, loaded_modified)) = loaded.as_mut() else {
+            continue;
         };
         
         // Ask for a prompt at each iteration
-        let mut current_prompt = String::new();
-        print!("\nEnter your prompt: ");
-        io::stdout().flush()?;
-        io::stdin().lock().read_line(&mut current_prompt)?;
-        current_prompt = current_prompt.trim().to_string();
+        println!();
+        let mut current_prompt = match editor.read_prompt("Enter your prompt: ")? {
+            // Ctrl+C can arrive outside the line editor, e.g. when input is piped in
+            Some(_) if interrupts.exit_requested() => break,
+            Some(prompt) => prompt,
+            None => {
+                println!("Exiting MCP interactive mode");
+                break;
+            }
+        };
         
         // Check for exit command
         if current_prompt.to_lowercase() == "exit" || current_prompt.to_lowercase() == "quit" {
@@ -87,54 +275,494 @@ async fn main() -> Result<(), Box<dyn Error>> {
             println!("Prompt is empty, please try again");
             continue;
         }
-        
-        println!("Processing prompt: {}", current_prompt);
-        
-        // Generate content with Gemini
-        match client.generate_content(&current_prompt, &place, 8000, 0.8, context.clone()).await {
-            Ok(response) => {
-                // Extract and process the response
-                let text_option = GeminiClient::extract_text(&response);
-                match text_option {
-                    Some(text) => {
-                        println!("Gemini API Response:");
-                        println!("{}", text);
-                        
-                        // Try to parse the response as JSON directly
-                        match serde_json::from_str::<Modification>(&text) {
-                            Ok(modification) => {
-                                // Modify the place with the parsed data
-                                let root_ref = place.root_ref();
-                                if let Err(e) = roblox::json_to_weakdom(&mut place, &modification, root_ref) {
-                                    eprintln!("Error modifying place: {}", e);
-                                    continue;
-                                }
-                                
-                                // Save by overwriting the original input file
-                                if let Err(e) = write_roblox_file(&filepath, &place) {
-                                    eprintln!("Error writing to input file: {}", e);
+
+        if current_prompt == "status" || current_prompt == "/status" {
+            stats.print_summary(&client, filepath);
+            continue;
+        }
+
+        if current_prompt == "stats" || current_prompt == "/stats" {
+            session::print_token_usage(&client);
+            continue;
+        }
+
+        // `:image FILE` attaches an image to the next prompt
+        if let Some(args) = current_prompt.strip_prefix(":image") {
+            match args.trim() {
+                "" => {
+                    for image in session_images.iter().chain(&next_images) {
+                        println!("{}", image.path.display());
+                    }
+                }
+                "clear" => {
+                    next_images.clear();
+                    println!("Cleared the images for the next prompt");
+                }
+                path => match ImageAttachment::load(Path::new(path)) {
+                    Ok(image) => {
+                        next_images.push(image);
+                        println!("Attached {} to the next prompt", path);
+                    }
+                    Err(e) => eprintln!("{}", e),
+                },
+            }
+            continue;
+        }
+
+        // Generation settings can be changed between prompts, e.g. `:set temperature 0.3`
+        if let Some(setting) = current_prompt.strip_prefix(":set") {
+            if let Err(e) = session::change_setting(&mut client, setting).await {
+                eprintln!("{}", e);
+            }
+            continue;
+        }
+
+        // Handle local commands without calling Gemini
+        if let Some(command) = ReplCommand::parse(&current_prompt) {
+            match command {
+                Ok(command) => {
+                    if command.modifies_place() && changed_on_disk(watcher.as_ref(), filepath, *loaded_modified) {
+                        match ask_about_external_edit(&mut editor, filepath)? {
+                            ExternalEdit::Reload => match reload(filepath, place, loaded_modified, &mut writer) {
+                                Ok(()) => {}
+                                Err(e) => {
+                                    error!("Error parsing place file: {}", e);
                                     continue;
                                 }
-                                
-                                println!("Updated original file: {}", filepath.display());
                             },
-                            Err(e) => {
-                                eprintln!("Error parsing JSON: {}", e);
-                                eprintln!("Raw response: {}", text);
+                            ExternalEdit::Overwrite => {}
+                            ExternalEdit::Cancel => {
+                                println!("Cancelled, nothing was changed");
+                                continue;
                             }
                         }
-                    },
-                    None => {
-                        eprintln!("No text found in Gemini response");
                     }
+                    let before = bridge.as_ref().filter(|_| command.modifies_place()).map(|_| roblox::snapshot(place));
+                    match repl::run_command(place, filepath, &command, &apply_options) {
+                        Ok(CommandOutcome::Unchanged) => {}
+                        Ok(CommandOutcome::Changed { changed, description, counts }) => {
+                            match save_change(place, &mut writer, filepath, changed, &description) {
+                                Ok(change_id) => {
+                                    stats.record_edit(&counts);
+                                    record_transcript(transcript.as_ref(), TranscriptEntry::Edit {
+                                        timestamp: journal::now(),
+                                        change_id: Some(change_id),
+                                        description: description.clone(),
+                                        place_hash: transcript::file_hash(filepath).ok(),
+                                    });
+                                    if let (Some(bridge), Some(before)) = (&bridge, &before) {
+                                        bridge.publish(&description, before, place);
+                                    }
+                                    *loaded_modified = file_modified(filepath);
+                                }
+                                Err(e) => {
+                                    error!("Error writing to input file: {}", e);
+                                    loaded = None;
+                                }
+                            }
+                        }
+                        Err(e) => error!("Error running command: {}", e),
+                    }
+                }
+                Err(usage) => eprintln!("{}", usage),
+            }
+            continue;
+        }
+        
+        // Expand @macros from the config
+        let (expanded, unknown) = expand_macros(&current_prompt, &config.macros);
+        for name in unknown {
+            warn!("@{} is not a macro in the config, leaving it as is", name);
+        }
+        current_prompt = expanded;
+
+        info!("Processing prompt: {}", current_prompt);
+        
+        // Generate content with Gemini
+        let images: Vec<ImageAttachment> = session_images.iter().cloned().chain(next_images.drain(..)).collect();
+        let dom_context = if use_retrieval {
+            // Gemini looks through the place a few levels at a time before making the change
+            println!("Looking through the place... press Ctrl+C to cancel");
+            let retrieval = Retrieval { client: &client, place, options: &context_options, budget: &config.context_budget };
+            let cancel = interrupts.start_request();
+            let gathered = retrieval.gather(&current_prompt, &place_name, context.clone(), &images, &history, &cancel).await;
+            interrupts.finish_request();
+            match gathered {
+                Ok(description) => description,
+                Err(RbxMcpError::Cancelled) if interrupts.exit_requested() => break,
+                Err(RbxMcpError::Cancelled) => {
+                    println!("Cancelled, nothing was changed");
+                    continue;
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    continue;
+                }
+            }
+        } else {
+            match context::describe_place_within_budget(place, &context_options, &config.context_budget) {
+                Ok(description) => description,
+                Err(e) => {
+                    error!("{}", e);
+                    continue;
+                }
+            }
+        };
+        let place_vars = TemplateVars { place_name: &place_name, dom_summary: &dom_context };
+        println!("Generating... press Ctrl+C to cancel");
+        stats.record_prompt();
+        let change_id = journal::new_change_id();
+        let options = ApplyOptions { change_id: Some(change_id.clone()), ..apply_options.clone() };
+        // With --tools Gemini edits a copy of the place through function calls, which replaces the place if it's applied
+        let mut edited: Option<(WeakDom, ChangeCounts)> = None;
+        let generation = if use_tools {
+            let mut tool_session = ToolSession::new(roblox::snapshot(place), &options);
+            if use_catalog {
+                tool_session = tool_session.with_catalog(CatalogClient::new(std::env::var("ROBLOX_API_KEY").ok()));
+            }
+            let cancel = interrupts.start_request();
+            let summary = tokio::select! {
+                summary = client.generate_with_tools(&current_prompt, &place_vars, context.clone(), &images, &history, &mut tool_session) => summary,
+                () = cancel.cancelled() => Err(RbxMcpError::Cancelled),
+            };
+            interrupts.finish_request();
+            summary.map(|summary| {
+                println!("{}", summary);
+                let response = serde_json::json!({ "calls": tool_session.calls, "summary": summary }).to_string();
+                edited = Some((tool_session.place, tool_session.counts));
+                Generation { modification: tool_session.modification, response }
+            })
+        } else {
+            generate_cancellable(&client, &interrupts, &current_prompt, &place_vars, context.clone(), &images, &history).await
+        };
+        let Generation { mut modification, mut response } = match generation {
+            Ok(generation) => generation,
+            Err(RbxMcpError::Cancelled) if interrupts.exit_requested() => break,
+            Err(RbxMcpError::Cancelled) => {
+                println!("Cancelled, nothing was changed");
+                continue;
+            }
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+
+        // Scripts that index Workspace directly break when instances stream out
+        if edited.is_none() && streaming_feedback && streaming::streaming_enabled(place) {
+            let warnings = streaming::streaming_warnings(&modification);
+            if !warnings.is_empty() {
+                info!("{} script problems with StreamingEnabled, asking Gemini to fix them...", warnings.len());
+                let fix_prompt = streaming::feedback_prompt(&current_prompt, &modification, &warnings)?;
+                match generate_cancellable(&client, &interrupts, &fix_prompt, &place_vars, context.clone(), &images, &history).await {
+                    Ok(fixed) => (modification, response) = (fixed.modification, fixed.response),
+                    Err(e) => warn!("Keeping the original response, fix request failed: {}", e),
+                }
+            }
+        }
+
+        // Modifications that fail validation, e.g. scripts with syntax errors, get one chance to be fixed
+        // Function calls were already checked as they ran
+        let problems = if edited.is_none() { validation::validate_modification(&modification, apply_options.strict) } else { Vec::new() };
+        if !problems.is_empty() {
+            for problem in &problems {
+                warn!("{}", problem);
+            }
+            info!("{} problems with the response, asking Gemini to fix them...", problems.len());
+            let fix_prompt = validation::feedback_prompt(&current_prompt, &modification, &problems)?;
+            match generate_cancellable(&client, &interrupts, &fix_prompt, &place_vars, context.clone(), &images, &history).await {
+                Ok(fixed) => (modification, response) = (fixed.modification, fixed.response),
+                Err(e) => warn!("Keeping the original response, fix request failed: {}", e),
+            }
+        }
+
+        // New parts put inside existing ones get one chance to be moved, found by applying to a copy first
+        if edited.is_none() && overlap_feedback {
+            let mut trial = roblox::snapshot(place);
+            let trial_root = trial.root_ref();
+            let overlaps = match roblox::json_to_weakdom(&mut trial, &modification, trial_root, &options) {
+                Ok(report) => overlap::find_overlaps(&trial, &report.counts.created),
+                Err(_) => Vec::new(),
+            };
+            if !overlaps.is_empty() {
+                for overlap in &overlaps {
+                    warn!("{}", overlap);
+                }
+                info!("{} new parts overlap existing ones, asking Gemini to move them...", overlaps.len());
+                let fix_prompt = overlap::feedback_prompt(&current_prompt, &modification, &overlaps)?;
+                match generate_cancellable(&client, &interrupts, &fix_prompt, &place_vars, context.clone(), &images, &history).await {
+                    Ok(fixed) => (modification, response) = (fixed.modification, fixed.response),
+                    Err(e) => warn!("Keeping the original response, fix request failed: {}", e),
+                }
+            }
+        }
+
+        commands::warn_before_apply(place, &modification, &config);
+
+        // Prompts go in the transcript whether or not they end up applied
+        let record_prompt = |change_id: Option<&str>, applied: bool| {
+            record_transcript(transcript.as_ref(), TranscriptEntry::Prompt {
+                timestamp: journal::now(),
+                change_id: change_id.map(str::to_string),
+                prompt: current_prompt.clone(),
+                response: response.clone(),
+                modification: Box::new(modification.clone()),
+                applied,
+                place_hash: if applied { transcript::file_hash(filepath).ok() } else { None },
+            });
+        };
+
+        // Ctrl+C while a fix was being generated ends the session without applying anything
+        if interrupts.exit_requested() {
+            record_prompt(None, false);
+            break;
+        }
+
+        // In plan mode the modification is saved for review and applied later with the apply subcommand
+        if let Some(plan_path) = plan_out {
+            match serde_json::to_string_pretty(&modification).map(|json| std::fs::write(plan_path, json)) {
+                Ok(Ok(())) => info!(
+                    "Wrote plan to {}. Review it, then run: roblox-mcp apply {} {}",
+                    plan_path.display(), plan_path.display(), filepath.display()
+                ),
+                Ok(Err(e)) => error!("Error writing plan: {}", e),
+                Err(e) => error!("Error serializing plan: {}", e),
+            }
+            record_prompt(None, false);
+            continue;
+        }
+
+        // Something else, like a save from Studio, may have written the place while Gemini was working
+        if changed_on_disk(watcher.as_ref(), filepath, *loaded_modified) {
+            match ask_about_external_edit(&mut editor, filepath)? {
+                ExternalEdit::Reload => {
+                    if let Err(e) = reload(filepath, place, loaded_modified, &mut writer) {
+                        error!("Error parsing place file: {}", e);
+                        record_prompt(None, false);
+                        continue;
+                    }
+                    // Function calls edited the old version, so their result can't be used
+                    if edited.is_some() {
+                        println!("Reloaded, the changes Gemini made to the old version were dropped");
+                        record_prompt(None, false);
+                        continue;
+                    }
+                }
+                ExternalEdit::Overwrite => {}
+                ExternalEdit::Cancel => {
+                    println!("Cancelled, nothing was changed");
+                    record_prompt(None, false);
+                    continue;
+                }
+            }
+        }
+
+        // A runaway response could add so much that the place is hard to recover, so big changes are confirmed
+        let mut options = options;
+        if let Some(limit) = options.max_new_instances {
+            let count = match &edited {
+                Some((_, counts)) => counts.added,
+                None => budget::count_new_instances(place, place.root_ref(), &modification, options.asset_library.as_deref()).unwrap_or(usize::MAX),
+            };
+            if count > limit {
+                warn!("This change creates {} instances, more than --max-new-instances ({})", count, limit);
+                if !matches!(editor.ask("Apply it anyway [y/N]? ")?.as_deref(), Some("y" | "yes")) {
+                    println!("Cancelled, nothing was changed");
+                    record_prompt(None, false);
+                    continue;
                 }
-            },
+                options.max_new_instances = None;
+            }
+        }
+
+        // Inline images and audio become assets only now, since uploads can't be taken back.
+        // The upload goes into a copy, the transcript keeps the response as Gemini gave it.
+        let mut uploaded = None;
+        if edited.is_none() && upload::has_inline_data(&modification) {
+            let mut copy = modification.clone();
+            let result = match roblox::check_modification(place, &copy, place.root_ref(), &options) {
+                Ok(()) => upload::upload_modification(&mut copy, &config.upload).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!("{}", e);
+                record_prompt(None, false);
+                continue;
+            }
+            uploaded = Some(copy);
+        }
+        let applied_modification = uploaded.as_ref().unwrap_or(&modification);
+
+        // Modify the place with the parsed data
+        let root_ref = place.root_ref();
+        // The audit log keeps the inverse of every modification, so it always needs the place from before
+        let before = roblox::snapshot(place);
+        // Tool sessions edit a copy of the place, so only their counts come back and not a report
+        let applied = match edited {
+            Some((edited_place, counts)) => {
+                *place = edited_place;
+                writer.clear();
+                Ok((counts, None))
+            }
+            None => roblox::json_to_weakdom(place, applied_modification, root_ref, &options).map(|report| {
+                log_warnings(&report.warnings);
+                writer.mark_changed(report.changed.clone());
+                (report.counts.clone(), Some(report))
+            }),
+        };
+        let (counts, report) = match applied {
+            Ok(applied) => applied,
+            Err(e) => {
+                // The place may be half modified, so start again from the file
+                error!("Error modifying place: {}", e);
+                loaded = None;
+                record_prompt(None, false);
+                continue;
+            }
+        };
+
+        for overlap in overlap::find_overlaps(place, &counts.created) {
+            warn!("{}", overlap);
+        }
+
+        // Save by overwriting the original input file
+        match writer.write(filepath, place) {
+            Ok(stats) => info!("Saved {} changed chunks, reused {}", stats.serialized, stats.reused),
             Err(e) => {
-                eprintln!("Error generating content: {}", e);
+                error!("Error writing to input file: {}", e);
+                loaded = None;
+                record_prompt(None, false);
                 continue;
             }
         }
+        if let Some(bridge) = &bridge {
+            bridge.publish(&current_prompt, &before, place);
+        }
+        *loaded_modified = file_modified(filepath);
+
+        info!("Updated original file: {}", filepath.display());
+        stats.record_apply(&counts);
+        let description = format!("{} ({})", current_prompt, journal::describe_modification(&modification));
+        if let Err(e) = journal::record(filepath, Some(&change_id), &description) {
+            warn!("Error writing journal: {}", e);
+        }
+        let logged = match &report {
+            Some(report) => audit::record_applied(filepath, &change_id, applied_modification, &before, place, report),
+            None => audit::record(filepath, &change_id, Some(applied_modification), None, &before, place),
+        };
+        if let Err(e) = logged {
+            warn!("Error writing audit log: {}", e);
+        }
+        record_prompt(Some(&change_id), true);
+        history.push(ConversationTurn { prompt: current_prompt, response });
+    }
+
+    bridge_shutdown.cancel();
+    client.clear_context_cache().await;
+    println!();
+    stats.print_summary(&client, filepath);
+    if let Some(transcript) = &transcript {
+        println!("Transcript: {} (continue with --resume {})", transcript.path().display(), transcript.id());
+    }
+    Ok(())
+}
+
+/// Ask Gemini for a modification, cancelling the request if Ctrl+C is pressed while waiting
+async fn generate_cancellable(
+    client: &GeminiClient,
+    interrupts: &Interrupts,
+    prompt: &str,
+    place: &TemplateVars<'_>,
+    context: Option<String>,
+    images: &[ImageAttachment],
+    history: &[ConversationTurn],
+) -> Result<Generation, RbxMcpError> {
+    let cancel = interrupts.start_request();
+    let result = client.generate_modification(prompt, place, context, images, history, &cancel).await;
+    interrupts.finish_request();
+    result
+}
+
+/// Save a change made by a REPL command: tag what changed, write the place and journal it.
+/// Returns the change ID.
+fn save_change(
+    place: &mut WeakDom,
+    writer: &mut IncrementalWriter,
+    filepath: &Path,
+    changed: Vec<Ref>,
+    description: &str,
+) -> Result<String, Box<dyn Error>> {
+    let change_id = journal::new_change_id();
+    for &id in &changed {
+        roblox::set_attribute(place, id, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.clone()));
+    }
+    writer.mark_changed(changed);
+    writer.write(filepath, place)?;
+    info!("Updated original file: {}", filepath.display());
+    if let Err(e) = journal::record(filepath, Some(&change_id), description) {
+        warn!("Error writing journal: {}", e);
+    }
+    Ok(change_id)
+}
+
+fn record_transcript(transcript: Option<&Transcript>, entry: TranscriptEntry) {
+    if let Some(transcript) = transcript {
+        if let Err(e) = transcript.append(&entry) {
+            warn!("Error writing transcript: {}", e);
+        }
+    }
+}
+
+/// What to do about the place changing on disk while a change to it was being prepared
+enum ExternalEdit {
+    /// Load the new version and make the change to that
+    Reload,
+    /// Make the change to the loaded version, losing what the other program wrote
+    Overwrite,
+    Cancel,
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Whether something else wrote the place since it was loaded or last saved
+fn changed_on_disk(watcher: Option<&PlaceWatcher>, filepath: &Path, loaded_modified: Option<SystemTime>) -> bool {
+    if watcher.is_some_and(|watcher| !watcher.take_touched()) {
+        return false;
+    }
+    // The watcher also sees rbx-mcp's own saves, which left the time it was saved with
+    file_modified(filepath) != loaded_modified
+}
+
+fn ask_about_external_edit(editor: &mut LineEditor, filepath: &Path) -> Result<ExternalEdit, Box<dyn Error>> {
+    warn!("{} was changed by another program, e.g. saved from Studio", filepath.display());
+    loop {
+        let answer = editor.ask("Reload it and make this change on top [R], overwrite the other changes [o] or cancel [c]? ")?;
+        match answer.as_deref() {
+            Some("" | "r" | "reload") => return Ok(ExternalEdit::Reload),
+            Some("o" | "overwrite") => {
+                // Saves refuse to replace a file that changed since it was read, unless told it's been seen
+                lock::record_current(filepath)?;
+                return Ok(ExternalEdit::Overwrite);
+            }
+            Some("c" | "cancel") | None => return Ok(ExternalEdit::Cancel),
+            Some(_) => {}
+        }
     }
+}
 
+/// Replace the loaded place with the version on disk
+fn reload(
+    filepath: &Path,
+    place: &mut WeakDom,
+    loaded_modified: &mut Option<SystemTime>,
+    writer: &mut IncrementalWriter,
+) -> Result<(), RbxMcpError> {
+    let modified = file_modified(filepath);
+    *place = roblox::parse_roblox_file(filepath)?;
+    *loaded_modified = modified;
+    writer.clear();
+    info!("Reloaded {}", filepath.display());
     Ok(())
 }
@@ -0,0 +1,295 @@
+use rbx_dom_weak::WeakDom;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::patch::{create_patch, Patch, PatchOperation};
+use crate::roblox::child_path;
+
+/// Two sides changed the same thing in different ways. Ours was kept.
+pub struct Conflict {
+    /// Path of the instance in the base place
+    pub path: String,
+    pub description: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+/// The combined changes of both sides, to apply to the base place
+pub struct MergeResult {
+    pub patch: Patch,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merge the changes ours and theirs each made to base.
+///
+/// Changes to different instances, properties or attributes are all kept, and changes both
+/// sides made the same way are kept once. Anything else is a conflict, resolved by keeping ours.
+pub fn merge_places(base: &WeakDom, ours: &WeakDom, theirs: &WeakDom) -> MergeResult {
+    merge_patches(create_patch(base, ours), create_patch(base, theirs))
+}
+
+/// Merge two patches made against the same base
+pub fn merge_patches(ours: Patch, theirs: Patch) -> MergeResult {
+    let mut conflicts = Vec::new();
+    let mut operations = ours.operations;
+
+    // Everything ours does, by the base path it does it to
+    let mut sets = HashMap::new();
+    let mut renames = HashMap::new();
+    let mut moves = HashMap::new();
+    let mut adds = HashMap::new();
+    let mut removes = HashSet::new();
+    for (index, operation) in operations.iter().enumerate() {
+        match operation {
+            PatchOperation::Set { path, .. } => {
+                sets.insert(path.clone(), index);
+            }
+            PatchOperation::Rename { path, new_name } => {
+                renames.insert(path.clone(), new_name.clone());
+            }
+            PatchOperation::Move { path, new_parent } => {
+                moves.insert(path.clone(), new_parent.clone());
+            }
+            PatchOperation::Add { parent, instance } => {
+                adds.insert((parent.clone(), instance.name.clone()), instance.clone());
+            }
+            PatchOperation::Remove { path } => {
+                removes.insert(path.clone());
+            }
+        }
+    }
+
+    // Instances theirs edits which ours removed, or which are under one ours removed
+    let removed_by_ours = |path: &str| removes.iter().find(|removed| is_within(path, removed)).cloned();
+
+    let mut accepted = Vec::new();
+    for operation in theirs.operations {
+        let edited = match &operation {
+            PatchOperation::Set { path, .. } | PatchOperation::Rename { path, .. } => vec![path.as_str()],
+            PatchOperation::Move { path, new_parent } => vec![path.as_str(), new_parent.as_str()],
+            PatchOperation::Add { parent, .. } => vec![parent.as_str()],
+            PatchOperation::Remove { .. } => Vec::new(),
+        };
+        if let Some(removed) = edited.iter().find_map(|path| removed_by_ours(path)) {
+            conflicts.push(Conflict {
+                path: removed,
+                description: format!("removed in ours but changed in theirs ({})", describe(&operation)),
+            });
+            continue;
+        }
+
+        match operation {
+            PatchOperation::Set { path, properties, attributes } => {
+                let Some(&index) = sets.get(&path) else {
+                    accepted.push(PatchOperation::Set { path, properties, attributes });
+                    continue;
+                };
+                let PatchOperation::Set { properties: our_properties, attributes: our_attributes, .. } = &mut operations[index] else {
+                    continue;
+                };
+                merge_values(&path, "", our_properties, properties, &mut conflicts);
+                merge_values(&path, "@", our_attributes, attributes, &mut conflicts);
+            }
+            PatchOperation::Rename { path, new_name } => match renames.get(&path) {
+                Some(ours) if *ours == new_name => {}
+                Some(ours) => conflicts.push(Conflict {
+                    path,
+                    description: format!("renamed to '{}' in ours and '{}' in theirs", ours, new_name),
+                }),
+                None => accepted.push(PatchOperation::Rename { path, new_name }),
+            },
+            PatchOperation::Move { path, new_parent } => match moves.get(&path) {
+                Some(ours) if *ours == new_parent => {}
+                Some(ours) => conflicts.push(Conflict {
+                    path,
+                    description: format!("moved into '{}' in ours and '{}' in theirs", ours, new_parent),
+                }),
+                None => accepted.push(PatchOperation::Move { path, new_parent }),
+            },
+            PatchOperation::Add { parent, instance } => match adds.get(&(parent.clone(), instance.name.clone())) {
+                Some(ours) if *ours == instance => {}
+                Some(_) => conflicts.push(Conflict {
+                    path: child_path(&parent, &instance.name),
+                    description: "added in both with different contents".to_string(),
+                }),
+                None => accepted.push(PatchOperation::Add { parent, instance }),
+            },
+            PatchOperation::Remove { path } => {
+                if removes.contains(&path) {
+                    continue;
+                }
+                // Anything ours did inside what theirs removed
+                let edited_by_ours = operations.iter().find(|operation| match operation {
+                    PatchOperation::Set { path: p, .. } | PatchOperation::Rename { path: p, .. } => is_within(p, &path),
+                    PatchOperation::Move { path: p, new_parent } => is_within(p, &path) || is_within(new_parent, &path),
+                    PatchOperation::Add { parent, .. } => is_within(parent, &path),
+                    PatchOperation::Remove { .. } => false,
+                });
+                match edited_by_ours {
+                    Some(operation) => conflicts.push(Conflict {
+                        description: format!("removed in theirs but changed in ours ({})", describe(operation)),
+                        path,
+                    }),
+                    None => accepted.push(PatchOperation::Remove { path }),
+                }
+            }
+        }
+    }
+
+    operations.extend(accepted);
+    MergeResult { patch: Patch { operations }, conflicts }
+}
+
+/// Add theirs' values to ours, keeping ours where both set the same key differently
+fn merge_values<V: PartialEq>(
+    path: &str,
+    prefix: &str,
+    ours: &mut BTreeMap<String, Option<V>>,
+    theirs: BTreeMap<String, Option<V>>,
+    conflicts: &mut Vec<Conflict>,
+) {
+    for (name, value) in theirs {
+        match ours.get(&name) {
+            Some(ours) if *ours == value => {}
+            Some(_) => conflicts.push(Conflict {
+                path: path.to_string(),
+                description: format!("{}{} set differently in ours and theirs", prefix, name),
+            }),
+            None => {
+                ours.insert(name, value);
+            }
+        }
+    }
+}
+
+/// Whether path is the instance at ancestor or under it
+fn is_within(path: &str, ancestor: &str) -> bool {
+    path == ancestor || path.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn describe(operation: &PatchOperation) -> String {
+    match operation {
+        PatchOperation::Set { path, .. } => format!("set properties of {}", path),
+        PatchOperation::Rename { path, new_name } => format!("renamed {} to {}", path, new_name),
+        PatchOperation::Move { path, new_parent } => format!("moved {} into {}", path, new_parent),
+        PatchOperation::Add { parent, instance } => format!("added {}/{}", parent, instance.name),
+        PatchOperation::Remove { path } => format!("removed {}", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::apply_patch;
+    use crate::roblox::{self, find_instance_by_path};
+    use rbx_dom_weak::types::Variant;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn base_place() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Door").with_property("Transparency", 0.0f32));
+        dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Lamp"));
+        dom
+    }
+
+    /// A copy of the base place with an edit made to it
+    fn edited(base: &WeakDom, edit: impl FnOnce(&mut WeakDom)) -> WeakDom {
+        let mut copy = roblox::snapshot(base);
+        edit(&mut copy);
+        copy
+    }
+
+    fn set(dom: &mut WeakDom, path: &str, property: &str, value: f32) {
+        let id = find_instance_by_path(dom, dom.root_ref(), path).unwrap();
+        dom.get_by_ref_mut(id).unwrap().properties.insert(property.into(), Variant::Float32(value));
+    }
+
+    fn get(dom: &WeakDom, path: &str, property: &str) -> Option<Variant> {
+        let id = find_instance_by_path(dom, dom.root_ref(), path)?;
+        dom.get_by_ref(id)?.properties.get(&property.into()).cloned()
+    }
+
+    fn rename(dom: &mut WeakDom, path: &str, new_name: &str) {
+        let id = find_instance_by_path(dom, dom.root_ref(), path).unwrap();
+        dom.get_by_ref_mut(id).unwrap().name = new_name.to_string();
+    }
+
+    /// Apply a merge to a copy of the base place
+    fn merged_place(base: &WeakDom, merged: &MergeResult) -> WeakDom {
+        let mut place = roblox::snapshot(base);
+        assert!(apply_patch(&mut place, &merged.patch, None).warnings.is_empty());
+        place
+    }
+
+    #[test]
+    fn changes_to_different_things_are_all_kept() {
+        let base = base_place();
+        let ours = edited(&base, |dom| set(dom, "Workspace/Door", "Transparency", 0.5));
+        let theirs = edited(&base, |dom| {
+            set(dom, "Workspace/Door", "Reflectance", 0.25);
+            rename(dom, "Workspace/Lamp", "Light");
+        });
+        let merged = merge_places(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+
+        let place = merged_place(&base, &merged);
+        assert_eq!(get(&place, "Workspace/Door", "Transparency"), Some(Variant::Float32(0.5)));
+        assert_eq!(get(&place, "Workspace/Door", "Reflectance"), Some(Variant::Float32(0.25)));
+        assert!(find_instance_by_path(&place, place.root_ref(), "Workspace/Light").is_some());
+    }
+
+    #[test]
+    fn the_same_change_on_both_sides_is_kept_once() {
+        let base = base_place();
+        let ours = edited(&base, |dom| rename(dom, "Workspace/Lamp", "Light"));
+        let theirs = edited(&base, |dom| rename(dom, "Workspace/Lamp", "Light"));
+        let merged = merge_places(&base, &ours, &theirs);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.patch.operations.len(), 1);
+    }
+
+    #[test]
+    fn a_property_set_differently_keeps_ours() {
+        let base = base_place();
+        let ours = edited(&base, |dom| set(dom, "Workspace/Door", "Transparency", 0.5));
+        let theirs = edited(&base, |dom| set(dom, "Workspace/Door", "Transparency", 1.0));
+        let merged = merge_places(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].path, "Workspace/Door");
+        assert!(merged.conflicts[0].description.contains("Transparency"));
+
+        let place = merged_place(&base, &merged);
+        assert_eq!(get(&place, "Workspace/Door", "Transparency"), Some(Variant::Float32(0.5)));
+    }
+
+    #[test]
+    fn removing_what_the_other_side_changed_is_a_conflict() {
+        let base = base_place();
+        let door_removed = |dom: &mut WeakDom| dom.destroy(find_instance_by_path(dom, dom.root_ref(), "Workspace/Door").unwrap());
+        let door_changed = |dom: &mut WeakDom| set(dom, "Workspace/Door", "Transparency", 0.5);
+
+        let merged = merge_places(&base, &edited(&base, door_removed), &edited(&base, door_changed));
+        assert_eq!(merged.conflicts.len(), 1);
+        assert!(merged.conflicts[0].description.starts_with("removed in ours"));
+        let place = merged_place(&base, &merged);
+        assert!(find_instance_by_path(&place, place.root_ref(), "Workspace/Door").is_none());
+
+        let merged = merge_places(&base, &edited(&base, door_changed), &edited(&base, door_removed));
+        assert_eq!(merged.conflicts.len(), 1);
+        assert!(merged.conflicts[0].description.starts_with("removed in theirs"));
+        let place = merged_place(&base, &merged);
+        assert_eq!(get(&place, "Workspace/Door", "Transparency"), Some(Variant::Float32(0.5)));
+    }
+
+    #[test]
+    fn paths_inside_a_removed_instance_are_within_it() {
+        assert!(is_within("Workspace/House", "Workspace/House"));
+        assert!(is_within("Workspace/House/Door", "Workspace/House"));
+        assert!(!is_within("Workspace/Houseboat", "Workspace/House"));
+    }
+}
@@ -0,0 +1,182 @@
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::RbxMcpError;
+use crate::upload::UploadConfig;
+
+/// Client for the Roblox Open Cloud APIs
+pub struct OpenCloudClient {
+    api_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishResponse {
+    version_number: u64,
+}
+
+#[derive(Deserialize)]
+struct AssetLocation {
+    location: String,
+}
+
+/// An upload in progress, done once the asset is created
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Operation {
+    operation_id: Option<String>,
+    #[serde(default)]
+    done: bool,
+    response: Option<CreatedAsset>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatedAsset {
+    asset_id: String,
+}
+
+/// How many times to check on an upload, a second apart, before giving up
+const UPLOAD_POLLS: usize = 30;
+
+impl OpenCloudClient {
+    pub fn new(api_key: String) -> Self {
+        OpenCloudClient { api_key }
+    }
+
+    /// Upload a place file as a new version. version_type is "Saved" or "Published".
+    /// Returns the new version number.
+    pub async fn publish_place(
+        &self,
+        universe_id: u64,
+        place_id: u64,
+        place_file: &Path,
+        version_type: &str,
+    ) -> Result<u64, RbxMcpError> {
+        let body = std::fs::read(place_file)?;
+        let content_type = match place_file.extension().and_then(|ext| ext.to_str()) {
+            Some("rbxlx") => "application/xml",
+            _ => "application/octet-stream",
+        };
+
+        let response = reqwest::Client::new()
+            .post(format!(
+                "https://apis.roblox.com/universes/v1/{}/places/{}/versions?versionType={}",
+                universe_id, place_id, version_type
+            ))
+            .header("x-api-key", &self.api_key)
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(RbxMcpError::OpenCloudError(format!("HTTP {}. Details: {}", status, text)));
+        }
+        let published: PublishResponse = serde_json::from_str(&text)
+            .map_err(|e| RbxMcpError::OpenCloudError(format!("Unexpected response {}: {}", text, e)))?;
+        Ok(published.version_number)
+    }
+
+    /// Create an asset, like a Decal from an image, owned by the user or group in config.
+    /// Returns its asset ID once Roblox has processed it.
+    pub async fn upload_asset(
+        &self,
+        asset_type: &str,
+        display_name: &str,
+        content_type: &str,
+        contents: Vec<u8>,
+        config: &UploadConfig,
+    ) -> Result<u64, RbxMcpError> {
+        let creator = match (config.user_id, config.group_id) {
+            (_, Some(group_id)) => json!({ "groupId": group_id.to_string() }),
+            (Some(user_id), None) => json!({ "userId": user_id.to_string() }),
+            (None, None) => return Err(RbxMcpError::OpenCloudError("No user or group to upload as".to_string())),
+        };
+        let request = json!({
+            "assetType": asset_type,
+            "displayName": display_name,
+            "description": "Uploaded by rbx-mcp",
+            "creationContext": { "creator": creator },
+        });
+        let file = Part::bytes(contents)
+            .file_name(display_name.to_string())
+            .mime_str(content_type)
+            .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        let form = Form::new().text("request", request.to_string()).part("fileContent", file);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post("https://apis.roblox.com/assets/v1/assets")
+            .header("x-api-key", &self.api_key)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        let mut operation: Operation = self.read_json(response).await?;
+
+        // Uploads are processed in the background, the operation says when the asset exists
+        let operation_id = operation.operation_id.clone()
+            .ok_or_else(|| RbxMcpError::OpenCloudError("The upload response has no operation ID".to_string()))?;
+        for _ in 0..UPLOAD_POLLS {
+            if operation.done {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let response = client
+                .get(format!("https://apis.roblox.com/assets/v1/operations/{}", operation_id))
+                .header("x-api-key", &self.api_key)
+                .send()
+                .await
+                .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+            operation = self.read_json(response).await?;
+        }
+        let created = operation.response.filter(|_| operation.done)
+            .ok_or_else(|| RbxMcpError::OpenCloudError(format!("Upload {} didn't finish in time", operation_id)))?;
+        created.asset_id.parse()
+            .map_err(|_| RbxMcpError::OpenCloudError(format!("Unexpected asset ID '{}'", created.asset_id)))
+    }
+
+    async fn read_json<T: for<'de> Deserialize<'de>>(&self, response: reqwest::Response) -> Result<T, RbxMcpError> {
+        let status = response.status();
+        let text = response.text().await.map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(RbxMcpError::OpenCloudError(format!("HTTP {}. Details: {}", status, text)));
+        }
+        serde_json::from_str(&text).map_err(|e| RbxMcpError::OpenCloudError(format!("Unexpected response {}: {}", text, e)))
+    }
+
+    /// Download the contents of an asset the key's owner can use, like a free model
+    pub async fn download_asset(&self, asset_id: u64) -> Result<Vec<u8>, RbxMcpError> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("https://apis.roblox.com/asset-delivery-api/v1/assetId/{}", asset_id))
+            .header("x-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(RbxMcpError::OpenCloudError(format!("HTTP {}. Details: {}", status, text)));
+        }
+        let asset: AssetLocation = serde_json::from_str(&text)
+            .map_err(|e| RbxMcpError::OpenCloudError(format!("Unexpected response {}: {}", text, e)))?;
+
+        // The contents themselves come from a CDN link that needs no key
+        let response = client.get(&asset.location)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        let contents = response.bytes().await.map_err(|e| RbxMcpError::OpenCloudError(e.to_string()))?;
+        Ok(contents.to_vec())
+    }
+}
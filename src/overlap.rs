@@ -0,0 +1,111 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, WeakDom};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::query::{part_bounds, Region};
+use crate::roblox::{instance_path, Modification};
+
+/// Share of the smaller of two parts that has to be inside the other for them to overlap.
+/// Parts resting on each other, or sunk up to halfway into the ground, don't count.
+const MIN_OVERLAP: f32 = 0.5;
+
+/// A new part that ended up inside anchored parts that were already in the place
+pub struct Overlap {
+    pub path: String,
+    /// Path of the existing part it overlaps most
+    pub existing: String,
+    /// How much of the smaller of the two parts is inside the other, from 0 to 1
+    pub fraction: f32,
+    /// How many other existing parts it overlaps
+    pub others: usize,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} overlaps {} ({:.0}% of the smaller part)", self.path, self.existing, self.fraction * 100.0)?;
+        if self.others > 0 {
+            write!(f, " and {} other parts", self.others)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the parts in the subtrees under `created` that overlap anchored parts from outside them.
+/// Parts are compared by their axis-aligned boxes, so rotated parts count as a little bigger than they are.
+pub fn find_overlaps(dom: &WeakDom, created: &[Ref]) -> Vec<Overlap> {
+    let mut new_ids = HashSet::new();
+    let mut new_parts = Vec::new();
+    for &root_id in created {
+        if dom.get_by_ref(root_id).is_none() {
+            continue;
+        }
+        for instance in dom.descendants_of(root_id) {
+            new_ids.insert(instance.referent());
+            if let Some(bounds) = part_bounds(instance) {
+                new_parts.push((instance.referent(), bounds));
+            }
+        }
+    }
+    let Some(reach) = new_parts.iter().map(|(_, bounds)| *bounds).reduce(Region::union) else {
+        return Vec::new();
+    };
+
+    // Only existing parts near the new ones are compared with each of them
+    let existing: Vec<(Ref, Region)> = dom.descendants()
+        .filter(|instance| !new_ids.contains(&instance.referent()) && is_anchored(instance))
+        .filter_map(|instance| part_bounds(instance).map(|bounds| (instance.referent(), bounds)))
+        .filter(|(_, bounds)| intersection_volume(bounds, &reach) > 0.0)
+        .collect();
+
+    new_parts.iter()
+        .filter_map(|(id, bounds)| {
+            let hits: Vec<(Ref, f32)> = existing.iter()
+                .map(|(existing_id, existing_bounds)| (*existing_id, overlap_fraction(bounds, existing_bounds)))
+                .filter(|(_, fraction)| *fraction > MIN_OVERLAP)
+                .collect();
+            let (existing_id, fraction) = hits.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1))?;
+            Some(Overlap {
+                path: instance_path(dom, *id),
+                existing: instance_path(dom, existing_id),
+                fraction,
+                others: hits.len() - 1,
+            })
+        })
+        .collect()
+}
+
+/// Build a follow-up prompt asking Gemini to move the parts it put inside existing ones
+pub fn feedback_prompt(prompt: &str, modification: &Modification, overlaps: &[Overlap]) -> Result<String, Box<dyn Error>> {
+    let overlaps: Vec<String> = overlaps.iter().map(Overlap::to_string).collect();
+    Ok(format!(
+        "{}\n\nYour previous response puts new parts inside parts that are already in the place:\n{}\n\
+         Respond with the same modification, but move these parts so they don't overlap what's already there, \
+         using the bounds of the models in the place description. Previous response: {}",
+        prompt,
+        overlaps.join("\n"),
+        serde_json::to_string(modification)?
+    ))
+}
+
+fn is_anchored(instance: &Instance) -> bool {
+    matches!(instance.properties.get(&"Anchored".into()), Some(Variant::Bool(true)))
+}
+
+/// How much of the smaller box is inside the other, from 0 to 1
+fn overlap_fraction(a: &Region, b: &Region) -> f32 {
+    let smaller = volume(a).min(volume(b));
+    if smaller <= 0.0 {
+        return 0.0;
+    }
+    intersection_volume(a, b) / smaller
+}
+
+fn intersection_volume(a: &Region, b: &Region) -> f32 {
+    (0..3).map(|axis| (a.max[axis].min(b.max[axis]) - a.min[axis].max(b.min[axis])).max(0.0)).product()
+}
+
+fn volume(region: &Region) -> f32 {
+    (0..3).map(|axis| region.max[axis] - region.min[axis]).product()
+}
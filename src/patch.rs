@@ -0,0 +1,493 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, InstanceBuilder, WeakDom};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tracing::{debug, info};
+
+use crate::diff::{match_places, values_equal};
+use crate::query;
+use crate::roblox::{
+    find_instance_by_path, instance_path, is_a, move_instance, path_not_found, remove_instance, set_attribute,
+    suggest_paths, ApplyReport, ChangeCounts, PathIndex, CHANGE_ID_ATTRIBUTE,
+};
+use crate::warnings::ApplyWarning;
+
+/// Changes made to one copy of a place, which can be replayed onto another copy.
+///
+/// Paths name instances as they were before the changes, so every target can be found
+/// before anything moves. Ref values name their target as it is after the changes.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Patch {
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOperation {
+    /// Set properties and attributes. A null value clears one.
+    Set {
+        path: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        properties: BTreeMap<String, Option<PatchValue>>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        attributes: BTreeMap<String, Option<Variant>>,
+    },
+    Rename { path: String, new_name: String },
+    Move { path: String, new_parent: String },
+    Add { parent: String, instance: PatchInstance },
+    Remove { path: String },
+}
+
+/// A property value. Refs are kept as paths, since referents differ between copies of a place.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchValue {
+    /// Path of the target, or empty for no target
+    Ref(String),
+    Value(Variant),
+}
+
+/// An added instance with everything under it
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct PatchInstance {
+    pub class: String,
+    pub name: String,
+    #[serde(default)]
+    pub properties: BTreeMap<String, PatchValue>,
+    #[serde(default)]
+    pub children: Vec<PatchInstance>,
+}
+
+/// Build a patch that turns old into new
+pub fn create_patch(old: &WeakDom, new: &WeakDom) -> Patch {
+    let matching = match_places(old, new);
+    let old_of: HashMap<Ref, Ref> = matching.pairs.iter().map(|&(old_id, new_id)| (new_id, old_id)).collect();
+    // Paths in the old place, indexed since a big patch names many siblings of the same parent
+    let mut old_paths = PathIndex::new();
+    // Where a new instance's parent was in the old place
+    let old_parent_path = |old_paths: &mut PathIndex, new_id: Ref| {
+        let parent = new.get_by_ref(new_id)?.parent();
+        old_of.get(&parent).map(|&old_parent| old_paths.path(old, old_parent))
+    };
+
+    let mut operations = Vec::new();
+    for &(old_id, new_id) in &matching.pairs {
+        if let Some(set) = set_operation(old, new, &mut old_paths, old_id, new_id) {
+            operations.push(set);
+        }
+    }
+    for &(old_id, new_id) in &matching.renamed {
+        if let Some(instance) = new.get_by_ref(new_id) {
+            operations.push(PatchOperation::Rename { path: old_paths.path(old, old_id), new_name: instance.name.clone() });
+        }
+    }
+    for &(old_id, new_id) in &matching.moved {
+        if let Some(new_parent) = old_parent_path(&mut old_paths, new_id) {
+            operations.push(PatchOperation::Move { path: old_paths.path(old, old_id), new_parent });
+        }
+    }
+    for &new_id in &matching.added {
+        if let (Some(parent), Some(instance)) = (old_parent_path(&mut old_paths, new_id), new.get_by_ref(new_id)) {
+            operations.push(PatchOperation::Add { parent, instance: patch_instance(new, instance) });
+        }
+    }
+    for &old_id in &matching.removed {
+        operations.push(PatchOperation::Remove { path: old_paths.path(old, old_id) });
+    }
+    Patch { operations }
+}
+
+/// Build the patch that undoes an apply, looking only at the instances its report says changed.
+/// before must keep the referents of the place it was copied from, as roblox::snapshot does.
+pub fn create_inverse(before: &WeakDom, after: &WeakDom, report: &ApplyReport) -> Patch {
+    let mut after_paths = PathIndex::new();
+    let in_both = |id: &Ref| before.get_by_ref(*id).is_some() && after.get_by_ref(*id).is_some();
+    // Services a modification creates are in no report, so the root's children are always compared
+    let mut seen = HashSet::new();
+    let scopes: Vec<Ref> = std::iter::once(after.root_ref())
+        .chain(report.changed.iter().copied().filter(in_both))
+        .filter(|&id| seen.insert(id))
+        .collect();
+
+    let mut sets = Vec::new();
+    let mut renames = Vec::new();
+    let mut moves = Vec::new();
+    let mut adds = Vec::new();
+    let mut removes = Vec::new();
+    let mut new_roots: Vec<Ref> = report.counts.created.clone();
+    for &id in &scopes {
+        let (Some(now), Some(was)) = (after.get_by_ref(id), before.get_by_ref(id)) else { continue };
+        if let Some(set) = set_operation(after, before, &mut after_paths, id, id) {
+            sets.push(set);
+        }
+        if now.name != was.name {
+            renames.push(PatchOperation::Rename { path: after_paths.path(after, id), new_name: was.name.clone() });
+        }
+        if now.parent() != was.parent() {
+            // An instance whose old parent went too comes back with that parent's subtree
+            if after.get_by_ref(was.parent()).is_some() {
+                moves.push(PatchOperation::Move { path: after_paths.path(after, id), new_parent: after_paths.path(after, was.parent()) });
+            } else {
+                removes.push(PatchOperation::Remove { path: after_paths.path(after, id) });
+            }
+        }
+        for &child_id in was.children() {
+            if let (None, Some(child)) = (after.get_by_ref(child_id), before.get_by_ref(child_id)) {
+                adds.push(PatchOperation::Add { parent: after_paths.path(after, id), instance: patch_instance(before, child) });
+            }
+        }
+        new_roots.extend(now.children().iter().copied().filter(|&child_id| before.get_by_ref(child_id).is_none()));
+    }
+    let mut seen = HashSet::new();
+    new_roots.retain(|&id| after.get_by_ref(id).is_some() && seen.insert(id));
+    for id in query::outermost_only(after, &new_roots) {
+        removes.push(PatchOperation::Remove { path: after_paths.path(after, id) });
+    }
+
+    let operations = sets.into_iter().chain(renames).chain(moves).chain(adds).chain(removes).collect();
+    Patch { operations }
+}
+
+/// The properties and attributes that differ between two matched instances
+fn set_operation(old: &WeakDom, new: &WeakDom, old_paths: &mut PathIndex, old_id: Ref, new_id: Ref) -> Option<PatchOperation> {
+    let (old_instance, new_instance) = (old.get_by_ref(old_id)?, new.get_by_ref(new_id)?);
+    let mut properties = BTreeMap::new();
+    let mut attributes = BTreeMap::new();
+
+    let names: BTreeSet<&str> = old_instance.properties.keys()
+        .chain(new_instance.properties.keys())
+        .map(|name| name.as_str())
+        .collect();
+    for name in names {
+        let old_value = old_instance.properties.get(&name.into());
+        let new_value = new_instance.properties.get(&name.into());
+        if matches!(old_value, Some(Variant::Attributes(_))) || matches!(new_value, Some(Variant::Attributes(_))) {
+            let old_attributes = match old_value {
+                Some(Variant::Attributes(attributes)) => attributes.iter().collect(),
+                _ => BTreeMap::new(),
+            };
+            let new_attributes: BTreeMap<_, _> = match new_value {
+                Some(Variant::Attributes(attributes)) => attributes.iter().collect(),
+                _ => BTreeMap::new(),
+            };
+            let keys: BTreeSet<&String> = old_attributes.keys().chain(new_attributes.keys()).copied().collect();
+            for key in keys {
+                if key != CHANGE_ID_ATTRIBUTE && old_attributes.get(key) != new_attributes.get(key) {
+                    attributes.insert(key.clone(), new_attributes.get(key).map(|&value| value.clone()));
+                }
+            }
+        } else if !values_equal(old, new, old_value, new_value) {
+            properties.insert(name.to_string(), new_value.map(|value| patch_value(new, value)));
+        }
+    }
+
+    if properties.is_empty() && attributes.is_empty() {
+        return None;
+    }
+    Some(PatchOperation::Set { path: old_paths.path(old, old_id), properties, attributes })
+}
+
+fn patch_value(dom: &WeakDom, value: &Variant) -> PatchValue {
+    match value {
+        Variant::Ref(target) if target.is_none() => PatchValue::Ref(String::new()),
+        Variant::Ref(target) => PatchValue::Ref(instance_path(dom, *target)),
+        other => PatchValue::Value(other.clone()),
+    }
+}
+
+fn patch_instance(dom: &WeakDom, instance: &Instance) -> PatchInstance {
+    let properties = instance.properties.iter()
+        .filter_map(|(name, value)| match value {
+            // Change IDs belong to the journal of the copy they were made in
+            Variant::Attributes(attributes) => {
+                let mut attributes = attributes.clone();
+                attributes.remove(CHANGE_ID_ATTRIBUTE);
+                (!attributes.is_empty()).then(|| (name.to_string(), PatchValue::Value(Variant::Attributes(attributes))))
+            }
+            value => Some((name.to_string(), patch_value(dom, value))),
+        })
+        .collect();
+    PatchInstance {
+        class: instance.class.to_string(),
+        name: instance.name.clone(),
+        properties,
+        children: instance.children().iter()
+            .filter_map(|&child_id| dom.get_by_ref(child_id))
+            .map(|child| patch_instance(dom, child))
+            .collect(),
+    }
+}
+
+/// A Ref property to fill in once every operation is done
+struct PendingRef {
+    instance_id: Ref,
+    property: String,
+    path: String,
+}
+
+/// Apply a patch to a place. Operations whose paths aren't in the place are skipped with a warning.
+/// Instances the patch sets, renames, moves or adds are tagged with change_id.
+pub fn apply_patch(dom: &mut WeakDom, patch: &Patch, change_id: Option<&str>) -> ApplyReport {
+    let root_ref = dom.root_ref();
+    let mut warnings = Vec::new();
+    let mut changed = Vec::new();
+    let mut counts = ChangeCounts::default();
+    let mut touched = Vec::new();
+    let mut pending_refs = Vec::new();
+
+    // Paths refer to the place before the patch, so find every target before anything changes
+    let mut resolve = |path: &str, operation: &str, dom: &WeakDom| {
+        let found = find_instance_by_path(dom, root_ref, path);
+        if found.is_none() {
+            warnings.push(path_not_found(dom, root_ref, operation, path));
+        }
+        found
+    };
+    let resolved: Vec<(Option<Ref>, Option<Ref>)> = patch.operations.iter()
+        .map(|operation| match operation {
+            PatchOperation::Set { path, .. } => (resolve(path, "set", dom), None),
+            PatchOperation::Rename { path, .. } => (resolve(path, "rename", dom), None),
+            PatchOperation::Move { path, new_parent } => (resolve(path, "move", dom), resolve(new_parent, "move into", dom)),
+            PatchOperation::Add { parent, .. } => (resolve(parent, "add into", dom), None),
+            PatchOperation::Remove { path } => (resolve(path, "remove", dom), None),
+        })
+        .collect();
+
+    let mut removals = Vec::new();
+    for (operation, resolved) in patch.operations.iter().zip(resolved) {
+        match (operation, resolved) {
+            (PatchOperation::Set { properties, attributes, .. }, (Some(id), _)) => {
+                for (name, value) in attributes {
+                    match value {
+                        Some(value) => set_attribute(dom, id, name, value.clone()),
+                        None => remove_attribute(dom, id, name),
+                    }
+                }
+                let Some(instance) = dom.get_by_ref_mut(id) else {
+                    continue;
+                };
+                for (name, value) in properties {
+                    match value {
+                        Some(PatchValue::Value(value)) => {
+                            instance.properties.insert(name.as_str().into(), value.clone());
+                        }
+                        Some(PatchValue::Ref(path)) => pending_refs.push(PendingRef { instance_id: id, property: name.clone(), path: path.clone() }),
+                        None => {
+                            instance.properties.remove(&name.as_str().into());
+                        }
+                    }
+                }
+                if properties.contains_key("Source") && is_a(&instance.class, "LuaSourceContainer") {
+                    counts.scripts.push(instance_path(dom, id));
+                }
+                touched.push(id);
+            }
+            (PatchOperation::Rename { path, new_name }, (Some(id), _)) => {
+                if let Some(instance) = dom.get_by_ref_mut(id) {
+                    instance.name = new_name.clone();
+                    info!("Renamed '{}' to '{}'", path, new_name);
+                    touched.push(id);
+                }
+            }
+            (PatchOperation::Move { path, new_parent }, (Some(id), Some(new_parent_id))) => {
+                match move_instance(dom, id, new_parent_id) {
+                    Ok(old_parent_id) => {
+                        info!("Moved '{}' into '{}'", path, new_parent);
+                        changed.push(old_parent_id);
+                        touched.push(id);
+                    }
+                    Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                        operation: "move".to_string(),
+                        path: path.clone(),
+                        reason: e.to_string(),
+                    }),
+                }
+            }
+            (PatchOperation::Add { parent, instance }, (Some(parent_id), _)) => {
+                let id = insert_instance(dom, instance, parent_id, &mut pending_refs);
+                info!("Added '{}' ({}) to '{}'", instance.name, instance.class, parent);
+                counts.record_added(dom, id);
+                touched.push(id);
+            }
+            // Removed last, so nothing else in the patch loses its target
+            (PatchOperation::Remove { path }, (Some(id), _)) => removals.push((path, id)),
+            _ => {}
+        }
+    }
+    for (path, id) in removals {
+        counts.record_removed(dom, id);
+        match remove_instance(dom, id) {
+            Ok(parent_id) => {
+                info!("Removed {}", path);
+                changed.push(parent_id);
+            }
+            Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                operation: "remove".to_string(),
+                path: path.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    // Ref paths name instances as they are after the patch
+    for pending in pending_refs {
+        let target = if pending.path.is_empty() {
+            Some(Ref::none())
+        } else {
+            find_instance_by_path(dom, root_ref, &pending.path)
+        };
+        let instance_name = dom.get_by_ref(pending.instance_id).map_or_else(String::new, |instance| instance.name.clone());
+        match (target, dom.get_by_ref_mut(pending.instance_id)) {
+            (Some(target), Some(instance)) => {
+                debug!("Resolved {} to '{}'", pending.property, pending.path);
+                instance.properties.insert(pending.property.as_str().into(), Variant::Ref(target));
+            }
+            _ => warnings.push(ApplyWarning::UnresolvedRef {
+                instance: instance_name,
+                property: pending.property,
+                suggestions: suggest_paths(dom, root_ref, &pending.path),
+                path: pending.path,
+            }),
+        }
+    }
+
+    if let Some(change_id) = change_id {
+        for &id in &touched {
+            set_attribute(dom, id, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.to_string()));
+        }
+    }
+    changed.extend(touched);
+    ApplyReport { warnings, changed, counts }
+}
+
+/// Insert an added instance and everything under it, leaving Ref properties for later
+fn insert_instance(dom: &mut WeakDom, instance: &PatchInstance, parent_id: Ref, pending_refs: &mut Vec<PendingRef>) -> Ref {
+    let mut builder = InstanceBuilder::new(instance.class.as_str()).with_name(instance.name.as_str());
+    for (name, value) in &instance.properties {
+        if let PatchValue::Value(value) = value {
+            builder = builder.with_property(name.as_str(), value.clone());
+        }
+    }
+    let id = dom.insert(parent_id, builder);
+    for (name, value) in &instance.properties {
+        if let PatchValue::Ref(path) = value {
+            pending_refs.push(PendingRef { instance_id: id, property: name.clone(), path: path.clone() });
+        }
+    }
+    for child in &instance.children {
+        insert_instance(dom, child, id, pending_refs);
+    }
+    id
+}
+
+fn remove_attribute(dom: &mut WeakDom, id: Ref, name: &str) {
+    if let Some(Variant::Attributes(attributes)) = dom.get_by_ref_mut(id).and_then(|instance| instance.properties.get_mut(&"Attributes".into())) {
+        attributes.remove(name);
+    }
+}
+
+/// Short summary of a patch, like "set 2, added 1"
+pub fn describe_patch(patch: &Patch) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for operation in &patch.operations {
+        let verb = match operation {
+            PatchOperation::Set { .. } => "set",
+            PatchOperation::Rename { .. } => "renamed",
+            PatchOperation::Move { .. } => "moved",
+            PatchOperation::Add { .. } => "added",
+            PatchOperation::Remove { .. } => "removed",
+        };
+        *counts.entry(verb).or_default() += 1;
+    }
+    if counts.is_empty() {
+        return "no changes".to_string();
+    }
+    counts.iter().map(|(verb, count)| format!("{} {}", verb, count)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff_places;
+    use crate::roblox::{self, json_to_weakdom, ApplyOptions, Modification};
+
+    /// A house with a door it points at, a shed with a crate, and some rubbish
+    fn sample_place() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let house_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("House"));
+        let door_id = dom.insert(house_id, InstanceBuilder::new("Part").with_name("Door").with_property("Transparency", 0.0f32));
+        dom.get_by_ref_mut(house_id).unwrap().properties.insert("PrimaryPart".into(), Variant::Ref(door_id));
+        let shed_id = dom.insert(workspace_id, InstanceBuilder::new("Folder").with_name("Shed"));
+        dom.insert(shed_id, InstanceBuilder::new("Part").with_name("Crate"));
+        dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Rubbish"));
+        dom
+    }
+
+    fn find(dom: &WeakDom, path: &str) -> Ref {
+        find_instance_by_path(dom, dom.root_ref(), path).unwrap_or_else(|| panic!("{} is missing", path))
+    }
+
+    #[test]
+    fn a_patch_replays_an_edit_onto_another_copy() {
+        let base = sample_place();
+        let mut edited = roblox::snapshot(&base);
+        let house_id = find(&edited, "Workspace/House");
+        let door_id = find(&edited, "Workspace/House/Door");
+        edited.get_by_ref_mut(door_id).unwrap().properties.insert("Transparency".into(), Variant::Float32(0.5));
+        let window_id = edited.insert(house_id, InstanceBuilder::new("Part").with_name("Window"));
+        edited.get_by_ref_mut(house_id).unwrap().properties.insert("PrimaryPart".into(), Variant::Ref(window_id));
+        let crate_id = find(&edited, "Workspace/Shed/Crate");
+        edited.transfer_within(crate_id, house_id);
+        edited.get_by_ref_mut(find(&edited, "Workspace/Shed")).unwrap().name = "Barn".to_string();
+        edited.destroy(find(&edited, "Workspace/Rubbish"));
+
+        let patch = create_patch(&base, &edited);
+        // A separate copy, so nothing can line up by referent
+        let mut copy = sample_place();
+        let report = apply_patch(&mut copy, &patch, None);
+        assert!(report.warnings.is_empty());
+        assert!(diff_places(&copy, &edited).is_empty());
+        let copy_house = copy.get_by_ref(find(&copy, "Workspace/House")).unwrap();
+        assert_eq!(copy_house.properties.get(&"PrimaryPart".into()), Some(&Variant::Ref(find(&copy, "Workspace/House/Window"))));
+    }
+
+    #[test]
+    fn the_inverse_undoes_an_apply() {
+        let mut place = sample_place();
+        let before = roblox::snapshot(&place);
+        let modification: Modification = serde_json::from_value(serde_json::json!({
+            "add": [{ "name": "Window", "class": "Part", "properties": {}, "target_parent": "Workspace/House" }],
+            "subtract": ["Workspace/Rubbish"],
+            "rename": [{ "path": "Workspace/Shed", "new_name": "Barn" }],
+            "move": [{ "path": "Workspace/Shed/Crate", "new_parent": "Workspace/House" }],
+            "set": [{ "path": "Workspace/House/Door", "properties": { "Transparency": { "type": "Float32", "value": 0.5 } } }],
+        })).unwrap();
+        let root_ref = place.root_ref();
+        let report = json_to_weakdom(&mut place, &modification, root_ref, &ApplyOptions::default()).unwrap();
+        assert!(!diff_places(&before, &place).is_empty());
+
+        let inverse = create_inverse(&before, &place, &report);
+        let undo_report = apply_patch(&mut place, &inverse, None);
+        assert!(undo_report.warnings.is_empty());
+        assert!(diff_places(&before, &place).is_empty());
+    }
+
+    #[test]
+    fn operations_on_missing_paths_are_skipped() {
+        let mut place = sample_place();
+        let patch: Patch = serde_json::from_value(serde_json::json!({ "operations": [
+            { "op": "remove", "path": "Workspace/Nothing" },
+            { "op": "rename", "path": "Workspace/House", "new_name": "Home" },
+        ] })).unwrap();
+        let report = apply_patch(&mut place, &patch, Some("abc123"));
+        assert_eq!(report.warnings.len(), 1);
+        let home_id = find(&place, "Workspace/Home");
+        assert!(matches!(
+            roblox::get_attribute(&place, home_id, CHANGE_ID_ATTRIBUTE),
+            Some(Variant::String(id)) if id == "abc123"
+        ));
+        assert_eq!(describe_patch(&patch), "removed 1, renamed 1");
+        assert_eq!(describe_patch(&Patch::default()), "no changes");
+    }
+}
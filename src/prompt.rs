@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::path::Path;
+
+/// The built-in system prompt: the response format, an example and how to write modifications
+pub const DEFAULT_SYSTEM_PROMPT: &str = include_str!("system_prompt.md");
+
+/// Values for the variables a system prompt template can use
+pub struct TemplateVars<'a> {
+    /// `{place_name}`, the place file's name without its extension
+    pub place_name: &'a str,
+    /// `{dom_summary}`, the description of the place sent to Gemini
+    pub dom_summary: &'a str,
+}
+
+/// Read a system prompt template from a file, e.g. given with --system-prompt
+pub fn load_template(path: &Path) -> Result<String, Box<dyn Error>> {
+    let template = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading system prompt {}: {}", path.display(), e))?;
+    if template.trim().is_empty() {
+        return Err(format!("System prompt {} is empty", path.display()).into());
+    }
+    Ok(template)
+}
+
+/// Fill in a template's variables. Other braces, like those of example JSON, are left alone.
+pub fn render(template: &str, vars: &TemplateVars) -> String {
+    template
+        .replace("{place_name}", vars.place_name)
+        .replace("{dom_summary}", vars.dom_summary)
+}
+
+/// Templates that include the place description themselves get it in the system instruction
+/// instead of after each prompt
+pub fn uses_dom_summary(template: &str) -> bool {
+    template.contains("{dom_summary}")
+}
+
+/// The name a place goes by in prompts, its file name without the extension
+pub fn place_name(path: &Path) -> String {
+    path.file_stem().map_or_else(|| "place".to_string(), |stem| stem.to_string_lossy().into_owned())
+}
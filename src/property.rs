@@ -0,0 +1,763 @@
+use rbx_dom_weak::types::{Axes, BrickColor, CFrame, Color3, Content, ContentId, ContentType, Enum, Faces, Font, FontStyle, FontWeight, Matrix3, NumberRange, Rect, Tags, UDim, UDim2, Variant, VariantType, Vector2, Vector3};
+use rbx_dom_weak::WeakDom;
+use rbx_reflection::DataType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::RbxMcpError;
+use crate::roblox::instance_path;
+
+/// A property value from a modification, checked when the JSON is deserialized.
+/// On the wire it is `{"type": "Vector3", "value": [1, 2, 3]}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "RawProperty", into = "RawProperty")]
+pub enum PropertyValue {
+    Vector3([f32; 3]),
+    /// Position and a row-major rotation matrix. Euler angles are converted when deserialized.
+    CFrame { position: [f32; 3], rotation: [f32; 9] },
+    /// A CFrame offset from the CFrame or pivot of the instance at a path, in that instance's frame like
+    /// `Base.CFrame * CFrame.new(offset)`. Resolved once every instance exists, like a Ref.
+    RelativeCFrame { relative_to: String, offset: [f32; 3], rotation: [f32; 9] },
+    String(String),
+    BrickColor(u16),
+    Bool(bool),
+    Float32(f32),
+    Float64(f64),
+    Int32(i32),
+    /// Like asset and user IDs, which don't fit in an Int32
+    Int64(i64),
+    Enum(EnumValue),
+    Color3([f32; 3]),
+    UDim2 { x: (f32, i32), y: (f32, i32) },
+    Vector2([f32; 2]),
+    /// Corners of a rectangle, like an ImageLabel's SliceCenter
+    Rect { min: [f32; 2], max: [f32; 2] },
+    /// Scale and offset
+    UDim(f32, i32),
+    /// Min and max, like a ParticleEmitter's Lifetime
+    NumberRange(f32, f32),
+    /// An asset URL, like a MeshPart's MeshId or a Sound's SoundId
+    Content(String),
+    /// Sides of a part, like a Handles' Faces, as the bits of Enum.NormalId
+    Faces(u8),
+    /// Axes, like an ArcHandles' Axes, with X, Y and Z as bits 1, 2 and 4
+    Axes(u8),
+    /// CollectionService tags
+    Tags(Vec<String>),
+    /// A FontFace: the family's asset URL, a weight from 100 to 900 and whether it's italic
+    Font { family: String, weight: u16, italic: bool },
+    /// Path of the instance the property points at
+    Ref(String),
+    /// A type this tool can't convert yet. Skipped with a warning when applied.
+    Unsupported { type_name: String, value: Value },
+}
+
+/// An enum item given by its number, or by its name like "SmoothPlastic"
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnumValue {
+    Number(u32),
+    Name(String),
+}
+
+/// The `{"type", "value"}` form properties have in JSON
+#[derive(Serialize, Deserialize)]
+struct RawProperty {
+    #[serde(rename = "type")]
+    type_name: String,
+    value: Value,
+}
+
+impl TryFrom<RawProperty> for PropertyValue {
+    type Error = String;
+
+    fn try_from(raw: RawProperty) -> Result<Self, Self::Error> {
+        let value = raw.value;
+        Ok(match raw.type_name.as_str() {
+            "Vector3" => PropertyValue::Vector3(vector3(&value)?),
+            "CFrame" => {
+                let obj = match &value {
+                    Value::Object(obj) => obj,
+                    // The 12 numbers of CFrame.new(x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22)
+                    Value::Array(_) => match numbers(&value, "CFrame")?.as_slice() {
+                        &[x, y, z, ref rotation @ ..] if rotation.len() == 9 => {
+                            return Ok(PropertyValue::CFrame { position: [x, y, z], rotation: rotation.try_into().unwrap() });
+                        }
+                        _ => return Err("CFrame array must have 12 numbers, the position and then the rotation matrix".to_string()),
+                    },
+                    _ => return Err("CFrame must be an object with position and rotation, or 12 numbers".to_string()),
+                };
+                let rotation = match obj.get("rotation") {
+                    None => IDENTITY,
+                    Some(rotation) => match numbers(rotation, "CFrame rotation")?.as_slice() {
+                        &[x, y, z] => matrix_rows(euler_degrees_to_matrix(x, y, z)),
+                        values if values.len() == 9 => values.try_into().unwrap(),
+                        _ => return Err("CFrame rotation must be 9 matrix values or 3 angles in degrees".to_string()),
+                    },
+                };
+                if let Some(relative_to) = obj.get("relative_to") {
+                    let relative_to = relative_to.as_str().ok_or("CFrame relative_to must be a path string")?.to_string();
+                    let offset = match obj.get("offset") {
+                        None => [0.0; 3],
+                        Some(offset) => vector3(offset).map_err(|_| "CFrame offset must be 3 numbers or {x, y, z}")?,
+                    };
+                    return Ok(PropertyValue::RelativeCFrame { relative_to, offset, rotation });
+                }
+                let position = obj.get("position")
+                    .ok_or("CFrame missing position")
+                    .and_then(|position| vector3(position).map_err(|_| "CFrame position must be 3 numbers or {x, y, z}"))?;
+                PropertyValue::CFrame { position, rotation }
+            }
+            "String" => match value {
+                Value::String(s) => PropertyValue::String(s),
+                // Numbers and other values are written as their JSON text
+                other => PropertyValue::String(other.to_string()),
+            },
+            "BrickColor" => match &value {
+                Value::String(name) => PropertyValue::BrickColor(brick_color_from_name(name)?),
+                _ => {
+                    let number = value.as_u64()
+                        .and_then(|n| u16::try_from(n).ok())
+                        .ok_or("BrickColor must be a number or a name")?;
+                    if BrickColor::from_number(number).is_none() {
+                        return Err(format!("Invalid BrickColor number: {}", number));
+                    }
+                    PropertyValue::BrickColor(number)
+                }
+            },
+            "Bool" => PropertyValue::Bool(value.as_bool().ok_or("Bool must be a boolean")?),
+            "Number" | "Float" | "Float32" => {
+                PropertyValue::Float32(value.as_f64().ok_or("Number must be a numeric value")? as f32)
+            }
+            "Int" | "Int32" => {
+                let n = value.as_i64().ok_or("Int must be a whole number")?;
+                PropertyValue::Int32(i32::try_from(n).map_err(|_| format!("Int {} is out of range, use Int64", n))?)
+            }
+            "Int64" => PropertyValue::Int64(match &value {
+                // Large IDs are sometimes quoted so they survive JSON tools that use doubles
+                Value::String(text) => text.trim().parse().map_err(|_| format!("Int64 {} isn't a whole number", text))?,
+                other => other.as_i64().ok_or("Int64 must be a whole number")?,
+            }),
+            "Double" | "Float64" => PropertyValue::Float64(value.as_f64().ok_or("Float64 must be a numeric value")?),
+            "Faces" => PropertyValue::Faces(flags(&value, "Faces", FACES)?),
+            "Axes" => PropertyValue::Axes(flags(&value, "Axes", AXES)?),
+            "Tags" => PropertyValue::Tags(serde_json::from_value(value).map_err(|_| "Tags must be a list of strings")?),
+            "Enum" => match value {
+                Value::String(name) => PropertyValue::Enum(EnumValue::Name(name)),
+                other => {
+                    let number = other.as_u64()
+                        .and_then(|n| u32::try_from(n).ok())
+                        .ok_or("Enum must be a whole number or an item name")?;
+                    PropertyValue::Enum(EnumValue::Number(number))
+                }
+            },
+            "Color3" => match &value {
+                Value::String(text) => PropertyValue::Color3(color_from_text(text)?),
+                _ => match numbers(&value, "Color3")?.as_slice() {
+                    &[r, g, b] => PropertyValue::Color3([r, g, b]),
+                    _ => return Err("Color3 must have 3 components".to_string()),
+                },
+            },
+            "UDim2" => match numbers(&value, "UDim2")?.as_slice() {
+                &[x_scale, x_offset, y_scale, y_offset] => PropertyValue::UDim2 {
+                    x: (x_scale, x_offset as i32),
+                    y: (y_scale, y_offset as i32),
+                },
+                _ => return Err("UDim2 must have 4 components [xScale, xOffset, yScale, yOffset]".to_string()),
+            },
+            "Vector2" => PropertyValue::Vector2(vector2(&value)?),
+            "Rect" => match &value {
+                Value::Object(obj) => {
+                    let corner = |key: &str| obj.get(key)
+                        .ok_or_else(|| format!("Rect is missing {}", key))
+                        .and_then(vector2);
+                    PropertyValue::Rect { min: corner("min")?, max: corner("max")? }
+                }
+                _ => match numbers(&value, "Rect")?.as_slice() {
+                    &[min_x, min_y, max_x, max_y] => PropertyValue::Rect { min: [min_x, min_y], max: [max_x, max_y] },
+                    _ => return Err("Rect must have 4 components [minX, minY, maxX, maxY]".to_string()),
+                },
+            },
+            "UDim" => match numbers(&value, "UDim")?.as_slice() {
+                &[scale, offset] => PropertyValue::UDim(scale, offset as i32),
+                _ => return Err("UDim must have 2 components [scale, offset]".to_string()),
+            },
+            "NumberRange" => {
+                let (min, max) = match value.as_f64() {
+                    // A single number is a range of just that value
+                    Some(n) => (n as f32, n as f32),
+                    None => match numbers(&value, "NumberRange")?.as_slice() {
+                        &[min, max] => (min, max),
+                        _ => return Err("NumberRange must be a number or [min, max]".to_string()),
+                    },
+                };
+                if min > max {
+                    return Err(format!("NumberRange min {} is greater than max {}", min, max));
+                }
+                PropertyValue::NumberRange(min, max)
+            }
+            "Font" => font(&value)?,
+            "Content" | "ContentId" => match value {
+                Value::String(url) => PropertyValue::Content(url),
+                // A bare asset ID
+                other => PropertyValue::Content(format!(
+                    "rbxassetid://{}",
+                    other.as_u64().ok_or("Content must be an asset URL or ID")?
+                )),
+            },
+            "Ref" => PropertyValue::Ref(value.as_str().ok_or("Ref must be a path string")?.to_string()),
+            _ => PropertyValue::Unsupported { type_name: raw.type_name, value },
+        })
+    }
+}
+
+impl From<PropertyValue> for RawProperty {
+    fn from(property: PropertyValue) -> Self {
+        let type_name = property.type_name().to_string();
+        let value = match property {
+            PropertyValue::Vector3(v) | PropertyValue::Color3(v) => numbers_json(&v),
+            PropertyValue::CFrame { position, rotation } => serde_json::json!({
+                "position": numbers_json(&position),
+                "rotation": numbers_json(&rotation),
+            }),
+            PropertyValue::RelativeCFrame { relative_to, offset, rotation } => serde_json::json!({
+                "relative_to": relative_to,
+                "offset": numbers_json(&offset),
+                "rotation": numbers_json(&rotation),
+            }),
+            PropertyValue::String(s) | PropertyValue::Ref(s) | PropertyValue::Content(s) => Value::String(s),
+            PropertyValue::BrickColor(n) => serde_json::json!(n),
+            PropertyValue::Bool(b) => Value::Bool(b),
+            PropertyValue::Float32(n) => number_json(n),
+            PropertyValue::Int32(n) => serde_json::json!(n),
+            PropertyValue::Int64(n) => serde_json::json!(n),
+            PropertyValue::Float64(n) => serde_json::json!(n),
+            PropertyValue::Faces(bits) => flag_names(bits, FACES),
+            PropertyValue::Axes(bits) => flag_names(bits, AXES),
+            PropertyValue::Tags(tags) => serde_json::json!(tags),
+            PropertyValue::Enum(EnumValue::Number(n)) => serde_json::json!(n),
+            PropertyValue::Enum(EnumValue::Name(name)) => Value::String(name),
+            PropertyValue::UDim2 { x, y } => serde_json::json!([number_json(x.0), x.1, number_json(y.0), y.1]),
+            PropertyValue::Vector2(v) => numbers_json(&v),
+            PropertyValue::Rect { min, max } => numbers_json(&[min[0], min[1], max[0], max[1]]),
+            PropertyValue::UDim(scale, offset) => serde_json::json!([number_json(scale), offset]),
+            PropertyValue::NumberRange(min, max) => numbers_json(&[min, max]),
+            PropertyValue::Font { family, weight, italic } => serde_json::json!({
+                "family": family,
+                "weight": FONT_WEIGHTS.iter().find(|(_, w)| *w == weight).map_or(serde_json::json!(weight), |(name, _)| serde_json::json!(name)),
+                "style": if italic { "Italic" } else { "Normal" },
+            }),
+            PropertyValue::Unsupported { value, .. } => value,
+        };
+        RawProperty { type_name, value }
+    }
+}
+
+/// A JSON number for an f32, written with the f32's shortest digits rather than 0.6392157077789307
+fn number_json(n: f32) -> Value {
+    n.to_string().parse::<f64>().map_or(Value::Null, |n| serde_json::json!(n))
+}
+
+fn numbers_json(values: &[f32]) -> Value {
+    Value::Array(values.iter().map(|&n| number_json(n)).collect())
+}
+
+const IDENTITY: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+impl PropertyValue {
+    /// Convert a JSON value given with a type name, as in `{"type": "Vector3", "value": [1, 2, 3]}`
+    pub fn new(type_name: &str, value: Value) -> Result<PropertyValue, String> {
+        PropertyValue::try_from(RawProperty { type_name: type_name.to_string(), value })
+    }
+
+    /// Convert a property of a class from JSON, either `{"type", "value"}` or just the value,
+    /// whose type is then inferred
+    pub fn from_json(class: &str, property: &str, value: Value) -> Result<PropertyValue, String> {
+        let is_typed = value.as_object().is_some_and(|obj| obj.contains_key("type") && obj.contains_key("value"));
+        match is_typed {
+            true => serde_json::from_value(value).map_err(|e| e.to_string()),
+            false => PropertyValue::infer(class, property, value),
+        }
+    }
+
+    /// Convert a JSON value given without a type, using the property's type in the reflection database,
+    /// e.g. `"Anchored": true` or `"Size": [4, 1, 2]` on a Part
+    pub fn infer(class: &str, property: &str, value: Value) -> Result<PropertyValue, String> {
+        let database = rbx_reflection_database::get();
+        let descriptor = database.classes.get(class)
+            .ok_or_else(|| format!("unknown class {}, so the type of {} can't be looked up", class, property))?;
+        let data_type = database.superclasses_iter(descriptor)
+            .find_map(|class_descriptor| class_descriptor.properties.get(property))
+            .map(|property_descriptor| &property_descriptor.data_type)
+            .ok_or_else(|| format!("{} has no property {}, give its value with an explicit type", class, property))?;
+        let type_name = match data_type {
+            DataType::Enum(_) => "Enum".to_string(),
+            DataType::Value(VariantType::Ref) => "Ref".to_string(),
+            DataType::Value(VariantType::Color3uint8) => "Color3".to_string(),
+            DataType::Value(VariantType::OptionalCFrame) => "CFrame".to_string(),
+            DataType::Value(ty) => format!("{:?}", ty),
+            _ => return Err(format!("{}.{} has no known type, give its value with an explicit type", class, property)),
+        };
+        PropertyValue::new(&type_name, value)
+    }
+
+    /// Convert a Roblox value back to the form a modification gives it. Refs become paths.
+    pub fn from_variant(dom: &WeakDom, value: &Variant) -> PropertyValue {
+        match value {
+            Variant::Vector3(v) => PropertyValue::Vector3([v.x, v.y, v.z]),
+            Variant::CFrame(cframe) => PropertyValue::CFrame {
+                position: [cframe.position.x, cframe.position.y, cframe.position.z],
+                rotation: matrix_rows(cframe.orientation),
+            },
+            Variant::String(s) => PropertyValue::String(s.clone()),
+            Variant::BrickColor(color) => PropertyValue::BrickColor(*color as u16),
+            Variant::Bool(b) => PropertyValue::Bool(*b),
+            Variant::Float32(n) => PropertyValue::Float32(*n),
+            Variant::Float64(n) => PropertyValue::Float64(*n),
+            Variant::Int32(n) => PropertyValue::Int32(*n),
+            Variant::Int64(n) => PropertyValue::Int64(*n),
+            Variant::Faces(faces) => PropertyValue::Faces(faces.bits()),
+            Variant::Axes(axes) => PropertyValue::Axes(axes.bits()),
+            Variant::Tags(tags) => PropertyValue::Tags(tags.iter().map(str::to_string).collect()),
+            Variant::Enum(e) => PropertyValue::Enum(EnumValue::Number(e.to_u32())),
+            Variant::Color3(c) => PropertyValue::Color3([c.r, c.g, c.b]),
+            Variant::Color3uint8(c) => PropertyValue::Color3([c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0]),
+            Variant::UDim2(u) => PropertyValue::UDim2 { x: (u.x.scale, u.x.offset), y: (u.y.scale, u.y.offset) },
+            Variant::Vector2(v) => PropertyValue::Vector2([v.x, v.y]),
+            Variant::Rect(r) => PropertyValue::Rect { min: [r.min.x, r.min.y], max: [r.max.x, r.max.y] },
+            Variant::UDim(u) => PropertyValue::UDim(u.scale, u.offset),
+            Variant::NumberRange(r) => PropertyValue::NumberRange(r.min, r.max),
+            Variant::ContentId(id) => PropertyValue::Content(id.as_str().to_string()),
+            Variant::Content(content) => match content.value() {
+                ContentType::None => PropertyValue::Content(String::new()),
+                ContentType::Uri(uri) => PropertyValue::Content(uri.clone()),
+                // Content pointing at an instance, like an EditableImage
+                _ => PropertyValue::Unsupported { type_name: "Content".to_string(), value: Value::Null },
+            },
+            Variant::Font(font) => PropertyValue::Font {
+                family: font.family.clone(),
+                weight: font.weight.as_u16(),
+                italic: font.style == FontStyle::Italic,
+            },
+            Variant::Ref(referent) if referent.is_none() => PropertyValue::Ref(String::new()),
+            Variant::Ref(referent) => PropertyValue::Ref(instance_path(dom, *referent)),
+            other => PropertyValue::Unsupported { type_name: format!("{:?}", other.ty()), value: Value::Null },
+        }
+    }
+
+    /// The name used for the type in JSON
+    pub fn type_name(&self) -> &str {
+        match self {
+            PropertyValue::Vector3(_) => "Vector3",
+            PropertyValue::CFrame { .. } | PropertyValue::RelativeCFrame { .. } => "CFrame",
+            PropertyValue::String(_) => "String",
+            PropertyValue::BrickColor(_) => "BrickColor",
+            PropertyValue::Bool(_) => "Bool",
+            PropertyValue::Float32(_) => "Float32",
+            PropertyValue::Int32(_) => "Int32",
+            PropertyValue::Int64(_) => "Int64",
+            PropertyValue::Float64(_) => "Float64",
+            PropertyValue::Faces(_) => "Faces",
+            PropertyValue::Axes(_) => "Axes",
+            PropertyValue::Tags(_) => "Tags",
+            PropertyValue::Enum(_) => "Enum",
+            PropertyValue::Color3(_) => "Color3",
+            PropertyValue::UDim2 { .. } => "UDim2",
+            PropertyValue::Vector2(_) => "Vector2",
+            PropertyValue::Rect { .. } => "Rect",
+            PropertyValue::UDim(..) => "UDim",
+            PropertyValue::NumberRange(..) => "NumberRange",
+            PropertyValue::Content(_) => "Content",
+            PropertyValue::Font { .. } => "Font",
+            PropertyValue::Ref(_) => "Ref",
+            PropertyValue::Unsupported { type_name, .. } => type_name,
+        }
+    }
+
+    /// The text of a string value, including one given with a type this tool doesn't know
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::String(s) => Some(s),
+            PropertyValue::Unsupported { value: Value::String(s), .. } => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of a number property
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Float32(n) => Some(*n as f64),
+            PropertyValue::Float64(n) => Some(*n),
+            PropertyValue::Int32(n) => Some(*n as f64),
+            PropertyValue::Int64(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// Convert to a Roblox value for a property of a class. Refs, relative CFrames and unsupported types
+    /// give None, since Refs and relative CFrames are resolved once every instance exists.
+    pub fn to_variant(&self, class: &str, property: &str) -> Result<Option<Variant>, RbxMcpError> {
+        Ok(Some(match self {
+            PropertyValue::Vector3([x, y, z]) => Variant::Vector3(Vector3::new(*x, *y, *z)),
+            PropertyValue::CFrame { position: [x, y, z], rotation: r } => Variant::CFrame(CFrame::new(
+                Vector3::new(*x, *y, *z),
+                Matrix3::new(
+                    Vector3::new(r[0], r[1], r[2]),
+                    Vector3::new(r[3], r[4], r[5]),
+                    Vector3::new(r[6], r[7], r[8]),
+                ),
+            )),
+            // Asset properties given as plain strings still need to be content
+            PropertyValue::String(s) | PropertyValue::Content(s) => match reflected_type(class, property) {
+                Some(VariantType::Content) if s.is_empty() => Variant::Content(Content::none()),
+                Some(VariantType::Content) => Variant::Content(Content::from_uri(s.as_str())),
+                Some(VariantType::ContentId) => Variant::ContentId(ContentId::from(s.as_str())),
+                _ if matches!(self, PropertyValue::Content(_)) => Variant::ContentId(ContentId::from(s.as_str())),
+                _ => Variant::String(s.clone()),
+            },
+            PropertyValue::BrickColor(n) => Variant::BrickColor(
+                BrickColor::from_number(*n)
+                    .ok_or_else(|| RbxMcpError::conversion(property, format!("Invalid BrickColor number: {}", n)))?,
+            ),
+            PropertyValue::Bool(b) => Variant::Bool(*b),
+            PropertyValue::Float32(_) | PropertyValue::Float64(_) | PropertyValue::Int32(_) | PropertyValue::Int64(_) => {
+                number_variant(self, class, property)?
+            }
+            PropertyValue::Faces(bits) => Variant::Faces(Faces::from_bits(*bits)
+                .ok_or_else(|| RbxMcpError::conversion(property, format!("Invalid Faces bits: {}", bits)))?),
+            PropertyValue::Axes(bits) => Variant::Axes(Axes::from_bits(*bits)
+                .ok_or_else(|| RbxMcpError::conversion(property, format!("Invalid Axes bits: {}", bits)))?),
+            PropertyValue::Tags(tags) => Variant::Tags(Tags::from(tags.clone())),
+            PropertyValue::Enum(EnumValue::Number(n)) => Variant::Enum(Enum::from_u32(*n)),
+            PropertyValue::Enum(EnumValue::Name(name)) => Variant::Enum(Enum::from_u32(enum_item(class, property, name)?)),
+            PropertyValue::Color3([r, g, b]) => Variant::Color3(Color3::new(*r, *g, *b)),
+            PropertyValue::UDim2 { x, y } => Variant::UDim2(UDim2::new(UDim::new(x.0, x.1), UDim::new(y.0, y.1))),
+            PropertyValue::Vector2([x, y]) => Variant::Vector2(Vector2::new(*x, *y)),
+            PropertyValue::Rect { min, max } => Variant::Rect(Rect::new(Vector2::new(min[0], min[1]), Vector2::new(max[0], max[1]))),
+            PropertyValue::UDim(scale, offset) => Variant::UDim(UDim::new(*scale, *offset)),
+            PropertyValue::NumberRange(min, max) => Variant::NumberRange(NumberRange::new(*min, *max)),
+            PropertyValue::Font { family, weight, italic } => Variant::Font(Font::new(
+                family,
+                FontWeight::from_u16(*weight)
+                    .ok_or_else(|| RbxMcpError::conversion(property, format!("Invalid font weight: {}", weight)))?,
+                if *italic { FontStyle::Italic } else { FontStyle::Normal },
+            )),
+            PropertyValue::Ref(_) | PropertyValue::RelativeCFrame { .. } | PropertyValue::Unsupported { .. } => return Ok(None),
+        }))
+    }
+}
+
+/// Convert a number to the width of the property it's for, so an Int32 given for an Int64 ID isn't
+/// written as the wrong type and ignored by Roblox
+fn number_variant(value: &PropertyValue, class: &str, property: &str) -> Result<Variant, RbxMcpError> {
+    Ok(match (reflected_type(class, property), value) {
+        (Some(VariantType::Int64), PropertyValue::Int32(n)) => Variant::Int64(*n as i64),
+        (Some(VariantType::Int32), PropertyValue::Int64(n)) => Variant::Int32(i32::try_from(*n).map_err(|_| {
+            RbxMcpError::conversion(property, format!("{} is too large for {}.{}, which is an Int32", n, class, property))
+        })?),
+        (Some(VariantType::Float64), PropertyValue::Float32(n)) => Variant::Float64(*n as f64),
+        (Some(VariantType::Float32), PropertyValue::Float64(n)) => Variant::Float32(*n as f32),
+        (_, PropertyValue::Float32(n)) => Variant::Float32(*n),
+        (_, PropertyValue::Float64(n)) => Variant::Float64(*n),
+        (_, PropertyValue::Int32(n)) => Variant::Int32(*n),
+        (_, PropertyValue::Int64(n)) => Variant::Int64(*n),
+        (_, other) => return Err(RbxMcpError::conversion(property, format!("{} is not a number", other.type_name()))),
+    })
+}
+
+/// Face names for Faces values, by bit
+const FACES: &[(&str, u8)] = &[("Right", 1), ("Top", 2), ("Back", 4), ("Left", 8), ("Bottom", 16), ("Front", 32)];
+
+/// Axis names for Axes values, by bit
+const AXES: &[(&str, u8)] = &[("X", 1), ("Y", 2), ("Z", 4)];
+
+/// Read a list of names like ["Top", "Front"] as bits
+fn flags(value: &Value, what: &str, names: &[(&str, u8)]) -> Result<u8, String> {
+    let expected = || names.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ");
+    let mut bits = 0;
+    for item in value.as_array().ok_or_else(|| format!("{} must be a list of names from {}", what, expected()))? {
+        let name = item.as_str().ok_or_else(|| format!("{} must be a list of names from {}", what, expected()))?;
+        let (_, bit) = names.iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown {} name {}, use {}", what, name, expected()))?;
+        bits |= bit;
+    }
+    Ok(bits)
+}
+
+fn flag_names(bits: u8, names: &[(&str, u8)]) -> Value {
+    Value::Array(names.iter().filter(|(_, bit)| bits & bit != 0).map(|(name, _)| Value::String(name.to_string())).collect())
+}
+
+/// The type of a property in the reflection database, looking through the class's superclasses
+fn reflected_type(class: &str, property: &str) -> Option<VariantType> {
+    let database = rbx_reflection_database::get();
+    let descriptor = database.classes.get(class)?;
+    match database.superclasses_iter(descriptor).find_map(|class_descriptor| class_descriptor.properties.get(property))?.data_type {
+        DataType::Value(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Look up an enum item by name for a property, using the property's enum type from the reflection database
+fn enum_item(class: &str, property: &str, name: &str) -> Result<u32, RbxMcpError> {
+    let database = rbx_reflection_database::get();
+    let mut descriptor = database.classes.get(class);
+    while let Some(class_descriptor) = descriptor {
+        if let Some(property_descriptor) = class_descriptor.properties.get(property) {
+            let DataType::Enum(enum_name) = &property_descriptor.data_type else {
+                return Err(RbxMcpError::conversion(property, format!("{}.{} is not an enum", class, property)));
+            };
+            let items = &database.enums.get(enum_name.as_ref())
+                .ok_or_else(|| RbxMcpError::conversion(property, format!("Unknown enum {}", enum_name)))?
+                .items;
+            return items.get(name).copied().ok_or_else(|| {
+                let mut names: Vec<&str> = items.keys().map(|name| name.as_ref()).collect();
+                names.sort();
+                RbxMcpError::conversion(property, format!("'{}' is not an item of Enum.{}. Items: {}", name, enum_name, names.join(", ")))
+            });
+        }
+        descriptor = class_descriptor.superclass.as_ref().and_then(|superclass| database.classes.get(superclass));
+    }
+    Err(RbxMcpError::conversion(property, format!("{} has no property {}, give the enum as a number", class, property)))
+}
+
+/// Read [x, y, z] or {x, y, z}
+fn vector3(value: &Value) -> Result<[f32; 3], String> {
+    match value {
+        Value::Object(obj) => {
+            let component = |key: &str| obj.get(key)
+                .and_then(Value::as_f64)
+                .map(|n| n as f32)
+                .ok_or_else(|| format!("Vector3 is missing a number for {}", key));
+            Ok([component("x")?, component("y")?, component("z")?])
+        }
+        _ => match numbers(value, "Vector3")?.as_slice() {
+            &[x, y, z] => Ok([x, y, z]),
+            _ => Err("Vector3 must have 3 components".to_string()),
+        },
+    }
+}
+
+/// Look up a BrickColor's number by its name, like "Bright red", ignoring case
+fn brick_color_from_name(name: &str) -> Result<u16, String> {
+    if let Some(color) = BrickColor::from_name(name.trim()) {
+        return Ok(color as u16);
+    }
+    (1..=u16::MAX)
+        .filter_map(BrickColor::from_number)
+        .find(|color| color.to_string().eq_ignore_ascii_case(name.trim()))
+        .map(|color| color as u16)
+        .ok_or_else(|| format!("Unknown BrickColor name: {}", name))
+}
+
+/// Colors a Color3 can be given by name, with their RGB values
+const COLOR_NAMES: &[(&str, [u8; 3])] = &[
+    ("black", [0, 0, 0]),
+    ("white", [255, 255, 255]),
+    ("gray", [128, 128, 128]),
+    ("grey", [128, 128, 128]),
+    ("light gray", [211, 211, 211]),
+    ("light grey", [211, 211, 211]),
+    ("dark gray", [64, 64, 64]),
+    ("dark grey", [64, 64, 64]),
+    ("red", [255, 0, 0]),
+    ("dark red", [139, 0, 0]),
+    ("green", [0, 128, 0]),
+    ("dark green", [0, 100, 0]),
+    ("lime", [0, 255, 0]),
+    ("blue", [0, 0, 255]),
+    ("navy", [0, 0, 128]),
+    ("light blue", [173, 216, 230]),
+    ("sky blue", [135, 206, 235]),
+    ("cyan", [0, 255, 255]),
+    ("teal", [0, 128, 128]),
+    ("yellow", [255, 255, 0]),
+    ("gold", [255, 215, 0]),
+    ("orange", [255, 165, 0]),
+    ("purple", [128, 0, 128]),
+    ("violet", [238, 130, 238]),
+    ("magenta", [255, 0, 255]),
+    ("pink", [255, 192, 203]),
+    ("brown", [139, 69, 19]),
+    ("tan", [210, 180, 140]),
+    ("beige", [245, 245, 220]),
+    ("maroon", [128, 0, 0]),
+    ("olive", [128, 128, 0]),
+    ("silver", [192, 192, 192]),
+];
+
+/// Read a Color3 given as hex, like "#FF8800" or "#F80", or by a name like "red"
+fn color_from_text(text: &str) -> Result<[f32; 3], String> {
+    let name = text.trim().to_lowercase();
+    let rgb = match COLOR_NAMES.iter().find(|(color, _)| *color == name) {
+        Some((_, rgb)) => *rgb,
+        None => {
+            let hex = name.strip_prefix('#').unwrap_or(&name);
+            let digits = match hex.len() {
+                // #RGB is short for #RRGGBB
+                3 => hex.chars().flat_map(|digit| [digit, digit]).collect(),
+                6 => hex.to_string(),
+                _ => String::new(),
+            };
+            let channel = |i: usize| digits.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok());
+            match (channel(0), channel(2), channel(4)) {
+                (Some(r), Some(g), Some(b)) => [r, g, b],
+                _ => return Err(format!("Color3 \"{}\" isn't a hex color like \"#FF8800\" or a color name like \"red\"", text)),
+            }
+        }
+    };
+    Ok(rgb.map(|channel| channel as f32 / 255.0))
+}
+
+/// Font weights by name, as in Enum.FontWeight
+const FONT_WEIGHTS: &[(&str, u16)] = &[
+    ("Thin", 100),
+    ("ExtraLight", 200),
+    ("Light", 300),
+    ("Regular", 400),
+    ("Medium", 500),
+    ("SemiBold", 600),
+    ("Bold", 700),
+    ("ExtraBold", 800),
+    ("Heavy", 900),
+];
+
+/// Read a FontFace from {family, weight, style}, or just the family. A family given by name,
+/// like "GothamSSm", is one of the built-in ones in rbxasset://fonts/families.
+fn font(value: &Value) -> Result<PropertyValue, String> {
+    let (family, weight, style) = match value {
+        Value::String(family) => (family.as_str(), None, None),
+        Value::Object(obj) => (
+            obj.get("family").and_then(Value::as_str).ok_or("Font must have a family")?,
+            obj.get("weight"),
+            obj.get("style").map(|style| style.as_str().ok_or("Font style must be Normal or Italic")).transpose()?,
+        ),
+        _ => return Err("Font must be an object with family, weight and style".to_string()),
+    };
+    let family = match family.contains("://") {
+        true => family.to_string(),
+        false => format!("rbxasset://fonts/families/{}.json", family.trim_end_matches(".json")),
+    };
+    let weight = match weight {
+        None => 400,
+        Some(Value::String(name)) => FONT_WEIGHTS.iter()
+            .find(|(weight, _)| weight.eq_ignore_ascii_case(name))
+            .map(|(_, weight)| *weight)
+            .ok_or_else(|| format!("Unknown font weight {}, use Thin, Light, Regular, Medium, SemiBold, Bold, Heavy or 100 to 900", name))?,
+        Some(number) => number.as_u64()
+            .and_then(|n| u16::try_from(n).ok())
+            .filter(|n| FontWeight::from_u16(*n).is_some())
+            .ok_or_else(|| format!("Font weight {} must be a multiple of 100 from 100 to 900", number))?,
+    };
+    let italic = match style {
+        None => false,
+        Some(style) if style.eq_ignore_ascii_case("normal") => false,
+        Some(style) if style.eq_ignore_ascii_case("italic") => true,
+        Some(style) => return Err(format!("Font style {} must be Normal or Italic", style)),
+    };
+    Ok(PropertyValue::Font { family, weight, italic })
+}
+
+/// Read [x, y] or {x, y}
+fn vector2(value: &Value) -> Result<[f32; 2], String> {
+    match value {
+        Value::Object(obj) => {
+            let component = |key: &str| obj.get(key)
+                .and_then(Value::as_f64)
+                .map(|n| n as f32)
+                .ok_or_else(|| format!("Vector2 is missing a number for {}", key));
+            Ok([component("x")?, component("y")?])
+        }
+        _ => match numbers(value, "Vector2")?.as_slice() {
+            &[x, y] => Ok([x, y]),
+            _ => Err("Vector2 must have 2 components".to_string()),
+        },
+    }
+}
+
+/// Read an array of numbers
+fn numbers(value: &Value, what: &str) -> Result<Vec<f32>, String> {
+    value.as_array()
+        .ok_or_else(|| format!("{} must be an array", what))?
+        .iter()
+        .map(|n| n.as_f64().map(|n| n as f32).ok_or_else(|| format!("{} must only contain numbers", what)))
+        .collect()
+}
+
+fn matrix_rows(matrix: Matrix3) -> [f32; 9] {
+    [
+        matrix.x.x, matrix.x.y, matrix.x.z,
+        matrix.y.x, matrix.y.y, matrix.y.z,
+        matrix.z.x, matrix.z.y, matrix.z.z,
+    ]
+}
+
+/// Build a rotation matrix from rotations around the X, Y and Z axes in degrees,
+/// applied in the same order as CFrame.Angles
+pub fn euler_degrees_to_matrix(x: f32, y: f32, z: f32) -> Matrix3 {
+    let (sx, cx) = x.to_radians().sin_cos();
+    let (sy, cy) = y.to_radians().sin_cos();
+    let (sz, cz) = z.to_radians().sin_cos();
+
+    // Rx * Ry * Rz
+    Matrix3::new(
+        Vector3::new(cy * cz, -cy * sz, sy),
+        Vector3::new(cx * sz + sx * sy * cz, cx * cz - sx * sy * sz, -sx * cy),
+        Vector3::new(sx * sz - cx * sy * cz, sx * cz + cx * sy * sz, cx * cy),
+    )
+}
+
+/// Compose a CFrame with an offset and rotation in its own frame, like `base * CFrame.new(offset) * rotation`
+pub fn offset_cframe(base: CFrame, offset: [f32; 3], rotation: [f32; 9]) -> CFrame {
+    let rows = [base.orientation.x, base.orientation.y, base.orientation.z];
+    let dot = |row: Vector3, v: [f32; 3]| row.x * v[0] + row.y * v[1] + row.z * v[2];
+    let column = |j: usize| [rotation[j], rotation[3 + j], rotation[6 + j]];
+    let [x, y, z] = rows.map(|row| Vector3::new(dot(row, column(0)), dot(row, column(1)), dot(row, column(2))));
+    CFrame::new(
+        Vector3::new(
+            base.position.x + dot(rows[0], offset),
+            base.position.y + dot(rows[1], offset),
+            base.position.z + dot(rows[2], offset),
+        ),
+        Matrix3::new(x, y, z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rows(matrix: Matrix3, rows: [[f32; 3]; 3]) {
+        for (row, expected) in [matrix.x, matrix.y, matrix.z].into_iter().zip(rows) {
+            for (value, expected) in [row.x, row.y, row.z].into_iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-6, "{:?} isn't {:?}", matrix, rows);
+            }
+        }
+    }
+
+    #[test]
+    fn euler_degrees_match_cframe_angles() {
+        assert_rows(euler_degrees_to_matrix(0.0, 0.0, 0.0), [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        // CFrame.Angles(math.rad(90), 0, 0)
+        assert_rows(euler_degrees_to_matrix(90.0, 0.0, 0.0), [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]]);
+        // CFrame.Angles(0, math.rad(90), 0)
+        assert_rows(euler_degrees_to_matrix(0.0, 90.0, 0.0), [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]);
+        // CFrame.Angles(0, 0, math.rad(90))
+        assert_rows(euler_degrees_to_matrix(0.0, 0.0, 90.0), [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        // CFrame.Angles(math.rad(90), math.rad(90), 0), X applied last
+        assert_rows(euler_degrees_to_matrix(90.0, 90.0, 0.0), [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn colors_from_hex_and_names() {
+        let orange = [1.0, 136.0 / 255.0, 0.0];
+        assert_eq!(color_from_text("#FF8800"), Ok(orange));
+        assert_eq!(color_from_text("ff8800"), Ok(orange));
+        assert_eq!(color_from_text("#f80"), Ok(orange));
+        assert_eq!(color_from_text(" Light Grey "), Ok([211.0 / 255.0; 3]));
+        assert_eq!(color_from_text("white"), Ok([1.0; 3]));
+        assert!(color_from_text("#FF88").is_err());
+        assert!(color_from_text("#GG8800").is_err());
+        assert!(color_from_text("mauve-ish").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use rbx_dom_weak::types::{CFrame, Ref, Variant, Vector3};
+use rbx_dom_weak::{Instance, WeakDom};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::RbxMcpError;
+use crate::roblox::{find_instance_by_path, is_a, suggest_paths};
+
+/// Number of results returned per page when no limit is given
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+
+/// Which slice of a result list to return
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Page { offset: 0, limit: DEFAULT_PAGE_LIMIT }
+    }
+}
+
+impl Page {
+    /// Start a page from a cursor returned by an earlier page
+    pub fn from_cursor(cursor: &str, limit: usize) -> Result<Page, String> {
+        let offset = cursor.parse::<usize>().map_err(|_| format!("Invalid cursor '{}'", cursor))?;
+        Ok(Page { offset, limit })
+    }
+}
+
+/// One page of results, with a cursor for the next page if there is one
+#[derive(Serialize, Debug)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub offset: usize,
+    pub total: usize,
+    pub next_cursor: Option<String>,
+}
+
+/// Cut a page out of a full result list
+pub fn paginate<T: Clone>(items: &[T], page: Page) -> Paged<T> {
+    let start = page.offset.min(items.len());
+    let end = start.saturating_add(page.limit).min(items.len());
+    Paged {
+        items: items[start..end].to_vec(),
+        offset: start,
+        total: items.len(),
+        next_cursor: (end < items.len()).then(|| end.to_string()),
+    }
+}
+
+/// Axis-aligned box given by two opposite corners
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Region {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Region {
+    /// Check whether a point lies inside the region, regardless of corner order
+    pub fn contains(&self, point: Vector3) -> bool {
+        let within = |v: f32, a: f32, b: f32| v >= a.min(b) && v <= a.max(b);
+        within(point.x, self.min[0], self.max[0])
+            && within(point.y, self.min[1], self.max[1])
+            && within(point.z, self.min[2], self.max[2])
+    }
+
+    /// The smallest region holding both, for regions with their corners in order
+    pub fn union(self, other: Region) -> Region {
+        Region {
+            min: std::array::from_fn(|axis| self.min[axis].min(other.min[axis])),
+            max: std::array::from_fn(|axis| self.max[axis].max(other.max[axis])),
+        }
+    }
+}
+
+/// Axis-aligned box around every part under an instance, counting each part's rotation.
+/// None when there are no parts with a CFrame and Size.
+pub fn bounding_box(dom: &WeakDom, instance_id: Ref) -> Option<Region> {
+    dom.descendants_of(instance_id).filter_map(part_bounds).reduce(Region::union)
+}
+
+/// Axis-aligned box a part takes up, counting its rotation.
+/// None for Terrain and anything that isn't a part with a CFrame and Size.
+pub fn part_bounds(instance: &Instance) -> Option<Region> {
+    if instance.class == "Terrain" || !is_a(&instance.class, "BasePart") {
+        return None;
+    }
+    let (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) =
+        (instance.properties.get(&"CFrame".into()), instance.properties.get(&"Size".into()))
+    else {
+        return None;
+    };
+
+    // How far the rotated part reaches from its center along each world axis
+    let rows = [cframe.orientation.x, cframe.orientation.y, cframe.orientation.z];
+    let reach = rows.map(|row| (row.x.abs() * size.x + row.y.abs() * size.y + row.z.abs() * size.z) / 2.0);
+    let center = [cframe.position.x, cframe.position.y, cframe.position.z];
+    Some(Region {
+        min: std::array::from_fn(|axis| center[axis] - reach[axis]),
+        max: std::array::from_fn(|axis| center[axis] + reach[axis]),
+    })
+}
+
+/// Get the world position of an instance from its CFrame, or its pivot for Models
+pub fn instance_position(dom: &WeakDom, instance_id: Ref) -> Option<Vector3> {
+    let instance = dom.get_by_ref(instance_id)?;
+    match instance.properties.get(&"CFrame".into()) {
+        Some(Variant::CFrame(cframe)) => Some(cframe.position),
+        _ => match instance.properties.get(&"WorldPivotData".into()) {
+            Some(Variant::OptionalCFrame(Some(cframe))) => Some(cframe.position),
+            _ => None,
+        },
+    }
+}
+
+/// Get the CFrame of an instance, or the pivot of a Model
+pub fn instance_cframe(dom: &WeakDom, instance_id: Ref) -> Option<CFrame> {
+    let instance = dom.get_by_ref(instance_id)?;
+    match instance.properties.get(&"CFrame".into()) {
+        Some(Variant::CFrame(cframe)) => Some(*cframe),
+        _ => match instance.properties.get(&"WorldPivotData".into()) {
+            Some(Variant::OptionalCFrame(Some(cframe))) => Some(*cframe),
+            _ => None,
+        },
+    }
+}
+
+/// Terrain and Camera have a CFrame but are never the subject of a spatial query
+fn is_spatial(dom: &WeakDom, instance_id: Ref) -> bool {
+    dom.get_by_ref(instance_id)
+        .is_some_and(|instance| instance.class != "Terrain" && instance.class != "Camera")
+}
+
+/// Find every instance below start_id whose position lies inside the region
+pub fn instances_in_region(dom: &WeakDom, start_id: Ref, region: &Region) -> Vec<Ref> {
+    dom.descendants_of(start_id)
+        .map(|instance| instance.referent())
+        .filter(|&id| id != start_id && is_spatial(dom, id))
+        .filter(|&id| instance_position(dom, id).is_some_and(|pos| region.contains(pos)))
+        .collect()
+}
+
+/// Find every instance below start_id within radius studs of the instance at path.
+/// The instance at path and its ancestors are never included.
+pub fn instances_near(dom: &WeakDom, start_id: Ref, path: &str, radius: f32) -> Result<Vec<Ref>, RbxMcpError> {
+    let root_ref = dom.root_ref();
+    let center_id = find_instance_by_path(dom, root_ref, path)
+        .ok_or_else(|| RbxMcpError::PathNotFound {
+            path: path.to_string(),
+            suggestions: suggest_paths(dom, root_ref, path),
+        })?;
+    let center = instance_position(dom, center_id)
+        .ok_or_else(|| RbxMcpError::InvalidOperation(format!("Instance at '{}' has no position", path)))?;
+
+    // Collect the center and its ancestors so "near spawn" never removes spawn itself
+    let mut excluded = vec![center_id];
+    let mut current = dom.get_by_ref(center_id).map(|i| i.parent());
+    while let Some(parent_id) = current.filter(|id| id.is_some()) {
+        excluded.push(parent_id);
+        current = dom.get_by_ref(parent_id).map(|i| i.parent());
+    }
+
+    Ok(dom.descendants_of(start_id)
+        .map(|instance| instance.referent())
+        .filter(|&id| id != start_id && !excluded.contains(&id) && is_spatial(dom, id))
+        .filter(|&id| {
+            instance_position(dom, id).is_some_and(|pos| {
+                let (dx, dy, dz) = (pos.x - center.x, pos.y - center.y, pos.z - center.z);
+                (dx * dx + dy * dy + dz * dz).sqrt() <= radius
+            })
+        })
+        .collect())
+}
+
+/// Build a case-insensitive name pattern. Text that isn't a valid regex is matched literally.
+pub fn name_pattern(pattern: &str) -> Regex {
+    Regex::new(&format!("(?i){}", pattern))
+        .or_else(|_| Regex::new(&format!("(?i){}", regex::escape(pattern))))
+        .expect("escaped pattern is a valid regex")
+}
+
+/// Find every instance below start_id whose name matches the pattern,
+/// optionally only of a class or its subclasses
+pub fn find_by_name(dom: &WeakDom, start_id: Ref, pattern: &Regex, class: Option<&str>) -> Vec<Ref> {
+    dom.descendants_of(start_id)
+        .filter(|instance| instance.referent() != start_id && pattern.is_match(&instance.name))
+        .filter(|instance| class.is_none_or(|class| is_a(&instance.class, class)))
+        .map(|instance| instance.referent())
+        .collect()
+}
+
+/// Drop any instance whose ancestor is also in the list, so removing the
+/// result does not try to destroy the same subtree twice
+pub fn outermost_only(dom: &WeakDom, ids: &[Ref]) -> Vec<Ref> {
+    ids.iter()
+        .copied()
+        .filter(|&id| {
+            let mut current = dom.get_by_ref(id).map(|i| i.parent());
+            while let Some(parent_id) = current.filter(|id| id.is_some()) {
+                if ids.contains(&parent_id) {
+                    return false;
+                }
+                current = dom.get_by_ref(parent_id).map(|i| i.parent());
+            }
+            true
+        })
+        .collect()
+}
@@ -0,0 +1,567 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::assets;
+use crate::csg;
+use crate::error::RbxMcpError;
+use crate::generators;
+use crate::journal;
+use crate::property::PropertyValue;
+use crate::query::{self, Page, Paged, Region};
+use crate::report;
+use crate::roblox::{
+    attributes_to_json, child_path, did_you_mean, find_instance_by_path, get_attribute, instance_path,
+    json_to_weakdom, ApplyOptions, ApplyReport, ChangeCounts, InsertOperation, Modification, MoveOperation, SetOperation,
+    SetTarget, SubtractTarget, CHANGE_ID_ATTRIBUTE,
+};
+use crate::tree::{self, TreeOptions};
+use crate::warnings::log_warnings;
+
+/// A command typed at the interactive prompt that is handled locally instead of being sent to Gemini
+pub enum ReplCommand {
+    QueryRegion { region: Region, page: Page },
+    QueryNear { path: String, radius: f32, page: Page },
+    ReportAssets,
+    FlattenUnions,
+    Blame(String),
+    Tree { path: Option<String>, options: TreeOptions },
+    Find { pattern: String, class: Option<String>, page: Page },
+    Props(String),
+    Set { path: String, property: String, type_name: String, value: String },
+    Remove(String),
+    Move { path: String, new_parent: String },
+    Insert { file: PathBuf, parent: String },
+    Export { path: String, output: PathBuf },
+    /// Run a generator with its parameters as a JSON object
+    Generate { generator: String, params: serde_json::Value },
+}
+
+/// Whether a command changed the place, which then needs saving
+pub enum CommandOutcome {
+    Unchanged,
+    Changed { changed: Vec<Ref>, description: String, counts: ChangeCounts },
+}
+
+impl ReplCommand {
+    /// Whether running the command can change the place
+    pub fn modifies_place(&self) -> bool {
+        matches!(self, ReplCommand::Set { .. } | ReplCommand::Remove(_) | ReplCommand::Move { .. }
+            | ReplCommand::Insert { .. } | ReplCommand::Generate { .. })
+    }
+
+    /// Parse a line of input. Returns None if the line is a prompt rather than a command.
+    pub fn parse(line: &str) -> Option<Result<ReplCommand, String>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().copied() {
+            Some("query") => Some(parse_query(&words[1..])),
+            Some("report") => Some(match &words[1..] {
+                ["assets"] => Ok(ReplCommand::ReportAssets),
+                _ => Err("Usage: report assets".to_string()),
+            }),
+            Some("flatten-unions") => Some(Ok(ReplCommand::FlattenUnions)),
+            Some("blame") => Some(match &words[1..] {
+                [path] => Ok(ReplCommand::Blame(path.to_string())),
+                _ => Err("Usage: blame <path>".to_string()),
+            }),
+            Some("tree") => Some(parse_tree(&words[1..])),
+            Some("find") => Some(parse_find(&words[1..])),
+            Some("props") => Some(match &words[1..] {
+                [path] => Ok(ReplCommand::Props(path.to_string())),
+                _ => Err("Usage: props <path>".to_string()),
+            }),
+            Some("set") => Some(match &words[1..] {
+                [path, property, type_name, _, ..] => Ok(ReplCommand::Set {
+                    path: path.to_string(),
+                    property: property.to_string(),
+                    type_name: type_name.to_string(),
+                    value: skip_words(line, 4).to_string(),
+                }),
+                _ => Err("Usage: set <path> <property> <type> <value>, e.g. set Workspace/House/Door Transparency Float 0.5".to_string()),
+            }),
+            Some("rm") => Some(match &words[1..] {
+                [path] => Ok(ReplCommand::Remove(path.to_string())),
+                _ => Err("Usage: rm <path>".to_string()),
+            }),
+            Some("mv") => Some(match &words[1..] {
+                [path, new_parent] => Ok(ReplCommand::Move { path: path.to_string(), new_parent: new_parent.to_string() }),
+                _ => Err("Usage: mv <path> <new-parent-path>".to_string()),
+            }),
+            Some("insert") => Some(match &words[1..] {
+                [file, parent] => Ok(ReplCommand::Insert { file: PathBuf::from(file), parent: parent.to_string() }),
+                _ => Err("Usage: insert <file.rbxmx> <parent-path>".to_string()),
+            }),
+            Some("export") => Some(match &words[1..] {
+                [path, "-o" | "--output", output] => Ok(ReplCommand::Export { path: path.to_string(), output: PathBuf::from(output) }),
+                _ => Err("Usage: export <path> -o <model.rbxmx>".to_string()),
+            }),
+            Some("gen") => Some(parse_generate(&words[1..])),
+            _ => None,
+        }
+    }
+}
+
+/// The rest of a line after its first n words, with its spacing kept
+fn skip_words(line: &str, n: usize) -> &str {
+    let mut rest = line.trim_start();
+    for _ in 0..n {
+        rest = rest.find(char::is_whitespace).map_or("", |end| rest[end..].trim_start());
+    }
+    rest.trim_end()
+}
+
+fn parse_generate(args: &[&str]) -> Result<ReplCommand, String> {
+    let usage = format!("Usage: gen <{}> [key=value ...], e.g. gen staircase steps=12 origin=0,0,20", generators::GENERATORS.join("|"));
+    let Some((generator, args)) = args.split_first() else {
+        return Err(usage);
+    };
+    let mut params = serde_json::Map::new();
+    for arg in args {
+        let (key, value) = arg.split_once('=').ok_or_else(|| format!("Expected key=value, not '{}'. {}", arg, usage))?;
+        params.insert(key.to_string(), parse_value(value));
+    }
+    Ok(ReplCommand::Generate { generator: generator.to_string(), params: serde_json::Value::Object(params) })
+}
+
+fn parse_query(args: &[&str]) -> Result<ReplCommand, String> {
+    const USAGE: &str = "Usage: query region <x1> <y1> <z1> <x2> <y2> <z2> | query near <path> <radius> \
+        [--limit N] [--offset N | --cursor C]";
+    let (args, page) = parse_page(args).map_err(|e| format!("{}. {}", e, USAGE))?;
+    match args.as_slice() {
+        ["region", coords @ ..] if coords.len() == 6 => {
+            let values = coords.iter()
+                .map(|c| c.parse::<f32>().map_err(|_| format!("Invalid number '{}'. {}", c, USAGE)))
+                .collect::<Result<Vec<f32>, String>>()?;
+            let region = Region {
+                min: [values[0], values[1], values[2]],
+                max: [values[3], values[4], values[5]],
+            };
+            Ok(ReplCommand::QueryRegion { region, page })
+        }
+        ["near", path, radius] => {
+            let radius = radius.parse::<f32>()
+                .map_err(|_| format!("Invalid radius '{}'. {}", radius, USAGE))?;
+            Ok(ReplCommand::QueryNear { path: path.to_string(), radius, page })
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_tree(args: &[&str]) -> Result<ReplCommand, String> {
+    const USAGE: &str = "Usage: tree [path] [--depth N] [--class ClassName]";
+    let mut path = None;
+    let mut options = TreeOptions::default();
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--depth" => {
+                let depth = iter.next().ok_or_else(|| format!("Missing value for --depth. {}", USAGE))?;
+                options.depth = depth.parse()
+                    .map_err(|_| format!("Invalid depth '{}'. {}", depth, USAGE))?;
+            }
+            "--class" => {
+                let class = iter.next().ok_or_else(|| format!("Missing value for --class. {}", USAGE))?;
+                options.class = Some(class.to_string());
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.to_string()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok(ReplCommand::Tree { path, options })
+}
+
+fn parse_find(args: &[&str]) -> Result<ReplCommand, String> {
+    const USAGE: &str = "Usage: find <name-or-regex> [--class ClassName] [--limit N] [--offset N | --cursor C]";
+    let (args, page) = parse_page(args).map_err(|e| format!("{}. {}", e, USAGE))?;
+    match args.as_slice() {
+        [pattern] => Ok(ReplCommand::Find { pattern: pattern.to_string(), class: None, page }),
+        [pattern, "--class", class] | ["--class", class, pattern] => Ok(ReplCommand::Find {
+            pattern: pattern.to_string(),
+            class: Some(class.to_string()),
+            page,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Split --limit/--offset/--cursor options off the end of a command's arguments
+fn parse_page<'a>(args: &[&'a str]) -> Result<(Vec<&'a str>, Page), String> {
+    let mut rest = Vec::new();
+    let mut limit = None;
+    let mut offset = None;
+    let mut cursor = None;
+    let mut iter = args.iter();
+    while let Some(&arg) = iter.next() {
+        let slot = match arg {
+            "--limit" => &mut limit,
+            "--offset" => &mut offset,
+            "--cursor" => &mut cursor,
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+        *slot = Some(*iter.next().ok_or_else(|| format!("Missing value for {}", arg))?);
+    }
+
+    let limit = match limit {
+        Some(limit) => limit.parse::<usize>()
+            .ok()
+            .filter(|&limit| limit > 0)
+            .ok_or_else(|| format!("Invalid limit '{}'", limit))?,
+        None => query::DEFAULT_PAGE_LIMIT,
+    };
+    let page = match (offset, cursor) {
+        (Some(_), Some(_)) => return Err("Use either --offset or --cursor, not both".to_string()),
+        (Some(offset), None) => Page {
+            offset: offset.parse().map_err(|_| format!("Invalid offset '{}'", offset))?,
+            limit,
+        },
+        (None, Some(cursor)) => Page::from_cursor(cursor, limit)?,
+        (None, None) => Page { offset: 0, limit },
+    };
+    Ok((rest, page))
+}
+
+/// Run a command against the place loaded from place_path, with the session's apply options
+pub fn run_command(place: &mut WeakDom, place_path: &Path, command: &ReplCommand, options: &ApplyOptions) -> Result<CommandOutcome, Box<dyn Error>> {
+    match command {
+        ReplCommand::QueryRegion { region, page } => {
+            let workspace_id = workspace(place)?;
+            let found = query::instances_in_region(place, workspace_id, region);
+            print_positions(place, &query::paginate(&found, *page));
+        }
+        ReplCommand::QueryNear { path, radius, page } => {
+            let workspace_id = workspace(place)?;
+            let found = query::instances_near(place, workspace_id, path, *radius)?;
+            print_positions(place, &query::paginate(&found, *page));
+        }
+        ReplCommand::ReportAssets => {
+            report::print_asset_report(place, &report::asset_report(place));
+        }
+        ReplCommand::FlattenUnions => {
+            csg::print_union_report(place);
+        }
+        ReplCommand::Blame(path) => blame(place, place_path, path)?,
+        ReplCommand::Tree { path, options } => {
+            let start_id = match path {
+                Some(path) => resolve(place, path)?,
+                None => place.root_ref(),
+            };
+            tree::print_tree(place, start_id, options);
+        }
+        ReplCommand::Find { pattern, class, page } => {
+            let found = query::find_by_name(place, place.root_ref(), &query::name_pattern(pattern), class.as_deref());
+            let found = query::paginate(&found, *page);
+            for &id in &found.items {
+                let class = place.get_by_ref(id).map_or("", |instance| instance.class.as_str());
+                println!("{} ({})", instance_path(place, id), class);
+            }
+            print_page_footer(&found);
+        }
+        ReplCommand::Props(path) => print_props(place, path)?,
+        ReplCommand::Set { path, property, type_name, value } => {
+            return set_property(place, path, property, type_name, value, options);
+        }
+        ReplCommand::Remove(path) => return remove(place, path, options),
+        ReplCommand::Move { path, new_parent } => return move_to(place, path, new_parent, options),
+        ReplCommand::Insert { file, parent } => return insert(place, file, parent, options),
+        ReplCommand::Export { path, output } => {
+            let instance_id = resolve(place, path)?;
+            let count = assets::export_model(place, instance_id, output)?;
+            println!("Wrote {} ({} instances) to {}", path, count, output.display());
+        }
+        ReplCommand::Generate { generator, params } => return generate(place, generator, params, options),
+    }
+    Ok(CommandOutcome::Unchanged)
+}
+
+/// Set one property of an instance, converting the value the same way as properties in a modification.
+/// The value is JSON, like 0.5, [1, 2, 3] or "text", or else plain text or a list of numbers.
+fn set_property(
+    place: &mut WeakDom,
+    path: &str,
+    property: &str,
+    type_name: &str,
+    value: &str,
+    options: &ApplyOptions,
+) -> Result<CommandOutcome, Box<dyn Error>> {
+    resolve(place, path)?;
+    let parsed = PropertyValue::new(type_name, parse_value(value))?;
+    let modification = Modification {
+        set: vec![SetOperation {
+            target: SetTarget::Path { path: path.to_string() },
+            properties: HashMap::from([(property.to_string(), parsed)]),
+        }],
+        ..Default::default()
+    };
+    let report = apply(place, &modification, options)?;
+    println!("Set {}.{} to {}", path, property, value);
+    Ok(CommandOutcome::Changed {
+        changed: report.changed,
+        description: format!("set {}.{} to {}", path, property, value),
+        counts: report.counts,
+    })
+}
+
+/// Remove an instance and everything under it
+fn remove(place: &mut WeakDom, path: &str, options: &ApplyOptions) -> Result<CommandOutcome, Box<dyn Error>> {
+    let instance_id = resolve(place, path)?;
+    if is_service(place, instance_id) {
+        return Err(format!("{} is a service and can't be removed", path).into());
+    }
+    let modification = Modification { subtract: vec![SubtractTarget::Path(path.to_string())], ..Default::default() };
+    let report = apply(place, &modification, options)?;
+    println!("Removed {}", path);
+    Ok(CommandOutcome::Changed {
+        changed: report.changed,
+        description: format!("rm {}", path),
+        counts: report.counts,
+    })
+}
+
+/// Move an instance under a new parent
+fn move_to(place: &mut WeakDom, path: &str, new_parent: &str, options: &ApplyOptions) -> Result<CommandOutcome, Box<dyn Error>> {
+    let instance_id = resolve(place, path)?;
+    resolve(place, new_parent)?;
+    if is_service(place, instance_id) {
+        return Err(format!("{} is a service and can't be moved", path).into());
+    }
+    let modification = Modification {
+        moves: vec![MoveOperation { path: path.to_string(), new_parent: new_parent.to_string() }],
+        ..Default::default()
+    };
+    let report = apply(place, &modification, options)?;
+    println!("Moved {} into {}", path, new_parent);
+    Ok(CommandOutcome::Changed {
+        changed: report.changed,
+        description: format!("mv {} {}", path, new_parent),
+        counts: report.counts,
+    })
+}
+
+/// Insert the instances of a model file under a parent
+fn insert(place: &mut WeakDom, file: &Path, parent: &str, options: &ApplyOptions) -> Result<CommandOutcome, Box<dyn Error>> {
+    resolve(place, parent)?;
+    let modification = Modification {
+        insert: vec![InsertOperation {
+            asset: file.to_string_lossy().into_owned(),
+            target_parent: Some(parent.to_string()),
+            name: None,
+            position: None,
+        }],
+        ..Default::default()
+    };
+    let report = apply(place, &modification, options)?;
+    for &instance_id in &report.counts.created {
+        println!("Inserted {}", instance_path(place, instance_id));
+    }
+    Ok(CommandOutcome::Changed {
+        changed: report.changed,
+        description: format!("insert {} {}", file.display(), parent),
+        counts: report.counts,
+    })
+}
+
+/// Make a command's change through json_to_weakdom, so it gets the same checks as a prompt's,
+/// like refusing package edits. A command is one operation, so a warning means it wasn't done.
+fn apply(place: &mut WeakDom, modification: &Modification, options: &ApplyOptions) -> Result<ApplyReport, Box<dyn Error>> {
+    let root_ref = place.root_ref();
+    let report = json_to_weakdom(place, modification, root_ref, options).map_err(with_limit_remedy)?;
+    if !report.warnings.is_empty() {
+        return Err(report.warnings.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n").into());
+    }
+    Ok(report)
+}
+
+/// Build with a generator and add the result to the place
+fn generate(place: &mut WeakDom, generator: &str, params: &serde_json::Value, options: &ApplyOptions) -> Result<CommandOutcome, Box<dyn Error>> {
+    let instance = generators::generate(generator, params)?;
+    let path = child_path(instance.target_parent.as_deref().unwrap_or("Workspace"), &instance.name);
+    let modification = Modification { add: vec![instance], ..Default::default() };
+    let root_ref = place.root_ref();
+    let report = json_to_weakdom(place, &modification, root_ref, options).map_err(with_limit_remedy)?;
+    log_warnings(&report.warnings);
+    println!("Added {} ({} instances)", path, report.counts.added);
+    Ok(CommandOutcome::Changed {
+        changed: report.changed,
+        description: format!("gen {} {}", generator, params),
+        counts: report.counts,
+    })
+}
+
+/// Say how to make a command that's over --max-new-instances anyway
+fn with_limit_remedy(e: RbxMcpError) -> Box<dyn Error> {
+    match e {
+        RbxMcpError::TooManyInstances { .. } => format!("{}. Raise --max-new-instances to allow it", e).into(),
+        e => e.into(),
+    }
+}
+
+/// Look up an instance by path, suggesting close matches when it isn't found
+fn resolve(place: &WeakDom, path: &str) -> Result<Ref, Box<dyn Error>> {
+    let root_ref = place.root_ref();
+    Ok(find_instance_by_path(place, root_ref, path)
+        .ok_or_else(|| format!("Could not find instance at path '{}'.{}", path, did_you_mean(place, root_ref, path)))?)
+}
+
+fn is_service(place: &WeakDom, instance_id: Ref) -> bool {
+    place.get_by_ref(instance_id).is_some_and(|instance| instance.parent() == place.root_ref())
+}
+
+/// Read a value typed at the prompt
+fn parse_value(text: &str) -> serde_json::Value {
+    if let Ok(value) = serde_json::from_str(text) {
+        return value;
+    }
+    // "1 2 3" or "1, 2, 3" for vectors and colors
+    let numbers: Option<Vec<f64>> = text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect();
+    match numbers {
+        Some(numbers) if numbers.len() > 1 => serde_json::json!(numbers),
+        _ => serde_json::Value::String(text.to_string()),
+    }
+}
+
+/// Print which change last created or modified an instance, from the closest tagged ancestor
+fn blame(place: &WeakDom, place_path: &Path, path: &str) -> Result<(), Box<dyn Error>> {
+    let instance_id = resolve(place, path)?;
+
+    let mut current = instance_id;
+    let tagged = loop {
+        // String attributes read back from a file come out as BinaryString
+        let change_id = match get_attribute(place, current, CHANGE_ID_ATTRIBUTE) {
+            Some(Variant::String(change_id)) => Some(change_id.clone()),
+            Some(Variant::BinaryString(bytes)) => Some(String::from_utf8_lossy(bytes.as_ref()).into_owned()),
+            _ => None,
+        };
+        if let Some(change_id) = change_id {
+            break Some((current, change_id));
+        }
+        match place.get_by_ref(current).map(|instance| instance.parent()) {
+            Some(parent) if parent.is_some() => current = parent,
+            _ => break None,
+        }
+    };
+
+    let Some((tagged_id, change_id)) = tagged else {
+        println!("{} has no recorded change. It wasn't created, moved or renamed by rbx-mcp", path);
+        return Ok(());
+    };
+    if tagged_id == instance_id {
+        println!("{} was last changed by {}", path, change_id);
+    } else {
+        println!("{} is part of {}, which was last changed by {}", path, instance_path(place, tagged_id), change_id);
+    }
+    match journal::find_change(place_path, &change_id)? {
+        Some(entry) => {
+            println!("  When: {}", journal::format_age(entry.timestamp));
+            if let Some(session) = &entry.session {
+                println!("  Session: {}", session);
+            }
+            println!("  Prompt: {}", entry.description);
+        }
+        None => println!("  No journal entry for {} in {}", change_id, journal::journal_path(place_path).display()),
+    }
+    Ok(())
+}
+
+/// Print an instance's properties and attributes as JSON in the shape an added instance takes
+fn print_props(place: &WeakDom, path: &str) -> Result<(), Box<dyn Error>> {
+    let instance_id = resolve(place, path)?;
+    let instance = place.get_by_ref(instance_id).ok_or("Instance not found")?;
+
+    let mut properties = Vec::new();
+    let mut attributes = serde_json::Map::new();
+    for (name, value) in &instance.properties {
+        match value {
+            Variant::Attributes(values) => attributes = attributes_to_json(values),
+            value => properties.push((name.as_str(), PropertyValue::from_variant(place, value))),
+        }
+    }
+    properties.sort_by_key(|(name, _)| *name);
+
+    // One property per line, so the output can be read as is or pasted into a modification
+    println!("{{");
+    println!("  \"class\": {},", serde_json::to_string(instance.class.as_str())?);
+    println!("  \"name\": {},", serde_json::to_string(&instance.name)?);
+    println!("  \"properties\": {{");
+    for (index, (name, value)) in properties.iter().enumerate() {
+        let separator = if index + 1 < properties.len() { "," } else { "" };
+        println!("    {}: {}{}", serde_json::to_string(name)?, serde_json::to_string(value)?, separator);
+    }
+    println!("  }},");
+    println!("  \"attributes\": {}", serde_json::to_string(&attributes)?);
+    println!("}}");
+    Ok(())
+}
+
+fn workspace(place: &WeakDom) -> Result<Ref, Box<dyn Error>> {
+    Ok(find_instance_by_path(place, place.root_ref(), "Workspace").ok_or("Place has no Workspace")?)
+}
+
+/// Print the path and position of each instance on the page
+fn print_positions(place: &WeakDom, found: &Paged<Ref>) {
+    for &id in &found.items {
+        match query::instance_position(place, id) {
+            Some(pos) => println!("{} ({:.1}, {:.1}, {:.1})", instance_path(place, id), pos.x, pos.y, pos.z),
+            None => println!("{}", instance_path(place, id)),
+        }
+    }
+    print_page_footer(found);
+}
+
+/// Print which results were shown and how to get the next page
+fn print_page_footer<T>(page: &Paged<T>) {
+    if page.items.is_empty() {
+        match page.total {
+            0 => println!("0 instances found"),
+            total => println!("{} instances found, none past offset {}", total, page.offset),
+        }
+        return;
+    }
+    println!(
+        "{} instances found, showing {}-{}",
+        page.total, page.offset + 1, page.offset + page.items.len()
+    );
+    if let Some(cursor) = &page.next_cursor {
+        println!("More results: repeat the command with --cursor {}", cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    /// A place with a package in Workspace holding one part
+    fn place_with_package() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace_id = dom.insert(root_ref, InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let package_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Tree"));
+        dom.insert(package_id, InstanceBuilder::new("PackageLink"));
+        dom.insert(package_id, InstanceBuilder::new("Part").with_name("Trunk"));
+        dom
+    }
+
+    #[test]
+    fn commands_refuse_package_edits() {
+        let mut place = place_with_package();
+        let options = ApplyOptions::default();
+        assert!(remove(&mut place, "Workspace/Tree/Trunk", &options).is_err());
+        assert!(move_to(&mut place, "Workspace/Tree/Trunk", "Workspace", &options).is_err());
+        assert!(set_property(&mut place, "Workspace/Tree/Trunk", "Transparency", "Float32", "0.5", &options).is_err());
+        let root_ref = place.root_ref();
+        assert!(find_instance_by_path(&place, root_ref, "Workspace/Tree/Trunk").is_some());
+
+        let options = ApplyOptions { allow_package_edits: true, ..Default::default() };
+        assert!(set_property(&mut place, "Workspace/Tree/Trunk", "Transparency", "Float32", "0.5", &options).is_ok());
+        assert!(remove(&mut place, "Workspace/Tree/Trunk", &options).is_ok());
+        assert!(find_instance_by_path(&place, root_ref, "Workspace/Tree/Trunk").is_none());
+    }
+}
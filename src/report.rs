@@ -0,0 +1,179 @@
+use rbx_dom_weak::types::{ContentType, Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::roblox::{instance_path, is_a, is_service_class};
+
+/// Properties whose values are asset references
+const ASSET_PROPERTIES: [&str; 3] = ["MeshId", "TextureId", "SoundId"];
+
+/// How many referencing instances to print per value before truncating
+const MAX_LISTED_INSTANCES: usize = 10;
+
+/// Every material and asset id in use, keyed by property then value
+pub type AssetReport = BTreeMap<String, BTreeMap<String, Vec<Ref>>>;
+
+/// Collect every Material and asset id used in the place along with the instances using them
+pub fn asset_report(dom: &WeakDom) -> AssetReport {
+    let mut report = AssetReport::new();
+
+    for instance in dom.descendants() {
+        if let Some(Variant::Enum(material)) = instance.properties.get(&"Material".into()) {
+            report.entry("Material".to_string())
+                .or_default()
+                .entry(material_name(material.to_u32()))
+                .or_default()
+                .push(instance.referent());
+        }
+
+        for property in ASSET_PROPERTIES {
+            let asset = match instance.properties.get(&property.into()) {
+                Some(Variant::String(s)) => s.clone(),
+                Some(Variant::ContentId(id)) => id.as_str().to_string(),
+                Some(Variant::Content(content)) => match content.value() {
+                    ContentType::Uri(uri) => uri.clone(),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if asset.is_empty() {
+                continue;
+            }
+            report.entry(property.to_string())
+                .or_default()
+                .entry(asset)
+                .or_default()
+                .push(instance.referent());
+        }
+    }
+
+    report
+}
+
+/// Look up the name of a Material enum value, falling back to the number
+pub fn material_name(value: u32) -> String {
+    rbx_reflection_database::get()
+        .enums
+        .get("Material")
+        .and_then(|material| material.items.iter().find(|(_, &v)| v == value))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Print an asset report with counts and the instances referencing each value
+pub fn print_asset_report(dom: &WeakDom, report: &AssetReport) {
+    if report.is_empty() {
+        println!("No materials or assets in use");
+        return;
+    }
+
+    for (property, values) in report {
+        println!("{} ({} distinct):", property, values.len());
+        for (value, instances) in values {
+            println!("  {} x{}", value, instances.len());
+            for &id in instances.iter().take(MAX_LISTED_INSTANCES) {
+                println!("    {}", instance_path(dom, id));
+            }
+            if instances.len() > MAX_LISTED_INSTANCES {
+                println!("    ... and {} more", instances.len() - MAX_LISTED_INSTANCES);
+            }
+        }
+    }
+}
+
+/// Totals describing what a place is made of
+pub struct PlaceStats {
+    /// Every instance except the DataModel and services
+    pub instances: usize,
+    /// Instance counts by class, most common first, not counting services
+    pub classes: Vec<(String, usize)>,
+    pub scripts: usize,
+    /// Lines of Luau in every script's Source, not counting blank ones
+    pub script_lines: usize,
+    /// Parts that physics will move, Terrain aside
+    pub unanchored: Vec<Ref>,
+    /// Instances with an empty or blank name, which paths can't reach
+    pub unnamed: Vec<Ref>,
+    /// How many levels below the DataModel the deepest instance is, services being 1
+    pub depth: usize,
+    pub deepest: Option<Ref>,
+}
+
+/// Count the instances of a place by class and find what's likely to cause trouble
+pub fn place_stats(dom: &WeakDom) -> PlaceStats {
+    let mut classes: HashMap<&str, usize> = HashMap::new();
+    let mut stats = PlaceStats {
+        instances: 0,
+        classes: Vec::new(),
+        scripts: 0,
+        script_lines: 0,
+        unanchored: Vec::new(),
+        unnamed: Vec::new(),
+        depth: 0,
+        deepest: None,
+    };
+
+    // Children are pushed in reverse so instances are visited in tree order
+    let mut stack: Vec<(Ref, usize)> = dom.root().children().iter().rev().map(|&id| (id, 1)).collect();
+    while let Some((id, depth)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(id) else {
+            continue;
+        };
+        stack.extend(instance.children().iter().rev().map(|&child| (child, depth + 1)));
+        if depth == 1 && is_service_class(&instance.class) {
+            continue;
+        }
+        stats.instances += 1;
+        *classes.entry(instance.class.as_str()).or_default() += 1;
+        if depth > stats.depth {
+            stats.depth = depth;
+            stats.deepest = Some(id);
+        }
+        if instance.name.trim().is_empty() {
+            stats.unnamed.push(id);
+        }
+
+        if is_a(&instance.class, "LuaSourceContainer") {
+            stats.scripts += 1;
+            if let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) {
+                stats.script_lines += source.lines().filter(|line| !line.trim().is_empty()).count();
+            }
+        } else if instance.class != "Terrain" && is_a(&instance.class, "BasePart") {
+            // Parts default to unanchored when the file leaves the property out
+            let anchored = matches!(instance.properties.get(&"Anchored".into()), Some(Variant::Bool(true)));
+            if !anchored {
+                stats.unanchored.push(id);
+            }
+        }
+    }
+
+    stats.classes = classes.into_iter().map(|(class, count)| (class.to_string(), count)).collect();
+    stats.classes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    stats
+}
+
+/// Print place stats, listing the first few instances of each problem
+pub fn print_place_stats(dom: &WeakDom, stats: &PlaceStats) {
+    println!("{} instances of {} classes", stats.instances, stats.classes.len());
+    for (class, count) in &stats.classes {
+        println!("  {:>8}  {}", count, class);
+    }
+    println!("{} scripts, {} lines of Luau", stats.scripts, stats.script_lines);
+    print_instances(dom, &format!("{} unanchored parts", stats.unanchored.len()), &stats.unanchored);
+    print_instances(dom, &format!("{} instances without a name", stats.unnamed.len()), &stats.unnamed);
+    match stats.deepest {
+        Some(id) => println!("Deepest nesting: {} levels, at {}", stats.depth, instance_path(dom, id)),
+        None => println!("Deepest nesting: the place is empty"),
+    }
+}
+
+fn print_instances(dom: &WeakDom, heading: &str, instances: &[Ref]) {
+    println!("{}", heading);
+    for &id in instances.iter().take(MAX_LISTED_INSTANCES) {
+        let class = dom.get_by_ref(id).map_or("", |instance| instance.class.as_str());
+        println!("  {} ({})", instance_path(dom, id), class);
+    }
+    if instances.len() > MAX_LISTED_INSTANCES {
+        println!("  ... and {} more", instances.len() - MAX_LISTED_INSTANCES);
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Cached responses are kept under the working directory, like session transcripts
+pub const CACHE_DIR: &str = ".rbx-mcp/cache";
+
+/// A response saved for a request, `.rbx-mcp/cache/KEY.json`
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    model: String,
+    prompt: String,
+    response: String,
+}
+
+/// Responses to earlier requests, by a hash of everything sent with them, so the same prompt
+/// on the same place doesn't call Gemini again
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new() -> ResponseCache {
+        ResponseCache { dir: PathBuf::from(CACHE_DIR) }
+    }
+
+    /// Key of a request: SHA-256 of its JSON, in hex
+    pub fn key(request: &Value) -> String {
+        let digest = Sha256::digest(request.to_string());
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// The response saved for a key, if there is one
+    pub fn get(&self, key: &str) -> Option<String> {
+        let text = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str::<CacheEntry>(&text).ok().map(|entry| entry.response)
+    }
+
+    /// Save a response. The model and prompt are kept alongside it for anyone looking through the cache.
+    pub fn put(&self, key: &str, model: &str, prompt: &str, response: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let entry = CacheEntry { model: model.to_string(), prompt: prompt.to_string(), response: response.to_string() };
+        std::fs::write(self.path(key), serde_json::to_string_pretty(&entry)?)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        ResponseCache::new()
+    }
+}
+
+/// Delete every cached response, returning how many there were
+pub fn clear(dir: &Path) -> Result<usize, Box<dyn Error>> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
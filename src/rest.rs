@@ -0,0 +1,340 @@
+use rbx_dom_weak::WeakDom;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::reply::{Json, WithStatus};
+use tokio_util::sync::CancellationToken;
+use warp::Filter;
+
+use crate::assets;
+use crate::audit;
+use crate::config::Config;
+use crate::context::{self, ContextOptions};
+use crate::error::RbxMcpError;
+use crate::gemini_api::GeminiClient;
+use crate::journal;
+use crate::lock::PlaceLock;
+use crate::prompt::{self, TemplateVars};
+use crate::query::{self, Page, Paged, Region};
+use crate::roblox::{self, find_instance_by_path, instance_path, ApplyOptions, Modification};
+use crate::upload;
+use crate::warnings::ApplyWarning;
+
+/// OpenAPI description of the REST API, served at /openapi.json
+pub const OPENAPI_SPEC: &str = include_str!("openapi.json");
+
+/// Everything the handlers share
+pub struct RestState {
+    pub config: Config,
+    pub apply_options: ApplyOptions,
+    /// None when no API key was given, in which case /generate is unavailable
+    pub client: Option<GeminiClient>,
+    /// Cancelled to stop the server, which also cancels generations in flight
+    pub shutdown: CancellationToken,
+    /// Canonical directory every place and output a request names must be inside
+    pub root: PathBuf,
+}
+
+impl RestState {
+    /// Resolve a path from a request against the root, refusing one that ends up outside it.
+    /// Symlinks and .. are followed first. A file that doesn't exist yet is checked by its directory.
+    fn resolve(&self, path: &Path) -> Result<PathBuf, RbxMcpError> {
+        let joined = self.root.join(path);
+        let outside = || RbxMcpError::OutsideRoot { path: path.to_path_buf(), root: self.root.clone() };
+        let resolved = match joined.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                let file_name = joined.file_name().ok_or_else(outside)?;
+                joined.parent().ok_or_else(outside)?.canonicalize()?.join(file_name)
+            }
+        };
+        if !resolved.starts_with(&self.root) {
+            return Err(outside());
+        }
+        Ok(resolved)
+    }
+}
+
+#[derive(Deserialize)]
+struct ParseRequest {
+    place: PathBuf,
+}
+
+#[derive(Serialize)]
+struct ParseResponse {
+    instances: usize,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct NearQuery {
+    path: String,
+    radius: f32,
+}
+
+#[derive(Deserialize)]
+struct QueryRequest {
+    place: PathBuf,
+    region: Option<Region>,
+    near: Option<NearQuery>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct QueryResult {
+    path: String,
+    position: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct ApplyRequest {
+    place: PathBuf,
+    modification: Modification,
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct ApplyResponse {
+    output: PathBuf,
+    change_id: String,
+    warnings: Vec<ApplyWarning>,
+}
+
+#[derive(Deserialize)]
+struct GenerateRequest {
+    place: PathBuf,
+    prompt: String,
+    context: Option<String>,
+}
+
+/// Serve the REST API until the shutdown token is cancelled
+pub async fn serve(addr: SocketAddr, state: RestState) {
+    let shutdown = state.shutdown.clone();
+    let root = state.root.clone();
+    let state = Arc::new(state);
+    let with_state = warp::any().map(move || state.clone());
+
+    let openapi = warp::path("openapi.json")
+        .and(warp::get())
+        .map(|| warp::reply::with_header(OPENAPI_SPEC, "Content-Type", "application/json"));
+    let parse = warp::path("parse")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state.clone())
+        .and_then(handle_parse);
+    let query = warp::path("query")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state.clone())
+        .and_then(handle_query);
+    let apply = warp::path("apply")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state.clone())
+        .and_then(handle_apply);
+    let generate = warp::path("generate")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state)
+        .and_then(handle_generate);
+
+    let routes = openapi.or(parse).or(query).or(apply).or(generate);
+    info!("REST API listening on http://{} (spec at /openapi.json), serving places under {}", addr, root.display());
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, async move { shutdown.cancelled().await });
+    server.await;
+    info!("REST API stopped");
+}
+
+async fn handle_parse(request: ParseRequest, state: Arc<RestState>) -> Result<WithStatus<Json>, Infallible> {
+    Ok(respond(blocking(move || {
+        let place = roblox::parse_roblox_file(state.resolve(&request.place)?)?;
+        Ok(ParseResponse {
+            instances: place.descendants().count(),
+            description: describe_place(&place, &state)?,
+        })
+    }).await))
+}
+
+async fn handle_query(request: QueryRequest, state: Arc<RestState>) -> Result<WithStatus<Json>, Infallible> {
+    Ok(respond(blocking(move || -> Result<Paged<QueryResult>, RbxMcpError> {
+        let place = roblox::parse_roblox_file(state.resolve(&request.place)?)?;
+        let workspace_id = find_instance_by_path(&place, place.root_ref(), "Workspace")
+            .ok_or_else(|| RbxMcpError::InvalidOperation("Place has no Workspace".to_string()))?;
+
+        let found = match (&request.region, &request.near) {
+            (Some(region), None) => query::instances_in_region(&place, workspace_id, region),
+            (None, Some(near)) => query::instances_near(&place, workspace_id, &near.path, near.radius)?,
+            _ => return Err(RbxMcpError::InvalidOperation("Give exactly one of region or near".to_string())),
+        };
+        let results: Vec<QueryResult> = found.iter()
+            .map(|&id| QueryResult {
+                path: instance_path(&place, id),
+                position: query::instance_position(&place, id).map(|p| [p.x, p.y, p.z]),
+            })
+            .collect();
+
+        let limit = request.limit.unwrap_or(query::DEFAULT_PAGE_LIMIT).max(1);
+        let page = match &request.cursor {
+            Some(cursor) => Page::from_cursor(cursor, limit).map_err(RbxMcpError::InvalidOperation)?,
+            None => Page { offset: 0, limit },
+        };
+        Ok(query::paginate(&results, page))
+    }).await))
+}
+
+async fn handle_apply(mut request: ApplyRequest, state: Arc<RestState>) -> Result<WithStatus<Json>, Infallible> {
+    let paths = state.resolve(&request.place).and_then(|place_path| {
+        let output_path = match &request.output {
+            Some(output) => state.resolve(output)?,
+            None => place_path.clone(),
+        };
+        Ok((place_path, output_path))
+    });
+    let (place_path, output_path) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return Ok(respond::<()>(Err(e))),
+    };
+
+    // Uploads can't be taken back, so they wait until the modification is known to apply
+    if upload::has_inline_data(&request.modification) {
+        let checked = blocking({
+            let (place_path, modification, state) = (place_path.clone(), request.modification.clone(), state.clone());
+            move || {
+                let place = roblox::parse_roblox_file(&place_path)?;
+                roblox::check_modification(&place, &modification, place.root_ref(), &state.apply_options)
+            }
+        }).await;
+        if let Err(e) = checked {
+            return Ok(respond::<()>(Err(e)));
+        }
+        if let Err(e) = upload::upload_modification(&mut request.modification, &state.config.upload).await {
+            return Ok(respond::<()>(Err(e)));
+        }
+    }
+    Ok(respond(blocking(move || {
+        // Held until the response, so a concurrent apply to the same place gets a conflict
+        let _lock = PlaceLock::acquire(&output_path)?;
+        let mut place = roblox::parse_roblox_file(&place_path)?;
+        let root_ref = place.root_ref();
+        let change_id = journal::new_change_id();
+        let options = ApplyOptions { change_id: Some(change_id.clone()), ..state.apply_options.clone() };
+        let before = roblox::snapshot(&place);
+        let report = roblox::json_to_weakdom(&mut place, &request.modification, root_ref, &options)?;
+        roblox::write_roblox_file(&output_path, &place)?;
+        let description = format!("Applied over REST ({})", journal::describe_modification(&request.modification));
+        if let Err(e) = journal::record(&output_path, Some(&change_id), &description) {
+            warn!("Error writing journal: {}", e);
+        }
+        if let Err(e) = audit::record_applied(&output_path, &change_id, &request.modification, &before, &place, &report) {
+            warn!("Error writing audit log: {}", e);
+        }
+        let output = request.output.unwrap_or(request.place);
+        Ok(ApplyResponse { output, change_id, warnings: report.warnings })
+    }).await))
+}
+
+async fn handle_generate(request: GenerateRequest, state: Arc<RestState>) -> Result<WithStatus<Json>, Infallible> {
+    let Some(client) = &state.client else {
+        return Ok(error_reply(StatusCode::SERVICE_UNAVAILABLE, "Server was started without a Gemini API key"));
+    };
+    let described = blocking({
+        let (place_path, state) = (request.place.clone(), state.clone());
+        move || describe_place(&roblox::parse_roblox_file(state.resolve(&place_path)?)?, &state)
+    }).await;
+    let dom_context = match described {
+        Ok(description) => description,
+        Err(e) => return Ok(respond::<()>(Err(e))),
+    };
+    let place_name = prompt::place_name(&request.place);
+    let place_vars = TemplateVars { place_name: &place_name, dom_summary: &dom_context };
+    let cancel = state.shutdown.child_token();
+    let generation = client.generate_modification(&request.prompt, &place_vars, request.context, &[], &[], &cancel).await;
+    Ok(respond(generation.map(|generation| generation.modification)))
+}
+
+/// Run a handler's file and DOM work on the blocking pool, so a big place doesn't stall other requests
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, RbxMcpError> + Send + 'static,
+) -> Result<T, RbxMcpError> {
+    tokio::task::spawn_blocking(work).await.unwrap_or_else(|e| Err(RbxMcpError::IoError(std::io::Error::other(e))))
+}
+
+/// Describe places the same way the interactive mode does, without package contents
+fn describe_place(place: &WeakDom, state: &RestState) -> Result<String, RbxMcpError> {
+    let options = ContextOptions {
+        include_packages: false,
+        filters: state.config.context_filters.clone(),
+        viewpoints: state.config.viewpoints.clone(),
+        assets: state.config.asset_library.as_deref().map(assets::list_assets).unwrap_or_default(),
+        max_depth: None,
+        max_children: None,
+    };
+    context::describe_place_within_budget(place, &options, &state.config.context_budget).map_err(RbxMcpError::InvalidOperation)
+}
+
+/// Turn a handler result into a JSON reply, with an error status picked from the error kind
+fn respond<T: Serialize>(result: Result<T, RbxMcpError>) -> WithStatus<Json> {
+    match result {
+        Ok(value) => warp::reply::with_status(warp::reply::json(&value), StatusCode::OK),
+        Err(e) => {
+            let status = match e {
+                RbxMcpError::PathNotFound { .. } | RbxMcpError::InstanceNotFound(_) => StatusCode::NOT_FOUND,
+                RbxMcpError::IoError(ref io) if io.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                RbxMcpError::LlmError(_) => StatusCode::BAD_GATEWAY,
+                RbxMcpError::Blocked(_) | RbxMcpError::TooManyInstances { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+                RbxMcpError::Cancelled => StatusCode::SERVICE_UNAVAILABLE,
+                RbxMcpError::ChangedOnDisk(_) | RbxMcpError::Locked { .. } => StatusCode::CONFLICT,
+                RbxMcpError::OutsideRoot { .. } => StatusCode::FORBIDDEN,
+                RbxMcpError::IoError(_) | RbxMcpError::SerializeError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            let message = match e {
+                RbxMcpError::TooManyInstances { .. } => format!("{}. The limit is the server's --max-new-instances", e),
+                _ => e.to_string(),
+            };
+            error_reply(status, &message)
+        }
+    }
+}
+
+fn error_reply(status: StatusCode, message: &str) -> WithStatus<Json> {
+    warp::reply::with_status(warp::reply::json(&json!({ "error": message })), status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_root(root: &Path) -> RestState {
+        RestState {
+            config: Config::default(),
+            apply_options: ApplyOptions::default(),
+            client: None,
+            shutdown: CancellationToken::new(),
+            root: root.canonicalize().unwrap(),
+        }
+    }
+
+    #[test]
+    fn paths_outside_the_root_are_refused() {
+        let root = std::env::temp_dir().join(format!("rbx-mcp-rest-root-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("places")).unwrap();
+        std::fs::write(root.join("places/game.rbxlx"), "").unwrap();
+        let state = state_with_root(&root);
+
+        assert_eq!(state.resolve(Path::new("places/game.rbxlx")).unwrap(), state.root.join("places/game.rbxlx"));
+        // Outputs that don't exist yet are checked by their directory
+        assert_eq!(state.resolve(Path::new("places/new.rbxlx")).unwrap(), state.root.join("places/new.rbxlx"));
+        assert!(matches!(state.resolve(Path::new("../game.rbxlx")), Err(RbxMcpError::OutsideRoot { .. })));
+        assert!(matches!(state.resolve(Path::new("places/../../game.rbxlx")), Err(RbxMcpError::OutsideRoot { .. })));
+        assert!(matches!(state.resolve(&std::env::temp_dir().join("game.rbxlx")), Err(RbxMcpError::OutsideRoot { .. })));
+        assert!(state.resolve(Path::new("places/..")).is_ok());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
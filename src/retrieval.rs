@@ -0,0 +1,122 @@
+use rbx_dom_weak::WeakDom;
+use serde::Deserialize;
+use std::fmt::Write;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::context::{self, ContextBudget, ContextOptions};
+use crate::error::RbxMcpError;
+use crate::gemini_api::{ConversationTurn, GeminiClient, ImageAttachment};
+use crate::prompt::TemplateVars;
+use crate::roblox::find_instance_by_path;
+
+/// Rounds of expand requests before the change is asked for with what's been shown
+const MAX_ROUNDS: usize = 3;
+/// Depth the place is described to before anything is expanded. Services are depth 0.
+const SHALLOW_DEPTH: usize = 1;
+/// Levels described under an expanded instance, deeper ones can be expanded in the next round
+const EXPAND_DEPTH: usize = 3;
+/// Children described for each instance, in the shallow tree and in expanded subtrees
+const MAX_CHILDREN: usize = 50;
+
+/// A response asking to see more of the place before making a change
+#[derive(Deserialize)]
+pub struct ExpandRequest {
+    /// Paths of the instances to describe, empty when Gemini has seen enough
+    pub expand: Vec<String>,
+}
+
+/// Describes a place to Gemini a few levels at a time, expanding the parts it asks for
+pub struct Retrieval<'a> {
+    pub client: &'a GeminiClient,
+    pub place: &'a WeakDom,
+    pub options: &'a ContextOptions,
+    /// The shallow tree and the expanded subtrees together stay within its max_tokens
+    pub budget: &'a ContextBudget,
+}
+
+impl Retrieval<'_> {
+    /// Send a shallow tree of the place and let Gemini ask for the subtrees it needs with
+    /// `{"expand": [paths]}`, for a few rounds. Returns the shallow tree with those subtrees,
+    /// to describe the place in the request that makes the change.
+    pub async fn gather(
+        &self,
+        prompt: &str,
+        place_name: &str,
+        context: Option<String>,
+        images: &[ImageAttachment],
+        history: &[ConversationTurn],
+        cancel: &CancellationToken,
+    ) -> Result<String, RbxMcpError> {
+        let shallow = format!(
+            "{}\n{}",
+            context::describe_overview(self.place),
+            context::describe_place(self.place, &ContextOptions {
+                max_depth: Some(SHALLOW_DEPTH),
+                max_children: Some(MAX_CHILDREN),
+                ..self.options.clone()
+            })
+        );
+        let mut tokens = context::estimate_tokens(&shallow);
+        let mut expanded: Vec<(String, String)> = Vec::new();
+        let expand_prompt = expand_prompt(prompt);
+
+        for _ in 0..MAX_ROUNDS {
+            let description = describe_expanded(&shallow, &expanded);
+            let place_vars = TemplateVars { place_name, dom_summary: &description };
+            let paths = self.client.generate_expansion(&expand_prompt, &place_vars, context.clone(), images, history, cancel).await?;
+            let new_paths: Vec<String> = paths.into_iter()
+                .filter(|path| !expanded.iter().any(|(expanded_path, _)| expanded_path == path))
+                .collect();
+            if new_paths.is_empty() {
+                break;
+            }
+            info!("Gemini asked to see {}", new_paths.join(", "));
+
+            for path in new_paths {
+                let subtree = self.describe_subtree(&path);
+                let subtree_tokens = context::estimate_tokens(&subtree);
+                if tokens + subtree_tokens > self.budget.max_tokens {
+                    warn!("Not expanding {}, it would go over the context budget of {} tokens", path, self.budget.max_tokens);
+                    expanded.push((path, "[left out, the description is at its budget]\n".to_string()));
+                    continue;
+                }
+                tokens += subtree_tokens;
+                expanded.push((path, subtree));
+            }
+        }
+        Ok(describe_expanded(&shallow, &expanded))
+    }
+
+    fn describe_subtree(&self, path: &str) -> String {
+        match find_instance_by_path(self.place, self.place.root_ref(), path) {
+            Some(id) => context::describe_subtree(self.place, id, &ContextOptions {
+                max_depth: Some(EXPAND_DEPTH),
+                max_children: Some(MAX_CHILDREN),
+                ..self.options.clone()
+            }),
+            None => "[no instance at this path]\n".to_string(),
+        }
+    }
+}
+
+/// The prompt for the rounds before the change, asking which instances to expand
+fn expand_prompt(prompt: &str) -> String {
+    format!(
+        "Don't make this change yet, first decide what you need to see of the place to make it: {}\n\
+         The place is only described a few levels deep, [N descendants: ...] stands for the contents of an instance. \
+         RESPOND ONLY WITH {{\"expand\": [\"Workspace/House\"]}}, listing the paths of the instances whose contents you need, \
+         or {{\"expand\": []}} when you've seen enough. Expanded instances are described a few levels deep and can be expanded further. \
+         The place",
+        prompt
+    )
+}
+
+/// The shallow tree followed by each expanded subtree
+fn describe_expanded(shallow: &str, expanded: &[(String, String)]) -> String {
+    let mut out = shallow.to_string();
+    for (path, subtree) in expanded {
+        let _ = write!(out, "\nContents of {}:\n{}", path, subtree);
+    }
+    out
+}
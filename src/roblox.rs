@@ -1,257 +1,1609 @@
-use rbx_dom_weak::types::{BrickColor, CFrame, Color3, Enum, Matrix3, Ref, UDim, UDim2, Variant, Vector3};
-use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_dom_weak::types::{Attributes, Ref, Variant, Vector3};
+use rbx_dom_weak::{Instance, InstanceBuilder, WeakDom};
+use rbx_reflection::ClassTag;
+use rayon::prelude::*;
+use regex::Regex;
 use serde_json::Value;
+use tracing::{debug, debug_span, info, trace};
 use serde::{Serialize, Deserialize};
-use std::error::Error;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use std::path::Path;
-use std::collections::HashMap;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-#[derive(Deserialize, Serialize)]
+use crate::assets;
+use crate::budget;
+use crate::clean::{self, Protected};
+use crate::defaults::{self, DefaultProperties};
+use crate::error::RbxMcpError;
+use crate::lock;
+use crate::luau;
+use crate::property::{self, PropertyValue};
+use crate::query::{self, Region};
+use crate::terrain::{self, Voxel};
+use crate::tidy;
+use crate::upload;
+use crate::validation;
+use crate::warnings::{suggestion_hint, ApplyWarning};
+
+#[derive(Deserialize, Serialize, Clone, Default)]
 pub struct Modification {
     pub add: Vec<JsonInstance>,
     #[serde(default)]
-    pub subtract: Vec<String>,  // Paths to instances that should be removed
+    pub subtract: Vec<SubtractTarget>,  // Instances that should be removed
+    #[serde(default)]
+    pub rename: Vec<RenameOperation>,
+    #[serde(default, rename = "move")]
+    pub moves: Vec<MoveOperation>,
+    #[serde(default)]
+    pub clone: Vec<CloneOperation>,
+    /// Models from .rbxmx files, like prefabs from the asset library
+    #[serde(default)]
+    pub insert: Vec<InsertOperation>,
+    /// Properties to set on existing instances, after everything else is added
+    #[serde(default)]
+    pub set: Vec<SetOperation>,
+    /// Regions of Smooth Terrain to fill with a material
+    #[serde(default)]
+    pub terrain_fill: Vec<TerrainFill>,
+    /// Regions of Smooth Terrain to empty, before the fills
+    #[serde(default)]
+    pub terrain_clear: Vec<TerrainClear>,
+}
+
+/// Fill a box of Smooth Terrain with a material, like Water for a lake
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TerrainFill {
+    pub region: Region,
+    pub material: String,
+}
+
+/// Empty a box of Smooth Terrain
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TerrainClear {
+    pub region: Region,
+}
+
+/// Give an existing instance a new name
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RenameOperation {
+    pub path: String,
+    pub new_name: String,
+}
+
+/// Most copies one clone operation may make
+pub const MAX_CLONE_COUNT: usize = 1000;
+
+/// Deep copy an existing instance, optionally several times with each copy shifted further by offset
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CloneOperation {
+    pub source_path: String,
+    pub target_parent: String,
+    #[serde(default = "default_clone_count")]
+    pub count: usize,
+    #[serde(default)]
+    pub offset: Option<[f32; 3]>,
+}
+
+fn default_clone_count() -> usize {
+    1
+}
+
+/// Set properties of existing instances, each given as `{"type", "value"}`
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SetOperation {
+    #[serde(flatten)]
+    pub target: SetTarget,
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+/// What a set changes: an instance by path, or every Workspace instance in a region or near an instance,
+/// picked the same way as for subtract
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum SetTarget {
+    Path { path: String },
+    Region { region: Region },
+    Near { near: String, radius: f32 },
+}
+
+impl fmt::Display for SetTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetTarget::Path { path } => write!(f, "'{}'", path),
+            SetTarget::Region { region } => write!(f, "instances in region {:?} to {:?}", region.min, region.max),
+            SetTarget::Near { near, radius } => write!(f, "instances within {} studs of '{}'", radius, near),
+        }
+    }
+}
+
+/// Insert the instances of a model file, by name from the asset library or by path
+#[derive(Deserialize, Serialize, Clone)]
+pub struct InsertOperation {
+    pub asset: String,
+    #[serde(default)]
+    pub target_parent: Option<String>,
+    /// New name for the inserted instance
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Where the bottom middle of the model goes, instead of where it was saved
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+}
+
+/// Reparent an existing instance, keeping its properties and descendants
+#[derive(Deserialize, Serialize, Clone)]
+pub struct MoveOperation {
+    pub path: String,
+    pub new_parent: String,
+}
+
+/// Something to remove: a path or glob, every Workspace instance in a region,
+/// or every instance of a class or with a tag
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum SubtractTarget {
+    Path(String),
+    Region { region: Region },
+    Near { near: String, radius: f32 },
+    Filter(InstanceFilter),
+}
+
+/// Every instance of a class (or a subclass of it), with a tag, or both, under an instance
+/// or anywhere in the place
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "RawInstanceFilter")]
+pub struct InstanceFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub under: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawInstanceFilter {
+    class: Option<String>,
+    tag: Option<String>,
+    under: Option<String>,
+}
+
+impl TryFrom<RawInstanceFilter> for InstanceFilter {
+    type Error = String;
+
+    fn try_from(raw: RawInstanceFilter) -> Result<Self, Self::Error> {
+        if raw.class.is_none() && raw.tag.is_none() {
+            return Err("a class or tag filter needs a class, a tag or both".to_string());
+        }
+        Ok(InstanceFilter { class: raw.class, tag: raw.tag, under: raw.under })
+    }
+}
+
+impl fmt::Display for InstanceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.class, &self.tag) {
+            (Some(class), Some(tag)) => write!(f, "{} instances tagged '{}'", class, tag)?,
+            (Some(class), None) => write!(f, "{} instances", class)?,
+            (None, tag) => write!(f, "instances tagged '{}'", tag.as_deref().unwrap_or_default())?,
+        }
+        if let Some(under) = &self.under {
+            write!(f, " under '{}'", under)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "RawInstance")]
 pub struct JsonInstance {
     pub class: String,
     pub name: String,
-    pub properties: HashMap<String, JsonProperty>,
+    /// Each given as `{"type", "value"}`, or as just the value with the type looked up from the class
+    pub properties: HashMap<String, PropertyValue>,
+    #[serde(default)]
+    pub attributes: HashMap<String, Value>,  // Custom attributes, typed from their JSON value
     #[serde(default)]
     pub children: Vec<JsonInstance>,
     #[serde(default)]
     pub target_parent: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct JsonProperty {
-    #[serde(rename = "type")]
-    pub type_name: String,
-    pub value: Value,
+/// An instance as it is in JSON, with properties that may be missing their types
+#[derive(Deserialize)]
+struct RawInstance {
+    class: String,
+    name: String,
+    properties: HashMap<String, Value>,
+    #[serde(default)]
+    attributes: HashMap<String, Value>,
+    #[serde(default)]
+    children: Vec<JsonInstance>,
+    #[serde(default)]
+    target_parent: Option<String>,
+}
+
+impl TryFrom<RawInstance> for JsonInstance {
+    type Error = String;
+
+    fn try_from(raw: RawInstance) -> Result<Self, Self::Error> {
+        let mut properties = HashMap::new();
+        for (name, value) in raw.properties {
+            let property = PropertyValue::from_json(&raw.class, &name, value)
+                .map_err(|e| format!("{}.{}: {}", raw.name, name, e))?;
+            properties.insert(name, property);
+        }
+        Ok(JsonInstance {
+            class: raw.class,
+            name: raw.name,
+            properties,
+            attributes: raw.attributes,
+            children: raw.children,
+            target_parent: raw.target_parent,
+        })
+    }
 }
 
 /// Parse a Roblox XML file into a WeakDom
-pub fn parse_roblox_file(path: impl AsRef<Path>) -> Result<WeakDom, Box<dyn Error>> {
-    let file = BufReader::new(File::open(path)?);
-    let place = rbx_xml::from_reader_default(file)?;
+pub fn parse_roblox_file(path: impl AsRef<Path>) -> Result<WeakDom, RbxMcpError> {
+    let path = path.as_ref();
+    let contents = std::fs::read(path)?;
+    let place = rbx_xml::from_reader_default(contents.as_slice())?;
+    lock::record_checksum(path, &contents);
     Ok(place)
 }
 
 /// Parse a Roblox XML string into a WeakDom
-pub fn parse_roblox_str(xml: &str) -> Result<WeakDom, Box<dyn Error>> {
+pub fn parse_roblox_str(xml: &str) -> Result<WeakDom, RbxMcpError> {
     let place = rbx_xml::from_str_default(xml)?;
     Ok(place)
 }
 
+/// Options controlling how a modification is applied
+#[derive(Default, Clone)]
+pub struct ApplyOptions {
+    /// Allow changes inside packages, which are lost or desync when the package updates
+    pub allow_package_edits: bool,
+    /// Directory of .rbxmx models that inserts can name without a path
+    pub asset_library: Option<PathBuf>,
+    /// Tag every instance the modification creates, moves or renames with this ID
+    /// in the CHANGE_ID_ATTRIBUTE attribute, so `blame` can trace it back to its prompt
+    pub change_id: Option<String>,
+    /// Format the Source of added scripts with StyLua
+    pub format_scripts: bool,
+    /// Reject the whole modification when a property has a type that can't be converted,
+    /// instead of leaving the property out
+    pub strict: bool,
+    /// Give added instances every property they're missing, with its default from the reflection database
+    pub fill_defaults: bool,
+    /// Properties added instances get when they don't set them, by class, from the config
+    pub default_properties: DefaultProperties,
+    /// Remove the Folders and Models that removals and moves leave empty
+    pub auto_clean: bool,
+    /// Paths that cleaning up never removes, from the config
+    pub clean_protected: Vec<String>,
+    /// Refuse modifications that create more instances than this, counting clones,
+    /// so a runaway response can't bloat the place
+    pub max_new_instances: Option<usize>,
+    /// Round the positions and sizes of created parts to this grid, in studs
+    pub snap: Option<f32>,
+    /// Anchor every created part, so builds don't fall apart when the game runs
+    pub auto_anchor: bool,
+}
+
+/// What applying a modification did
+pub struct ApplyReport {
+    /// Non-fatal problems found along the way
+    pub warnings: Vec<ApplyWarning>,
+    /// Instances whose XML changed: everything created, moved, renamed or tagged,
+    /// and the former parents of removed and moved instances
+    pub changed: Vec<Ref>,
+    pub counts: ChangeCounts,
+}
+
+/// How many instances a change added and removed, and which scripts it created or edited
+#[derive(Debug, Clone, Default)]
+pub struct ChangeCounts {
+    /// Instances created, counting everything under them
+    pub added: usize,
+    /// Instances destroyed, counting everything under them
+    pub removed: usize,
+    /// Paths of the scripts created or edited
+    pub scripts: Vec<String>,
+    /// Roots of the subtrees created
+    pub created: Vec<Ref>,
+}
+
+impl ChangeCounts {
+    /// Count a subtree that was just added
+    pub fn record_added(&mut self, dom: &WeakDom, root_id: Ref) {
+        if dom.get_by_ref(root_id).is_none() {
+            return;
+        }
+        self.created.push(root_id);
+        for instance in dom.descendants_of(root_id) {
+            self.added += 1;
+            if is_a(&instance.class, "LuaSourceContainer") {
+                self.scripts.push(instance_path(dom, instance.referent()));
+            }
+        }
+    }
+
+    /// Count a subtree that is about to be removed
+    pub fn record_removed(&mut self, dom: &WeakDom, root_id: Ref) {
+        if dom.get_by_ref(root_id).is_none() {
+            return;
+        }
+        self.removed += dom.descendants_of(root_id).count();
+    }
+}
+
+/// Attribute holding the ID of the change that last created or modified an instance
+pub const CHANGE_ID_ATTRIBUTE: &str = "RbxMcpChangeId";
+
+/// Check classes and property values for every added instance, with the defaults options give them,
+/// and that the modification doesn't create more instances than options allow. json_to_weakdom does
+/// this too; it's run on its own before anything is done on a modification's behalf, like uploading its inline data.
+pub fn check_modification(dom: &WeakDom, json: &Modification, parent_id: Ref, options: &ApplyOptions) -> Result<(), RbxMcpError> {
+    let filled = with_defaults(json, options);
+    check_filled(dom, filled.as_ref().unwrap_or(json), parent_id, options)
+}
+
+/// A copy of the modification with missing properties filled in, if options give any defaults
+fn with_defaults(json: &Modification, options: &ApplyOptions) -> Option<Modification> {
+    if !options.fill_defaults && options.default_properties.is_empty() {
+        return None;
+    }
+    let mut copy = json.clone();
+    defaults::fill(&mut copy, &options.default_properties, options.fill_defaults);
+    Some(copy)
+}
+
+/// Validate a modification whose defaults are already filled in, in parallel for big ones, and check its size
+fn check_filled(dom: &WeakDom, json: &Modification, parent_id: Ref, options: &ApplyOptions) -> Result<(), RbxMcpError> {
+    let problems = validation::validate_modification(json, options.strict);
+    if !problems.is_empty() {
+        return Err(RbxMcpError::InvalidModification(problems));
+    }
+
+    if let Some(limit) = options.max_new_instances {
+        // A count too big to add up is over any limit
+        let count = budget::count_new_instances(dom, parent_id, json, options.asset_library.as_deref()).unwrap_or(usize::MAX);
+        if count > limit {
+            return Err(RbxMcpError::TooManyInstances { count, limit });
+        }
+    }
+    Ok(())
+}
+
 /// Add instances from JSON to the Roblox place
 /// parent_id should be the DataModel reference for proper structure
-pub fn json_to_weakdom(dom: &mut WeakDom, json: &Modification, parent_id: Ref) -> Result<(), Box<dyn Error>> {
-    println!("Adding instances to Roblox place...");
-    
-    // Maps service names to their refs
-    let mut service_refs: HashMap<String, Ref> = HashMap::new();
+/// Returns the non-fatal problems found along the way and the instances that changed
+pub fn json_to_weakdom(dom: &mut WeakDom, json: &Modification, parent_id: Ref, options: &ApplyOptions) -> Result<ApplyReport, RbxMcpError> {
+    let _span = debug_span!("apply").entered();
+    debug!("Adding instances to Roblox place...");
+    let mut warnings = Vec::new();
+    let mut changed = Vec::new();
+    let mut counts = ChangeCounts::default();
+    // Instances created, moved or renamed, tagged with the change ID at the end
+    let mut touched = Vec::new();
+
+    // Paths are resolved through one index until the removals are done, since a long
+    // subtract list would otherwise scan the same children for every path
+    let mut index = PathIndex::new();
+
+    // Refuse to touch package contents before anything is changed
+    if !options.allow_package_edits {
+        check_package_edits(dom, &mut index, parent_id, json)?;
+    }
+
+    // Missing properties are filled in before validation, so the defaults are checked too
+    let filled = with_defaults(json, options);
+    let json = filled.as_ref().unwrap_or(json);
+    check_filled(dom, json, parent_id, options)?;
+    // Inline data would end up in the place as it is, so it has to be uploaded first
+    if upload::has_inline_data(json) {
+        return Err(RbxMcpError::InvalidModification(vec![
+            "The modification holds inline data that hasn't been uploaded through Open Cloud".to_string(),
+        ]));
+    }
+
+    // Format scripts once they are known to parse
+    let formatted;
+    let json = if options.format_scripts {
+        let mut copy = json.clone();
+        warnings.extend(luau::format_modification(&mut copy));
+        formatted = copy;
+        &formatted
+    } else {
+        json
+    };
     
+    // Convert every added instance's properties before anything changes, in parallel for big modifications
+    let parallel = json.add.iter().map(subtree_size).sum::<usize>() >= validation::PARALLEL_THRESHOLD;
+    let prepared: Vec<PreparedInstance> = if parallel {
+        json.add.par_iter().map(|instance| prepare_instance(instance, true)).collect::<Result<_, _>>()?
+    } else {
+        json.add.iter().map(|instance| prepare_instance(instance, false)).collect::<Result<_, _>>()?
+    };
+
     // Get the DataModel root
     let data_model_id = parent_id;
     
-    // Find or create Workspace
+    // Find or create Workspace, the default parent. Other services are created when targeted.
     let workspace_id = find_or_create_service(dom, data_model_id, "Workspace")?;
-    service_refs.insert("Workspace".to_string(), workspace_id);
-    
-    // Define common Roblox services
-    let common_services = [
-        "StarterPlayer", "Lighting", "ReplicatedStorage", "ServerScriptService", 
-        "ServerStorage", "SoundService", "Chat", "Teams"
-    ];
-    
-    // Find or create common services
-    for service_name in common_services.iter() {
-        let service_id = find_or_create_service(dom, data_model_id, service_name)?;
-        service_refs.insert(service_name.to_string(), service_id);
-    }
-    
-    // Special case: Find or create StarterPlayerScripts under StarterPlayer
-    // First, get the ref without keeping a borrow on service_refs
-    let starter_player_id_opt = service_refs.get("StarterPlayer").copied();
     
-    if let Some(starter_player_id) = starter_player_id_opt {
-        let starter_player_scripts_id = find_or_create_service(dom, starter_player_id, "StarterPlayerScripts")?;
-        service_refs.insert("StarterPlayerScripts".to_string(), starter_player_scripts_id);
-        
-        let starter_character_scripts_id = find_or_create_service(dom, starter_player_id, "StarterCharacterScripts")?;
-        service_refs.insert("StarterCharacterScripts".to_string(), starter_character_scripts_id);
+    // Resolve paths before anything changes, so they refer to the original tree
+    let add_parents: Vec<Option<Ref>> = json.add.iter()
+        .map(|instance| match &instance.target_parent {
+            Some(target) => resolve_parent(dom, data_model_id, target),
+            None => Ok(Some(workspace_id)),
+        })
+        .collect::<Result<_, _>>()?;
+    let renames: Vec<(Option<Ref>, &RenameOperation)> = json.rename.iter()
+        .map(|op| (index.find(dom, data_model_id, &op.path), op))
+        .collect();
+    let moves: Vec<(Option<Ref>, Option<Ref>, &MoveOperation)> = json.moves.iter()
+        .map(|op| (
+            index.find(dom, data_model_id, &op.path),
+            resolve_parent(dom, data_model_id, &op.new_parent).ok().flatten(),
+            op,
+        ))
+        .collect();
+    let clones: Vec<(Option<Ref>, Option<Ref>, &CloneOperation)> = json.clone.iter()
+        .map(|op| (
+            index.find(dom, data_model_id, &op.source_path),
+            resolve_parent(dom, data_model_id, &op.target_parent).ok().flatten(),
+            op,
+        ))
+        .collect();
+    // Model files are read up front too, so one that's missing only skips its insert
+    let inserts: Vec<(Result<WeakDom, String>, Option<Ref>, &InsertOperation)> = json.insert.iter()
+        .map(|op| (
+            assets::read_model(&assets::resolve_asset(options.asset_library.as_deref(), &op.asset)),
+            match &op.target_parent {
+                Some(target) => resolve_parent(dom, data_model_id, target).ok().flatten(),
+                None => Some(workspace_id),
+            },
+            op,
+        ))
+        .collect();
+
+    // Terrain is edited on a decoded copy, so a place without Terrain or a region that's too big
+    // is refused before anything else changes
+    if !json.terrain_clear.is_empty() || !json.terrain_fill.is_empty() {
+        changed.push(edit_terrain(dom, json)?);
     }
-    
+
     // Process all subtract operations first
     if !json.subtract.is_empty() {
-        println!("Processing {} removal operations...", json.subtract.len());
-        for path in &json.subtract {
-            println!("Trying to remove instance at path: {}", path);
-            if let Some(instance_id) = find_instance_by_path(dom, data_model_id, path) {
+        debug!("Processing {} removal operations...", json.subtract.len());
+        for target in &json.subtract {
+            let path = match target {
+                SubtractTarget::Path(path) if is_glob(path) => {
+                    let mut found = index.find_all(dom, data_model_id, path);
+                    if !options.allow_package_edits {
+                        found.retain(|&id| index.enclosing_package(dom, id).is_none());
+                    }
+                    if found.is_empty() {
+                        warnings.push(path_not_found(dom, data_model_id, "remove", path));
+                    } else {
+                        info!("Removing {} instances matching '{}'", found.len(), path);
+                        remove_all(dom, &mut index, &query::outermost_only(dom, &found), &mut warnings, &mut changed, &mut counts);
+                    }
+                    continue;
+                }
+                SubtractTarget::Path(path) => path,
+                SubtractTarget::Filter(filter) => {
+                    let Some(mut found) = find_matching(dom, data_model_id, filter) else {
+                        warnings.push(path_not_found(dom, data_model_id, "remove instances under", filter.under.as_deref().unwrap_or_default()));
+                        continue;
+                    };
+                    if !options.allow_package_edits {
+                        found.retain(|&id| index.enclosing_package(dom, id).is_none());
+                    }
+                    info!("Removing {} {}", found.len(), filter);
+                    remove_all(dom, &mut index, &query::outermost_only(dom, &found), &mut warnings, &mut changed, &mut counts);
+                    continue;
+                }
+                SubtractTarget::Region { region } => {
+                    let mut found = query::instances_in_region(dom, workspace_id, region);
+                    if !options.allow_package_edits {
+                        found.retain(|&id| index.enclosing_package(dom, id).is_none());
+                    }
+                    info!("Removing {} instances in region {:?}", found.len(), region);
+                    remove_all(dom, &mut index, &query::outermost_only(dom, &found), &mut warnings, &mut changed, &mut counts);
+                    continue;
+                }
+                SubtractTarget::Near { near, radius } => {
+                    match query::instances_near(dom, workspace_id, near, *radius) {
+                        Ok(mut found) => {
+                            if !options.allow_package_edits {
+                                found.retain(|&id| index.enclosing_package(dom, id).is_none());
+                            }
+                            info!("Removing {} instances within {} studs of '{}'", found.len(), radius, near);
+                            remove_all(dom, &mut index, &query::outermost_only(dom, &found), &mut warnings, &mut changed, &mut counts);
+                        }
+                        Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                            operation: "remove instances near".to_string(),
+                            path: near.clone(),
+                            reason: e.to_string(),
+                        }),
+                    }
+                    continue;
+                }
+            };
+            debug!("Trying to remove instance at path: {}", path);
+            if let Some(instance_id) = index.find(dom, data_model_id, path) {
                 // Remove the instance
-                if let Err(e) = remove_instance(dom, instance_id) {
-                    println!("Warning: Failed to remove instance at '{}': {}", path, e);
-                } else {
-                    println!("Successfully removed instance at path: {}", path);
+                counts.record_removed(dom, instance_id);
+                match index.remove(dom, instance_id) {
+                    Ok(parent_id) => {
+                        info!("Removed {}", path);
+                        changed.push(parent_id);
+                    }
+                    Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                        operation: "remove".to_string(),
+                        path: path.clone(),
+                        reason: e.to_string(),
+                    }),
                 }
             } else {
-                println!("Warning: Could not find instance at path '{}' to remove", path);
+                warnings.push(path_not_found(dom, data_model_id, "remove", path));
             }
         }
     }
     
-    // Process all top-level instances
-    for instance in &json.add {
-        // Debug output to see what's being received
-        println!("Instance: {}, target_parent: {:?}", instance.name, instance.target_parent);
-        
-        // Determine the parent based on target_parent, defaulting to Workspace
-        let target_parent = match &instance.target_parent {
-            Some(target) => {
-                println!("  - Target parent specified: {}", target);
-                
-                // First, check if it's a direct service reference
-                if service_refs.contains_key(target) {
-                    println!("  - Found matching service for '{}'", target);
-                    *service_refs.get(target).unwrap()
-                } else {
-                    // If not a service, try to find it by path
-                    match find_instance_by_path(dom, data_model_id, target) {
-                        Some(id) => {
-                            println!("  - Found instance at path '{}'", target);
-                            id
-                        }
-                        None => {
-                            println!("  - Could not find target '{}', defaulting to Workspace", target);
-                            workspace_id
-                        }
+    // Reparent existing instances
+    for (instance_id, new_parent_id, op) in moves {
+        match (instance_id, new_parent_id) {
+            (Some(instance_id), Some(new_parent_id)) => {
+                match move_instance(dom, instance_id, new_parent_id) {
+                    Ok(old_parent_id) => {
+                        info!("Moved '{}' into '{}'", op.path, op.new_parent);
+                        touched.push(instance_id);
+                        changed.push(old_parent_id);
                     }
+                    Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                        operation: "move".to_string(),
+                        path: op.path.clone(),
+                        reason: e.to_string(),
+                    }),
                 }
             }
+            (None, _) => warnings.push(path_not_found(dom, data_model_id, "move", &op.path)),
+            (_, None) => warnings.push(path_not_found(dom, data_model_id, "move into", &op.new_parent)),
+        }
+    }
+
+    // Rename existing instances
+    for (instance_id, op) in renames {
+        match instance_id.and_then(|id| dom.get_by_ref_mut(id)) {
+            Some(instance) => {
+                info!("Renamed '{}' to '{}'", op.path, op.new_name);
+                instance.name = op.new_name.clone();
+                touched.push(instance.referent());
+            }
+            None => warnings.push(path_not_found(dom, data_model_id, "rename", &op.path)),
+        }
+    }
+
+    // Copy existing subtrees
+    for (source_id, target_parent_id, op) in clones {
+        // Either may have been removed by a subtract since it was looked up
+        let exists = |id: &Ref| dom.get_by_ref(*id).is_some();
+        match (source_id.filter(exists), target_parent_id.filter(exists)) {
+            (Some(source_id), Some(target_parent_id)) => {
+                for copy in 1..=op.count {
+                    let offset = op.offset
+                        .map(|[x, y, z]| Vector3::new(x * copy as f32, y * copy as f32, z * copy as f32));
+                    let copy_id = clone_instance(dom, source_id, target_parent_id, offset);
+                    counts.record_added(dom, copy_id);
+                    touched.push(copy_id);
+                }
+                info!("Cloned '{}' {} times into '{}'", op.source_path, op.count, op.target_parent);
+            }
+            (None, _) => warnings.push(path_not_found(dom, data_model_id, "clone", &op.source_path)),
+            (_, None) => warnings.push(path_not_found(dom, data_model_id, "clone into", &op.target_parent)),
+        }
+    }
+
+    // Insert model files
+    for (model, target_parent_id, op) in inserts {
+        match (model, target_parent_id.filter(|&id| dom.get_by_ref(id).is_some())) {
+            (Ok(model), Some(target_parent_id)) => {
+                let inserted = assets::insert_model(dom, model, target_parent_id, op.name.as_deref(), op.position);
+                for &instance_id in &inserted {
+                    counts.record_added(dom, instance_id);
+                }
+                info!("Inserted '{}' into '{}'", op.asset, instance_path(dom, target_parent_id));
+                touched.extend(inserted);
+            }
+            (Err(reason), _) => warnings.push(ApplyWarning::OperationFailed {
+                operation: "insert".to_string(),
+                path: op.asset.clone(),
+                reason,
+            }),
+            (_, None) => warnings.push(path_not_found(dom, data_model_id, "insert into", op.target_parent.as_deref().unwrap_or_default())),
+        }
+    }
+
+    // Ref properties waiting for their targets to be created
+    let mut pending_refs = Vec::new();
+
+    // Process all top-level instances
+    for ((instance, prepared), target_parent_id) in json.add.iter().zip(prepared).zip(add_parents) {
+        debug!("Instance: {}, target_parent: {:?}", instance.name, instance.target_parent);
+
+        // A parent that wasn't found, or that the removals took away, falls back to Workspace
+        let target_parent = match target_parent_id.filter(|&id| dom.get_by_ref(id).is_some()) {
+            Some(id) => id,
             None => {
-                println!("  - No target_parent specified, defaulting to Workspace");
+                let target = instance.target_parent.clone().unwrap_or_default();
+                debug!("Could not find target '{}', defaulting to Workspace", target);
+                warnings.push(ApplyWarning::DefaultedParent {
+                    instance: instance.name.clone(),
+                    suggestions: suggest_paths(dom, data_model_id, &target),
+                    target,
+                });
                 workspace_id
             }
         };
-        
+
         // Create each instance and all its children recursively
-        process_instance_with_children(dom, instance, target_parent)?;
+        let instance_id = insert_prepared(dom, prepared, target_parent, &mut pending_refs, &mut warnings);
+        counts.record_added(dom, instance_id);
+        touched.push(instance_id);
+        info!("Added '{}' ({}) to '{}'", instance.name, instance.class, instance_path(dom, target_parent));
+    }
+
+    // Resolve Ref properties and relative CFrames now that every instance they could point at exists.
+    // A CFrame relative to another relative one resolves when that one comes first.
+    for pending in &pending_refs {
+        let target = if pending.path.is_empty() && pending.offset.is_none() {
+            Some(Ref::none())
+        } else {
+            find_instance_by_path(dom, data_model_id, &pending.path)
+        };
+        let value = match (target, pending.offset) {
+            (Some(target), Some((offset, rotation))) => match query::instance_cframe(dom, target) {
+                Some(base) => Some(Variant::CFrame(property::offset_cframe(base, offset, rotation))),
+                None => {
+                    warnings.push(ApplyWarning::NoPivot {
+                        instance: pending.instance_name.clone(),
+                        property: pending.property.clone(),
+                        path: pending.path.clone(),
+                    });
+                    continue;
+                }
+            },
+            (target, None) => target.map(Variant::Ref),
+            (None, Some(_)) => None,
+        };
+        match (value, dom.get_by_ref_mut(pending.instance_id)) {
+            (Some(value), Some(instance)) => {
+                debug!("Resolved {} to '{}'", pending.property, pending.path);
+                instance.properties.insert(pending.property.as_str().into(), value);
+            }
+            _ => warnings.push(ApplyWarning::UnresolvedRef {
+                instance: pending.instance_name.clone(),
+                property: pending.property.clone(),
+                path: pending.path.clone(),
+                suggestions: suggest_paths(dom, data_model_id, &pending.path),
+            }),
+        }
+    }
+    
+    // Properties are set last, so they can be set on and point at instances the modification added
+    for op in &json.set {
+        let found = match &op.target {
+            SetTarget::Path { path } => match find_instance_by_path(dom, data_model_id, path) {
+                Some(instance_id) => vec![instance_id],
+                None => {
+                    warnings.push(path_not_found(dom, data_model_id, "set properties of", path));
+                    continue;
+                }
+            },
+            SetTarget::Region { region } => query::instances_in_region(dom, workspace_id, region),
+            SetTarget::Near { near, radius } => match query::instances_near(dom, workspace_id, near, *radius) {
+                Ok(found) => found,
+                Err(e) => {
+                    warnings.push(ApplyWarning::OperationFailed {
+                        operation: "set properties of instances near".to_string(),
+                        path: near.clone(),
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            },
+        };
+        let found: Vec<Ref> = match &op.target {
+            SetTarget::Path { .. } => found,
+            _ if options.allow_package_edits => found,
+            _ => found.into_iter().filter(|&id| index.enclosing_package(dom, id).is_none()).collect(),
+        };
+        // A property an area's instances don't all have is set on the ones that do, with one warning
+        for (property, value) in &op.properties {
+            let failures: Vec<RbxMcpError> = found.iter()
+                .filter_map(|&instance_id| set_property(dom, instance_id, property, value).err())
+                .collect();
+            if let Some(first) = failures.first() {
+                let reason = match &op.target {
+                    SetTarget::Path { .. } => first.to_string(),
+                    _ => format!("failed for {} of {} instances, e.g. {}", failures.len(), found.len(), first),
+                };
+                let path = match &op.target {
+                    SetTarget::Path { path } => path.clone(),
+                    target => target.to_string(),
+                };
+                warnings.push(ApplyWarning::OperationFailed { operation: format!("set {} of", property), path, reason });
+            }
+        }
+        if op.properties.contains_key("Source") {
+            for &instance_id in &found {
+                let path = index.path(dom, instance_id);
+                if !counts.scripts.contains(&path) {
+                    counts.scripts.push(path);
+                }
+            }
+        }
+        info!("Set {} properties of {}", op.properties.len(), op.target);
+        touched.extend(found);
+    }
+
+    if options.snap.is_some() || options.auto_anchor {
+        let tidied: usize = counts.created.iter()
+            .map(|&root_id| tidy::tidy_parts(dom, root_id, options.snap, options.auto_anchor))
+            .sum();
+        debug!("Tidied {} created parts", tidied);
+    }
+
+    // Containers the removals and moves emptied go too, but not ones the modification made
+    if options.auto_clean {
+        let protected = Protected::resolve(dom, &options.clean_protected, options.allow_package_edits);
+        let touched_ids: HashSet<Ref> = touched.iter().copied().collect();
+        let vacated: Vec<Ref> = changed.iter().copied().filter(|id| !touched_ids.contains(id)).collect();
+        for (path, parent_id) in clean::remove_emptied(dom, &vacated, &protected) {
+            info!("Removed '{}', which was left empty", path);
+            counts.removed += 1;
+            changed.push(parent_id);
+        }
+        changed.retain(|&id| dom.get_by_ref(id).is_some());
+    }
+
+    if let Some(change_id) = &options.change_id {
+        for &instance_id in &touched {
+            set_attribute(dom, instance_id, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.clone()));
+        }
     }
     
-    println!("Successfully processed all operations!");
+    changed.extend(touched);
+    debug!("Successfully processed all operations!");
+    Ok(ApplyReport { warnings, changed, counts })
+}
+
+/// Clear and then fill the terrain regions of a modification. Returns the Terrain instance.
+fn edit_terrain(dom: &mut WeakDom, json: &Modification) -> Result<Ref, RbxMcpError> {
+    let terrain_error = |e: Box<dyn std::error::Error>| RbxMcpError::InvalidOperation(e.to_string());
+    let (terrain_id, mut grid) = terrain::read_terrain(dom).map_err(terrain_error)?;
+    for clear in &json.terrain_clear {
+        let count = grid.fill_region(&clear.region, Voxel::AIR).map_err(terrain_error)?;
+        info!("Cleared {} voxels of terrain", count);
+    }
+    for fill in &json.terrain_fill {
+        let material = terrain::material_id(&fill.material)
+            .ok_or_else(|| RbxMcpError::InvalidOperation(format!("Unknown terrain material '{}'", fill.material)))?;
+        let count = grid.fill_region(&fill.region, Voxel { material, occupancy: 255 }).map_err(terrain_error)?;
+        info!("Filled {} voxels of terrain with {}", count, terrain::material_name(material));
+    }
+    terrain::write_terrain(dom, terrain_id, &grid).map_err(terrain_error)?;
+    Ok(terrain_id)
+}
+
+/// Set one property of an existing instance, converting the value the same way as properties in a modification.
+/// Ref values and the instances CFrames are relative to are paths from the root, and Name sets the instance's name.
+pub fn set_property(dom: &mut WeakDom, instance_id: Ref, property: &str, value: &PropertyValue) -> Result<(), RbxMcpError> {
+    let class = dom.get_by_ref(instance_id).ok_or(RbxMcpError::InstanceNotFound(instance_id))?.class;
+    let variant = match value {
+        PropertyValue::Ref(target) if target.is_empty() => Variant::Ref(Ref::none()),
+        PropertyValue::Ref(target) => {
+            let root_ref = dom.root_ref();
+            Variant::Ref(find_instance_by_path(dom, root_ref, target).ok_or_else(|| RbxMcpError::PathNotFound {
+                path: target.clone(),
+                suggestions: suggest_paths(dom, root_ref, target),
+            })?)
+        }
+        PropertyValue::RelativeCFrame { relative_to, offset, rotation } => {
+            let root_ref = dom.root_ref();
+            let target = find_instance_by_path(dom, root_ref, relative_to).ok_or_else(|| RbxMcpError::PathNotFound {
+                path: relative_to.clone(),
+                suggestions: suggest_paths(dom, root_ref, relative_to),
+            })?;
+            let base = query::instance_cframe(dom, target)
+                .ok_or_else(|| RbxMcpError::conversion(property, format!("'{}' has no CFrame or pivot to be relative to", relative_to)))?;
+            Variant::CFrame(property::offset_cframe(base, *offset, *rotation))
+        }
+        other => other.to_variant(&class, property)?
+            .ok_or_else(|| RbxMcpError::conversion(property, "this type is not supported"))?,
+    };
+
+    let instance = dom.get_by_ref_mut(instance_id).ok_or(RbxMcpError::InstanceNotFound(instance_id))?;
+    // Name isn't stored as a property
+    if property == "Name" {
+        let Variant::String(name) = variant else {
+            return Err(RbxMcpError::conversion(property, "Name must be a String"));
+        };
+        instance.name = name;
+    } else {
+        instance.properties.insert(property.into(), variant);
+    }
     Ok(())
 }
 
+/// Set one attribute on an instance, keeping the attributes it already has
+pub fn set_attribute(dom: &mut WeakDom, instance_id: Ref, name: &str, value: Variant) {
+    let Some(instance) = dom.get_by_ref_mut(instance_id) else {
+        return;
+    };
+    match instance.properties.get_mut(&"Attributes".into()) {
+        Some(Variant::Attributes(attributes)) => {
+            attributes.insert(name.to_string(), value);
+        }
+        _ => {
+            let mut attributes = Attributes::new();
+            attributes.insert(name.to_string(), value);
+            instance.properties.insert("Attributes".into(), Variant::Attributes(attributes));
+        }
+    }
+}
+
+/// Get an attribute of an instance
+pub fn get_attribute<'a>(dom: &'a WeakDom, instance_id: Ref, name: &str) -> Option<&'a Variant> {
+    match dom.get_by_ref(instance_id)?.properties.get(&"Attributes".into()) {
+        Some(Variant::Attributes(attributes)) => attributes.get(name),
+        _ => None,
+    }
+}
+
+/// Warning for an operation whose path doesn't exist, with the closest paths that do
+pub(crate) fn path_not_found(dom: &WeakDom, data_model_id: Ref, operation: &str, path: &str) -> ApplyWarning {
+    ApplyWarning::PathNotFound {
+        operation: operation.to_string(),
+        path: path.to_string(),
+        suggestions: suggest_paths(dom, data_model_id, path),
+    }
+}
+
+/// Check whether an instance is the root of a package, i.e. has a PackageLink child
+pub fn is_package_root(dom: &WeakDom, instance_id: Ref) -> bool {
+    dom.get_by_ref(instance_id).is_some_and(|instance| {
+        instance.children().iter()
+            .filter_map(|&child_id| dom.get_by_ref(child_id))
+            .any(|child| child.class == "PackageLink")
+    })
+}
+
+/// Find the package root an instance belongs to, counting the instance itself
+pub fn containing_package(dom: &WeakDom, instance_id: Ref) -> Option<Ref> {
+    let mut current = instance_id;
+    while let Some(instance) = dom.get_by_ref(current) {
+        if is_package_root(dom, current) {
+            return Some(current);
+        }
+        current = instance.parent();
+    }
+    None
+}
+
+/// Find the package whose contents include an instance.
+/// The package root itself doesn't count, since removing a whole package is safe.
+pub fn enclosing_package(dom: &WeakDom, instance_id: Ref) -> Option<Ref> {
+    dom.get_by_ref(instance_id)
+        .and_then(|instance| containing_package(dom, instance.parent()))
+}
+
+/// Check whether an instance is part of a package's contents
+pub fn is_inside_package(dom: &WeakDom, instance_id: Ref) -> bool {
+    enclosing_package(dom, instance_id).is_some()
+}
+
+/// Fail if any path a modification targets is inside a package
+fn check_package_edits(dom: &WeakDom, index: &mut PathIndex, data_model_id: Ref, json: &Modification) -> Result<(), RbxMcpError> {
+    let refuse = |path: &str, package_id: Ref| -> RbxMcpError {
+        RbxMcpError::InvalidOperation(format!(
+            "Refusing to modify '{}' because it is inside package '{}'. Package edits are lost when the package updates; use --allow-package-edits to override",
+            path, instance_path(dom, package_id)
+        ))
+    };
+
+    for target in &json.subtract {
+        if let SubtractTarget::Path(path) = target {
+            if let Some(package_id) = index.find(dom, data_model_id, path).and_then(|id| index.enclosing_package(dom, id)) {
+                return Err(refuse(path, package_id));
+            }
+        }
+    }
+
+    // Adding into or moving into a package changes its contents, as does the package root itself
+    let into_package = json.add.iter()
+        .filter_map(|instance| instance.target_parent.as_deref())
+        .chain(json.moves.iter().map(|op| op.new_parent.as_str()))
+        .chain(json.clone.iter().map(|op| op.target_parent.as_str()))
+        .chain(json.insert.iter().filter_map(|op| op.target_parent.as_deref()));
+    for path in into_package {
+        if let Some(package_id) = index.find(dom, data_model_id, path).and_then(|id| index.containing_package(dom, id)) {
+            return Err(refuse(path, package_id));
+        }
+    }
+
+    // Moving, renaming or setting properties of something inside a package changes it too
+    let inside_package = json.moves.iter().map(|op| op.path.as_str())
+        .chain(json.rename.iter().map(|op| op.path.as_str()))
+        .chain(json.set.iter().filter_map(|op| match &op.target {
+            SetTarget::Path { path } => Some(path.as_str()),
+            _ => None,
+        }));
+    for path in inside_package {
+        if let Some(package_id) = index.find(dom, data_model_id, path).and_then(|id| index.enclosing_package(dom, id)) {
+            return Err(refuse(path, package_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Containers Studio creates under StarterPlayer, which models target like services
+const STARTER_PLAYER_CONTAINERS: [&str; 2] = ["StarterPlayerScripts", "StarterCharacterScripts"];
+
+/// Check whether a class is a service according to the reflection database
+pub fn is_service_class(class: &str) -> bool {
+    rbx_reflection_database::get()
+        .classes
+        .get(class)
+        .is_some_and(|descriptor| descriptor.tags.contains(&ClassTag::Service))
+}
+
+/// Find the parent instance a path names. A path naming just a service the place doesn't have yet
+/// creates it, as does one naming a container under StarterPlayer, but nothing is created for a path
+/// that goes deeper, since it couldn't be found anyway. A bare StarterPlayerScripts or
+/// StarterCharacterScripts refers to the container under StarterPlayer.
+fn resolve_parent(dom: &mut WeakDom, data_model_id: Ref, target: &str) -> Result<Option<Ref>, RbxMcpError> {
+    let target = target.strip_prefix("DataModel/").unwrap_or(target);
+    let path = if STARTER_PLAYER_CONTAINERS.contains(&target) {
+        format!("StarterPlayer/{}", target)
+    } else {
+        target.to_string()
+    };
+
+    if let Some(id) = find_instance_by_path(dom, data_model_id, &path) {
+        return Ok(Some(id));
+    }
+
+    match split_path(&path)[..] {
+        [service_name] if is_service_class(service_name) => Ok(Some(find_or_create_service(dom, data_model_id, service_name)?)),
+        ["StarterPlayer", container] if STARTER_PLAYER_CONTAINERS.contains(&container) => {
+            let starter_player_id = find_or_create_service(dom, data_model_id, "StarterPlayer")?;
+            Ok(Some(find_or_create_service(dom, starter_player_id, container)?))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// Find a service by name or create it if it doesn't exist
-fn find_or_create_service(dom: &mut WeakDom, parent_id: Ref, service_name: &str) -> Result<Ref, Box<dyn Error>> {
+fn find_or_create_service(dom: &mut WeakDom, parent_id: Ref, service_name: &str) -> Result<Ref, RbxMcpError> {
     // Try to find the service among the parent's children
     let parent = dom.get_by_ref(parent_id)
-        .ok_or_else(|| format!("Invalid parent reference: {:?}", parent_id))?;
+        .ok_or(RbxMcpError::InstanceNotFound(parent_id))?;
     for &child_id in parent.children() {
         let instance = dom.get_by_ref(child_id)
-            .ok_or_else(|| format!("Invalid child reference: {:?}", child_id))?;
+            .ok_or(RbxMcpError::InstanceNotFound(child_id))?;
         if instance.name == service_name {
-            println!("Found existing service: {}", service_name);
+            debug!("Found existing service: {}", service_name);
             return Ok(child_id);
         }
     }
     
     // If not found, create the service
-    println!("Creating service: {}", service_name);
+    info!("Creating service: {}", service_name);
     let service_id = dom.insert(parent_id, InstanceBuilder::new(service_name).with_name(service_name));
     
     Ok(service_id)
 }
 
-/// Find instance by path (e.g., "Workspace/Models/House")
-fn find_instance_by_path(dom: &WeakDom, start_id: Ref, path: &str) -> Option<Ref> {
-    let path_parts: Vec<&str> = path.split('/').collect();
-    
+/// Find instance by path (e.g., "Workspace/Models/House").
+/// A segment can pick one of several siblings with the same name by its 1-based index, `Workspace/Part[2]`,
+/// filter by class, `Workspace/Model:House`, or both, `Workspace/Model:House[2]`. A `/`, `\`, `:`, `[` or `]`
+/// in a name is escaped with a backslash, like `Workspace/Up\/Down` or `Workspace/Part\[2\]`. A segment whose
+/// index or class picks no child names the child with exactly that name, so unescaped names still resolve.
+pub fn find_instance_by_path(dom: &WeakDom, start_id: Ref, path: &str) -> Option<Ref> {
+    resolve_path(start_id, path, |parent_id, segment| find_child(dom, parent_id, segment))
+}
+
+/// Walk a path from start_id, looking up each segment under the instance before it
+fn resolve_path(start_id: Ref, path: &str, mut find_child: impl FnMut(Ref, &str) -> Option<Ref>) -> Option<Ref> {
+    let path_parts = split_path(path);
+
     // If path is empty, return the starting point
-    if path_parts.is_empty() || (path_parts.len() == 1 && path_parts[0].is_empty()) {
+    if path_parts.len() == 1 && path_parts[0].is_empty() {
         return Some(start_id);
     }
-    
-    // Start with the first part of the path
-    let mut current_id = if path_parts[0] == "DataModel" {
-        // If path starts with DataModel, skip it and use start_id (which should be DataModel)
-        if path_parts.len() == 1 {
-            return Some(start_id);
+
+    // A leading DataModel is start_id itself
+    let skip = usize::from(path_parts[0] == "DataModel");
+    let mut current_id = start_id;
+    for &part in &path_parts[skip..] {
+        match find_child(current_id, part) {
+            Some(child_id) => current_id = child_id,
+            None => {
+                debug!("Could not find '{}' in path '{}'", part, path);
+                return None;
+            }
         }
-        start_id
-    } else {
-        // Otherwise, find the first part as a direct child of start_id
-        let service_name = path_parts[0];
-        
-        // Check if it's a service
-        match find_service(dom, start_id, service_name) {
-            Some(id) => id,
-            None => return None,
+    }
+
+    Some(current_id)
+}
+
+/// Children by name for each parent looked up so far, so resolving many paths in a large place
+/// doesn't scan the same children again for every path, along with whether the parent is a package root.
+/// A parent's entry is built on first use and rebuilt if its number of children changes; removing through
+/// the index keeps entries current. Renaming an instance isn't noticed, so an index shouldn't outlive
+/// a modification's removals.
+#[derive(Default)]
+pub struct PathIndex {
+    parents: HashMap<Ref, ChildIndex>,
+}
+
+/// The children of one parent, by name in child order
+struct ChildIndex {
+    count: usize,
+    by_name: HashMap<String, Vec<Ref>>,
+    package_root: bool,
+}
+
+impl PathIndex {
+    pub fn new() -> PathIndex {
+        PathIndex::default()
+    }
+
+    /// Find an instance by path, the same as `find_instance_by_path`
+    pub fn find(&mut self, dom: &WeakDom, start_id: Ref, path: &str) -> Option<Ref> {
+        resolve_path(start_id, path, |parent_id, segment| self.child(dom, parent_id, segment))
+    }
+
+    /// Find every instance a path or glob matches, the same as `find_instances_by_path`
+    pub fn find_all(&mut self, dom: &WeakDom, start_id: Ref, path: &str) -> Vec<Ref> {
+        resolve_glob(dom, start_id, path, &mut |parent_id, segment| self.child(dom, parent_id, segment))
+    }
+
+    /// The path of an instance, the same as `instance_path`
+    pub fn path(&mut self, dom: &WeakDom, instance_id: Ref) -> String {
+        build_path(dom, instance_id, |instance_id, instance| {
+            let same_name = self.children(dom, instance.parent())?.by_name.get(&instance.name)?;
+            (same_name.len() > 1).then(|| same_name.iter().position(|&id| id == instance_id).unwrap_or(0) + 1)
+        })
+    }
+
+    /// Find the package root an instance belongs to, the same as `containing_package`
+    pub fn containing_package(&mut self, dom: &WeakDom, instance_id: Ref) -> Option<Ref> {
+        let mut current = instance_id;
+        while let Some(instance) = dom.get_by_ref(current) {
+            if self.children(dom, current)?.package_root {
+                return Some(current);
+            }
+            current = instance.parent();
+        }
+        None
+    }
+
+    /// Find the package whose contents include an instance, the same as `enclosing_package`
+    pub fn enclosing_package(&mut self, dom: &WeakDom, instance_id: Ref) -> Option<Ref> {
+        let parent_id = dom.get_by_ref(instance_id)?.parent();
+        self.containing_package(dom, parent_id)
+    }
+
+    /// Remove an instance and everything under it, updating its parent's entry
+    /// Returns the parent it was removed from
+    pub fn remove(&mut self, dom: &mut WeakDom, instance_id: Ref) -> Result<Ref, RbxMcpError> {
+        let (name, class) = dom.get_by_ref(instance_id)
+            .map(|instance| (instance.name.clone(), instance.class))
+            .ok_or(RbxMcpError::InstanceNotFound(instance_id))?;
+        let parent_id = remove_instance(dom, instance_id)?;
+        if let Some(entry) = self.parents.get_mut(&parent_id) {
+            if let Some(ids) = entry.by_name.get_mut(&name) {
+                ids.retain(|&id| id != instance_id);
+            }
+            entry.count -= 1;
+            // The parent may not be a package anymore
+            if class == "PackageLink" {
+                self.parents.remove(&parent_id);
+            }
+        }
+        Ok(parent_id)
+    }
+
+    /// The entry for a parent, built or rebuilt if it isn't current
+    fn children(&mut self, dom: &WeakDom, parent_id: Ref) -> Option<&ChildIndex> {
+        let parent = dom.get_by_ref(parent_id)?;
+        let entry = self.parents.entry(parent_id)
+            .or_insert_with(|| ChildIndex { count: usize::MAX, by_name: HashMap::new(), package_root: false });
+        if entry.count != parent.children().len() {
+            entry.by_name.clear();
+            entry.package_root = false;
+            for &child_id in parent.children() {
+                if let Some(child) = dom.get_by_ref(child_id) {
+                    entry.by_name.entry(child.name.clone()).or_default().push(child_id);
+                    entry.package_root |= child.class == "PackageLink";
+                }
+            }
+            entry.count = parent.children().len();
+        }
+        Some(entry)
+    }
+
+    fn child(&mut self, dom: &WeakDom, parent_id: Ref, segment: &str) -> Option<Ref> {
+        let entry = self.children(dom, parent_id)?;
+        let picked = parse_segment(segment).and_then(|(class, name, index)| {
+            entry.by_name.get(&name)?
+                .iter()
+                .copied()
+                .filter(|&id| class.as_deref().is_none_or(|class| dom.get_by_ref(id).is_some_and(|child| is_a(child.class.as_str(), class))))
+                .nth(index.unwrap_or(1).checked_sub(1)?)
+        });
+        picked.or_else(|| entry.by_name.get(&unescape_name(segment)).and_then(|ids| ids.first()).copied())
+    }
+}
+
+/// Find the child a path segment names: the one picked by the segment's class filter and index,
+/// or else the first with exactly that name, which also covers names written without escapes
+fn find_child(dom: &WeakDom, parent_id: Ref, segment: &str) -> Option<Ref> {
+    let parent = dom.get_by_ref(parent_id)?;
+    let picked = parse_segment(segment).and_then(|(class, name, index)| {
+        parent.children().iter()
+            .copied()
+            .filter(|&id| dom.get_by_ref(id).is_some_and(|child| {
+                child.name == name && class.as_deref().is_none_or(|class| is_a(child.class.as_str(), class))
+            }))
+            .nth(index.unwrap_or(1).checked_sub(1)?)
+    });
+    if picked.is_some() {
+        return picked;
+    }
+
+    let name = unescape_name(segment);
+    parent.children().iter().copied().find(|&id| dom.get_by_ref(id).is_some_and(|child| child.name == name))
+}
+
+/// Find every instance a path matches. Besides plain names, segments can be globs: `*` matches
+/// any run of characters and `?` any one, like `Workspace/Trees/*` or `Workspace/Tree?`, optionally
+/// with a class filter, `Workspace/Part:*`. A `**` segment matches any number of levels, like
+/// `Workspace/**/Decal`. A path naming an instance exactly matches only that instance, and `\*`
+/// or `\?` match the character itself.
+pub fn find_instances_by_path(dom: &WeakDom, start_id: Ref, path: &str) -> Vec<Ref> {
+    resolve_glob(dom, start_id, path, &mut |parent_id, segment| find_child(dom, parent_id, segment))
+}
+
+/// Find the instances a path or glob matches, looking up plain segments with find_child
+fn resolve_glob(dom: &WeakDom, start_id: Ref, path: &str, find_child: &mut dyn FnMut(Ref, &str) -> Option<Ref>) -> Vec<Ref> {
+    if let Some(id) = resolve_path(start_id, path, &mut *find_child) {
+        return vec![id];
+    }
+    if !is_glob(path) {
+        return Vec::new();
+    }
+
+    let parts = split_path(path);
+    let skip = usize::from(parts.first() == Some(&"DataModel"));
+    let segments: Vec<Segment> = parts[skip..].iter().map(|&part| Segment::parse(part)).collect();
+    let mut found = Vec::new();
+    collect_matches(dom, start_id, &segments, find_child, &mut found);
+
+    // A `**` can reach the same instance more than one way
+    let mut seen = HashSet::new();
+    found.retain(|&id| seen.insert(id));
+    found
+}
+
+/// Whether a path has a `*` or `?` that isn't escaped
+pub fn is_glob(path: &str) -> bool {
+    let mut escaped = false;
+    for c in path.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '*' | '?' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// One segment of a glob path
+enum Segment<'a> {
+    /// `**`, any number of levels
+    AnyDepth,
+    /// A name pattern and an optional class filter
+    Pattern(Option<&'a str>, Regex),
+    /// A plain name, resolved like any path segment
+    Name(&'a str),
+}
+
+impl<'a> Segment<'a> {
+    fn parse(segment: &'a str) -> Segment<'a> {
+        if segment == "**" {
+            return Segment::AnyDepth;
+        }
+        if !is_glob(segment) {
+            return Segment::Name(segment);
+        }
+        match segment.split_once(':') {
+            Some((class, pattern)) if rbx_reflection_database::get().classes.contains_key(class) => {
+                Segment::Pattern(Some(class), glob_regex(pattern))
+            }
+            _ => Segment::Pattern(None, glob_regex(segment)),
+        }
+    }
+}
+
+/// Translate a name glob into an anchored regex
+fn glob_regex(pattern: &str) -> Regex {
+    let mut regex = String::from("(?s)^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => regex.extend(chars.next().map(|next| regex::escape(&next.to_string()))),
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
         }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+/// Collect the instances under an instance that the remaining segments match
+fn collect_matches(
+    dom: &WeakDom,
+    instance_id: Ref,
+    segments: &[Segment],
+    find_child: &mut dyn FnMut(Ref, &str) -> Option<Ref>,
+    found: &mut Vec<Ref>,
+) {
+    let Some((segment, rest)) = segments.split_first() else {
+        found.push(instance_id);
+        return;
     };
-    
-    // Traverse the rest of the path
-    for &part in &path_parts[if path_parts[0] == "DataModel" { 2 } else { 1 }..] {
-        let parent = dom.get_by_ref(current_id).unwrap();
-        
-        let mut found = false;
-        for &child_id in parent.children() {
-            let child = dom.get_by_ref(child_id).unwrap();
-            if child.name == part {
-                current_id = child_id;
-                found = true;
-                break;
+    let Some(instance) = dom.get_by_ref(instance_id) else {
+        return;
+    };
+    match segment {
+        Segment::AnyDepth => {
+            collect_matches(dom, instance_id, rest, find_child, found);
+            for &child_id in instance.children() {
+                collect_matches(dom, child_id, segments, find_child, found);
+            }
+        }
+        Segment::Pattern(class, pattern) => {
+            for &child_id in instance.children() {
+                let matches = dom.get_by_ref(child_id).is_some_and(|child| {
+                    pattern.is_match(&child.name) && class.is_none_or(|class| is_a(child.class.as_str(), class))
+                });
+                if matches {
+                    collect_matches(dom, child_id, rest, find_child, found);
+                }
             }
         }
-        
-        if !found {
-            println!("Could not find '{}' in path '{}'", part, path);
-            return None;
+        Segment::Name(name) => {
+            if let Some(child_id) = find_child(instance_id, name) {
+                collect_matches(dom, child_id, rest, find_child, found);
+            }
         }
     }
-    
-    Some(current_id)
 }
 
-/// Find a service by name or None if it doesn't exist
-fn find_service(dom: &WeakDom, parent_id: Ref, service_name: &str) -> Option<Ref> {
-    let parent = dom.get_by_ref(parent_id).unwrap();
-    for &child_id in parent.children() {
-        let instance = dom.get_by_ref(child_id).unwrap();
-        if instance.name == service_name {
-            return Some(child_id);
+/// Read a segment's class filter, name and index, like `Model:House[2]`. None when it has neither,
+/// since then it can only be a plain name. Only unescaped `:`, `[` and `]` count, and text before
+/// a `:` is only a filter if it's a class name.
+fn parse_segment(segment: &str) -> Option<(Option<String>, String, Option<usize>)> {
+    // Byte offsets of the characters that aren't escaped
+    let mut unescaped = Vec::new();
+    let mut escaped = false;
+    for (i, c) in segment.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c => unescaped.push((i, c)),
         }
     }
-    None
+
+    let mut rest = segment;
+    let mut index = None;
+    if let Some(&(close, ']')) = unescaped.last().filter(|&&(i, _)| i == segment.len() - 1) {
+        let open = unescaped.iter().rev().find(|&&(_, c)| c == '[').map(|&(i, _)| i);
+        if let Some((open, n)) = open.and_then(|open| Some((open, segment[open + 1..close].parse::<usize>().ok()?))) {
+            index = Some(n);
+            rest = &segment[..open];
+        }
+    }
+    let mut class = None;
+    if let Some(&(colon, _)) = unescaped.iter().find(|&&(i, c)| c == ':' && i < rest.len()) {
+        let prefix = &rest[..colon];
+        if rbx_reflection_database::get().classes.contains_key(prefix) {
+            class = Some(prefix.to_string());
+            rest = &rest[colon + 1..];
+        }
+    }
+    if class.is_none() && index.is_none() {
+        return None;
+    }
+    Some((class, unescape_name(rest), index))
 }
 
-/// Process an instance and all its children recursively
-fn process_instance_with_children(dom: &mut WeakDom, instance: &JsonInstance, parent_id: Ref) -> Result<Ref, Box<dyn Error>> {
-    // Add the current instance
-    println!("Processing instance: {} ({})", instance.name, instance.class);
-    let instance_id = add_instance_to_weakdom(dom, instance, parent_id)?;
-    
-    // Process all children recursively
-    if !instance.children.is_empty() {
-        println!("Processing {} children for {}", instance.children.len(), instance.name);
-        for child in &instance.children {
-            process_instance_with_children(dom, child, instance_id)?;
+/// Split a path at the slashes that aren't escaped. Segments keep their escapes.
+pub fn split_path(path: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' => {
+                parts.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
         }
     }
-    
-    Ok(instance_id)
+    parts.push(&path[start..]);
+    parts
+}
+
+/// An instance name as it's written in a path, with `\`, `/` and the `:`, `[` and `]` of
+/// class filters and indexes escaped
+pub fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '\\' | '/' | ':' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The name a path segment is for, with its escapes removed
+fn unescape_name(segment: &str) -> String {
+    let mut name = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next()),
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// The path of a new instance under a parent path
+pub fn child_path(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent, escape_name(name))
+}
+
+/// How many path suggestions to offer when a path can't be found
+const MAX_PATH_SUGGESTIONS: usize = 3;
+
+/// Suggest existing paths close to one that couldn't be found, best match first.
+/// Each segment is matched against sibling names by edit distance, ignoring case.
+pub fn suggest_paths(dom: &WeakDom, start_id: Ref, path: &str) -> Vec<String> {
+    let parts: Vec<String> = split_path(path).into_iter()
+        .filter(|part| !part.is_empty())
+        .skip_while(|&part| part == "DataModel")
+        .map(|part| parse_segment(part).map_or_else(|| unescape_name(part), |(_, name, _)| name))
+        .collect();
+
+    let mut candidates = Vec::new();
+    collect_suggestions(dom, start_id, &parts, String::new(), 0, &mut candidates);
+    candidates.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    candidates.into_iter()
+        .take(MAX_PATH_SUGGESTIONS)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+fn collect_suggestions(
+    dom: &WeakDom,
+    current_id: Ref,
+    parts: &[String],
+    prefix: String,
+    distance: usize,
+    candidates: &mut Vec<(usize, String)>,
+) {
+    let Some((part, rest)) = parts.split_first() else {
+        candidates.push((distance, prefix));
+        return;
+    };
+    let Some(current) = dom.get_by_ref(current_id) else {
+        return;
+    };
+
+    // Allow roughly one typo per three characters, and at least two
+    let max_distance = (part.len() / 3).max(2);
+    for &child_id in current.children() {
+        let Some(child) = dom.get_by_ref(child_id) else {
+            continue;
+        };
+        let child_distance = edit_distance(&part.to_lowercase(), &child.name.to_lowercase());
+        if child_distance <= max_distance {
+            let child_path = if prefix.is_empty() {
+                escape_name(&child.name)
+            } else {
+                child_path(&prefix, &child.name)
+            };
+            collect_suggestions(dom, child_id, rest, child_path, distance + child_distance, candidates);
+        }
+    }
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A " Did you mean ...?" hint for a path that couldn't be found, or nothing if no path is close
+pub fn did_you_mean(dom: &WeakDom, start_id: Ref, path: &str) -> String {
+    suggestion_hint(&suggest_paths(dom, start_id, path))
+}
+
+/// Build the slash separated path of an instance, without the DataModel prefix.
+/// Names are escaped, and an instance with siblings of the same name gets its index among them, like `Part[2]`.
+pub fn instance_path(dom: &WeakDom, instance_id: Ref) -> String {
+    build_path(dom, instance_id, |instance_id, instance| {
+        let parent = dom.get_by_ref(instance.parent())?;
+        let same_name: Vec<Ref> = parent.children().iter()
+            .copied()
+            .filter(|&id| dom.get_by_ref(id).is_some_and(|sibling| sibling.name == instance.name))
+            .collect();
+        (same_name.len() > 1).then(|| same_name.iter().position(|&id| id == instance_id).unwrap_or(0) + 1)
+    })
+}
+
+/// Build the path of an instance from its escaped names. sibling_index gives an instance's 1-based
+/// position among siblings with the same name, or None if its name is unique.
+fn build_path(dom: &WeakDom, instance_id: Ref, mut sibling_index: impl FnMut(Ref, &Instance) -> Option<usize>) -> String {
+    let mut parts = Vec::new();
+    let mut current = instance_id;
+    while let Some(instance) = dom.get_by_ref(current) {
+        if current == dom.root_ref() {
+            break;
+        }
+        let mut part = escape_name(&instance.name);
+        if let Some(index) = sibling_index(current, instance) {
+            part = format!("{}[{}]", part, index);
+        }
+        parts.push(part);
+        current = instance.parent();
+    }
+    parts.reverse();
+    parts.join("/")
+}
+
+/// Check whether a class inherits from another, like Instance:IsA
+pub fn is_a(class: &str, superclass: &str) -> bool {
+    let database = rbx_reflection_database::get();
+    match (database.classes.get(class), database.classes.get(superclass)) {
+        (Some(descriptor), Some(super_descriptor)) => database.has_superclass(descriptor, super_descriptor),
+        _ => class == superclass,
+    }
+}
+
+/// A Ref property to point at the instance at path once all adds are done,
+/// or a CFrame to offset from that instance's
+struct PendingRef {
+    instance_id: Ref,
+    instance_name: String,
+    property: String,
+    path: String,
+    /// Offset and rotation of a relative CFrame, None for a Ref
+    offset: Option<([f32; 3], [f32; 9])>,
+}
+
+/// An added instance with its properties converted, ready to insert
+struct PreparedInstance<'a> {
+    json: &'a JsonInstance,
+    builder: InstanceBuilder,
+    warnings: Vec<ApplyWarning>,
+    children: Vec<PreparedInstance<'a>>,
+}
+
+/// Number of instances in an added subtree
+fn subtree_size(instance: &JsonInstance) -> usize {
+    1 + instance.children.iter().map(subtree_size).sum::<usize>()
+}
+
+/// Convert the properties of an instance and everything under it without touching the place,
+/// converting children in parallel if asked to
+fn prepare_instance(json: &JsonInstance, parallel: bool) -> Result<PreparedInstance<'_>, RbxMcpError> {
+    let mut warnings = Vec::new();
+    let builder = instance_builder(json, &mut warnings)?;
+    let children = if parallel {
+        json.children.par_iter().map(|child| prepare_instance(child, true)).collect::<Result<_, _>>()?
+    } else {
+        json.children.iter().map(|child| prepare_instance(child, false)).collect::<Result<_, _>>()?
+    };
+    Ok(PreparedInstance { json, builder, warnings, children })
+}
+
+/// Insert a prepared instance and all its children, recording Ref properties to resolve later
+fn insert_prepared(
+    dom: &mut WeakDom,
+    prepared: PreparedInstance,
+    parent_id: Ref,
+    pending_refs: &mut Vec<PendingRef>,
+    warnings: &mut Vec<ApplyWarning>,
+) -> Ref {
+    let PreparedInstance { json: instance, builder, warnings: instance_warnings, children } = prepared;
+    let _span = debug_span!("instance", name = %instance.name, class = %instance.class).entered();
+    let instance_id = dom.insert(parent_id, builder);
+    debug!("Created instance with ID: {:?}", instance_id);
+    warnings.extend(instance_warnings);
+
+    // Refs and relative CFrames may point at instances that haven't been created yet
+    for (prop_name, prop) in &instance.properties {
+        let (path, offset) = match prop {
+            PropertyValue::Ref(path) => (path, None),
+            PropertyValue::RelativeCFrame { relative_to, offset, rotation } => (relative_to, Some((*offset, *rotation))),
+            _ => continue,
+        };
+        pending_refs.push(PendingRef {
+            instance_id,
+            instance_name: instance.name.clone(),
+            property: prop_name.clone(),
+            path: path.clone(),
+            offset,
+        });
+    }
+
+    if !children.is_empty() {
+        debug!("Processing {} children for {}", children.len(), instance.name);
+        for child in children {
+            insert_prepared(dom, child, instance_id, pending_refs, warnings);
+        }
+    }
+
+    instance_id
 }
 
 /// Add a single instance to WeakDom
+/// Properties with unsupported types are skipped and reported in warnings
 pub fn add_instance_to_weakdom(
     dom: &mut WeakDom,
     json: &JsonInstance,
     parent_id: Ref,
-) -> Result<Ref, Box<dyn Error>> {
-    println!("Creating instance: {} ({})", json.name, json.class);
+    warnings: &mut Vec<ApplyWarning>,
+) -> Result<Ref, RbxMcpError> {
+    let builder = instance_builder(json, warnings)?;
+    let instance_id = dom.insert(parent_id, builder);
+    debug!("Created instance with ID: {:?}", instance_id);
+    Ok(instance_id)
+}
+
+/// Build a single instance, without its children, converting its properties and attributes
+/// Properties with unsupported types are skipped and reported in warnings
+fn instance_builder(json: &JsonInstance, warnings: &mut Vec<ApplyWarning>) -> Result<InstanceBuilder, RbxMcpError> {
+    debug!("Creating instance: {} ({})", json.name, json.class);
     let mut builder = InstanceBuilder::new(&json.class).with_name(&json.name);
 
     let is_script = json.class == "Script" || 
@@ -260,241 +1612,419 @@ pub fn add_instance_to_weakdom(
 
     // Add properties to the instance builder
     for (prop_name, prop) in &json.properties {
-        // Special case for Script Source property
+        // Script sources are kept as text whatever type they were given
         if is_script && prop_name == "Source" {
-            if let Some(source) = prop.value.as_str() {
+            if let Some(source) = prop.as_str() {
                 builder = builder.with_property("Source", Variant::String(source.to_string()));
                 continue;
             }
         }
 
-        println!("  - Adding property: {}", prop_name);
-        let variant = match prop.type_name.as_str() {
-            "Vector3" => {
-                if let Value::Array(vec) = &prop.value {
-                    if vec.len() == 3 {
-                        let x = vec[0].as_f64().unwrap_or(0.0) as f32;
-                        let y = vec[1].as_f64().unwrap_or(0.0) as f32;
-                        let z = vec[2].as_f64().unwrap_or(0.0) as f32;
-                        
-                        println!("    - Vector3: [{}, {}, {}]", x, y, z);
-                        Variant::Vector3(Vector3::new(x, y, z))
-                    } else {
-                        return Err("Vector3 must have 3 components".into());
-                    }
-                } else if let Value::Object(obj) = &prop.value {
-                    // Handle Vector3 as an object with x, y, z properties
-                    let x = obj.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                    let y = obj.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                    let z = obj.get("z").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                    
-                    println!("    - Vector3 (object): [{}, {}, {}]", x, y, z);
-                    Variant::Vector3(Vector3::new(x, y, z))
-                } else {
-                    return Err("Vector3 must be an array or object".into());
-                }
-            }
-            "CFrame" => {
-                // Create verbose debug output to diagnose the issue
-                println!("    - Raw CFrame value: {:?}", prop.value);
-                
-                if let Value::Object(obj) = &prop.value {
-                    // Try to extract position
-                    if let Some(pos_val) = obj.get("position") {
-                        println!("    - Position value: {:?}", pos_val);
-                        
-                        let pos = if let Some(pos_arr) = pos_val.as_array() {
-                            if pos_arr.len() == 3 {
-                                let x = pos_arr[0].as_f64().unwrap_or(0.0) as f32;
-                                let y = pos_arr[1].as_f64().unwrap_or(0.0) as f32;
-                                let z = pos_arr[2].as_f64().unwrap_or(0.0) as f32;
-                                Vector3::new(x, y, z)
-                            } else {
-                                return Err("CFrame position must have 3 components".into());
-                            }
-                        } else if let Some(pos_obj) = pos_val.as_object() {
-                            // Handle position as an object with x, y, z properties
-                            let x = pos_obj.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                            let y = pos_obj.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                            let z = pos_obj.get("z").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-                            Vector3::new(x, y, z)
-                        } else {
-                            return Err("CFrame position must be an array or object".into());
-                        };
-
-                        // Log the position to verify
-                        println!("    - CFrame position: [{}, {}, {}]", pos.x, pos.y, pos.z);
-
-                        // Extract rotation (optional)
-                        let rot = if let Some(rot_val) = obj.get("rotation") {
-                            println!("    - Rotation value: {:?}", rot_val);
-                            
-                            if let Some(rot_arr) = rot_val.as_array() {
-                                if rot_arr.len() == 9 {
-                                    // Convert all 9 values to f32
-                                    let values: Vec<f32> = rot_arr.iter()
-                                        .map(|v| v.as_f64().unwrap_or(0.0) as f32)
-                                        .collect();
-                                    
-                                    println!("    - Using rotation matrix: {:?}", values);
-                                    
-                                    Matrix3::new(
-                                        Vector3::new(values[0], values[1], values[2]),
-                                        Vector3::new(values[3], values[4], values[5]),
-                                        Vector3::new(values[6], values[7], values[8])
-                                    )
-                                } else if rot_arr.len() == 3 {
-                                    // Handle rotation as just angles
-                                    println!("    - Using rotation angles");
-                                    // For simplicity, using identity matrix when only angles provided
-                                    Matrix3::identity()
-                                } else {
-                                    // Default to identity matrix if rotation not provided correctly
-                                    println!("    - Using identity matrix for rotation (incorrect length)");
-                                    Matrix3::identity()
-                                }
-                            } else {
-                                // Default to identity matrix
-                                println!("    - Using identity matrix for rotation (not an array)");
-                                Matrix3::identity()
-                            }
-                        } else {
-                            // If rotation is missing, use identity matrix
-                            println!("    - Using identity matrix for rotation (missing)");
-                            Matrix3::identity()
-                        };
-
-                        // Create the CFrame with position and rotation
-                        let cframe = CFrame::new(pos, rot);
-                        println!("    - Final CFrame position: [{}, {}, {}]", 
-                            cframe.position.x, cframe.position.y, cframe.position.z);
-                        
-                        Variant::CFrame(cframe)
-                    } else {
-                        return Err("CFrame missing position".into());
-                    }
-                } else {
-                    return Err("CFrame must be an object with position and rotation".into());
-                }
-            }
-            "String" => {
-                if let Value::String(s) = &prop.value {
-                    Variant::String(s.clone())
-                } else {
-                    // Also try to convert numbers or other types to string
-                    Variant::String(prop.value.to_string())
-                }
-            }
-            "BrickColor" => {
-                if let Value::Number(n) = &prop.value {
-                    // Convert to u16 as required by from_number
-                    let number = n.as_u64().unwrap_or(1) as u16;
-                    match BrickColor::from_number(number) {
-                        Some(color) => Variant::BrickColor(color),
-                        None => return Err(format!("Invalid BrickColor number: {}", number).into())
-                    }
-                } else {
-                    return Err("BrickColor must be a number".into());
-                }
-            }
-            "Bool" => {
-                if let Value::Bool(b) = &prop.value {
-                    Variant::Bool(*b)
-                } else {
-                    return Err("Bool must be a boolean".into());
-                }
-            }
-            "Number" | "Float" | "Float32" => {
-                if let Value::Number(n) = &prop.value {
-                    Variant::Float32(n.as_f64().unwrap_or(0.0) as f32)
-                } else {
-                    return Err("Number must be a numeric value".into());
-                }
-            }
-            "Int" | "Int32" => {
-                if let Value::Number(n) = &prop.value {
-                    Variant::Int32(n.as_i64().unwrap_or(0) as i32)
-                } else {
-                    return Err("Int must be a numeric value".into());
-                }
-            }
-            "Enum" => {
-                if let Value::Number(n) = &prop.value {
-                    Variant::Enum(Enum::from_u32(n.as_u64().unwrap_or(1).try_into().unwrap()))
-                } else {
-                    return Err("Enum must be a numeric value".into());
-                }
-            }
-            "Color3" => {
-                if let Value::Array(vec) = &prop.value {
-                    if vec.len() == 3 {
-                        Variant::Color3(Color3::new(
-                            vec[0].as_f64().unwrap_or(0.0) as f32,
-                            vec[1].as_f64().unwrap_or(0.0) as f32,
-                            vec[2].as_f64().unwrap_or(0.0) as f32,
-                        ))
-                    } else {
-                        return Err("Color3 must have 3 components".into());
-                    }
-                } else {
-                    return Err("Color3 must be an array".into());
-                }
-            }
-            "UDim2" => {
-                if let Value::Array(vec) = &prop.value {
-                    if vec.len() == 4 {
-                        // UDim2::new requires two UDim values (x and y)
-                        // Each UDim has a scale (float) and offset (integer)
-                        let x = UDim::new(
-                            vec[0].as_f64().unwrap_or(0.0) as f32,
-                            vec[1].as_i64().unwrap_or(0) as i32
-                        );
-                        let y = UDim::new(
-                            vec[2].as_f64().unwrap_or(0.0) as f32,
-                            vec[3].as_i64().unwrap_or(0) as i32
-                        );
-                        Variant::UDim2(UDim2::new(x, y))
-                    } else {
-                        return Err("UDim2 must have 4 components [xScale, xOffset, yScale, yOffset]".into());
-                    }
-                } else {
-                    return Err("UDim2 must be an array".into());
-                }
+        trace!("Adding property: {} ({})", prop_name, prop.type_name());
+        match prop.to_variant(&json.class, prop_name)? {
+            Some(variant) => builder = builder.with_property(prop_name, variant),
+            // Resolved by json_to_weakdom once every instance exists
+            None if matches!(prop, PropertyValue::Ref(_) | PropertyValue::RelativeCFrame { .. }) => {}
+            None => warnings.push(ApplyWarning::SkippedProperty {
+                instance: json.name.clone(),
+                property: prop_name.clone(),
+                type_name: prop.type_name().to_string(),
+            }),
+        }
+    }
+
+    if !json.attributes.is_empty() {
+        debug!("Adding {} attributes", json.attributes.len());
+        builder = builder.with_property("Attributes", json_to_attributes(&json.attributes)?);
+    }
+
+    Ok(builder)
+}
+
+/// Convert JSON attribute values to Roblox attributes.
+/// Strings, numbers, bools and 3 number arrays (Vector3) are supported.
+pub(crate) fn json_to_attributes(values: &HashMap<String, Value>) -> Result<Attributes, RbxMcpError> {
+    let mut attributes = Attributes::new();
+    for (name, value) in values {
+        let variant = match value {
+            Value::String(s) => Variant::String(s.clone()),
+            Value::Bool(b) => Variant::Bool(*b),
+            // Attribute numbers are always doubles in Roblox
+            Value::Number(n) => Variant::Float64(n.as_f64().unwrap_or(0.0)),
+            Value::Array(vec) if vec.len() == 3 && vec.iter().all(|v| v.is_number()) => {
+                Variant::Vector3(Vector3::new(
+                    vec[0].as_f64().unwrap_or(0.0) as f32,
+                    vec[1].as_f64().unwrap_or(0.0) as f32,
+                    vec[2].as_f64().unwrap_or(0.0) as f32,
+                ))
             }
-            // Add more types as needed
-            _ => continue,
+            _ => return Err(RbxMcpError::conversion(name, "Attribute must be a string, number, bool or [x, y, z] array")),
         };
-        builder = builder.with_property(prop_name, variant);
+        attributes.insert(name.clone(), variant);
     }
+    Ok(attributes)
+}
 
-    // Insert the instance into the DOM
-    let instance_id = dom.insert(parent_id, builder);
-    println!("  Created instance with ID: {:?}", instance_id);
-    
-    Ok(instance_id)
+/// Convert Roblox attributes to the plain JSON values a modification uses.
+/// Attributes of other types are left out.
+pub fn attributes_to_json(attributes: &Attributes) -> serde_json::Map<String, Value> {
+    attributes.iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Variant::String(s) => Value::String(s.clone()),
+                // String attributes read back from a file come out as BinaryString
+                Variant::BinaryString(bytes) => Value::String(String::from_utf8_lossy(bytes.as_ref()).into_owned()),
+                Variant::Bool(b) => Value::Bool(*b),
+                Variant::Float64(n) => serde_json::json!(n),
+                Variant::Float32(n) => serde_json::json!(n),
+                Variant::Int32(n) => serde_json::json!(n),
+                Variant::Vector3(v) => serde_json::json!([v.x, v.y, v.z]),
+                _ => return None,
+            };
+            Some((name.clone(), value))
+        })
+        .collect()
 }
 
 /// Remove an instance and all its children from the WeakDom
-fn remove_instance(dom: &mut WeakDom, instance_id: Ref) -> Result<(), Box<dyn Error>> {
+/// Returns the parent it was removed from
+pub fn remove_instance(dom: &mut WeakDom, instance_id: Ref) -> Result<Ref, RbxMcpError> {
     // Get the instance name for logging
-    let instance_name = match dom.get_by_ref(instance_id) {
-        Some(instance) => instance.name.clone(),
-        None => return Err(format!("Instance with ref {:?} not found", instance_id).into()),
+    let (instance_name, parent_id) = match dom.get_by_ref(instance_id) {
+        Some(instance) => (instance.name.clone(), instance.parent()),
+        None => return Err(RbxMcpError::InstanceNotFound(instance_id)),
     };
     
     // Remove the instance
     dom.destroy(instance_id);
-    println!("Removed instance: {}", instance_name);
+    debug!("Removed instance: {}", instance_name);
     
-    Ok(())
+    Ok(parent_id)
+}
+
+/// Move an instance under a new parent, refusing to move it into its own subtree
+/// Returns the parent it was moved from
+pub fn move_instance(dom: &mut WeakDom, instance_id: Ref, new_parent_id: Ref) -> Result<Ref, RbxMcpError> {
+    // The new parent may have been removed since it was looked up
+    if dom.get_by_ref(new_parent_id).is_none() {
+        return Err(RbxMcpError::InvalidOperation("The new parent no longer exists".to_string()));
+    }
+    let mut current = new_parent_id;
+    while let Some(instance) = dom.get_by_ref(current) {
+        if current == instance_id {
+            return Err(RbxMcpError::InvalidOperation("Cannot move an instance into itself or one of its descendants".to_string()));
+        }
+        current = instance.parent();
+    }
+    let old_parent_id = match dom.get_by_ref(instance_id) {
+        Some(instance) => instance.parent(),
+        None => return Err(RbxMcpError::InstanceNotFound(instance_id)),
+    };
+
+    dom.transfer_within(instance_id, new_parent_id);
+    Ok(old_parent_id)
+}
+
+/// Deep copy an instance under a new parent, shifting every CFrame in the copy by offset
+pub fn clone_instance(dom: &mut WeakDom, source_id: Ref, parent_id: Ref, offset: Option<Vector3>) -> Ref {
+    let copy_id = dom.clone_within(source_id);
+    dom.transfer_within(copy_id, parent_id);
+
+    if let Some(offset) = offset {
+        shift_cframes(dom, copy_id, offset);
+    }
+
+    copy_id
+}
+
+/// Shift every CFrame and pivot in a subtree by offset
+pub fn shift_cframes(dom: &mut WeakDom, root_id: Ref, offset: Vector3) {
+    let ids: Vec<Ref> = dom.descendants_of(root_id).map(|i| i.referent()).collect();
+    for id in ids {
+        let Some(instance) = dom.get_by_ref_mut(id) else {
+            continue;
+        };
+        for property in ["CFrame", "WorldPivotData"] {
+            match instance.properties.get_mut(&property.into()) {
+                Some(Variant::CFrame(cframe)) | Some(Variant::OptionalCFrame(Some(cframe))) => {
+                    let p = cframe.position;
+                    cframe.position = Vector3::new(p.x + offset.x, p.y + offset.y, p.z + offset.z);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The instances a filter matches, or None if the instance it's limited to doesn't exist.
+/// Services are never included.
+pub fn find_matching(dom: &WeakDom, data_model_id: Ref, filter: &InstanceFilter) -> Option<Vec<Ref>> {
+    let root_id = match &filter.under {
+        Some(path) => find_instance_by_path(dom, data_model_id, path)?,
+        None => data_model_id,
+    };
+    Some(dom.descendants_of(root_id)
+        .filter(|instance| instance.referent() != root_id && !is_service_class(instance.class.as_str()))
+        .filter(|instance| filter.class.as_deref().is_none_or(|class| is_a(instance.class.as_str(), class)))
+        .filter(|instance| filter.tag.as_deref().is_none_or(|tag| has_tag(instance, tag)))
+        .map(|instance| instance.referent())
+        .collect())
+}
+
+/// Whether an instance has a CollectionService tag
+pub fn has_tag(instance: &Instance, tag: &str) -> bool {
+    matches!(instance.properties.get(&"Tags".into()), Some(Variant::Tags(tags)) if tags.iter().any(|t| t == tag))
+}
+
+/// Remove several instances, collecting warnings for any that fail
+fn remove_all(
+    dom: &mut WeakDom,
+    index: &mut PathIndex,
+    instance_ids: &[Ref],
+    warnings: &mut Vec<ApplyWarning>,
+    changed: &mut Vec<Ref>,
+    counts: &mut ChangeCounts,
+) {
+    for &instance_id in instance_ids {
+        let path = instance_path(dom, instance_id);
+        counts.record_removed(dom, instance_id);
+        match index.remove(dom, instance_id) {
+            Ok(parent_id) => changed.push(parent_id),
+            Err(e) => warnings.push(ApplyWarning::OperationFailed {
+                operation: "remove".to_string(),
+                path,
+                reason: e.to_string(),
+            }),
+        }
+    }
+}
+
+/// Copy a whole place, keeping every instance's referent so the copy can be compared with the place
+/// after a change, or used in its place
+pub fn snapshot(dom: &WeakDom) -> WeakDom {
+    fn builder(dom: &WeakDom, instance: &Instance) -> InstanceBuilder {
+        InstanceBuilder::new(instance.class)
+            .with_referent(instance.referent())
+            .with_name(instance.name.as_str())
+            .with_properties(instance.properties.iter().map(|(name, value)| (*name, value.clone())))
+            .with_children(instance.children().iter()
+                .filter_map(|&child_id| dom.get_by_ref(child_id))
+                .map(|child| builder(dom, child)))
+    }
+    WeakDom::new(builder(dom, dom.root()))
 }
 
 /// Write a Roblox WeakDom to a file
+/// The place is written to a temporary file next to the target and renamed over it,
+/// so a crash mid-write never leaves a truncated place behind.
+/// Fails with ChangedOnDisk if something else saved the file since this process read it.
 pub fn write_roblox_file(
     path: impl AsRef<Path>,
     model: &WeakDom,
-) -> Result<(), Box<dyn Error>> {
-    let file = BufWriter::new(File::create(path)?);
-    rbx_xml::to_writer_default(file, model, model.root().children())?;
+) -> Result<(), RbxMcpError> {
+    write_atomically(path.as_ref(), |writer| {
+        rbx_xml::to_writer_default(writer, model, model.root().children())?;
+        Ok(())
+    })
+}
+
+/// Write a file through a temporary file that is synced to disk and then renamed over path
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), RbxMcpError>,
+) -> Result<(), RbxMcpError> {
+    let file_name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Output path has no file name"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    lock::verify_checksum(path)?;
+    let result = write_and_sync(&temp_path, write).and_then(|()| Ok(std::fs::rename(&temp_path, path)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result?;
+    lock::record_current(path)
+}
+
+/// Write to path and flush it all the way to disk
+fn write_and_sync(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), RbxMcpError>,
+) -> Result<(), RbxMcpError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A place whose Workspace holds names that look like path syntax
+    fn tricky_place() -> (WeakDom, Vec<Ref>) {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace_id = dom.insert(root_ref, InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let ids = ["Part", "Part", "Part[2]", "Model:House", "Up/Down", "Back\\slash", "Lone[2]"]
+            .into_iter()
+            .map(|name| dom.insert(workspace_id, InstanceBuilder::new("Part").with_name(name)))
+            .collect();
+        (dom, ids)
+    }
+
+    #[test]
+    fn instance_paths_resolve_to_the_same_instance() {
+        let (dom, ids) = tricky_place();
+        let root_ref = dom.root_ref();
+        let mut index = PathIndex::new();
+        for id in ids {
+            let path = instance_path(&dom, id);
+            assert_eq!(find_instance_by_path(&dom, root_ref, &path), Some(id), "{}", path);
+            assert_eq!(index.find(&dom, root_ref, &path), Some(id), "{}", path);
+            assert_eq!(index.path(&dom, id), path);
+        }
+    }
+
+    #[test]
+    fn index_syntax_wins_over_a_name_that_looks_like_it() {
+        let (dom, ids) = tricky_place();
+        let root_ref = dom.root_ref();
+        assert_eq!(instance_path(&dom, ids[1]), "Workspace/Part[2]");
+        assert_eq!(instance_path(&dom, ids[2]), "Workspace/Part\\[2\\]");
+        assert_eq!(find_instance_by_path(&dom, root_ref, "Workspace/Part[2]"), Some(ids[1]));
+        assert_eq!(find_instance_by_path(&dom, root_ref, "Workspace/Part\\[2\\]"), Some(ids[2]));
+    }
+
+    #[test]
+    fn unescaped_names_still_resolve() {
+        let (dom, ids) = tricky_place();
+        let root_ref = dom.root_ref();
+        assert_eq!(find_instance_by_path(&dom, root_ref, "Workspace/Lone[2]"), Some(ids[6]));
+        assert_eq!(find_instance_by_path(&dom, root_ref, "Workspace/Model:House"), Some(ids[3]));
+        assert_eq!(find_instance_by_path(&dom, root_ref, "Workspace/Part:Part[1]"), Some(ids[0]));
+    }
+
+    #[test]
+    fn globs_escape_and_match_one_segment() {
+        assert!(glob_regex("Tree?").is_match("Tree1"));
+        assert!(!glob_regex("Tree?").is_match("Tree12"));
+        assert!(glob_regex("Tree*").is_match("Tree"));
+        assert!(glob_regex("Tree\\*").is_match("Tree*"));
+        assert!(!glob_regex("Tree\\*").is_match("Tree1"));
+        assert!(glob_regex("a.b*").is_match("a.bc"));
+        assert!(!glob_regex("a.b*").is_match("axbc"));
+    }
+
+    #[test]
+    fn any_depth_matches_every_level_once() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let workspace_id = dom.insert(root_ref, InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let near = dom.insert(workspace_id, InstanceBuilder::new("Decal").with_name("Decal"));
+        let model_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Model"));
+        let inner_id = dom.insert(model_id, InstanceBuilder::new("Folder").with_name("Inner"));
+        let deep = dom.insert(inner_id, InstanceBuilder::new("Decal").with_name("Decal"));
+        let star = dom.insert(model_id, InstanceBuilder::new("Part").with_name("Star*"));
+        dom.insert(model_id, InstanceBuilder::new("Part").with_name("Starfish"));
+
+        let mut found = find_instances_by_path(&dom, root_ref, "Workspace/**/Decal");
+        found.sort_by_key(|&id| instance_path(&dom, id));
+        let mut expected = vec![near, deep];
+        expected.sort_by_key(|&id| instance_path(&dom, id));
+        assert_eq!(found, expected);
+        assert_eq!(find_instances_by_path(&dom, root_ref, "Workspace/**/Star\\*"), vec![star]);
+        assert_eq!(find_instances_by_path(&dom, root_ref, "**/Folder:*"), vec![inner_id]);
+    }
+
+    fn place_with_folder() -> (WeakDom, Ref) {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let folder_id = dom.insert(workspace_id, InstanceBuilder::new("Folder").with_name("Old"));
+        (dom, folder_id)
+    }
+
+    #[test]
+    fn bad_attributes_are_refused_before_anything_changes() {
+        let (mut dom, folder_id) = place_with_folder();
+        let modification: Modification = serde_json::from_value(serde_json::json!({
+            "subtract": ["Workspace/Old"],
+            "add": [{ "name": "Sign", "class": "Part", "properties": {}, "attributes": { "Owner": { "id": 1 } } }],
+        })).unwrap();
+        let root_ref = dom.root_ref();
+        let result = json_to_weakdom(&mut dom, &modification, root_ref, &ApplyOptions::default());
+        assert!(matches!(result, Err(RbxMcpError::InvalidModification(_))));
+        assert!(dom.get_by_ref(folder_id).is_some());
+    }
+
+    #[test]
+    fn add_parents_refer_to_the_place_before_renames() {
+        let (mut dom, folder_id) = place_with_folder();
+        let modification: Modification = serde_json::from_value(serde_json::json!({
+            "rename": [{ "path": "Workspace/Old", "new_name": "New" }],
+            "add": [{ "name": "Sign", "class": "Part", "properties": {}, "target_parent": "Workspace/Old" }],
+        })).unwrap();
+        let root_ref = dom.root_ref();
+        let report = json_to_weakdom(&mut dom, &modification, root_ref, &ApplyOptions::default()).unwrap();
+        assert!(report.warnings.is_empty());
+        let sign_id = find_instance_by_path(&dom, root_ref, "Workspace/New/Sign").unwrap();
+        assert_eq!(dom.get_by_ref(sign_id).unwrap().parent(), folder_id);
+    }
+
+    /// Parts at x = 0, 10 and 100 in Workspace, plus one at x = 5 inside a package
+    fn place_with_row_of_parts() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let part_at = |name: &str, x: f32| {
+            let cframe = rbx_dom_weak::types::CFrame::new(Vector3::new(x, 0.0, 0.0), property::euler_degrees_to_matrix(0.0, 0.0, 0.0));
+            InstanceBuilder::new("Part").with_name(name).with_property("CFrame", cframe)
+        };
+        for (name, x) in [("Spawn", 0.0), ("Near", 10.0), ("Far", 100.0)] {
+            dom.insert(workspace_id, part_at(name, x));
+        }
+        let package_id = dom.insert(workspace_id, InstanceBuilder::new("Model").with_name("Package"));
+        dom.insert(package_id, InstanceBuilder::new("PackageLink"));
+        dom.insert(package_id, part_at("Packaged", 5.0));
+        dom
+    }
+
+    fn transparency(dom: &WeakDom, path: &str) -> Option<f32> {
+        let instance = dom.get_by_ref(find_instance_by_path(dom, dom.root_ref(), path)?)?;
+        match instance.properties.get(&"Transparency".into()) {
+            Some(Variant::Float32(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn set_can_pick_instances_by_region_or_distance() {
+        // Like subtract, near leaves out the instance it's measured from
+        for (target, spawn) in [
+            (serde_json::json!({ "region": { "min": [-1, -1, -1], "max": [20, 1, 1] } }), Some(0.5)),
+            (serde_json::json!({ "near": "Workspace/Spawn", "radius": 15 }), None),
+        ] {
+            let mut dom = place_with_row_of_parts();
+            let mut op = target.clone();
+            op["properties"] = serde_json::json!({ "Transparency": { "type": "Float32", "value": 0.5 } });
+            let modification: Modification = serde_json::from_value(serde_json::json!({ "add": [], "set": [op] })).unwrap();
+            let root_ref = dom.root_ref();
+            let report = json_to_weakdom(&mut dom, &modification, root_ref, &ApplyOptions::default()).unwrap();
+            assert!(report.warnings.is_empty(), "{}", target);
+            assert_eq!(transparency(&dom, "Workspace/Spawn"), spawn, "{}", target);
+            assert_eq!(transparency(&dom, "Workspace/Near"), Some(0.5), "{}", target);
+            assert_eq!(transparency(&dom, "Workspace/Far"), None, "{}", target);
+            // Package contents are left alone unless package edits are allowed
+            assert_eq!(transparency(&dom, "Workspace/Package/Packaged"), None, "{}", target);
+        }
+    }
+
+    #[test]
+    fn set_by_path_still_reads_and_writes_a_path() {
+        let op: SetOperation = serde_json::from_value(serde_json::json!({ "path": "Workspace/Spawn", "properties": {} })).unwrap();
+        assert!(matches!(&op.target, SetTarget::Path { path } if path == "Workspace/Spawn"));
+        assert_eq!(serde_json::to_value(&op).unwrap(), serde_json::json!({ "path": "Workspace/Spawn", "properties": {} }));
+    }
+}
@@ -0,0 +1,522 @@
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{ustr, Instance, InstanceBuilder, WeakDom};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::property::PropertyValue;
+use crate::roblox::{instance_path, parse_roblox_file, json_to_attributes, set_attribute};
+use crate::scripts::{is_file_name, script_suffix};
+
+/// Name of the project file Rojo looks for in a project directory
+pub const PROJECT_FILE: &str = "default.project.json";
+
+/// Properties Studio gives every instance its own value for, which only add noise to a project
+const IDENTITY_PROPERTIES: [&str; 3] = ["UniqueId", "HistoryId", "ScriptGuid"];
+
+/// Convert a place to a Rojo project in dir, which must be empty or not exist yet.
+///
+/// Each service gets a node in default.project.json and a directory under src/. Scripts become
+/// .luau files, instances with scripts somewhere under them become directories, and everything
+/// else is written as an .rbxmx model. Properties that differ from their defaults go in the
+/// project file or a .meta.json file. Returns the files written.
+pub fn export_project(dom: &WeakDom, dir: &Path, name: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!("{} is not empty", dir.display()).into());
+    }
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+
+    let mut tree = Map::new();
+    tree.insert("$className".to_string(), json!("DataModel"));
+    for service_id in file_children(dom, dom.root_ref()) {
+        let Some(service) = dom.get_by_ref(service_id) else {
+            continue;
+        };
+        let mut node = Map::new();
+        node.insert("$className".to_string(), json!(service.class));
+        let properties = properties_to_json(service, &[]);
+        if !properties.is_empty() {
+            node.insert("$properties".to_string(), Value::Object(properties));
+        }
+        if !service.children().is_empty() {
+            let service_dir = dir.join("src").join(&service.name);
+            std::fs::create_dir_all(&service_dir)?;
+            write_children(dom, service_id, &service_dir, &mut written)?;
+            node.insert("$path".to_string(), json!(format!("src/{}", service.name)));
+        }
+        tree.insert(service.name.clone(), Value::Object(node));
+    }
+
+    let project = json!({ "name": name, "tree": tree });
+    let project_path = dir.join(PROJECT_FILE);
+    write_json(&project_path, &project)?;
+    written.push(project_path);
+    Ok(written)
+}
+
+fn write_children(dom: &WeakDom, parent_id: Ref, dir: &Path, written: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for child_id in file_children(dom, parent_id) {
+        write_instance(dom, child_id, dir, written)?;
+    }
+    Ok(())
+}
+
+/// Write one instance and everything under it into dir
+fn write_instance(dom: &WeakDom, id: Ref, dir: &Path, written: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let Some(instance) = dom.get_by_ref(id) else {
+        return Ok(());
+    };
+
+    if let Some(suffix) = script_suffix(&instance.class) {
+        let source = match instance.properties.get(&"Source".into()) {
+            Some(Variant::String(source)) => source.as_str(),
+            _ => "",
+        };
+        let meta = meta_json(instance, None, &["Source"]);
+        if instance.children().is_empty() {
+            write_file(&dir.join(format!("{}{}.luau", instance.name, suffix)), source, written)?;
+            if let Some(meta) = meta {
+                write_meta(&dir.join(format!("{}.meta.json", instance.name)), &meta, written)?;
+            }
+            return Ok(());
+        }
+        if has_file_children(dom, id) {
+            let script_dir = dir.join(&instance.name);
+            std::fs::create_dir_all(&script_dir)?;
+            write_file(&script_dir.join(format!("init{}.luau", suffix)), source, written)?;
+            if let Some(meta) = meta {
+                write_meta(&script_dir.join("init.meta.json"), &meta, written)?;
+            }
+            return write_children(dom, id, &script_dir, written);
+        }
+    } else if has_script_descendant(dom, id) && has_file_children(dom, id) {
+        // A directory is a Folder unless its init.meta.json names another class
+        let class_name = (instance.class != "Folder").then_some(instance.class.as_str());
+        let instance_dir = dir.join(&instance.name);
+        std::fs::create_dir_all(&instance_dir)?;
+        if let Some(meta) = meta_json(instance, class_name, &[]) {
+            write_meta(&instance_dir.join("init.meta.json"), &meta, written)?;
+        }
+        return write_children(dom, id, &instance_dir, written);
+    }
+
+    let model_path = dir.join(format!("{}.rbxmx", instance.name));
+    let file = BufWriter::new(File::create(&model_path)?);
+    rbx_xml::to_writer_default(file, dom, &[id])?;
+    debug!("Wrote {}", model_path.display());
+    written.push(model_path);
+    Ok(())
+}
+
+/// Children whose names can be file names, skipping any that share a name with an earlier sibling
+fn file_children(dom: &WeakDom, parent_id: Ref) -> Vec<Ref> {
+    let Some(parent) = dom.get_by_ref(parent_id) else {
+        return Vec::new();
+    };
+    let mut names = HashSet::new();
+    let mut children = Vec::new();
+    for &child_id in parent.children() {
+        let Some(child) = dom.get_by_ref(child_id) else {
+            continue;
+        };
+        if !is_file_name(&child.name) {
+            warn!("Skipping {}: its name can't be a file name", instance_path(dom, child_id));
+        } else if !names.insert(child.name.as_str()) {
+            warn!("Skipping {}: a sibling has the same name", instance_path(dom, child_id));
+        } else {
+            children.push(child_id);
+        }
+    }
+    children
+}
+
+/// Whether every child can be written as its own file, so the instance can be a directory
+fn has_file_children(dom: &WeakDom, id: Ref) -> bool {
+    let Some(instance) = dom.get_by_ref(id) else {
+        return false;
+    };
+    let mut names = HashSet::new();
+    instance.children().iter().all(|&child_id| {
+        dom.get_by_ref(child_id)
+            .is_some_and(|child| is_file_name(&child.name) && names.insert(child.name.as_str()))
+    })
+}
+
+fn has_script_descendant(dom: &WeakDom, id: Ref) -> bool {
+    dom.descendants_of(id).any(|instance| instance.referent() != id && script_suffix(&instance.class).is_some())
+}
+
+/// The contents of a .meta.json file, or None if there's nothing to put in it
+fn meta_json(instance: &Instance, class_name: Option<&str>, skip: &[&str]) -> Option<Value> {
+    let mut meta = Map::new();
+    if let Some(class_name) = class_name {
+        meta.insert("className".to_string(), json!(class_name));
+    }
+    let properties = properties_to_json(instance, skip);
+    if !properties.is_empty() {
+        meta.insert("properties".to_string(), Value::Object(properties));
+    }
+    (!meta.is_empty()).then_some(Value::Object(meta))
+}
+
+/// Properties that differ from the class defaults, in Rojo's explicitly typed form like {"Vector3": [0, 1, 0]}.
+/// Refs are left out since Rojo can't name their targets, and so are the identity properties.
+fn properties_to_json(instance: &Instance, skip: &[&str]) -> Map<String, Value> {
+    let mut properties = Map::new();
+    for (name, value) in &instance.properties {
+        if skip.contains(&name.as_str()) || IDENTITY_PROPERTIES.contains(&name.as_str()) || matches!(value, Variant::Ref(_)) || is_default(&instance.class, name, value) {
+            continue;
+        }
+        if let Variant::Attributes(attributes) = value {
+            if attributes.is_empty() {
+                continue;
+            }
+        }
+        match serde_json::to_value(value) {
+            Ok(json) => {
+                properties.insert(name.to_string(), json);
+            }
+            Err(e) => debug!("Leaving out {}.{}: {}", instance.name, name, e),
+        }
+    }
+    properties
+}
+
+/// Whether a property has the value the reflection database gives as its default
+fn is_default(class: &str, property: &str, value: &Variant) -> bool {
+    let database = rbx_reflection_database::get();
+    let Some(descriptor) = database.classes.get(class) else {
+        return false;
+    };
+    database.superclasses_iter(descriptor)
+        .find_map(|class_descriptor| class_descriptor.default_properties.get(property))
+        .is_some_and(|default| default == value)
+}
+
+fn write_file(path: &Path, contents: &str, written: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, contents)?;
+    debug!("Wrote {}", path.display());
+    written.push(path.to_path_buf());
+    Ok(())
+}
+
+fn write_meta(path: &Path, meta: &Value, written: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    write_json(path, meta)?;
+    written.push(path.to_path_buf());
+    Ok(())
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, serde_json::to_string_pretty(value)? + "\n")?;
+    debug!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Script file suffixes and the classes they make, longest first so .server.luau isn't read as a ModuleScript
+const SCRIPT_FILES: [(&str, &str); 6] = [
+    (".server.luau", "Script"),
+    (".server.lua", "Script"),
+    (".client.luau", "LocalScript"),
+    (".client.lua", "LocalScript"),
+    (".luau", "ModuleScript"),
+    (".lua", "ModuleScript"),
+];
+
+/// Build a place from a Rojo project. project is a directory with a default.project.json
+/// or a .project.json file, whose tree must be a DataModel.
+///
+/// Supports $className, $path, $properties and $attributes in the project, and in the
+/// filesystem: directories, init scripts, script files, .meta.json files, .rbxmx models,
+/// .model.json models, .txt files as StringValues and nested projects.
+pub fn build_project(project: &Path) -> Result<WeakDom, Box<dyn Error>> {
+    let project_path = if project.is_dir() { project.join(PROJECT_FILE) } else { project.to_path_buf() };
+    let (tree, base_dir) = read_project(&project_path)?;
+    if tree.get("$className").and_then(Value::as_str) != Some("DataModel") {
+        return Err(format!("{} doesn't describe a place: its tree must have \"$className\": \"DataModel\"", project_path.display()).into());
+    }
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+    apply_node(&mut dom, root_ref, &tree, &base_dir)?;
+    Ok(dom)
+}
+
+/// The tree of a project file and the directory its paths are relative to
+fn read_project(project_path: &Path) -> Result<(Map<String, Value>, PathBuf), Box<dyn Error>> {
+    let text = std::fs::read_to_string(project_path)
+        .map_err(|e| format!("Error reading {}: {}", project_path.display(), e))?;
+    let project: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Error parsing {}: {}", project_path.display(), e))?;
+    let Some(Value::Object(tree)) = project.get("tree") else {
+        return Err(format!("{} has no tree", project_path.display()).into());
+    };
+    let base_dir = project_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok((tree.clone(), base_dir))
+}
+
+/// Create the instance a project node describes under parent_id
+fn build_node(dom: &mut WeakDom, parent_id: Ref, name: &str, node: &Map<String, Value>, base_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let class_name = node.get("$className").and_then(Value::as_str);
+    let id = match node.get("$path").and_then(Value::as_str) {
+        Some(path) => {
+            let Some(id) = build_path(dom, parent_id, &base_dir.join(path), Some(name))? else {
+                return Ok(());
+            };
+            if let (Some(class_name), Some(instance)) = (class_name, dom.get_by_ref_mut(id)) {
+                instance.class = ustr(class_name);
+            }
+            id
+        }
+        None => {
+            let class_name = class_name.ok_or_else(|| format!("Project node {} needs a $className or a $path", name))?;
+            dom.insert(parent_id, InstanceBuilder::new(class_name).with_name(name))
+        }
+    };
+    apply_node(dom, id, node, base_dir)
+}
+
+/// Apply a node's properties and attributes to an instance and build its child nodes
+fn apply_node(dom: &mut WeakDom, id: Ref, node: &Map<String, Value>, base_dir: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(Value::Object(properties)) = node.get("$properties") {
+        set_properties(dom, id, properties);
+    }
+    if let Some(Value::Object(attributes)) = node.get("$attributes") {
+        set_attributes(dom, id, attributes)?;
+    }
+    for (name, child) in node {
+        if let (false, Value::Object(child)) = (name.starts_with('$'), child) {
+            build_node(dom, id, name, child, base_dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create the instance for a file or directory under parent_id, named after the file unless name is given.
+/// Returns None for files Rojo would ignore or this tool can't read.
+fn build_path(dom: &mut WeakDom, parent_id: Ref, path: &Path, name: Option<&str>) -> Result<Option<Ref>, Box<dyn Error>> {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+
+    if path.is_dir() {
+        let name = name.unwrap_or(&file_name);
+        let nested_project = path.join(PROJECT_FILE);
+        if nested_project.is_file() {
+            let (tree, base_dir) = read_project(&nested_project)?;
+            build_node(dom, parent_id, name, &tree, &base_dir)?;
+            return Ok(dom.get_by_ref(parent_id).and_then(|parent| parent.children().last().copied()));
+        }
+
+        let init = SCRIPT_FILES.iter().find_map(|(suffix, class)| {
+            let init_path = path.join(format!("init{}", suffix));
+            init_path.is_file().then_some((init_path, *class))
+        });
+        let id = match &init {
+            Some((init_path, class)) => {
+                let source = std::fs::read_to_string(init_path)?;
+                dom.insert(parent_id, InstanceBuilder::new(*class).with_name(name).with_property("Source", Variant::String(source)))
+            }
+            None => dom.insert(parent_id, InstanceBuilder::new("Folder").with_name(name)),
+        };
+        apply_meta(dom, id, &path.join("init.meta.json"), init.is_none())?;
+
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let entry_name = entry.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if entry_name.starts_with('.') || entry_name.starts_with("init.") || entry_name.ends_with(".meta.json") {
+                continue;
+            }
+            build_path(dom, id, &entry, None)?;
+        }
+        return Ok(Some(id));
+    }
+
+    let (stem, id) = if let Some((suffix, class)) = SCRIPT_FILES.iter().find(|(suffix, _)| file_name.ends_with(suffix)) {
+        let stem = &file_name[..file_name.len() - suffix.len()];
+        let source = std::fs::read_to_string(path)?;
+        let builder = InstanceBuilder::new(*class)
+            .with_name(name.unwrap_or(stem))
+            .with_property("Source", Variant::String(source));
+        (stem, dom.insert(parent_id, builder))
+    } else if let Some(stem) = file_name.strip_suffix(".model.json") {
+        let text = std::fs::read_to_string(path)?;
+        let model: Value = serde_json::from_str(&text).map_err(|e| format!("Error parsing {}: {}", path.display(), e))?;
+        (stem, build_json_model(dom, parent_id, &model, name.unwrap_or(stem))?)
+    } else if let Some(stem) = file_name.strip_suffix(".rbxmx") {
+        let mut model = parse_roblox_file(path)?;
+        let &[model_root] = model.root().children() else {
+            return Err(format!("{} must hold exactly one instance", path.display()).into());
+        };
+        model.transfer(model_root, dom, parent_id);
+        if let Some(instance) = dom.get_by_ref_mut(model_root) {
+            instance.name = name.unwrap_or(stem).to_string();
+        }
+        (stem, model_root)
+    } else if let Some(stem) = file_name.strip_suffix(".txt") {
+        let text = std::fs::read_to_string(path)?;
+        let builder = InstanceBuilder::new("StringValue")
+            .with_name(name.unwrap_or(stem))
+            .with_property("Value", Variant::String(text));
+        (stem, dom.insert(parent_id, builder))
+    } else {
+        warn!("Skipping {}: not a file type build-rojo reads", path.display());
+        return Ok(None);
+    };
+
+    apply_meta(dom, id, &path.with_file_name(format!("{}.meta.json", stem)), false)?;
+    Ok(Some(id))
+}
+
+/// Create the instance a .model.json file describes, and its children
+fn build_json_model(dom: &mut WeakDom, parent_id: Ref, model: &Value, name: &str) -> Result<Ref, Box<dyn Error>> {
+    let field = |lower: &str, upper: &str| model.get(lower).or_else(|| model.get(upper));
+    let class_name = field("className", "ClassName")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Model {} has no className", name))?;
+    let id = dom.insert(parent_id, InstanceBuilder::new(class_name).with_name(name));
+    if let Some(Value::Object(properties)) = field("properties", "Properties") {
+        set_properties(dom, id, properties);
+    }
+    if let Some(Value::Object(attributes)) = field("attributes", "Attributes") {
+        set_attributes(dom, id, attributes)?;
+    }
+    if let Some(Value::Array(children)) = field("children", "Children") {
+        for child in children {
+            let child_name = child.get("name").or_else(|| child.get("Name")).and_then(Value::as_str).unwrap_or(class_name);
+            build_json_model(dom, id, child, child_name)?;
+        }
+    }
+    Ok(id)
+}
+
+/// Apply a .meta.json file to an instance if it exists. className is only honoured for directories without an init script.
+fn apply_meta(dom: &mut WeakDom, id: Ref, meta_path: &Path, allow_class_name: bool) -> Result<(), Box<dyn Error>> {
+    if !meta_path.is_file() {
+        return Ok(());
+    }
+    let text = std::fs::read_to_string(meta_path)?;
+    let meta: Value = serde_json::from_str(&text).map_err(|e| format!("Error parsing {}: {}", meta_path.display(), e))?;
+
+    if let Some(class_name) = meta.get("className").and_then(Value::as_str) {
+        match dom.get_by_ref_mut(id) {
+            Some(instance) if allow_class_name => instance.class = ustr(class_name),
+            _ => warn!("{}: className only applies to directories without an init script", meta_path.display()),
+        }
+    }
+    if let Some(Value::Object(properties)) = meta.get("properties") {
+        set_properties(dom, id, properties);
+    }
+    if let Some(Value::Object(attributes)) = meta.get("attributes") {
+        set_attributes(dom, id, attributes)?;
+    }
+    Ok(())
+}
+
+/// Set properties given explicitly typed like {"Vector3": [0, 1, 0]} or implicitly like [0, 1, 0].
+/// Properties that can't be converted are skipped with a warning.
+fn set_properties(dom: &mut WeakDom, id: Ref, properties: &Map<String, Value>) {
+    let Some(instance) = dom.get_by_ref_mut(id) else {
+        return;
+    };
+    for (name, value) in properties {
+        match property_from_json(&instance.class, name, value) {
+            Ok(variant) => {
+                instance.properties.insert(ustr(name), variant);
+            }
+            Err(e) => warn!("Skipping {}.{}: {}", instance.name, name, e),
+        }
+    }
+}
+
+fn property_from_json(class: &str, property: &str, value: &Value) -> Result<Variant, String> {
+    if value.is_object() {
+        return serde_json::from_value(value.clone()).map_err(|e| e.to_string());
+    }
+
+    let property_value = PropertyValue::infer(class, property, value.clone())?;
+    property_value.to_variant(class, property)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} values must be given with an explicit type", property_value.type_name()))
+}
+
+/// Add attributes given as plain JSON values
+fn set_attributes(dom: &mut WeakDom, id: Ref, attributes: &Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    let values = attributes.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+    for (name, value) in json_to_attributes(&values)?.iter() {
+        set_attribute(dom, id, name, value.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roblox::find_instance_by_path;
+    use rbx_dom_weak::types::Vector3;
+
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rbx-mcp-rojo-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn sample_place() -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let storage_id = dom.insert(dom.root_ref(), InstanceBuilder::new("ServerScriptService").with_name("ServerScriptService"));
+        dom.insert(storage_id, InstanceBuilder::new("Script").with_name("Main").with_property("Source", "print(\"hi\")"));
+        let lib_id = dom.insert(storage_id, InstanceBuilder::new("Folder").with_name("Lib"));
+        dom.insert(lib_id, InstanceBuilder::new("ModuleScript").with_name("Util").with_property("Source", "return {}"));
+        let workspace_id = dom.insert(dom.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        dom.insert(workspace_id, InstanceBuilder::new("Part").with_name("Floor").with_property("Size", Vector3::new(64.0, 1.0, 64.0)));
+        dom
+    }
+
+    fn property<'a>(dom: &'a WeakDom, path: &str, name: &str) -> Option<&'a Variant> {
+        let id = find_instance_by_path(dom, dom.root_ref(), path)?;
+        dom.get_by_ref(id)?.properties.get(&name.into())
+    }
+
+    #[test]
+    fn an_exported_project_builds_back_into_the_place() {
+        let dir = temp_project("round-trip");
+        let written = export_project(&sample_place(), &dir, "Sample").unwrap();
+        assert!(written.contains(&dir.join("src/ServerScriptService/Main.server.luau")));
+        assert!(written.contains(&dir.join("src/ServerScriptService/Lib/Util.luau")));
+        assert!(written.contains(&dir.join("src/Workspace/Floor.rbxmx")));
+
+        let built = build_project(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(property(&built, "ServerScriptService/Main", "Source"), Some(&Variant::String("print(\"hi\")".to_string())));
+        assert_eq!(property(&built, "ServerScriptService/Lib/Util", "Source"), Some(&Variant::String("return {}".to_string())));
+        let lib_id = find_instance_by_path(&built, built.root_ref(), "ServerScriptService/Lib").unwrap();
+        assert_eq!(built.get_by_ref(lib_id).unwrap().class, "Folder");
+        assert_eq!(property(&built, "Workspace/Floor", "Size"), Some(&Variant::Vector3(Vector3::new(64.0, 1.0, 64.0))));
+    }
+
+    #[test]
+    fn export_refuses_a_directory_with_files_in_it() {
+        let dir = temp_project("not-empty");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "keep me").unwrap();
+        let result = export_project(&sample_place(), &dir, "Sample");
+        assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "keep me");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.unwrap_err().to_string().contains("is not empty"));
+    }
+
+    #[test]
+    fn build_refuses_a_project_that_isnt_a_place() {
+        let dir = temp_project("model");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(PROJECT_FILE), r#"{"name": "Lib", "tree": {"$className": "Folder"}}"#).unwrap();
+        let result = build_project(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.unwrap_err().to_string().contains("doesn't describe a place"));
+    }
+}
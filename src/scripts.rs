@@ -0,0 +1,157 @@
+use rbx_dom_weak::types::Variant;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::luau;
+use crate::roblox::{find_instance_by_path, instance_path, set_attribute, CHANGE_ID_ATTRIBUTE};
+
+/// Script classes and the suffix their files get, in the style Rojo uses
+const SCRIPT_SUFFIXES: [(&str, &str); 3] = [
+    ("Script", ".server"),
+    ("LocalScript", ".client"),
+    ("ModuleScript", ""),
+];
+
+/// What an import changed
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub updated: Vec<String>,
+    pub created: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Write the Source of every script in the place to a file under dir, mirroring its path.
+/// A Script at Workspace/Door/Open is written to dir/Workspace/Door/Open.server.luau.
+/// Returns the files written.
+pub fn export_scripts(dom: &WeakDom, dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut written = Vec::new();
+    let mut seen = HashSet::new();
+    for instance in dom.descendants() {
+        let Some(suffix) = script_suffix(&instance.class) else {
+            continue;
+        };
+        let path = instance_path(dom, instance.referent());
+        if path.split('/').any(|name| !is_file_name(name)) {
+            warn!("Skipping {}: its path has a name that can't be a file name", path);
+            continue;
+        }
+        // Siblings with the same name would write the same file
+        if !seen.insert(path.clone()) {
+            warn!("Skipping {}: another script has the same path", path);
+            continue;
+        }
+        let source = match instance.properties.get(&"Source".into()) {
+            Some(Variant::String(source)) => source.as_str(),
+            _ => "",
+        };
+
+        let file = dir.join(format!("{}{}.luau", path, suffix));
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&file, source)?;
+        debug!("Wrote {}", file.display());
+        written.push(file);
+    }
+    Ok(written)
+}
+
+/// Write every script file under dir back into the place. Scripts are matched by path and class,
+/// and files for scripts the place doesn't have yet are created if their parent exists.
+/// Updated and created scripts are tagged with change_id.
+pub fn import_scripts(dom: &mut WeakDom, dir: &Path, change_id: Option<&str>) -> Result<ImportReport, Box<dyn Error>> {
+    let mut report = ImportReport::default();
+    let mut files = Vec::new();
+    collect_script_files(dir, &mut files)?;
+    files.sort();
+
+    let root_ref = dom.root_ref();
+    for file in files {
+        let Some((path, class)) = script_for_file(dir, &file) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(&file)?;
+        // Imported anyway, since the file is what the user wants in the place
+        for error in luau::syntax_errors(&source) {
+            warn!("{}: syntax error, {}", file.display(), error);
+        }
+
+        let id = match find_instance_by_path(dom, root_ref, &path) {
+            Some(id) => {
+                let Some(instance) = dom.get_by_ref_mut(id) else {
+                    continue;
+                };
+                if instance.class != class {
+                    warn!("Skipping {}: {} is a {}, not a {}", file.display(), path, instance.class, class);
+                    continue;
+                }
+                if matches!(instance.properties.get(&"Source".into()), Some(Variant::String(current)) if *current == source) {
+                    report.unchanged += 1;
+                    continue;
+                }
+                instance.properties.insert("Source".into(), Variant::String(source));
+                report.updated.push(path);
+                id
+            }
+            None => {
+                let (parent_path, name) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
+                let Some(parent_id) = find_instance_by_path(dom, root_ref, parent_path).filter(|_| !parent_path.is_empty()) else {
+                    warn!("Skipping {}: there is no {} to put it in", file.display(), parent_path);
+                    continue;
+                };
+                let builder = InstanceBuilder::new(class)
+                    .with_name(name)
+                    .with_property("Source", Variant::String(source));
+                let id = dom.insert(parent_id, builder);
+                report.created.push(path);
+                id
+            }
+        };
+        if let Some(change_id) = change_id {
+            set_attribute(dom, id, CHANGE_ID_ATTRIBUTE, Variant::String(change_id.to_string()));
+        }
+    }
+    Ok(report)
+}
+
+pub(crate) fn script_suffix(class: &str) -> Option<&'static str> {
+    SCRIPT_SUFFIXES.iter().find(|(script_class, _)| *script_class == class).map(|(_, suffix)| *suffix)
+}
+
+/// Whether an instance name can be used as is for a file or directory
+pub(crate) fn is_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+}
+
+fn collect_script_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_script_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "luau" || extension == "lua") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The instance path and class a script file stands for
+fn script_for_file(dir: &Path, file: &Path) -> Option<(String, &'static str)> {
+    let relative = file.strip_prefix(dir).ok()?;
+    let relative = relative.to_str()?.replace('\\', "/");
+    let stem = relative.strip_suffix(".luau").or_else(|| relative.strip_suffix(".lua"))?;
+    let (path, class) = if let Some(path) = stem.strip_suffix(".server") {
+        (path, "Script")
+    } else if let Some(path) = stem.strip_suffix(".client") {
+        (path, "LocalScript")
+    } else {
+        (stem, "ModuleScript")
+    };
+    Some((path.to_string(), class))
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::gemini_api::GeminiClient;
+use crate::journal;
+use crate::roblox::ChangeCounts;
+
+/// What an interactive session has done to the place, printed on exit and by `status`
+pub struct SessionStats {
+    started: Instant,
+    /// Prompts sent to Gemini
+    prompts: usize,
+    /// Prompts whose modification was applied to the place
+    applied: usize,
+    /// Changes made with REPL commands like set and rm
+    edits: usize,
+    added: usize,
+    removed: usize,
+    scripts: BTreeSet<String>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        SessionStats {
+            started: Instant::now(),
+            prompts: 0,
+            applied: 0,
+            edits: 0,
+            added: 0,
+            removed: 0,
+            scripts: BTreeSet::new(),
+        }
+    }
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a prompt sent to Gemini, whether or not its result gets applied
+    pub fn record_prompt(&mut self) {
+        self.prompts += 1;
+    }
+
+    /// Count a modification from Gemini that was applied
+    pub fn record_apply(&mut self, counts: &ChangeCounts) {
+        self.applied += 1;
+        self.record_counts(counts);
+    }
+
+    /// Count a change made with a REPL command
+    pub fn record_edit(&mut self, counts: &ChangeCounts) {
+        self.edits += 1;
+        self.record_counts(counts);
+    }
+
+    fn record_counts(&mut self, counts: &ChangeCounts) {
+        self.added += counts.added;
+        self.removed += counts.removed;
+        self.scripts.extend(counts.scripts.iter().cloned());
+    }
+
+    /// Print everything the session did, with the tokens the client used
+    pub fn print_summary(&self, client: &GeminiClient, place: &Path) {
+        println!("===== SESSION SUMMARY =====");
+        println!("Session: {} ({})", journal::session_id(), format_duration(self.started.elapsed()));
+        println!("Prompts: {} sent, {} applied", self.prompts, self.applied);
+        println!("Manual edits: {}", self.edits);
+
+        let usage = client.usage();
+        println!(
+            "Tokens: {} prompt ({} cached) + {} output = {} ({})",
+            usage.prompt_tokens, usage.cached_tokens, usage.output_tokens, usage.total(), client.describe_cost(usage)
+        );
+
+        println!("Instances: {} added, {} removed", self.added, self.removed);
+        println!("Scripts created or edited: {}", self.scripts.len());
+        for script in &self.scripts {
+            println!("  {}", script);
+        }
+        println!("Journal: {}", journal::journal_path(place).display());
+    }
+}
+
+/// Print the tokens each Gemini request used and the session's total, for `stats`
+pub fn print_token_usage(client: &GeminiClient) {
+    let requests = client.request_usage();
+    println!("===== TOKEN USAGE =====");
+    for (number, usage) in requests.iter().enumerate() {
+        println!(
+            "Request {}: {} prompt ({} cached) + {} output ({})",
+            number + 1, usage.prompt_tokens, usage.cached_tokens, usage.output_tokens, client.describe_cost(*usage)
+        );
+    }
+    let total = client.usage();
+    println!(
+        "Total: {} requests, {} prompt ({} cached) + {} output = {} ({})",
+        requests.len(), total.prompt_tokens, total.cached_tokens, total.output_tokens, total.total(), client.describe_cost(total)
+    );
+}
+
+/// Change a generation setting from `:set NAME VALUE`, or print them all with no arguments
+pub async fn change_setting(client: &mut GeminiClient, args: &str) -> Result<(), String> {
+    let mut words = args.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (None, _, _) => println!("model {}, {}", client.model(), client.params()),
+        (Some("model"), Some(model), None) => {
+            client.set_model(model.to_string()).await;
+            println!("Using {}", model);
+        }
+        (Some(name), Some(value), None) => {
+            client.params_mut().set(name, value)?;
+            println!("{}", client.params());
+        }
+        _ => return Err("Usage: :set [model|temperature|max-tokens|top-p|top-k VALUE]".to_string()),
+    }
+    Ok(())
+}
+
+/// A duration like "42s", "12m 5s" or "1h 3m"
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
@@ -0,0 +1,109 @@
+use rbx_dom_weak::types::Variant;
+use rbx_dom_weak::WeakDom;
+use std::error::Error;
+
+use crate::property::{EnumValue, PropertyValue};
+use crate::roblox::{JsonInstance, Modification};
+
+/// RunContext enum value for scripts that run on the client
+const RUN_CONTEXT_CLIENT: u32 = 2;
+
+/// Check whether the place has instance streaming turned on
+pub fn streaming_enabled(dom: &WeakDom) -> bool {
+    dom.root().children().iter()
+        .filter_map(|&id| dom.get_by_ref(id))
+        .filter(|instance| instance.class == "Workspace")
+        .any(|workspace| matches!(workspace.properties.get(&"StreamingEnabled".into()), Some(Variant::Bool(true))))
+}
+
+/// Find client scripts in a modification that index Workspace children directly.
+/// With streaming those children may not exist yet, so WaitForChild must be used.
+pub fn streaming_warnings(modification: &Modification) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for instance in &modification.add {
+        collect_warnings(instance, &mut warnings);
+    }
+    warnings
+}
+
+fn collect_warnings(instance: &JsonInstance, warnings: &mut Vec<String>) {
+    if is_client_script(instance) {
+        if let Some(source) = instance.properties.get("Source").and_then(PropertyValue::as_str) {
+            for (line_number, member) in direct_workspace_indexes(source) {
+                warnings.push(format!(
+                    "{} line {}: indexes workspace.{} directly, use workspace:WaitForChild(\"{}\") with StreamingEnabled",
+                    instance.name, line_number, member, member
+                ));
+            }
+        }
+    }
+    for child in &instance.children {
+        collect_warnings(child, warnings);
+    }
+}
+
+fn is_client_script(instance: &JsonInstance) -> bool {
+    match instance.class.as_str() {
+        "LocalScript" => true,
+        "Script" => match instance.properties.get("RunContext") {
+            Some(PropertyValue::Enum(EnumValue::Number(value))) => *value == RUN_CONTEXT_CLIENT,
+            Some(PropertyValue::Enum(EnumValue::Name(name))) => name == "Client",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Find `workspace.Child` style indexes, returning the line number and child name.
+/// Members of Workspace itself (workspace.CurrentCamera, workspace.Gravity) are allowed.
+fn direct_workspace_indexes(source: &str) -> Vec<(usize, String)> {
+    const PREFIXES: [&str; 3] = ["workspace.", "Workspace.", "GetService(\"Workspace\")."];
+    let mut found = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        for prefix in PREFIXES {
+            let mut rest = line;
+            while let Some(position) = rest.find(prefix) {
+                let before = rest[..position].chars().last();
+                let after = &rest[position + prefix.len()..];
+                rest = after;
+
+                // Skip identifiers that merely end in "workspace", like myworkspace.
+                if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let member: String = after.chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_')
+                    .collect();
+                if !member.is_empty() && !is_workspace_member(&member) {
+                    found.push((index + 1, member));
+                }
+            }
+        }
+    }
+    found
+}
+
+fn is_workspace_member(name: &str) -> bool {
+    let database = rbx_reflection_database::get();
+    let mut class = database.classes.get("Workspace");
+    while let Some(descriptor) = class {
+        if descriptor.properties.contains_key(name) {
+            return true;
+        }
+        class = descriptor.superclass.as_ref().and_then(|s| database.classes.get(s));
+    }
+    false
+}
+
+/// Build a follow-up prompt asking Gemini to fix the scripts it generated
+pub fn feedback_prompt(prompt: &str, modification: &Modification, warnings: &[String]) -> Result<String, Box<dyn Error>> {
+    Ok(format!(
+        "{}\n\nThis place has StreamingEnabled, so Workspace children may not exist on the client yet. \
+         Your previous response had these problems:\n{}\n\
+         Respond with the same modification, but fix the scripts to use WaitForChild. Previous response: {}",
+        prompt,
+        warnings.join("\n"),
+        serde_json::to_string(modification)?
+    ))
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+use crate::journal::{self, JournalEntry};
+use crate::open_cloud::OpenCloudClient;
+
+/// Where and how often the sync daemon publishes
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub universe_id: Option<u64>,
+    pub place_id: Option<u64>,
+    /// Publish at most this often while changes keep coming in
+    pub interval_minutes: u64,
+    /// "Saved" keeps the version private, "Published" makes it live
+    pub version_type: String,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            universe_id: None,
+            place_id: None,
+            interval_minutes: 10,
+            version_type: "Saved".to_string(),
+        }
+    }
+}
+
+/// A version the daemon published, with the journal entries it included
+#[derive(Serialize, Deserialize)]
+pub struct PublishRecord {
+    pub timestamp: u64,
+    pub version: u64,
+    pub notes: String,
+}
+
+/// Open Cloud has no field for version notes, so they are kept next to the place, e.g. game.rbxlx.publishes.jsonl
+pub fn publish_log_path(place: &Path) -> PathBuf {
+    let mut name = place.file_name().unwrap_or_default().to_os_string();
+    name.push(".publishes.jsonl");
+    place.with_file_name(name)
+}
+
+/// Time of the last publish recorded for a place, or 0 if it has never been published
+fn last_publish_time(place: &Path) -> u64 {
+    std::fs::read_to_string(publish_log_path(place))
+        .ok()
+        .and_then(|text| text.lines().rev().find_map(|line| serde_json::from_str::<PublishRecord>(line).ok()))
+        .map_or(0, |record| record.timestamp)
+}
+
+/// Notes for a version: one line per journal entry since the last publish
+pub fn version_notes(entries: &[JournalEntry]) -> String {
+    if entries.is_empty() {
+        return "Changes made outside rbx-mcp".to_string();
+    }
+    entries.iter().map(|entry| format!("- {}", entry.description)).collect::<Vec<_>>().join("\n")
+}
+
+fn modified_time(place: &Path) -> Option<SystemTime> {
+    std::fs::metadata(place).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Watch a place file and publish it whenever it has changed, at most once per interval
+pub async fn run(place: &Path, config: &SyncConfig, client: &OpenCloudClient) -> Result<(), Box<dyn Error>> {
+    let universe_id = config.universe_id.ok_or("Set sync.universe_id in the config to use sync")?;
+    let place_id = config.place_id.ok_or("Set sync.place_id in the config to use sync")?;
+    let interval = Duration::from_secs(config.interval_minutes.max(1) * 60);
+    let poll = Duration::from_secs(5);
+
+    info!(
+        "Syncing {} to place {} in universe {} at most every {} minutes",
+        place.display(), place_id, universe_id, config.interval_minutes.max(1)
+    );
+
+    let mut published_at = last_publish_time(place);
+    let mut seen = modified_time(place);
+    // Changes journaled while the daemon wasn't running still need publishing
+    let mut dirty = journal::read(place)?.iter().any(|entry| entry.timestamp > published_at);
+    let mut last_attempt: Option<std::time::Instant> = None;
+
+    loop {
+        let modified = modified_time(place);
+        if modified != seen {
+            seen = modified;
+            dirty = true;
+        }
+
+        let waited = last_attempt.is_none_or(|attempt| attempt.elapsed() >= interval);
+        if dirty && waited {
+            last_attempt = Some(std::time::Instant::now());
+            let pending: Vec<JournalEntry> = journal::read(place)?
+                .into_iter()
+                .filter(|entry| entry.timestamp > published_at)
+                .collect();
+            let notes = version_notes(&pending);
+
+            match client.publish_place(universe_id, place_id, place, &config.version_type).await {
+                Ok(version) => {
+                    published_at = journal::now();
+                    dirty = false;
+                    let record = PublishRecord { timestamp: published_at, version, notes };
+                    let mut log = OpenOptions::new().create(true).append(true).open(publish_log_path(place))?;
+                    writeln!(log, "{}", serde_json::to_string(&record)?)?;
+                    info!("Published version {} with {} journal entries:\n{}", version, pending.len(), record.notes);
+                }
+                Err(e) => error!("Publish failed, will retry next interval: {}", e),
+            }
+        }
+
+        tokio::time::sleep(poll).await;
+    }
+}
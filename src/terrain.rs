@@ -0,0 +1,335 @@
+use rbx_dom_weak::types::{BinaryString, Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::query::Region;
+use crate::roblox::find_instance_by_path;
+
+/// Size of a terrain voxel in studs
+pub const VOXEL_SIZE: f32 = 4.0;
+
+/// Number of voxels along each side of a SmoothGrid chunk
+pub const CHUNK_SIZE: i32 = 32;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Most voxels one fill or clear may cover, a cube 1024 studs on each side
+pub const MAX_REGION_VOXELS: i64 = 256 * 256 * 256;
+
+/// SmoothGrid header: format version, then log2 of the chunk size
+const SMOOTH_GRID_HEADER: [u8; 2] = [0x01, 0x05];
+
+/// Terrain materials in SmoothGrid id order, with the colour used for them in material maps
+pub const MATERIALS: [(&str, [u8; 3]); 23] = [
+    ("Air", [0, 0, 0]),
+    ("Water", [12, 84, 92]),
+    ("Grass", [106, 127, 63]),
+    ("Slate", [63, 127, 107]),
+    ("Concrete", [127, 102, 63]),
+    ("Brick", [138, 86, 62]),
+    ("Sand", [143, 126, 95]),
+    ("WoodPlanks", [139, 109, 79]),
+    ("Rock", [102, 108, 111]),
+    ("Glacier", [101, 176, 234]),
+    ("Snow", [195, 199, 218]),
+    ("Sandstone", [137, 90, 71]),
+    ("Mud", [58, 46, 36]),
+    ("Basalt", [30, 30, 37]),
+    ("Ground", [102, 92, 59]),
+    ("CrackedLava", [232, 156, 74]),
+    ("Asphalt", [115, 123, 107]),
+    ("Cobblestone", [132, 123, 90]),
+    ("Ice", [129, 194, 224]),
+    ("LeafyGrass", [115, 132, 74]),
+    ("Salt", [198, 189, 181]),
+    ("Limestone", [206, 173, 148]),
+    ("Pavement", [148, 148, 140]),
+];
+
+/// Look up a terrain material id by name
+pub fn material_id(name: &str) -> Option<u8> {
+    MATERIALS.iter().position(|(material, _)| material.eq_ignore_ascii_case(name)).map(|id| id as u8)
+}
+
+/// Name of a terrain material id, or "Unknown" for ids newer than this table
+pub fn material_name(id: u8) -> &'static str {
+    MATERIALS.get(id as usize).map_or("Unknown", |(name, _)| name)
+}
+
+/// A single terrain voxel. Occupancy runs from 0 (empty) to 255 (full)
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Voxel {
+    pub material: u8,
+    pub occupancy: u8,
+}
+
+impl Voxel {
+    pub const AIR: Voxel = Voxel { material: 0, occupancy: 0 };
+
+    pub fn is_air(&self) -> bool {
+        self.material == 0 || self.occupancy == 0
+    }
+}
+
+/// Decoded smooth terrain, stored as 32x32x32 voxel chunks
+#[derive(Default, Clone, Debug)]
+pub struct TerrainGrid {
+    chunks: BTreeMap<[i32; 3], Vec<Voxel>>,
+}
+
+impl TerrainGrid {
+    /// Decode a SmoothGrid binary string.
+    /// Layout: the header, then for each chunk its coordinates as three little-endian i32
+    /// followed by run-length encoded voxels. Each run starts with a flag byte holding the
+    /// material in the low 6 bits; bit 6 means an occupancy byte follows (otherwise the
+    /// voxel is full), bit 7 means a count byte follows (the run is count + 1 voxels).
+    pub fn decode(data: &[u8]) -> Result<TerrainGrid, Box<dyn Error>> {
+        if data.len() < 2 || data[..2] != SMOOTH_GRID_HEADER {
+            return Err(format!("Unsupported SmoothGrid header {:02x?}", &data[..data.len().min(2)]).into());
+        }
+
+        let mut grid = TerrainGrid::default();
+        let mut rest = &data[2..];
+        while !rest.is_empty() {
+            let coords = take(&mut rest, 12)?;
+            let coord = |i: usize| i32::from_le_bytes([coords[i], coords[i + 1], coords[i + 2], coords[i + 3]]);
+            let chunk_pos = [coord(0), coord(4), coord(8)];
+
+            let mut voxels = Vec::with_capacity(CHUNK_VOLUME);
+            while voxels.len() < CHUNK_VOLUME {
+                let flag = take(&mut rest, 1)?[0];
+                let material = flag & 0x3f;
+                let occupancy = match (material, flag & 0x40 != 0) {
+                    (0, _) => 0,
+                    (_, true) => take(&mut rest, 1)?[0],
+                    (_, false) => 255,
+                };
+                let count = if flag & 0x80 != 0 { take(&mut rest, 1)?[0] as usize + 1 } else { 1 };
+                if voxels.len() + count > CHUNK_VOLUME {
+                    return Err(format!("SmoothGrid chunk {:?} has too many voxels", chunk_pos).into());
+                }
+                voxels.extend(std::iter::repeat_n(Voxel { material, occupancy }, count));
+            }
+            grid.chunks.insert(chunk_pos, voxels);
+        }
+        Ok(grid)
+    }
+
+    /// Encode back into a SmoothGrid binary string, dropping chunks that are entirely air
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = SMOOTH_GRID_HEADER.to_vec();
+        for (chunk_pos, voxels) in &self.chunks {
+            if voxels.iter().all(Voxel::is_air) {
+                continue;
+            }
+            for coord in chunk_pos {
+                data.extend_from_slice(&coord.to_le_bytes());
+            }
+
+            let mut i = 0;
+            while i < voxels.len() {
+                let voxel = normalize(voxels[i]);
+                let mut count = 1;
+                while count < 256 && i + count < voxels.len() && normalize(voxels[i + count]) == voxel {
+                    count += 1;
+                }
+
+                let mut flag = voxel.material;
+                let write_occupancy = voxel.material != 0 && voxel.occupancy != 255;
+                if write_occupancy {
+                    flag |= 0x40;
+                }
+                if count > 1 {
+                    flag |= 0x80;
+                }
+                data.push(flag);
+                if write_occupancy {
+                    data.push(voxel.occupancy);
+                }
+                if count > 1 {
+                    data.push((count - 1) as u8);
+                }
+                i += count;
+            }
+        }
+        data
+    }
+
+    /// Get the voxel at voxel coordinates, which is air outside stored chunks
+    pub fn get(&self, pos: [i32; 3]) -> Voxel {
+        let (chunk_pos, index) = split_position(pos);
+        self.chunks.get(&chunk_pos).map_or(Voxel::AIR, |voxels| voxels[index])
+    }
+
+    /// Set the voxel at voxel coordinates
+    pub fn set(&mut self, pos: [i32; 3], voxel: Voxel) {
+        let (chunk_pos, index) = split_position(pos);
+        if voxel.is_air() && !self.chunks.contains_key(&chunk_pos) {
+            return;
+        }
+        self.chunks.entry(chunk_pos).or_insert_with(|| vec![Voxel::AIR; CHUNK_VOLUME])[index] = voxel;
+    }
+
+    /// Set every voxel a region of studs touches, with its corners in any order.
+    /// Returns how many voxels that was.
+    pub fn fill_region(&mut self, region: &Region, voxel: Voxel) -> Result<usize, Box<dyn Error>> {
+        let low: [i32; 3] = std::array::from_fn(|axis| stud_to_voxel(region.min[axis].min(region.max[axis])));
+        let high: [i32; 3] = std::array::from_fn(|axis| {
+            let top = region.min[axis].max(region.max[axis]);
+            ((top / VOXEL_SIZE).ceil() as i32 - 1).max(low[axis])
+        });
+        let count: i64 = (0..3).map(|axis| (high[axis] - low[axis] + 1) as i64).product();
+        if count > MAX_REGION_VOXELS {
+            return Err(format!("A terrain region of {} voxels is more than the {} one fill or clear may cover", count, MAX_REGION_VOXELS).into());
+        }
+
+        for z in low[2]..=high[2] {
+            for y in low[1]..=high[1] {
+                for x in low[0]..=high[0] {
+                    self.set([x, y, z], voxel);
+                }
+            }
+        }
+        Ok(count as usize)
+    }
+
+    /// Number of non-air voxels
+    pub fn solid_count(&self) -> usize {
+        self.chunks.values().flatten().filter(|voxel| !voxel.is_air()).count()
+    }
+}
+
+/// Split the next count bytes off the front of data
+fn take<'a>(data: &mut &'a [u8], count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+    if data.len() < count {
+        return Err("SmoothGrid data is truncated".into());
+    }
+    let (bytes, rest) = data.split_at(count);
+    *data = rest;
+    Ok(bytes)
+}
+
+/// Air is always stored as material 0 with no occupancy
+fn normalize(voxel: Voxel) -> Voxel {
+    if voxel.is_air() { Voxel::AIR } else { voxel }
+}
+
+/// Split voxel coordinates into chunk coordinates and an index within the chunk (x fastest, then y, then z)
+fn split_position(pos: [i32; 3]) -> ([i32; 3], usize) {
+    let chunk_pos = pos.map(|v| v.div_euclid(CHUNK_SIZE));
+    let [x, y, z] = pos.map(|v| v.rem_euclid(CHUNK_SIZE) as usize);
+    let size = CHUNK_SIZE as usize;
+    (chunk_pos, x + size * (y + size * z))
+}
+
+/// Convert a position in studs to the voxel containing it
+pub fn stud_to_voxel(studs: f32) -> i32 {
+    (studs / VOXEL_SIZE).floor() as i32
+}
+
+/// Find the Terrain instance in Workspace
+pub fn find_terrain(dom: &WeakDom) -> Option<Ref> {
+    let workspace_id = find_instance_by_path(dom, dom.root_ref(), "Workspace")?;
+    dom.get_by_ref(workspace_id)?
+        .children()
+        .iter()
+        .copied()
+        .find(|&id| dom.get_by_ref(id).is_some_and(|instance| instance.class == "Terrain"))
+}
+
+/// Read and decode the place's terrain
+pub fn read_terrain(dom: &WeakDom) -> Result<(Ref, TerrainGrid), Box<dyn Error>> {
+    let terrain_id = find_terrain(dom).ok_or("Place has no Workspace.Terrain")?;
+    let terrain = dom.get_by_ref(terrain_id).ok_or("Terrain instance is missing")?;
+    let grid = match terrain.properties.get(&"SmoothGrid".into()) {
+        Some(Variant::BinaryString(data)) => TerrainGrid::decode(data.as_ref())?,
+        Some(_) => return Err("Terrain.SmoothGrid is not a binary string".into()),
+        None => TerrainGrid::default(),
+    };
+    Ok((terrain_id, grid))
+}
+
+/// Encode the grid into the Terrain instance's SmoothGrid
+pub fn write_terrain(dom: &mut WeakDom, terrain_id: Ref, grid: &TerrainGrid) -> Result<(), Box<dyn Error>> {
+    let terrain = dom.get_by_ref_mut(terrain_id).ok_or("Terrain instance is missing")?;
+    terrain.properties.insert("SmoothGrid".into(), Variant::BinaryString(BinaryString::from(grid.encode())));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRASS: u8 = 2;
+
+    /// One chunk at the origin: a full grass voxel, a half full one, then air
+    fn fixture() -> Vec<u8> {
+        let mut data = vec![0x01, 0x05];
+        data.extend_from_slice(&[0; 12]);
+        data.push(GRASS);
+        data.extend_from_slice(&[GRASS | 0x40, 128]);
+        // 32766 voxels of air: 127 runs of 256 and one of 254
+        for _ in 0..127 {
+            data.extend_from_slice(&[0x80, 255]);
+        }
+        data.extend_from_slice(&[0x80, 253]);
+        data
+    }
+
+    #[test]
+    fn known_bytes_decode_and_encode_unchanged() {
+        let grid = TerrainGrid::decode(&fixture()).unwrap();
+        assert_eq!(grid.get([0, 0, 0]), Voxel { material: GRASS, occupancy: 255 });
+        assert_eq!(grid.get([1, 0, 0]), Voxel { material: GRASS, occupancy: 128 });
+        assert_eq!(grid.get([2, 0, 0]), Voxel::AIR);
+        assert_eq!(grid.get([0, 0, -1]), Voxel::AIR);
+        assert_eq!(grid.solid_count(), 2);
+        assert_eq!(grid.encode(), fixture());
+    }
+
+    #[test]
+    fn runs_split_at_256_voxels_and_chunk_edges() {
+        let mut grid = TerrainGrid::default();
+        let rock = Voxel { material: material_id("Rock").unwrap(), occupancy: 255 };
+        // Runs of exactly 256 and 257 voxels, then one crossing into the next chunk along x
+        for x in 0..256 {
+            grid.set([x % CHUNK_SIZE, x / CHUNK_SIZE, 0], rock);
+        }
+        for x in 0..257 {
+            grid.set([x % CHUNK_SIZE, x / CHUNK_SIZE, 1], Voxel { material: GRASS, occupancy: 10 });
+        }
+        for x in 30..34 {
+            grid.set([x, 0, 2], rock);
+        }
+        grid.set([-1, -1, -1], Voxel { material: GRASS, occupancy: 255 });
+
+        let decoded = TerrainGrid::decode(&grid.encode()).unwrap();
+        assert_eq!(decoded.chunks, grid.chunks);
+        assert_eq!(decoded.get([256 % CHUNK_SIZE, 256 / CHUNK_SIZE, 1]), Voxel { material: GRASS, occupancy: 10 });
+        assert_eq!(decoded.get([32, 0, 2]), rock);
+        assert_eq!(decoded.get([-1, -1, -1]).material, GRASS);
+    }
+
+    #[test]
+    fn chunks_of_air_are_dropped() {
+        let mut grid = TerrainGrid::default();
+        grid.set([40, 0, 0], Voxel { material: GRASS, occupancy: 255 });
+        grid.set([40, 0, 0], Voxel::AIR);
+        // Air with a material left over is still air
+        grid.set([41, 0, 0], Voxel { material: GRASS, occupancy: 0 });
+        assert_eq!(grid.encode(), SMOOTH_GRID_HEADER);
+        assert_eq!(TerrainGrid::decode(&SMOOTH_GRID_HEADER).unwrap().solid_count(), 0);
+    }
+
+    #[test]
+    fn bad_data_is_refused() {
+        assert!(TerrainGrid::decode(&[0x02, 0x05]).is_err());
+        let data = fixture();
+        assert!(TerrainGrid::decode(&data[..data.len() - 1]).is_err());
+        // One voxel more than a chunk holds
+        let mut data = fixture();
+        *data.last_mut().unwrap() = 254;
+        assert!(TerrainGrid::decode(&data).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use rbx_dom_weak::types::{Ref, Variant, Vector3};
+use rbx_dom_weak::WeakDom;
+
+use crate::roblox::is_a;
+
+/// Tidy up the parts in a subtree that was just created: with a grid, round their positions to it,
+/// and their sizes too unless they're thinner than it, and with `anchor`, anchor them.
+/// Rotations are left alone. Returns how many parts were changed.
+pub fn tidy_parts(dom: &mut WeakDom, root_id: Ref, grid: Option<f32>, anchor: bool) -> usize {
+    let Some(root) = dom.get_by_ref(root_id) else {
+        return 0;
+    };
+    let parts: Vec<Ref> = dom.descendants_of(root.referent())
+        .filter(|instance| instance.class != "Terrain" && is_a(&instance.class, "BasePart"))
+        .map(|instance| instance.referent())
+        .collect();
+
+    for &part_id in &parts {
+        let Some(part) = dom.get_by_ref_mut(part_id) else {
+            continue;
+        };
+        if let Some(grid) = grid.filter(|&grid| grid > 0.0) {
+            if let Some(Variant::CFrame(cframe)) = part.properties.get_mut(&"CFrame".into()) {
+                cframe.position = snap(cframe.position, grid, false);
+            }
+            if let Some(Variant::Vector3(size)) = part.properties.get_mut(&"Size".into()) {
+                *size = snap(*size, grid, true);
+            }
+        }
+        if anchor {
+            part.properties.insert("Anchored".into(), Variant::Bool(true));
+        }
+    }
+    parts.len()
+}
+
+/// Round each component to the nearest multiple of grid. Sizes thinner than the grid are kept,
+/// so panes and trims don't collapse to nothing.
+fn snap(vector: Vector3, grid: f32, is_size: bool) -> Vector3 {
+    let round = |v: f32| {
+        if is_size && v < grid {
+            v
+        } else {
+            (v / grid).round() * grid
+        }
+    };
+    Vector3::new(round(vector.x), round(vector.y), round(vector.z))
+}
@@ -0,0 +1,471 @@
+use rbx_dom_weak::WeakDom;
+use serde::Deserialize;
+use rbx_dom_weak::types::Ref;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+use crate::budget;
+use crate::catalog::{self, CatalogClient};
+use crate::context::{self, ContextOptions};
+use crate::error::RbxMcpError;
+use crate::generators;
+use crate::property::PropertyValue;
+use crate::query::{self, Region};
+use crate::roblox::{
+    self, find_instance_by_path, ApplyOptions, ChangeCounts, InsertOperation, JsonInstance, Modification, SetOperation, SetTarget,
+    SubtractTarget,
+};
+
+/// Most instances find_instances lists, and children describe_instance describes per instance
+const MAX_FOUND: usize = 100;
+
+/// Told to Gemini along with the functions, since the system prompt asks for a JSON reply
+pub const TOOLS_PROMPT: &str = "Make the changes by calling the add_instances, remove_instances and set_properties \
+    functions instead of replying with JSON. Instances and properties use the same JSON format described above. \
+    Use find_instances and describe_instance to look at the place as your calls have left it. \
+    For streets, staircases, spiral towers and forests, call the generate_ functions, which lay the parts out exactly. \
+    Check the result of each call and fix any problems with further calls. \
+    When everything is done, reply with a one line summary of what you changed.";
+
+/// Function declarations for Gemini's function calling, followed by the generators. Instances and
+/// properties are passed as JSON text, since their property values can be of any type.
+pub fn declarations() -> Value {
+    let mut declarations = json!([
+        {
+            "name": "add_instances",
+            "description": "Add instances to the place. Returns the path of each instance added and any warnings.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "instances_json": {
+                        "type": "STRING",
+                        "description": "JSON array of instances in the same format as the add list of a modification, each with class, name, target_parent, properties, attributes and children"
+                    }
+                },
+                "required": ["instances_json"]
+            }
+        },
+        {
+            "name": "remove_instances",
+            "description": "Remove instances and everything under them, by path or with the other subtract entries. \
+                Returns how many instances were removed and any warnings.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "paths": {
+                        "type": "ARRAY",
+                        "items": { "type": "STRING" },
+                        "description": "Paths of the instances to remove, like Workspace/House/Door"
+                    },
+                    "targets_json": {
+                        "type": "STRING",
+                        "description": "JSON array of entries in the same format as the subtract list of a modification, like {\"region\": {\"min\": [0, 0, 0], \"max\": [50, 20, 50]}}, {\"near\": \"Workspace/SpawnLocation\", \"radius\": 30} or {\"class\": \"Decal\", \"under\": \"Workspace/House\"}"
+                    }
+                }
+            }
+        },
+        {
+            "name": "find_instances",
+            "description": "Find instances in the place without changing anything. Every given filter has to match. \
+                Returns the path, class and position of each instance found, at most 100.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "name": {
+                        "type": "STRING",
+                        "description": "Case-insensitive regex the name has to match, like \"^Tree\""
+                    },
+                    "class": {
+                        "type": "STRING",
+                        "description": "Class the instance has to be, or a subclass of it, like BasePart"
+                    },
+                    "under": {
+                        "type": "STRING",
+                        "description": "Only look below this path"
+                    },
+                    "min": {
+                        "type": "ARRAY",
+                        "items": { "type": "NUMBER" },
+                        "description": "With max, only instances positioned inside this box of Workspace, [x, y, z]"
+                    },
+                    "max": {
+                        "type": "ARRAY",
+                        "items": { "type": "NUMBER" },
+                        "description": "Opposite corner of the box, [x, y, z]"
+                    },
+                    "near": {
+                        "type": "STRING",
+                        "description": "With radius, only instances within radius studs of the instance at this path"
+                    },
+                    "radius": {
+                        "type": "NUMBER"
+                    }
+                }
+            }
+        },
+        {
+            "name": "describe_instance",
+            "description": "Describe an instance with its properties and what's under it, as it is now, without changing anything.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "path": {
+                        "type": "STRING",
+                        "description": "Path of the instance, like Workspace/House"
+                    },
+                    "depth": {
+                        "type": "INTEGER",
+                        "description": "How many levels of children to describe, 2 by default"
+                    }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "set_properties",
+            "description": "Set properties of an existing instance. Returns the properties that were set.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "path": {
+                        "type": "STRING",
+                        "description": "Path of the instance, like Workspace/House/Door"
+                    },
+                    "properties_json": {
+                        "type": "STRING",
+                        "description": "JSON object of property names to values in the same format as a modification, like {\"Transparency\": {\"type\": \"Float\", \"value\": 0.5}}"
+                    }
+                },
+                "required": ["path", "properties_json"]
+            }
+        }
+    ]);
+    if let Some(list) = declarations.as_array_mut() {
+        list.extend(generators::declarations());
+    }
+    declarations
+}
+
+/// A copy of the place that Gemini edits through function calls, with a record of what they did
+pub struct ToolSession<'a> {
+    pub place: WeakDom,
+    options: &'a ApplyOptions,
+    /// Everything the calls did, as one modification for the journal and --plan-out
+    pub modification: Modification,
+    pub counts: ChangeCounts,
+    /// Each call with its arguments and result, oldest first
+    pub calls: Vec<Value>,
+    /// Set when Gemini may search the catalog and insert what it finds
+    catalog: Option<CatalogClient>,
+}
+
+#[derive(Deserialize)]
+struct AddArgs {
+    instances_json: String,
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    category: String,
+    keyword: String,
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+struct InsertAssetArgs {
+    asset_id: u64,
+    #[serde(default)]
+    target_parent: Option<String>,
+    #[serde(default)]
+    position: Option<[f32; 3]>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RemoveArgs {
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    targets_json: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FindArgs {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default)]
+    under: Option<String>,
+    #[serde(default)]
+    min: Option<[f32; 3]>,
+    #[serde(default)]
+    max: Option<[f32; 3]>,
+    #[serde(default)]
+    near: Option<String>,
+    #[serde(default)]
+    radius: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct DescribeArgs {
+    path: String,
+    #[serde(default = "default_describe_depth")]
+    depth: usize,
+}
+
+fn default_describe_depth() -> usize {
+    2
+}
+
+#[derive(Deserialize)]
+struct SetArgs {
+    path: String,
+    properties_json: String,
+}
+
+impl<'a> ToolSession<'a> {
+    pub fn new(place: WeakDom, options: &'a ApplyOptions) -> Self {
+        ToolSession {
+            place,
+            options,
+            modification: Modification::default(),
+            counts: ChangeCounts::default(),
+            calls: Vec::new(),
+            catalog: None,
+        }
+    }
+
+    /// Let Gemini search the catalog and insert free models from it
+    pub fn with_catalog(mut self, catalog: CatalogClient) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// The functions Gemini can call in this session
+    pub fn declarations(&self) -> Value {
+        let mut declarations = declarations();
+        if let (Some(list), Some(_)) = (declarations.as_array_mut(), &self.catalog) {
+            list.extend(catalog::declarations());
+        }
+        declarations
+    }
+
+    /// Run a function call against the place. The result goes back to Gemini as the function
+    /// response, with an error instead when the call failed so it can try again.
+    pub fn call(&mut self, name: &str, args: &Value) -> Value {
+        let result = match name {
+            "add_instances" => self.add_instances(args),
+            "remove_instances" => self.remove_instances(args),
+            "find_instances" => self.find_instances(args),
+            "describe_instance" => self.describe_instance(args),
+            "set_properties" => self.set_properties(args),
+            "search_catalog" if self.catalog.is_some() => self.search_catalog(args),
+            "insert_asset" if self.catalog.is_some() => self.insert_asset(args),
+            _ if name.starts_with("generate_") => self.generate(&name["generate_".len()..], args),
+            _ => Err(RbxMcpError::InvalidOperation(format!("There is no function called {}", name))),
+        };
+        let response = match result {
+            Ok(result) => result,
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        info!("Called {}: {}", name, response);
+        self.calls.push(json!({ "name": name, "args": args, "response": response }));
+        response
+    }
+
+    fn add_instances(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: AddArgs = parse_args(args)?;
+        let instances: Vec<JsonInstance> = serde_json::from_str(&args.instances_json)
+            .map_err(|e| RbxMcpError::InvalidOperation(format!("instances_json isn't a list of instances: {}", e)))?;
+        let added = instances.iter()
+            .map(|instance| roblox::child_path(instance.target_parent.as_deref().unwrap_or("Workspace"), &instance.name))
+            .collect::<Vec<_>>();
+        let modification = Modification { add: instances, ..Default::default() };
+        let warnings = self.apply(&modification)?;
+        self.modification.add.extend(modification.add);
+        Ok(json!({ "added": added, "warnings": warnings }))
+    }
+
+    /// Build with one of the generators and add the result, so exact layouts don't have to be written out part by part
+    fn generate(&mut self, generator: &str, args: &Value) -> Result<Value, RbxMcpError> {
+        let instance = generators::generate(generator, args).map_err(RbxMcpError::InvalidOperation)?;
+        let added = roblox::child_path(instance.target_parent.as_deref().unwrap_or("Workspace"), &instance.name);
+        let modification = Modification { add: vec![instance], ..Default::default() };
+        let warnings = self.apply(&modification)?;
+        self.modification.add.extend(modification.add);
+        Ok(json!({ "added": added, "warnings": warnings }))
+    }
+
+    fn search_catalog(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: SearchArgs = parse_args(args)?;
+        let catalog = self.catalog.as_ref().ok_or_else(|| RbxMcpError::InvalidOperation("The catalog isn't enabled".to_string()))?;
+        let found = block_on(catalog.search(&args.category, &args.keyword, args.limit))?;
+        Ok(json!({ "assets": found }))
+    }
+
+    /// Download a catalog asset and insert it like a model file, so the modification can be applied again from the cache
+    fn insert_asset(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: InsertAssetArgs = parse_args(args)?;
+        let catalog = self.catalog.as_ref().ok_or_else(|| RbxMcpError::InvalidOperation("The catalog isn't enabled".to_string()))?;
+        let path = block_on(catalog.download_model(args.asset_id))?;
+        let modification = Modification {
+            insert: vec![InsertOperation {
+                asset: path.to_string_lossy().into_owned(),
+                target_parent: args.target_parent,
+                name: args.name,
+                position: args.position,
+            }],
+            ..Default::default()
+        };
+        let created_before = self.counts.created.len();
+        let warnings = self.apply(&modification)?;
+        let inserted: Vec<String> = self.counts.created[created_before..].iter()
+            .map(|&id| roblox::instance_path(&self.place, id))
+            .collect();
+        self.modification.insert.extend(modification.insert);
+        Ok(json!({ "inserted": inserted, "warnings": warnings }))
+    }
+
+    fn remove_instances(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: RemoveArgs = parse_args(args)?;
+        let mut subtract: Vec<SubtractTarget> = args.paths.into_iter().map(SubtractTarget::Path).collect();
+        if let Some(targets_json) = &args.targets_json {
+            let targets: Vec<SubtractTarget> = serde_json::from_str(targets_json)
+                .map_err(|e| RbxMcpError::InvalidOperation(format!("targets_json isn't a list of subtract entries: {}", e)))?;
+            subtract.extend(targets);
+        }
+        if subtract.is_empty() {
+            return Err(RbxMcpError::InvalidOperation("Give paths or targets_json to remove".to_string()));
+        }
+        let removed_before = self.counts.removed;
+        let modification = Modification { subtract, ..Default::default() };
+        let warnings = self.apply(&modification)?;
+        self.modification.subtract.extend(modification.subtract);
+        Ok(json!({ "removed": self.counts.removed - removed_before, "warnings": warnings }))
+    }
+
+    /// Look through the place as it is after the calls so far, so results can be checked before going on
+    fn find_instances(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: FindArgs = parse_args(args)?;
+        let place = &self.place;
+        let root_ref = place.root_ref();
+        // Boxes and distances are in Workspace, like the subtract entries
+        let spatial = args.min.is_some() || args.max.is_some() || args.near.is_some();
+        let start_id = match &args.under {
+            Some(path) => self.resolve(path)?,
+            None if spatial => self.resolve("Workspace")?,
+            None => root_ref,
+        };
+        let mut found = match (args.min, args.max, &args.near, args.radius) {
+            (Some(min), Some(max), None, _) => query::instances_in_region(place, start_id, &Region { min, max }),
+            (None, None, Some(near), Some(radius)) => query::instances_near(place, start_id, near, radius)?,
+            (None, None, None, _) => place.descendants_of(start_id).map(|instance| instance.referent()).filter(|&id| id != start_id).collect(),
+            _ => return Err(RbxMcpError::InvalidOperation("Give both min and max, or both near and radius, but not both pairs".to_string())),
+        };
+        if args.name.is_some() || args.class.is_some() {
+            let pattern = query::name_pattern(args.name.as_deref().unwrap_or(""));
+            let matching: HashSet<Ref> = query::find_by_name(place, start_id, &pattern, args.class.as_deref()).into_iter().collect();
+            found.retain(|id| matching.contains(id));
+        }
+        let results: Vec<Value> = found.iter()
+            .take(MAX_FOUND)
+            .map(|&id| json!({
+                "path": roblox::instance_path(place, id),
+                "class": place.get_by_ref(id).map_or("", |instance| instance.class.as_str()),
+                "position": query::instance_position(place, id).map(|p| [p.x, p.y, p.z]),
+            }))
+            .collect();
+        Ok(json!({ "found": results, "total": found.len() }))
+    }
+
+    fn describe_instance(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: DescribeArgs = parse_args(args)?;
+        let instance_id = self.resolve(&args.path)?;
+        let options = ContextOptions { max_depth: Some(args.depth), max_children: Some(MAX_FOUND), ..Default::default() };
+        Ok(json!({ "description": context::describe_subtree(&self.place, instance_id, &options) }))
+    }
+
+    fn resolve(&self, path: &str) -> Result<Ref, RbxMcpError> {
+        let root_ref = self.place.root_ref();
+        find_instance_by_path(&self.place, root_ref, path).ok_or_else(|| RbxMcpError::PathNotFound {
+            path: path.to_string(),
+            suggestions: roblox::suggest_paths(&self.place, root_ref, path),
+        })
+    }
+
+    fn set_properties(&mut self, args: &Value) -> Result<Value, RbxMcpError> {
+        let args: SetArgs = parse_args(args)?;
+        let properties: HashMap<String, PropertyValue> = serde_json::from_str(&args.properties_json)
+            .map_err(|e| RbxMcpError::InvalidOperation(format!("properties_json isn't a map of properties: {}", e)))?;
+        let set: Vec<String> = properties.keys().cloned().collect();
+        let modification = Modification {
+            set: vec![SetOperation { target: SetTarget::Path { path: args.path }, properties }],
+            ..Default::default()
+        };
+        let warnings = self.apply(&modification)?;
+        self.modification.set.extend(modification.set);
+        Ok(json!({ "set": set, "warnings": warnings }))
+    }
+
+    /// Apply part of a modification to the place, returning its warnings. The instance limit
+    /// covers everything the session creates, not each call on its own.
+    fn apply(&mut self, modification: &Modification) -> Result<Vec<String>, RbxMcpError> {
+        let root_ref = self.place.root_ref();
+        if let Some(limit) = self.options.max_new_instances {
+            let count = budget::count_new_instances(&self.place, root_ref, modification, self.options.asset_library.as_deref())
+                .and_then(|count| count.checked_add(self.counts.added))
+                .unwrap_or(usize::MAX);
+            if count > limit {
+                return Err(RbxMcpError::TooManyInstances { count, limit });
+            }
+        }
+        let report = roblox::json_to_weakdom(&mut self.place, modification, root_ref, self.options)?;
+        self.counts.added += report.counts.added;
+        self.counts.removed += report.counts.removed;
+        self.counts.scripts.extend(report.counts.scripts);
+        self.counts.created.extend(report.counts.created);
+        Ok(report.warnings.iter().map(ToString::to_string).collect())
+    }
+}
+
+/// Wait for a request from inside a function call, which Gemini's request loop makes synchronously
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+fn parse_args<T: for<'de> Deserialize<'de>>(args: &Value) -> Result<T, RbxMcpError> {
+    serde_json::from_value(args.clone()).map_err(|e| RbxMcpError::InvalidOperation(format!("Invalid arguments: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn add_parts(session: &mut ToolSession, names: &[&str]) -> Value {
+        let instances: Vec<Value> = names.iter().map(|name| json!({ "name": name, "class": "Part", "properties": {} })).collect();
+        session.call("add_instances", &json!({ "instances_json": serde_json::to_string(&instances).unwrap() }))
+    }
+
+    #[test]
+    fn instance_limit_covers_the_whole_session() {
+        let mut place = WeakDom::new(InstanceBuilder::new("DataModel"));
+        place.insert(place.root_ref(), InstanceBuilder::new("Workspace").with_name("Workspace"));
+        let options = ApplyOptions { max_new_instances: Some(3), ..Default::default() };
+        let mut session = ToolSession::new(place, &options);
+
+        assert!(add_parts(&mut session, &["A", "B"]).get("error").is_none());
+        let refused = add_parts(&mut session, &["C", "D"]);
+        assert!(refused["error"].as_str().unwrap().contains("4 instances"), "{}", refused);
+        assert!(add_parts(&mut session, &["C"]).get("error").is_none());
+        assert_eq!(session.counts.added, 3);
+    }
+}
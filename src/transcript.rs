@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::gemini_api::ConversationTurn;
+use crate::journal;
+use crate::roblox::Modification;
+
+/// Transcripts are kept under the working directory, like the config file
+pub const SESSIONS_DIR: &str = ".rbx-mcp/sessions";
+
+/// One event in an interactive session, appended to its transcript
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEntry {
+    /// The session started, or was resumed
+    Start {
+        timestamp: u64,
+        place: PathBuf,
+        /// Contents of the --context file
+        #[serde(default)]
+        context: Option<String>,
+        #[serde(default)]
+        place_hash: Option<String>,
+    },
+    /// A prompt sent to Gemini and what came of it
+    Prompt {
+        timestamp: u64,
+        #[serde(default)]
+        change_id: Option<String>,
+        prompt: String,
+        /// Gemini's raw response
+        response: String,
+        modification: Box<Modification>,
+        applied: bool,
+        /// Hash of the place file once the modification was saved
+        #[serde(default)]
+        place_hash: Option<String>,
+    },
+    /// A change made with a REPL command like set or rm
+    Edit {
+        timestamp: u64,
+        #[serde(default)]
+        change_id: Option<String>,
+        description: String,
+        #[serde(default)]
+        place_hash: Option<String>,
+    },
+}
+
+/// What a new run needs to carry on where a transcript left off
+pub struct ResumedSession {
+    pub place: PathBuf,
+    pub context: Option<String>,
+    /// Every prompt whose modification was applied, oldest first
+    pub history: Vec<ConversationTurn>,
+    /// Hash of the place as the session last saw it
+    pub place_hash: Option<String>,
+}
+
+/// A session's transcript, `.rbx-mcp/sessions/SESSION.jsonl`
+pub struct Transcript {
+    id: String,
+    path: PathBuf,
+}
+
+impl Transcript {
+    /// Start a transcript for this run
+    pub fn create() -> Result<Transcript, Box<dyn Error>> {
+        std::fs::create_dir_all(SESSIONS_DIR)?;
+        let id = journal::session_id().to_string();
+        let path = Path::new(SESSIONS_DIR).join(format!("{}.jsonl", id));
+        Ok(Transcript { id, path })
+    }
+
+    /// Open an earlier session's transcript to continue it, by session ID or path
+    pub fn open(session: &str) -> Result<Transcript, Box<dyn Error>> {
+        let as_path = Path::new(session);
+        let path = if as_path.is_file() {
+            as_path.to_path_buf()
+        } else {
+            Path::new(SESSIONS_DIR).join(format!("{}.jsonl", session))
+        };
+        if !path.is_file() {
+            return Err(format!("No session {} in {}", session, SESSIONS_DIR).into());
+        }
+        let id = path.file_stem().map_or(session.to_string(), |stem| stem.to_string_lossy().into_owned());
+        Ok(Transcript { id, path })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &TranscriptEntry) -> Result<(), Box<dyn Error>> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    pub fn read(&self) -> Result<Vec<TranscriptEntry>, Box<dyn Error>> {
+        let text = std::fs::read_to_string(&self.path)?;
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Rebuild the conversation and context the session had when it ended
+    pub fn resume(&self) -> Result<ResumedSession, Box<dyn Error>> {
+        let mut resumed: Option<ResumedSession> = None;
+        for entry in self.read()? {
+            match entry {
+                TranscriptEntry::Start { place, context, place_hash, .. } => match &mut resumed {
+                    // A later resume may have been given a different context file
+                    Some(session) => {
+                        session.context = context.or(session.context.take());
+                        session.place_hash = place_hash.or(session.place_hash.take());
+                    }
+                    None => resumed = Some(ResumedSession { place, context, history: Vec::new(), place_hash }),
+                },
+                TranscriptEntry::Prompt { prompt, response, applied, place_hash, .. } => {
+                    let Some(session) = &mut resumed else { continue };
+                    if applied {
+                        session.history.push(ConversationTurn { prompt, response });
+                        session.place_hash = place_hash.or(session.place_hash.take());
+                    }
+                }
+                TranscriptEntry::Edit { place_hash, .. } => {
+                    if let Some(session) = &mut resumed {
+                        session.place_hash = place_hash.or(session.place_hash.take());
+                    }
+                }
+            }
+        }
+        resumed.ok_or_else(|| format!("{} doesn't start with a session", self.path.display()).into())
+    }
+}
+
+/// SHA-256 of a file, in hex
+pub fn file_hash(path: &Path) -> std::io::Result<String> {
+    let digest = Sha256::digest(std::fs::read(path)?);
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
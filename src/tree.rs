@@ -0,0 +1,93 @@
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+
+use crate::roblox::is_a;
+
+/// How many levels are printed when no depth is given
+pub const DEFAULT_TREE_DEPTH: usize = 3;
+
+/// What part of the hierarchy to print
+pub struct TreeOptions {
+    /// Levels below the starting instance to print
+    pub depth: usize,
+    /// Only print instances of this class or a subclass, with the ancestors leading to them
+    pub class: Option<String>,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        TreeOptions { depth: DEFAULT_TREE_DEPTH, class: None }
+    }
+}
+
+/// Render the hierarchy under an instance, one line per instance
+pub fn render_tree(dom: &WeakDom, root_id: Ref, options: &TreeOptions) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(root) = dom.get_by_ref(root_id) {
+        lines.push(format!("{} ({})", root.name, root.class));
+        render_children(dom, root_id, options, 1, "", &mut lines);
+    }
+    lines
+}
+
+pub fn print_tree(dom: &WeakDom, root_id: Ref, options: &TreeOptions) {
+    for line in render_tree(dom, root_id, options) {
+        println!("{}", line);
+    }
+}
+
+fn render_children(dom: &WeakDom, parent_id: Ref, options: &TreeOptions, level: usize, prefix: &str, lines: &mut Vec<String>) {
+    let Some(parent) = dom.get_by_ref(parent_id) else {
+        return;
+    };
+    let children: Vec<Ref> = parent.children().iter()
+        .copied()
+        .filter(|&child_id| shows(dom, child_id, options, level))
+        .collect();
+
+    for (index, &child_id) in children.iter().enumerate() {
+        let Some(child) = dom.get_by_ref(child_id) else {
+            continue;
+        };
+        let last = index + 1 == children.len();
+        let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+
+        let mut line = format!("{}{}{} ({})", prefix, branch, child.name, child.class);
+        if level >= options.depth {
+            let hidden = count_hidden(dom, child_id, options);
+            if hidden > 0 {
+                line.push_str(&format!(" [+{} more]", hidden));
+            }
+        }
+        lines.push(line);
+
+        if level < options.depth {
+            render_children(dom, child_id, options, level + 1, &format!("{}{}", prefix, indent), lines);
+        }
+    }
+}
+
+fn matches_class(dom: &WeakDom, instance_id: Ref, options: &TreeOptions) -> bool {
+    match (&options.class, dom.get_by_ref(instance_id)) {
+        (Some(class), Some(instance)) => is_a(&instance.class, class),
+        (None, Some(_)) => true,
+        (_, None) => false,
+    }
+}
+
+/// Whether an instance gets a line: it matches the class filter, or leads to a match within the depth
+fn shows(dom: &WeakDom, instance_id: Ref, options: &TreeOptions, level: usize) -> bool {
+    if matches_class(dom, instance_id, options) {
+        return true;
+    }
+    level < options.depth && dom.get_by_ref(instance_id).is_some_and(|instance| {
+        instance.children().iter().any(|&child_id| shows(dom, child_id, options, level + 1))
+    })
+}
+
+/// Number of descendants past the depth limit that would have been printed
+fn count_hidden(dom: &WeakDom, instance_id: Ref, options: &TreeOptions) -> usize {
+    dom.descendants_of(instance_id)
+        .filter(|instance| instance.referent() != instance_id && matches_class(dom, instance.referent(), options))
+        .count()
+}
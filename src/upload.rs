@@ -0,0 +1,239 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::error::RbxMcpError;
+use crate::open_cloud::OpenCloudClient;
+use crate::property::PropertyValue;
+use crate::roblox::{JsonInstance, Modification};
+
+/// Asset IDs of the data uploaded so far, by SHA-256 of the data, so the same image is only uploaded once
+pub const UPLOADS_FILE: &str = ".rbx-mcp/uploads.json";
+
+/// Kinds of data Open Cloud can make assets from, by MIME type
+const ASSET_TYPES: [(&str, &str); 6] = [
+    ("image/png", "Decal"),
+    ("image/jpeg", "Decal"),
+    ("image/bmp", "Decal"),
+    ("image/tga", "Decal"),
+    ("audio/mpeg", "Audio"),
+    ("audio/ogg", "Audio"),
+];
+
+/// Who uploaded assets belong to. One of the two is needed for uploads.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UploadConfig {
+    pub user_id: Option<u64>,
+    pub group_id: Option<u64>,
+}
+
+/// File data given inline in a Content property as a data URL, like `data:image/png;base64,...`
+pub struct InlineData {
+    pub content_type: String,
+    pub asset_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Whether a Content value is inline data rather than an asset URL
+pub fn is_data_url(value: &str) -> bool {
+    value.starts_with("data:")
+}
+
+/// Decode a base64 data URL, checking Open Cloud can make an asset from its type
+pub fn parse_data_url(value: &str) -> Result<InlineData, String> {
+    let rest = value.strip_prefix("data:").ok_or("not a data URL")?;
+    let (header, data) = rest.split_once(',').ok_or("the data URL has no data")?;
+    let content_type = header.strip_suffix(";base64").ok_or("inline data has to be base64 encoded")?;
+    // Open Cloud turns FBX files into Model assets, which a MeshPart's MeshId can't point at
+    if content_type.starts_with("model/") {
+        return Err(format!(
+            "Open Cloud makes a model rather than a mesh from {} data, so use an existing mesh's rbxassetid:// instead",
+            content_type
+        ));
+    }
+    let &(_, asset_type) = ASSET_TYPES.iter().find(|(mime, _)| *mime == content_type).ok_or_else(|| format!(
+        "Open Cloud can't make an asset from {} data, only {}",
+        content_type,
+        ASSET_TYPES.map(|(mime, _)| mime).join(", ")
+    ))?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim())
+        .map_err(|e| format!("the inline data isn't valid base64: {}", e))?;
+    Ok(InlineData { content_type: content_type.to_string(), asset_type, bytes })
+}
+
+/// Uploads inline data in modifications through Open Cloud and puts the asset IDs in its place
+pub struct Uploader {
+    client: OpenCloudClient,
+    config: UploadConfig,
+    uploaded: HashMap<String, u64>,
+}
+
+impl Uploader {
+    /// An uploader for the user or group in config, along with the uploads recorded so far
+    pub fn new(api_key: String, config: &UploadConfig) -> Uploader {
+        let uploaded = std::fs::read_to_string(UPLOADS_FILE).ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Uploader { client: OpenCloudClient::new(api_key), config: config.clone(), uploaded }
+    }
+
+    /// Upload every data URL in the Content properties of the instances a modification adds, replacing each
+    /// with the rbxassetid:// URL of its asset. Data URLs that can't be decoded are left for validation to report.
+    /// Returns how many properties were replaced.
+    pub async fn upload_inline_data(&mut self, modification: &mut Modification) -> Result<usize, RbxMcpError> {
+        let mut found = Vec::new();
+        for instance in &modification.add {
+            collect_data_urls(instance, &mut found);
+        }
+        let mut asset_ids = HashMap::new();
+        for (name, url) in found {
+            let Ok(data) = parse_data_url(&url) else {
+                continue;
+            };
+            let asset_id = self.upload(&name, &data).await?;
+            asset_ids.insert(url, asset_id);
+        }
+        if asset_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut replaced = 0;
+        for instance in &mut modification.add {
+            replaced += replace_data_urls(instance, &asset_ids);
+        }
+        Ok(replaced)
+    }
+
+    /// Upload data once, reusing the asset from an earlier upload of the same bytes
+    async fn upload(&mut self, name: &str, data: &InlineData) -> Result<u64, RbxMcpError> {
+        let digest = Sha256::digest(&data.bytes);
+        let key: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        if let Some(&asset_id) = self.uploaded.get(&key) {
+            return Ok(asset_id);
+        }
+        info!("Uploading {} bytes of {} for {} to Open Cloud...", data.bytes.len(), data.content_type, name);
+        let asset_id = self.client.upload_asset(data.asset_type, name, &data.content_type, data.bytes.clone(), &self.config).await?;
+        info!("Uploaded {} as asset {}", name, asset_id);
+
+        self.uploaded.insert(key, asset_id);
+        let path = PathBuf::from(UPLOADS_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(&self.uploaded).map_err(|e| RbxMcpError::InvalidOperation(e.to_string()))?)?;
+        Ok(asset_id)
+    }
+}
+
+/// Upload the inline data in a modification, which needs a user or group in the config and an Open Cloud
+/// key in ROBLOX_API_KEY. Call this only once the modification is going to be applied, since uploads are public.
+/// Returns how many properties were replaced.
+pub async fn upload_modification(modification: &mut Modification, config: &UploadConfig) -> Result<usize, RbxMcpError> {
+    if !has_inline_data(modification) {
+        return Ok(0);
+    }
+    let api_key = std::env::var("ROBLOX_API_KEY").ok();
+    match api_key {
+        Some(api_key) if config.user_id.is_some() || config.group_id.is_some() => {
+            Uploader::new(api_key, config).upload_inline_data(modification).await
+        }
+        _ => Err(RbxMcpError::InvalidOperation(
+            "The modification has inline data, which needs upload.user_id or upload.group_id in the config and ROBLOX_API_KEY to upload".to_string(),
+        )),
+    }
+}
+
+/// Whether any instance a modification adds has inline data to upload
+pub fn has_inline_data(modification: &Modification) -> bool {
+    let mut found = Vec::new();
+    for instance in &modification.add {
+        collect_data_urls(instance, &mut found);
+    }
+    !found.is_empty()
+}
+
+/// The name of each instance with a data URL in a Content property, with the URL
+fn collect_data_urls(instance: &JsonInstance, out: &mut Vec<(String, String)>) {
+    for value in instance.properties.values() {
+        if let PropertyValue::Content(url) = value {
+            if is_data_url(url) {
+                out.push((instance.name.clone(), url.clone()));
+            }
+        }
+    }
+    for child in &instance.children {
+        collect_data_urls(child, out);
+    }
+}
+
+fn replace_data_urls(instance: &mut JsonInstance, asset_ids: &HashMap<String, u64>) -> usize {
+    let mut replaced = 0;
+    for value in instance.properties.values_mut() {
+        if let PropertyValue::Content(url) = value {
+            if let Some(asset_id) = asset_ids.get(url.as_str()) {
+                *url = format!("rbxassetid://{}", asset_id);
+                replaced += 1;
+            }
+        }
+    }
+    for child in &mut instance.children {
+        replaced += replace_data_urls(child, asset_ids);
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &str = "data:image/png;base64,iVBORw0KGgo=";
+
+    fn modification_with(url: &str) -> Modification {
+        serde_json::from_value(serde_json::json!({ "add": [{
+            "class": "Part",
+            "name": "Sign",
+            "properties": {},
+            "children": [{
+                "class": "Decal",
+                "name": "Face",
+                "properties": { "Texture": { "type": "Content", "value": url } }
+            }]
+        }]})).unwrap()
+    }
+
+    #[test]
+    fn base64_images_and_sounds_are_decoded() {
+        let data = parse_data_url(PNG).unwrap();
+        assert_eq!(data.content_type, "image/png");
+        assert_eq!(data.asset_type, "Decal");
+        assert_eq!(data.bytes, b"\x89PNG\r\n\x1a\n");
+        assert_eq!(parse_data_url("data:audio/ogg;base64,T2dnUw==").unwrap().asset_type, "Audio");
+    }
+
+    #[test]
+    fn data_open_cloud_cant_use_is_refused() {
+        let error = |url: &str| parse_data_url(url).err().unwrap();
+        assert!(error("data:model/fbx;base64,AAAA").contains("rbxassetid://"));
+        assert!(error("data:image/gif;base64,AAAA").contains("image/png"));
+        assert!(error("data:image/png,plain").contains("base64"));
+        assert!(error("data:image/png;base64,not base64!").contains("valid base64"));
+        assert!(error("rbxassetid://123").contains("not a data URL"));
+    }
+
+    #[test]
+    fn data_urls_are_found_and_replaced_in_children() {
+        let mut modification = modification_with(PNG);
+        assert!(has_inline_data(&modification));
+        assert!(!has_inline_data(&modification_with("rbxassetid://123")));
+
+        let asset_ids = HashMap::from([(PNG.to_string(), 456)]);
+        assert_eq!(replace_data_urls(&mut modification.add[0], &asset_ids), 1);
+        assert!(!has_inline_data(&modification));
+        let texture = &modification.add[0].children[0].properties["Texture"];
+        assert!(matches!(texture, PropertyValue::Content(url) if url == "rbxassetid://456"));
+    }
+}
@@ -0,0 +1,121 @@
+use rayon::prelude::*;
+use rbx_reflection::ClassTag;
+use std::error::Error;
+
+use crate::luau;
+use crate::property::PropertyValue;
+use crate::terrain;
+use crate::upload;
+use crate::roblox::{child_path, is_a, json_to_attributes, InstanceFilter, JsonInstance, Modification, SubtractTarget, MAX_CLONE_COUNT};
+
+/// Modifications with fewer instances than this are checked and converted on the current thread,
+/// where rayon's overhead isn't worth it
+pub(crate) const PARALLEL_THRESHOLD: usize = 64;
+
+/// Check every instance a modification adds before the place is touched:
+/// the class must exist and be creatable, every property must convert for that class,
+/// attributes must have supported types, and script sources must parse as Luau. In strict mode,
+/// properties of types that can't be converted at all are problems too, rather than being left out when applied.
+/// Classes named by subtract filters and terrain fill materials must exist, and clones
+/// may make at most MAX_CLONE_COUNT copies.
+/// Returns one message per problem, naming the instance by its path within the modification.
+pub fn validate_modification(modification: &Modification, strict: bool) -> Vec<String> {
+    let mut instances = Vec::new();
+    for instance in &modification.add {
+        let parent = instance.target_parent.as_deref().unwrap_or("Workspace");
+        flatten(instance, parent, &mut instances);
+    }
+
+    let mut problems: Vec<String> = if instances.len() < PARALLEL_THRESHOLD {
+        instances.iter().flat_map(|(path, instance)| validate_instance(path, instance, strict)).collect()
+    } else {
+        instances.par_iter().flat_map_iter(|(path, instance)| validate_instance(path, instance, strict)).collect()
+    };
+
+    // A misspelled class would otherwise quietly remove nothing
+    for target in &modification.subtract {
+        if let SubtractTarget::Filter(InstanceFilter { class: Some(class), .. }) = target {
+            if !rbx_reflection_database::get().classes.contains_key(class.as_str()) {
+                problems.push(format!("subtract: unknown class '{}'", class));
+            }
+        }
+    }
+
+    for clone in &modification.clone {
+        if clone.count > MAX_CLONE_COUNT {
+            problems.push(format!("clone: {} copies of '{}', more than the {} one clone can make", clone.count, clone.source_path, MAX_CLONE_COUNT));
+        }
+    }
+
+    for fill in &modification.terrain_fill {
+        if terrain::material_id(&fill.material).is_none() {
+            problems.push(format!("terrain_fill: unknown terrain material '{}'", fill.material));
+        }
+    }
+    problems
+}
+
+/// Every instance in an added subtree, with the path it will have
+fn flatten<'a>(instance: &'a JsonInstance, parent: &str, out: &mut Vec<(String, &'a JsonInstance)>) {
+    let path = child_path(parent, &instance.name);
+    out.push((path.clone(), instance));
+    for child in &instance.children {
+        flatten(child, &path, out);
+    }
+}
+
+fn validate_instance(path: &str, instance: &JsonInstance, strict: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+    match rbx_reflection_database::get().classes.get(instance.class.as_str()) {
+        None => problems.push(format!("{}: unknown class '{}'", path, instance.class)),
+        Some(descriptor) if descriptor.tags.contains(&ClassTag::Service) => {
+            problems.push(format!("{}: {} is a service and can't be added", path, instance.class))
+        }
+        Some(descriptor) if descriptor.tags.contains(&ClassTag::NotCreatable) => {
+            problems.push(format!("{}: {} can't be created", path, instance.class))
+        }
+        Some(_) => {
+            for (name, value) in &instance.properties {
+                // Inline data is uploaded once the modification is known to apply, if Open Cloud can take it
+                if let PropertyValue::Content(url) = value {
+                    if upload::is_data_url(url) {
+                        if let Err(reason) = upload::parse_data_url(url) {
+                            problems.push(format!("{}: {} holds inline data that can't be uploaded, {}", path, name, reason));
+                        }
+                        continue;
+                    }
+                }
+                match value.to_variant(&instance.class, name) {
+                    Err(e) => problems.push(format!("{}: {}", path, e)),
+                    Ok(None) if strict && !matches!(value, PropertyValue::Ref(_) | PropertyValue::RelativeCFrame { .. }) => problems.push(format!(
+                        "{}: {} has type '{}', which can't be converted",
+                        path, name, value.type_name()
+                    )),
+                    Ok(_) => {}
+                }
+            }
+            if let Some(source) = instance.properties.get("Source").and_then(|value| value.as_str()) {
+                if is_a(&instance.class, "LuaSourceContainer") {
+                    for error in luau::syntax_errors(source) {
+                        problems.push(format!("{}: syntax error in Source, {}", path, error));
+                    }
+                }
+            }
+        }
+    }
+    if let Err(e) = json_to_attributes(&instance.attributes) {
+        problems.push(format!("{}: {}", path, e));
+    }
+    problems
+}
+
+/// Build a follow-up prompt asking Gemini to fix a modification that failed validation
+pub fn feedback_prompt(prompt: &str, modification: &Modification, problems: &[String]) -> Result<String, Box<dyn Error>> {
+    Ok(format!(
+        "{}\n\nYour previous response can't be applied because of these problems:\n{}\n\
+         Respond with the same modification with these problems fixed. Previous response: {}",
+        prompt,
+        problems.join("\n"),
+        serde_json::to_string(modification)?
+    ))
+}
@@ -0,0 +1,74 @@
+use serde::Serialize;
+use std::fmt;
+use tracing::warn;
+
+/// A non-fatal problem found while applying a modification
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApplyWarning {
+    /// An operation's path didn't match any instance, so it was skipped
+    PathNotFound { operation: String, path: String, suggestions: Vec<String> },
+    /// An added instance's target_parent didn't exist, so it went into Workspace
+    DefaultedParent { instance: String, target: String, suggestions: Vec<String> },
+    /// A property had a type that can't be converted, so it was left out
+    SkippedProperty { instance: String, property: String, type_name: String },
+    /// A Ref property's path didn't match any instance once all adds were done
+    UnresolvedRef { instance: String, property: String, path: String, suggestions: Vec<String> },
+    /// A relative CFrame's instance was found but has no CFrame or pivot to offset from
+    NoPivot { instance: String, property: String, path: String },
+    /// An operation found its target but couldn't carry it out
+    OperationFailed { operation: String, path: String, reason: String },
+}
+
+impl fmt::Display for ApplyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyWarning::PathNotFound { operation, path, suggestions } => {
+                write!(f, "Could not find '{}' to {}.{}", path, operation, suggestion_hint(suggestions))
+            }
+            ApplyWarning::DefaultedParent { instance, target, suggestions } => {
+                write!(f, "Could not find parent '{}' for {}, added to Workspace instead.{}", target, instance, suggestion_hint(suggestions))
+            }
+            ApplyWarning::SkippedProperty { instance, property, type_name } => {
+                write!(f, "Skipped {}.{} because type '{}' is not supported", instance, property, type_name)
+            }
+            ApplyWarning::UnresolvedRef { instance, property, path, suggestions } => {
+                write!(f, "Could not resolve {}.{} reference '{}'.{}", instance, property, path, suggestion_hint(suggestions))
+            }
+            ApplyWarning::NoPivot { instance, property, path } => {
+                write!(f, "Could not place {}.{} relative to '{}', which has no CFrame or pivot", instance, property, path)
+            }
+            ApplyWarning::OperationFailed { operation, path, reason } => {
+                write!(f, "Failed to {} '{}': {}", operation, path, reason)
+            }
+        }
+    }
+}
+
+/// A " Did you mean ...?" hint listing suggested paths, or nothing if there are none
+pub fn suggestion_hint(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+    format!(" Did you mean {}?", quoted.join(" or "))
+}
+
+/// Log each warning from an apply, then the properties that were left out all together
+pub fn log_warnings(warnings: &[ApplyWarning]) {
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    let skipped: Vec<String> = warnings.iter()
+        .filter_map(|warning| match warning {
+            ApplyWarning::SkippedProperty { instance, property, .. } => Some(format!("{}.{}", instance, property)),
+            _ => None,
+        })
+        .collect();
+    if !skipped.is_empty() {
+        warn!(
+            "{} properties were left out: {}. Use --strict to reject modifications with properties that can't be converted",
+            skipped.len(), skipped.join(", ")
+        );
+    }
+}
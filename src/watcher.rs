@@ -0,0 +1,52 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::error::Error;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Notices when something else writes the place file, like a save from Studio.
+///
+/// The file's directory is watched rather than the file itself, since many programs
+/// save by writing a new file and renaming it over the old one.
+pub struct PlaceWatcher {
+    touched: Arc<AtomicBool>,
+    // Watching stops when this is dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl PlaceWatcher {
+    pub fn new(place: &Path) -> Result<PlaceWatcher, Box<dyn Error>> {
+        let file_name: OsString = place.file_name().ok_or("Place path has no file name")?.to_os_string();
+        let directory = match place.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => std::env::current_dir()?,
+        };
+
+        let touched = Arc::new(AtomicBool::new(false));
+        let mut watcher = notify::recommended_watcher({
+            let touched = touched.clone();
+            move |result: notify::Result<Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+                if event.kind.is_access() {
+                    return;
+                }
+                if event.paths.iter().any(|path| path.file_name() == Some(file_name.as_os_str())) {
+                    debug!("Place file event: {:?}", event.kind);
+                    touched.store(true, Ordering::SeqCst);
+                }
+            }
+        })?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+        Ok(PlaceWatcher { touched, _watcher: watcher })
+    }
+
+    /// Whether the file was written since the last call. This includes rbx-mcp's own saves,
+    /// so callers compare the modification time against the one they saved with.
+    pub fn take_touched(&self) -> bool {
+        self.touched.swap(false, Ordering::SeqCst)
+    }
+}